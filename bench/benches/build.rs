@@ -0,0 +1,142 @@
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::path::Path;
+use std::time::Duration;
+
+use fasthash::{city, RandomState};
+
+use criterion::{
+    criterion_group, criterion_main, measurement::WallTime, BenchmarkGroup, Criterion, SamplingMode,
+};
+
+const SAMPLE_SIZE: usize = 10;
+const WARM_UP_TIME: Duration = Duration::from_secs(5);
+const MEASURE_TIME: Duration = Duration::from_secs(10);
+
+fn criterion_unidic_build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("unidic/build");
+    group.sample_size(SAMPLE_SIZE);
+    group.warm_up_time(WARM_UP_TIME);
+    group.measurement_time(MEASURE_TIME);
+    group.sampling_mode(SamplingMode::Flat);
+    let mut keys = load_file("data/unidic");
+
+    keys.sort_unstable();
+    add_build_benches(&mut group, &keys);
+}
+
+fn add_build_benches(group: &mut BenchmarkGroup<WallTime>, keys: &[String]) {
+    let records: Vec<_> = keys
+        .iter()
+        .enumerate()
+        .map(|(i, k)| (k, i as u32))
+        .collect();
+
+    bench_build(group, "simplearrayhash/HashMap", || {
+        simplearrayhash::HashMap::new(&records).unwrap()
+    });
+
+    bench_build(group, "simplearrayhash/HashMap/from_iter", || {
+        simplearrayhash::HashMapBuilder::new()
+            .build_from_iter(records.iter().copied())
+            .unwrap()
+    });
+
+    bench_build(group, "simplearrayhash/HashMap/sort_keys", || {
+        simplearrayhash::HashMapBuilder::new()
+            .sort_keys(true)
+            .build(&records)
+            .unwrap()
+    });
+
+    bench_build(group, "std/HashMap", || {
+        let mut map = std::collections::HashMap::new();
+        for &(key, i) in &records {
+            map.insert(key.clone(), i);
+        }
+        map
+    });
+
+    bench_build(group, "std/HashMap/city", || {
+        let s = RandomState::<city::Hash64>::new();
+        let mut map = std::collections::HashMap::with_hasher(s);
+        for &(key, i) in &records {
+            map.insert(key.clone(), i);
+        }
+        map
+    });
+
+    bench_build(group, "fnv/FnvHashMap", || {
+        let mut map = fnv::FnvHashMap::default();
+        for &(key, i) in &records {
+            map.insert(key.clone(), i);
+        }
+        map
+    });
+
+    bench_build(group, "crawdad/trie", || {
+        crawdad::Trie::from_keys(keys).unwrap()
+    });
+
+    bench_build(group, "yada", || {
+        yada::builder::DoubleArrayBuilder::build(&records).unwrap()
+    });
+}
+
+/// Benchmarks `build` and reports the peak memory growth of one run,
+/// which includes temporary buffers freed before `build` returns.
+fn bench_build<T, F>(group: &mut BenchmarkGroup<WallTime>, name: &str, mut build: F)
+where
+    F: FnMut() -> T,
+{
+    match peak_rss_growth(&mut build) {
+        Some(bytes) => println!("{name}: peak RSS growth {:.1} MiB", to_mib(bytes)),
+        None => println!("{name}: peak RSS is not available on this platform"),
+    }
+    group.bench_function(name, |b| b.iter(&mut build));
+}
+
+/// Runs `f` once and returns how much the peak resident set size exceeds
+/// the resident set size before the run, in bytes.
+///
+/// It resets the peak through `/proc/self/clear_refs`, so it is available only on Linux.
+fn peak_rss_growth<T, F>(f: F) -> Option<u64>
+where
+    F: FnOnce() -> T,
+{
+    let before = read_status_kib("VmRSS:")?;
+    std::fs::write("/proc/self/clear_refs", "5").ok()?;
+    let result = f();
+    let peak = read_status_kib("VmHWM:")?;
+    drop(result);
+    Some(peak.saturating_sub(before) * 1024)
+}
+
+/// Reads the field of `/proc/self/status` in KiB.
+fn read_status_kib(field: &str) -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with(field))?;
+    line[field.len()..]
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()
+}
+
+fn to_mib(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+fn load_file<P>(path: P) -> Vec<String>
+where
+    P: AsRef<Path>,
+{
+    let file = File::open(path).unwrap();
+    let buf = BufReader::new(file);
+    buf.lines().map(|line| line.unwrap()).collect()
+}
+
+criterion_group!(benches, criterion_unidic_build);
+criterion_main!(benches);
@@ -0,0 +1,84 @@
+//! Command-line tool to build, inspect, and query dictionaries.
+//!
+//! ```text
+//! cargo run --release --example sah -- build words.tsv words.sah
+//! cargo run --release --example sah -- stats words.sah
+//! cargo run --release --example sah -- query words.sah
+//! ```
+//!
+//! The input of `build` is a TSV file whose lines are `key<TAB>value`,
+//! where the value is optional and defaults to the empty string.
+//! `query` reads keys from the standard input line by line and
+//! prints `key<TAB>value` for each key in the dictionary, or `key<TAB>(none)` otherwise.
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::process;
+
+use anyhow::{Context, Result};
+use simplearrayhash::HashMap;
+
+const USAGE: &str = "Usage:
+    sah build <input.tsv> <output.sah>
+    sah stats <dict.sah>
+    sah query <dict.sah>";
+
+fn main() -> Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["build", input, output] => build(input, output),
+        ["stats", dict] => stats(&load(dict)?),
+        ["query", dict] => query(&load(dict)?),
+        _ => {
+            eprintln!("{USAGE}");
+            process::exit(2);
+        }
+    }
+}
+
+fn build(input: &str, output: &str) -> Result<()> {
+    let rdr = BufReader::new(File::open(input).with_context(|| format!("opening {input}"))?);
+    let mut records = vec![];
+    for (i, line) in rdr.lines().enumerate() {
+        let line = line.with_context(|| format!("reading line {} of {input}", i + 1))?;
+        let (key, val) = line.split_once('\t').unwrap_or((&line, ""));
+        records.push((key.to_string(), val.to_string()));
+    }
+    let map = HashMap::new(&records).with_context(|| format!("building from {input}"))?;
+    let wtr = BufWriter::new(File::create(output).with_context(|| format!("creating {output}"))?);
+    map.serialize_into(wtr)?;
+    stats(&map)
+}
+
+fn load(dict: &str) -> Result<HashMap<String>> {
+    let rdr = BufReader::new(File::open(dict).with_context(|| format!("opening {dict}"))?);
+    HashMap::deserialize_from(rdr).with_context(|| format!("loading {dict}"))
+}
+
+fn stats(map: &HashMap<String>) -> Result<()> {
+    println!("num_keys: {}", map.len());
+    println!("capacity: {}", map.capacity());
+    println!("load_factor: {:.3}", map.load_factor());
+    println!("serialized_bytes: {}", map.serialize_to_vec().len());
+    Ok(())
+}
+
+fn query(map: &HashMap<String>) -> Result<()> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    for line in io::stdin().lock().lines() {
+        let key = line?;
+        match map.get(&key) {
+            Some(val) => writeln!(out, "{key}\t{val}")?,
+            None => writeln!(out, "{key}\t(none)")?,
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,79 @@
+//! Compares answers of `simplearrayhash::HashMap` with `std::collections::HashMap`
+//! built from the same records.
+
+#![no_main]
+
+use std::collections::HashMap as StdHashMap;
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use simplearrayhash::HashMapBuilder;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    records: Vec<(Vec<u8>, u32)>,
+    inserts: Vec<(Vec<u8>, u32)>,
+    removes: Vec<Vec<u8>>,
+    tombstone_ratio: u8,
+    queries: Vec<Vec<u8>>,
+    load_factor: u8,
+    seed: u64,
+    align_keys: bool,
+    share_suffixes: bool,
+    sort_keys: bool,
+}
+
+fuzz_target!(|input: Input| {
+    let mut expected = StdHashMap::new();
+    let mut records = vec![];
+    for (key, val) in input.records {
+        if !expected.contains_key(&key) {
+            expected.insert(key.clone(), val);
+            records.push((key, val));
+        }
+    }
+    let builder = HashMapBuilder::new()
+        .max_load(u32::from(input.load_factor) + 1, 256)
+        .seed(input.seed)
+        .align_keys(input.align_keys)
+        .share_suffixes(input.share_suffixes)
+        .sort_keys(input.sort_keys)
+        .max_tombstones(u32::from(input.tombstone_ratio) + 1, 256);
+    let mut map = match builder.build(&records) {
+        Ok(map) => map,
+        Err(_) => {
+            assert!(
+                records.is_empty()
+                    || (input.share_suffixes && (input.align_keys || input.sort_keys))
+            );
+            return;
+        }
+    };
+
+    if !input.share_suffixes && !input.sort_keys {
+        for (key, val) in input.inserts {
+            assert_eq!(map.insert(&key, val).unwrap(), expected.insert(key, val));
+        }
+        for key in input.removes {
+            assert_eq!(map.remove(&key).unwrap(), expected.remove(&key));
+        }
+    }
+
+    assert_eq!(map.len(), expected.len());
+    for (key, val) in &expected {
+        assert_eq!(map.get(key), Some(val));
+    }
+    for query in &input.queries {
+        assert_eq!(map.get(query), expected.get(query));
+        assert_eq!(map.contains_key(query), expected.contains_key(query));
+    }
+
+    if input.sort_keys {
+        let mut keys: Vec<_> = expected.keys().collect();
+        keys.sort();
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(map.rank(key).unwrap(), i);
+            assert_eq!(map.select(i).unwrap().map(|(k, _)| k), Some(key.as_slice()));
+        }
+    }
+});
@@ -0,0 +1,31 @@
+//! Compares answers of `simplearrayhash::HashSet` with `std::collections::HashSet`
+//! built from the same keys.
+
+#![no_main]
+
+use std::collections::HashSet as StdHashSet;
+
+use libfuzzer_sys::fuzz_target;
+use simplearrayhash::HashSet;
+
+fuzz_target!(|input: (Vec<Vec<u8>>, Vec<Vec<u8>>)| {
+    let (keys, queries) = input;
+    let expected: StdHashSet<_> = keys.iter().cloned().collect();
+    let set = match HashSet::new(&keys) {
+        Ok(set) => set,
+        Err(_) => {
+            assert!(keys.is_empty() || expected.len() < keys.len());
+            return;
+        }
+    };
+    assert_eq!(expected.len(), keys.len());
+
+    assert_eq!(set.len(), expected.len());
+    for key in &keys {
+        assert!(set.contains(key));
+    }
+    for query in &queries {
+        assert_eq!(set.contains(query), expected.contains(query));
+        assert_eq!(set.get(query), expected.get(query).map(Vec::as_slice));
+    }
+});
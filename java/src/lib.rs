@@ -0,0 +1,198 @@
+//! Java bindings of simplearrayhash built with the `jni` crate.
+//!
+//! The class `io.github.kampersanda.simplearrayhash.Dictionary` maps string keys to string
+//! values in the serialization format of `simplearrayhash::HashMap<String>`, so dictionaries
+//! built by Rust programs, such as the `sah` example, can be queried from the JVM as they are.
+//!
+//! ```text
+//! cargo build --release
+//! javac -encoding UTF-8 -d classes src/main/java/io/github/kampersanda/simplearrayhash/*.java src/test/java/*.java
+//! java -Djava.library.path=target/release -cp classes DictionaryTest
+//! ```
+//!
+//! A dictionary is owned by its Java object through a pointer stored as a `long`,
+//! and it is freed by `Dictionary.close`.
+
+use jni::objects::{JByteArray, JClass, JObjectArray, JString};
+use jni::sys::{jboolean, jbyteArray, jlong, jstring, JNI_FALSE, JNI_TRUE};
+use jni::JNIEnv;
+use simplearrayhash::HashMap;
+
+/// Dictionary from string keys to string values owned by a Java object.
+type Dictionary = HashMap<String>;
+
+/// Class of the exceptions thrown on failures.
+const EXCEPTION_CLASS: &str = "java/lang/IllegalArgumentException";
+
+/// Builds a dictionary from parallel arrays of keys and values and returns its handle.
+#[no_mangle]
+pub extern "system" fn Java_io_github_kampersanda_simplearrayhash_Dictionary_nativeBuild<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    keys: JObjectArray<'local>,
+    values: JObjectArray<'local>,
+) -> jlong {
+    let result = read_strings(&mut env, &keys).and_then(|keys| {
+        let values = read_strings(&mut env, &values)?;
+        Dictionary::from_keys_and_values(&keys, values)
+    });
+    into_handle(&mut env, result)
+}
+
+/// Loads a dictionary from the file at `path` and returns its handle.
+#[no_mangle]
+pub extern "system" fn Java_io_github_kampersanda_simplearrayhash_Dictionary_nativeLoad<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    path: JString<'local>,
+) -> jlong {
+    let result = env
+        .get_string(&path)
+        .map_err(anyhow::Error::from)
+        .and_then(|path| {
+            let rdr = std::io::BufReader::new(std::fs::File::open(String::from(path))?);
+            Dictionary::deserialize_from(rdr)
+        });
+    into_handle(&mut env, result)
+}
+
+/// Deserializes a dictionary from `bytes` and returns its handle.
+#[no_mangle]
+pub extern "system" fn Java_io_github_kampersanda_simplearrayhash_Dictionary_nativeDeserialize<
+    'local,
+>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    bytes: JByteArray<'local>,
+) -> jlong {
+    let result = env
+        .convert_byte_array(&bytes)
+        .map_err(anyhow::Error::from)
+        .and_then(|bytes| Dictionary::deserialize_from_slice(&bytes));
+    into_handle(&mut env, result)
+}
+
+/// Serializes the dictionary of `handle` into a byte array.
+#[no_mangle]
+pub extern "system" fn Java_io_github_kampersanda_simplearrayhash_Dictionary_nativeSerialize<
+    'local,
+>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+) -> jbyteArray {
+    let bytes = dictionary(handle).serialize_to_vec();
+    match env.byte_array_from_slice(&bytes) {
+        Ok(array) => array.into_raw(),
+        Err(e) => {
+            throw(&mut env, e.into());
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Returns the value of `key`, or `null` if it is not stored.
+#[no_mangle]
+pub extern "system" fn Java_io_github_kampersanda_simplearrayhash_Dictionary_nativeGet<'local>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+    key: JString<'local>,
+) -> jstring {
+    let dict = dictionary(handle);
+    let result = env
+        .get_string(&key)
+        .map_err(anyhow::Error::from)
+        .and_then(|key| match dict.get(String::from(key)) {
+            Some(val) => Ok(env.new_string(val)?.into_raw()),
+            None => Ok(std::ptr::null_mut()),
+        });
+    result.unwrap_or_else(|e| {
+        throw(&mut env, e);
+        std::ptr::null_mut()
+    })
+}
+
+/// Returns true if `key` is stored.
+#[no_mangle]
+pub extern "system" fn Java_io_github_kampersanda_simplearrayhash_Dictionary_nativeContains<
+    'local,
+>(
+    mut env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+    key: JString<'local>,
+) -> jboolean {
+    let dict = dictionary(handle);
+    match env.get_string(&key).map(String::from) {
+        Ok(key) if dict.contains_key(&key) => JNI_TRUE,
+        Ok(_) => JNI_FALSE,
+        Err(e) => {
+            throw(&mut env, e.into());
+            JNI_FALSE
+        }
+    }
+}
+
+/// Returns the number of stored keys.
+#[no_mangle]
+pub extern "system" fn Java_io_github_kampersanda_simplearrayhash_Dictionary_nativeSize(
+    _env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+) -> jlong {
+    // Java arrays and collections are indexed by signed integers anyway.
+    dictionary(handle).len() as jlong
+}
+
+/// Frees the dictionary of `handle`, which must not be used afterwards.
+#[no_mangle]
+pub extern "system" fn Java_io_github_kampersanda_simplearrayhash_Dictionary_nativeFree(
+    _env: JNIEnv,
+    _class: JClass,
+    handle: jlong,
+) {
+    if handle != 0 {
+        // SAFETY: The handle was created by `into_handle` and the Java object
+        // forgets it on `close`, so it is freed only once.
+        drop(unsafe { Box::from_raw(handle as *mut Dictionary) });
+    }
+}
+
+/// Reads the elements of a Java array of strings.
+fn read_strings(env: &mut JNIEnv, array: &JObjectArray) -> anyhow::Result<Vec<String>> {
+    let len = env.get_array_length(array)?;
+    let mut strings = Vec::with_capacity(len as usize);
+    for i in 0..len {
+        let s = JString::from(env.get_object_array_element(array, i)?);
+        strings.push(env.get_string(&s)?.into());
+        env.delete_local_ref(s)?;
+    }
+    Ok(strings)
+}
+
+/// Moves a built dictionary to the heap and returns its handle,
+/// or throws the error and returns 0.
+fn into_handle(env: &mut JNIEnv, result: anyhow::Result<Dictionary>) -> jlong {
+    match result {
+        Ok(dict) => Box::into_raw(Box::new(dict)) as jlong,
+        Err(e) => {
+            throw(env, e);
+            0
+        }
+    }
+}
+
+/// Borrows the dictionary of `handle`.
+fn dictionary<'a>(handle: jlong) -> &'a Dictionary {
+    // SAFETY: The Java object passes only handles created by `into_handle`
+    // and checks that it is not closed.
+    unsafe { &*(handle as *const Dictionary) }
+}
+
+/// Throws an error as a Java exception, unless an exception is already pending.
+fn throw(env: &mut JNIEnv, e: anyhow::Error) {
+    if !env.exception_check().unwrap_or(true) {
+        let _ = env.throw_new(EXCEPTION_CLASS, format!("{e:#}"));
+    }
+}
@@ -0,0 +1,98 @@
+package io.github.kampersanda.simplearrayhash;
+
+/**
+ * Frozen dictionary from string keys to string values,
+ * in the serialization format of {@code simplearrayhash::HashMap<String>}.
+ *
+ * <p>The dictionary lives in native memory until {@link #close} is called,
+ * and it can be queried from multiple threads concurrently.
+ */
+public final class Dictionary implements AutoCloseable {
+    static {
+        System.loadLibrary("simplearrayhash_java");
+    }
+
+    private long handle;
+
+    private Dictionary(long handle) {
+        this.handle = handle;
+    }
+
+    /**
+     * Builds a dictionary from parallel arrays of keys and values.
+     *
+     * @throws IllegalArgumentException if the arrays are empty or differ in length,
+     *     or if the keys contain duplicates.
+     */
+    public static Dictionary build(String[] keys, String[] values) {
+        return new Dictionary(nativeBuild(keys, values));
+    }
+
+    /**
+     * Loads a dictionary from the file at {@code path}.
+     *
+     * @throws IllegalArgumentException if the file cannot be read or is broken.
+     */
+    public static Dictionary load(String path) {
+        return new Dictionary(nativeLoad(path));
+    }
+
+    /**
+     * Deserializes a dictionary from {@code bytes}.
+     *
+     * @throws IllegalArgumentException if the bytes are broken.
+     */
+    public static Dictionary deserialize(byte[] bytes) {
+        return new Dictionary(nativeDeserialize(bytes));
+    }
+
+    /** Serializes the dictionary into bytes, which can be loaded by Rust programs. */
+    public byte[] serialize() {
+        return nativeSerialize(handle());
+    }
+
+    /** Returns the value of {@code key}, or {@code null} if it is not stored. */
+    public String get(String key) {
+        return nativeGet(handle(), key);
+    }
+
+    /** Returns true if {@code key} is stored. */
+    public boolean contains(String key) {
+        return nativeContains(handle(), key);
+    }
+
+    /** Returns the number of stored keys. */
+    public long size() {
+        return nativeSize(handle());
+    }
+
+    /** Frees the native memory of the dictionary, which cannot be used afterwards. */
+    @Override
+    public synchronized void close() {
+        nativeFree(handle);
+        handle = 0;
+    }
+
+    private long handle() {
+        if (handle == 0) {
+            throw new IllegalStateException("The dictionary is closed.");
+        }
+        return handle;
+    }
+
+    private static native long nativeBuild(String[] keys, String[] values);
+
+    private static native long nativeLoad(String path);
+
+    private static native long nativeDeserialize(byte[] bytes);
+
+    private static native byte[] nativeSerialize(long handle);
+
+    private static native String nativeGet(long handle, String key);
+
+    private static native boolean nativeContains(long handle, String key);
+
+    private static native long nativeSize(long handle);
+
+    private static native void nativeFree(long handle);
+}
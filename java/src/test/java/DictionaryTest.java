@@ -0,0 +1,54 @@
+// Checks the bindings after `cargo build --release`.
+import java.nio.file.Files;
+import java.nio.file.Path;
+import java.util.Arrays;
+
+import io.github.kampersanda.simplearrayhash.Dictionary;
+
+public class DictionaryTest {
+    public static void main(String[] args) throws Exception {
+        String[] keys = {"icdm", "idce", "情報"};
+        String[] values = {"Data Mining", "Data Engineering", "Information"};
+        try (Dictionary dict = Dictionary.build(keys, values)) {
+            check(dict.size() == 3);
+            check(dict.get("idce").equals("Data Engineering"));
+            check(dict.get("情報").equals("Information"));
+            check(dict.get("sigir") == null);
+            check(dict.contains("icdm"));
+            check(!dict.contains("情"));
+
+            Path file = Files.createTempFile("simplearrayhash-test", ".sah");
+            Files.write(file, dict.serialize());
+            try (Dictionary loaded = Dictionary.load(file.toString())) {
+                check(loaded.get("icdm").equals("Data Mining"));
+                check(Arrays.equals(loaded.serialize(), dict.serialize()));
+            }
+            try (Dictionary deserialized = Dictionary.deserialize(Files.readAllBytes(file))) {
+                check(deserialized.get("情報").equals("Information"));
+            }
+            Files.delete(file);
+        }
+
+        Dictionary closed = Dictionary.build(keys, values);
+        closed.close();
+        check(throwsException(() -> closed.get("icdm")));
+        check(throwsException(() -> Dictionary.build(new String[] {"icdm", "icdm"}, new String[] {"a", "b"})));
+        check(throwsException(() -> Dictionary.deserialize(new byte[] {1, 2, 3})));
+        System.out.println("ok");
+    }
+
+    private static boolean throwsException(Runnable f) {
+        try {
+            f.run();
+            return false;
+        } catch (RuntimeException e) {
+            return true;
+        }
+    }
+
+    private static void check(boolean cond) {
+        if (!cond) {
+            throw new AssertionError();
+        }
+    }
+}
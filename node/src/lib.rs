@@ -0,0 +1,91 @@
+//! Node.js bindings of simplearrayhash built with napi-rs.
+//!
+//! A [`Dictionary`] maps string keys to string values in the serialization format of
+//! `simplearrayhash::HashMap<String>`, so dictionaries built by Rust programs,
+//! such as the `sah` example, can be loaded as they are and vice versa.
+//!
+//! ```text
+//! npm install
+//! npm run build
+//! npm test
+//! ```
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+
+use napi::bindgen_prelude::Buffer;
+use napi::{Error, Result};
+use napi_derive::napi;
+use simplearrayhash::HashMap;
+
+/// Dictionary from string keys to string values.
+#[napi]
+pub struct Dictionary {
+    map: HashMap<String>,
+}
+
+#[napi]
+impl Dictionary {
+    /// Builds a dictionary from parallel arrays of keys and values.
+    ///
+    /// An error is thrown when the arrays are empty or differ in length,
+    /// or when the keys contain duplicates.
+    #[napi(factory)]
+    pub fn build(keys: Vec<String>, values: Vec<String>) -> Result<Self> {
+        let map = HashMap::from_keys_and_values(&keys, values).map_err(to_napi)?;
+        Ok(Self { map })
+    }
+
+    /// Loads a dictionary from the file at `path`.
+    #[napi(factory)]
+    pub fn load(path: String) -> Result<Self> {
+        let rdr = BufReader::new(File::open(&path).map_err(|e| Error::from_reason(e.to_string()))?);
+        let map = HashMap::deserialize_from(rdr).map_err(to_napi)?;
+        Ok(Self { map })
+    }
+
+    /// Deserializes a dictionary from `bytes`.
+    #[napi(factory)]
+    pub fn deserialize(bytes: Buffer) -> Result<Self> {
+        let map = HashMap::deserialize_from_slice(&bytes).map_err(to_napi)?;
+        Ok(Self { map })
+    }
+
+    /// Saves the dictionary into the file at `path`.
+    #[napi]
+    pub fn save(&self, path: String) -> Result<()> {
+        let wtr =
+            BufWriter::new(File::create(&path).map_err(|e| Error::from_reason(e.to_string()))?);
+        self.map.serialize_into(wtr).map_err(to_napi)
+    }
+
+    /// Serializes the dictionary into bytes.
+    #[napi]
+    pub fn serialize(&self) -> Buffer {
+        self.map.serialize_to_vec().into()
+    }
+
+    /// Returns the value of `key`, or `null` if it is not stored.
+    #[napi]
+    pub fn get(&self, key: String) -> Option<String> {
+        self.map.get(key).cloned()
+    }
+
+    /// Returns true if `key` is stored.
+    #[napi]
+    pub fn has(&self, key: String) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Number of stored keys.
+    #[napi(getter)]
+    pub fn size(&self) -> u32 {
+        // JavaScript numbers cannot index more keys than fit in 32 bits anyway.
+        self.map.len() as u32
+    }
+}
+
+/// Converts an error of the library into a JavaScript exception.
+fn to_napi(e: anyhow::Error) -> Error {
+    Error::from_reason(format!("{e:#}"))
+}
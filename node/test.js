@@ -0,0 +1,23 @@
+// Checks the bindings after `npm run build`.
+const assert = require('assert');
+const fs = require('fs');
+const os = require('os');
+const path = require('path');
+
+const { Dictionary } = require('./index.js');
+
+const dict = Dictionary.build(['icdm', 'idce', 'sigmod'], ['Data Mining', 'Data Engineering', 'Databases']);
+assert.strictEqual(dict.size, 3);
+assert.strictEqual(dict.get('idce'), 'Data Engineering');
+assert.strictEqual(dict.get('sigir'), null);
+assert.ok(dict.has('icdm'));
+
+const file = path.join(os.tmpdir(), 'simplearrayhash-test.sah');
+dict.save(file);
+const loaded = Dictionary.load(file);
+assert.strictEqual(loaded.get('sigmod'), 'Databases');
+assert.deepStrictEqual(Dictionary.deserialize(fs.readFileSync(file)).serialize(), dict.serialize());
+fs.unlinkSync(file);
+
+assert.throws(() => Dictionary.build(['icdm', 'icdm'], ['a', 'b']));
+console.log('ok');
@@ -0,0 +1,284 @@
+//! Hash map implementation resolving alias keys to the values of canonical keys.
+
+use crate::{HashMap, HashMapBuilder, KeyLen};
+
+use anyhow::{anyhow, Result};
+
+/// Hash map implementation in which alias keys resolve to the values of canonical keys,
+/// such as `"colour"` to `"color"` or legacy identifiers to new ones.
+///
+/// The values are stored once in an inner map of the canonical keys,
+/// and an alias refers to the slot of its canonical key,
+/// so [`AliasedHashMap::get`] follows an alias without duplicating the value.
+/// Keys cannot be inserted into or removed from the inner map,
+/// which keeps the slots referred to by aliases valid.
+#[derive(Clone)]
+pub struct AliasedHashMap<V, L = usize>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    map: HashMap<V, L>,
+    // Slot positions in `map` of the canonical keys of the aliases.
+    aliases: HashMap<usize, L>,
+}
+
+impl<V> AliasedHashMap<V>
+where
+    V: Default + Clone,
+{
+    /// Creates a new [`AliasedHashMap`] from input records and aliases.
+    ///
+    /// # Arguments
+    ///
+    /// - `records`: List of key-value pairs of the canonical keys.
+    /// - `aliases`: List of pairs of an alias and the key it resolves to,
+    ///   which is a canonical key or a preceding alias.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `records` is empty,
+    ///  - `records` contains duplicate keys, or
+    ///  - `aliases` contains an invalid alias as described in [`AliasedHashMap::add_alias`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::AliasedHashMap;
+    ///
+    /// let records = vec![("color", 0), ("gray", 1)];
+    /// let aliases = vec![("colour", "color"), ("grey", "gray")];
+    /// let map = AliasedHashMap::new(&records, &aliases).unwrap();
+    /// assert_eq!(map.get("colour"), Some(&0));
+    /// assert_eq!(map.get("gray"), Some(&1));
+    /// assert_eq!(map.get("grey"), Some(&1));
+    /// ```
+    pub fn new<K, A, C>(records: &[(K, V)], aliases: &[(A, C)]) -> Result<Self>
+    where
+        K: AsRef<[u8]>,
+        A: AsRef<[u8]>,
+        C: AsRef<[u8]>,
+    {
+        let mut map = Self::from_map(HashMap::new(records)?)?;
+        for (alias, canonical) in aliases {
+            map.add_alias(alias, canonical)?;
+        }
+        Ok(map)
+    }
+}
+
+impl<V, L> AliasedHashMap<V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    /// Creates a new [`AliasedHashMap`] with no aliases from a map of the canonical keys,
+    /// which may be built with any options.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the table of aliases cannot be allocated.
+    pub fn from_map(map: HashMap<V, L>) -> Result<Self> {
+        // The builder rejects no records, so an empty table is made by clearing one.
+        let mut aliases = HashMapBuilder::new().key_len::<L>().build(&[("", 0)])?;
+        aliases.clear();
+        Ok(Self { map, aliases })
+    }
+
+    /// Registers `alias` resolving to `canonical`,
+    /// which is a canonical key or an alias resolving to one.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `canonical` is neither a canonical key nor an alias,
+    ///  - `alias` is a canonical key or an alias already, or
+    ///  - the length of `alias` exceeds the maximum of `L`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::{AliasedHashMap, HashMap};
+    ///
+    /// let map = HashMap::new(&[("sigmod", 0)]).unwrap();
+    /// let mut map = AliasedHashMap::from_map(map).unwrap();
+    /// map.add_alias("pods", "sigmod").unwrap();
+    /// map.add_alias("mod", "pods").unwrap();
+    /// assert_eq!(map.get("mod"), Some(&0));
+    /// assert!(map.add_alias("pods", "sigmod").is_err());
+    /// assert!(map.add_alias("vldb", "icde").is_err());
+    /// ```
+    pub fn add_alias<A, C>(&mut self, alias: A, canonical: C) -> Result<()>
+    where
+        A: AsRef<[u8]>,
+        C: AsRef<[u8]>,
+    {
+        let pos = self
+            .resolve(canonical.as_ref())
+            .ok_or_else(|| anyhow!("The key of an alias must be stored."))?;
+        if self.resolve(alias.as_ref()).is_some() {
+            return Err(anyhow!("The alias must not be a stored key or alias."));
+        }
+        self.aliases.insert(alias, pos)?;
+        Ok(())
+    }
+
+    /// Unregisters `alias`, returning true if it was registered.
+    ///
+    /// # Errors
+    ///
+    /// It never fails for a map created by [`AliasedHashMap::new`] or [`AliasedHashMap::from_map`].
+    pub fn remove_alias<A>(&mut self, alias: A) -> Result<bool>
+    where
+        A: AsRef<[u8]>,
+    {
+        Ok(self.aliases.remove(alias)?.is_some())
+    }
+
+    /// Returns a reference to the value corresponding to the key or the key an alias resolves to.
+    #[inline(always)]
+    pub fn get<K>(&self, key: K) -> Option<&V>
+    where
+        K: AsRef<[u8]>,
+    {
+        let pos = self.resolve(key.as_ref())?;
+        Some(&self.map.table.nodes[pos].val)
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key
+    /// or the key an alias resolves to, which is shared by the canonical key and its aliases.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::AliasedHashMap;
+    ///
+    /// let mut map = AliasedHashMap::new(&[("color", 0)], &[("colour", "color")]).unwrap();
+    /// *map.get_mut("colour").unwrap() += 1;
+    /// assert_eq!(map.get("color"), Some(&1));
+    /// ```
+    #[inline(always)]
+    pub fn get_mut<K>(&mut self, key: K) -> Option<&mut V>
+    where
+        K: AsRef<[u8]>,
+    {
+        let pos = self.resolve(key.as_ref())?;
+        Some(&mut self.map.table.nodes[pos].val)
+    }
+
+    /// Returns true if the map contains the key as a canonical key or an alias.
+    #[inline(always)]
+    pub fn contains_key<K>(&self, key: K) -> bool
+    where
+        K: AsRef<[u8]>,
+    {
+        self.resolve(key.as_ref()).is_some()
+    }
+
+    /// Returns the canonical key that the key resolves to, which is itself for a canonical key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::AliasedHashMap;
+    ///
+    /// let map = AliasedHashMap::new(&[("color", 0)], &[("colour", "color")]).unwrap();
+    /// assert_eq!(map.canonical_key("colour"), Some(&b"color"[..]));
+    /// assert_eq!(map.canonical_key("color"), Some(&b"color"[..]));
+    /// assert_eq!(map.canonical_key("hue"), None);
+    /// ```
+    pub fn canonical_key<K>(&self, key: K) -> Option<&[u8]>
+    where
+        K: AsRef<[u8]>,
+    {
+        let pos = self.resolve(key.as_ref())?;
+        Some(self.map.table.get_bytes(&self.map.table.nodes[pos]))
+    }
+
+    /// Returns the inner map of the canonical keys.
+    #[inline(always)]
+    pub const fn map(&self) -> &HashMap<V, L> {
+        &self.map
+    }
+
+    /// Returns the number of canonical keys.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns true if the map contains no canonical keys.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the number of aliases.
+    #[inline(always)]
+    pub fn num_aliases(&self) -> usize {
+        self.aliases.len()
+    }
+
+    /// Returns the slot position in the inner map of the key or the key an alias resolves to.
+    #[inline(always)]
+    fn resolve(&self, key: &[u8]) -> Option<usize> {
+        self.map
+            .table
+            .get_pos(key)
+            .or_else(|| self.aliases.get(key).copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let aliases = [
+            ("ICDM", "icdm"),
+            ("empty", ""),
+            ("SIGMOD", "sigmod"),
+            ("PODS", "SIGMOD"),
+        ];
+        let mut map = AliasedHashMap::new(&records, &aliases).unwrap();
+        assert_eq!(map.len(), 6);
+        assert_eq!(map.num_aliases(), 4);
+        for &(k, v) in &records {
+            assert_eq!(map.get(k), Some(&v));
+        }
+        assert_eq!(map.get("ICDM"), Some(&0));
+        assert_eq!(map.get("empty"), Some(&2));
+        assert_eq!(map.get("PODS"), Some(&3));
+        assert_eq!(map.canonical_key("PODS"), Some(&b"sigmod"[..]));
+        assert_eq!(map.get("IDCE"), None);
+
+        *map.get_mut("PODS").unwrap() = 10;
+        assert_eq!(map.get("sigmod"), Some(&10));
+        assert_eq!(map.get("SIGMOD"), Some(&10));
+
+        assert!(map.remove_alias("SIGMOD").unwrap());
+        assert!(!map.remove_alias("SIGMOD").unwrap());
+        assert_eq!(map.get("SIGMOD"), None);
+        assert_eq!(map.get("PODS"), Some(&10));
+    }
+
+    #[test]
+    fn test_invalid() {
+        let records = [("icdm", 0), ("idce", 1)];
+        assert!(AliasedHashMap::new(&records, &[("icdm", "idce")]).is_err());
+        assert!(AliasedHashMap::new(&records, &[("ICDM", "sigmod")]).is_err());
+        assert!(AliasedHashMap::new(&records, &[("ICDM", "icdm"), ("ICDM", "idce")]).is_err());
+        let map = HashMapBuilder::new()
+            .key_len::<u8>()
+            .build(&records)
+            .unwrap();
+        let mut map = AliasedHashMap::from_map(map).unwrap();
+        assert!(map.add_alias("a".repeat(256), "icdm").is_err());
+    }
+}
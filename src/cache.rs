@@ -0,0 +1,182 @@
+//! Bounded LRU cache of recent lookups placed in front of the probe path.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+// Marker of no entry in the links of the recency list.
+const NIL: usize = usize::MAX;
+
+/// Cache of the slot positions found by recent lookups, including misses,
+/// evicting the least recently used key when full.
+///
+/// The entries are valid only for the state of the table they were recorded in,
+/// which is identified by a stamp changing whenever a key is inserted or a slot is reused.
+/// A cloned cache starts empty with the same capacity.
+pub struct LookupCache {
+    capacity: usize,
+    lru: Mutex<Lru>,
+}
+
+impl LookupCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            lru: Mutex::new(Lru::default()),
+        }
+    }
+
+    /// Returns the cached position of `key` for the table state `stamp`,
+    /// or looks it up by `find` and caches the result.
+    ///
+    /// Concurrent lookups do not wait for each other:
+    /// a lookup finding the cache in use calls `find` without caching.
+    pub fn get_or_find<F>(&self, key: &[u8], stamp: (u64, usize), find: F) -> Option<usize>
+    where
+        F: FnOnce() -> Option<usize>,
+    {
+        if let Ok(mut lru) = self.lru.try_lock() {
+            if lru.stamp != stamp {
+                lru.clear(stamp);
+            } else if let Some(pos) = lru.get(key) {
+                return pos;
+            }
+        }
+        let pos = find();
+        if let Ok(mut lru) = self.lru.try_lock() {
+            if lru.stamp == stamp {
+                lru.insert(key, pos, self.capacity);
+            }
+        }
+        pos
+    }
+}
+
+impl Clone for LookupCache {
+    fn clone(&self) -> Self {
+        Self::new(self.capacity)
+    }
+}
+
+/// Entries linked in the order of recent use, from `head` (most recent) to `tail`.
+struct Lru {
+    stamp: (u64, usize),
+    index: HashMap<Box<[u8]>, usize>,
+    entries: Vec<Entry>,
+    head: usize,
+    tail: usize,
+}
+
+struct Entry {
+    key: Box<[u8]>,
+    pos: Option<usize>,
+    prev: usize,
+    next: usize,
+}
+
+impl Default for Lru {
+    fn default() -> Self {
+        Self {
+            stamp: (0, 0),
+            index: HashMap::new(),
+            entries: vec![],
+            head: NIL,
+            tail: NIL,
+        }
+    }
+}
+
+impl Lru {
+    fn clear(&mut self, stamp: (u64, usize)) {
+        self.stamp = stamp;
+        self.index.clear();
+        self.entries.clear();
+        self.head = NIL;
+        self.tail = NIL;
+    }
+
+    /// Returns the cached position of `key`, marking it as the most recently used.
+    fn get(&mut self, key: &[u8]) -> Option<Option<usize>> {
+        let i = *self.index.get(key)?;
+        self.unlink(i);
+        self.push_front(i);
+        Some(self.entries[i].pos)
+    }
+
+    /// Caches `pos` for `key`, which is not cached, evicting the least recently used key if full.
+    fn insert(&mut self, key: &[u8], pos: Option<usize>, capacity: usize) {
+        if self.index.contains_key(key) {
+            return;
+        }
+        let i = if self.entries.len() < capacity {
+            self.entries.push(Entry {
+                key: key.into(),
+                pos,
+                prev: NIL,
+                next: NIL,
+            });
+            self.entries.len() - 1
+        } else {
+            let i = self.tail;
+            self.unlink(i);
+            let entry = &mut self.entries[i];
+            self.index.remove(&entry.key);
+            entry.key = key.into();
+            entry.pos = pos;
+            i
+        };
+        self.index.insert(key.into(), i);
+        self.push_front(i);
+    }
+
+    fn unlink(&mut self, i: usize) {
+        let (prev, next) = (self.entries[i].prev, self.entries[i].next);
+        if prev == NIL {
+            self.head = next;
+        } else {
+            self.entries[prev].next = next;
+        }
+        if next == NIL {
+            self.tail = prev;
+        } else {
+            self.entries[next].prev = prev;
+        }
+    }
+
+    fn push_front(&mut self, i: usize) {
+        self.entries[i].prev = NIL;
+        self.entries[i].next = self.head;
+        if self.head == NIL {
+            self.tail = i;
+        } else {
+            self.entries[self.head].prev = i;
+        }
+        self.head = i;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru() {
+        let cache = LookupCache::new(2);
+        let calls = std::cell::Cell::new(0);
+        let find = |key: &[u8], stamp| {
+            cache.get_or_find(key, stamp, || {
+                calls.set(calls.get() + 1);
+                (key == b"icdm").then_some(3)
+            })
+        };
+        assert_eq!(find(b"icdm", (0, 1)), Some(3));
+        assert_eq!(find(b"sigir", (0, 1)), None);
+        assert_eq!(find(b"icdm", (0, 1)), Some(3));
+        assert_eq!(find(b"sigir", (0, 1)), None);
+        // "acl" evicts "icdm", which is less recently used than "sigir".
+        assert_eq!(find(b"acl", (0, 1)), None);
+        assert_eq!(find(b"sigir", (0, 1)), None);
+        assert_eq!(find(b"icdm", (0, 1)), Some(3));
+        assert_eq!(find(b"icdm", (1, 1)), Some(3));
+        assert_eq!(calls.get(), 5);
+    }
+}
@@ -0,0 +1,209 @@
+//! Encoding of multi-field composite keys.
+//!
+//! Each field is encoded as its length in LEB128 followed by its bytes,
+//! so different tuples of fields never collide unlike keys joined with a delimiter.
+
+/// Tuple of byte-like fields that can be encoded into a single key.
+///
+/// It is implemented for tuples of up to six fields and slices of fields.
+pub trait CompositeKey {
+    /// Appends the encoded key to `buf`.
+    fn encode_into(&self, buf: &mut Vec<u8>);
+}
+
+macro_rules! impl_composite_key {
+    ($($t:ident $i:tt),+) => {
+        impl<$($t),+> CompositeKey for ($($t,)+)
+        where
+            $($t: AsRef<[u8]>),+
+        {
+            fn encode_into(&self, buf: &mut Vec<u8>) {
+                $(encode_field(self.$i.as_ref(), buf);)+
+            }
+        }
+    };
+}
+
+impl_composite_key!(A 0);
+impl_composite_key!(A 0, B 1);
+impl_composite_key!(A 0, B 1, C 2);
+impl_composite_key!(A 0, B 1, C 2, D 3);
+impl_composite_key!(A 0, B 1, C 2, D 3, E 4);
+impl_composite_key!(A 0, B 1, C 2, D 3, E 4, F 5);
+
+impl<F> CompositeKey for [F]
+where
+    F: AsRef<[u8]>,
+{
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        for field in self {
+            encode_field(field.as_ref(), buf);
+        }
+    }
+}
+
+impl<F, const N: usize> CompositeKey for [F; N]
+where
+    F: AsRef<[u8]>,
+{
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        self[..].encode_into(buf);
+    }
+}
+
+impl<C> CompositeKey for &C
+where
+    C: CompositeKey + ?Sized,
+{
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        (*self).encode_into(buf);
+    }
+}
+
+fn encode_field(field: &[u8], buf: &mut Vec<u8>) {
+    let mut len = field.len();
+    while len >= 0x80 {
+        buf.push((len & 0x7f) as u8 | 0x80);
+        len >>= 7;
+    }
+    buf.push(len as u8);
+    buf.extend_from_slice(field);
+}
+
+/// Encoder of composite keys reusing an internal buffer.
+///
+/// # Examples
+///
+/// ```
+/// use simplearrayhash::{HashMap, KeyEncoder};
+///
+/// let records = vec![
+///     (KeyEncoder::encode(("ja", "東京")), 0),
+///     (KeyEncoder::encode(("en", "Tokyo")), 1),
+/// ];
+/// let map = HashMap::new(&records).unwrap();
+///
+/// let mut encoder = KeyEncoder::new();
+/// assert_eq!(map.get(encoder.set(("en", "Tokyo"))), Some(&1));
+/// assert_eq!(map.get(encoder.set(("ja", "Tokyo"))), None);
+/// ```
+#[derive(Default, Clone, Debug)]
+pub struct KeyEncoder {
+    buf: Vec<u8>,
+}
+
+impl KeyEncoder {
+    /// Creates a new encoder with an empty buffer.
+    pub const fn new() -> Self {
+        Self { buf: Vec::new() }
+    }
+
+    /// Encodes `fields` into a new key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::KeyEncoder;
+    ///
+    /// assert_eq!(KeyEncoder::encode(("ab", "c")), b"\x02ab\x01c");
+    /// assert_ne!(KeyEncoder::encode(("a", "bc")), KeyEncoder::encode(("ab", "c")));
+    /// ```
+    pub fn encode<C>(fields: C) -> Vec<u8>
+    where
+        C: CompositeKey,
+    {
+        let mut buf = vec![];
+        fields.encode_into(&mut buf);
+        buf
+    }
+
+    /// Replaces the buffer with the encoded `fields`, returning the encoded key.
+    pub fn set<C>(&mut self, fields: C) -> &[u8]
+    where
+        C: CompositeKey,
+    {
+        self.buf.clear();
+        fields.encode_into(&mut self.buf);
+        &self.buf
+    }
+
+    /// Appends a field to the buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::KeyEncoder;
+    ///
+    /// let mut encoder = KeyEncoder::new();
+    /// encoder.push("ab").push("c");
+    /// assert_eq!(encoder.as_bytes(), KeyEncoder::encode(("ab", "c")));
+    /// ```
+    pub fn push<F>(&mut self, field: F) -> &mut Self
+    where
+        F: AsRef<[u8]>,
+    {
+        encode_field(field.as_ref(), &mut self.buf);
+        self
+    }
+
+    /// Clears the buffer.
+    pub fn clear(&mut self) {
+        self.buf.clear();
+    }
+
+    /// Returns the encoded key in the buffer.
+    #[inline(always)]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl AsRef<[u8]> for KeyEncoder {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unambiguous() {
+        let keys = [
+            KeyEncoder::encode(("a", "bc")),
+            KeyEncoder::encode(("ab", "c")),
+            KeyEncoder::encode(("abc", "")),
+            KeyEncoder::encode(("", "abc")),
+            KeyEncoder::encode(("abc",)),
+            KeyEncoder::encode(("a", "b", "c")),
+        ];
+        for i in 0..keys.len() {
+            for j in i + 1..keys.len() {
+                assert_ne!(keys[i], keys[j]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_long_field() {
+        let field = "a".repeat(300);
+        let key = KeyEncoder::encode((field.as_str(), "b"));
+        assert_eq!(&key[..2], &[0xac, 0x02]);
+        assert_eq!(&key[2..302], field.as_bytes());
+        assert_eq!(&key[302..], b"\x01b");
+    }
+
+    #[test]
+    fn test_slice() {
+        let fields = vec!["ab", "c"];
+        assert_eq!(
+            KeyEncoder::encode(fields.as_slice()),
+            KeyEncoder::encode(("ab", "c"))
+        );
+        assert_eq!(
+            KeyEncoder::encode(["ab", "c"]),
+            KeyEncoder::encode(("ab", "c"))
+        );
+    }
+}
@@ -0,0 +1,293 @@
+//! Simple fast counter implementation for string kyes.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::HashMap;
+
+use anyhow::Result;
+
+/// Simple fast counter implementation for string kyes,
+/// mapping each key to its number of occurrences.
+#[derive(Clone)]
+pub struct CounterMap {
+    map: HashMap<u64>,
+}
+
+impl CounterMap {
+    /// Creates a new [`CounterMap`] by counting the occurrences of input keys.
+    ///
+    /// # Arguments
+    ///
+    /// - `keys`: List of keys, which may contain duplicates.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when `keys` is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::CounterMap;
+    ///
+    /// let keys = vec!["icdm", "idce", "icdm"];
+    /// let counter = CounterMap::new(&keys).unwrap();
+    /// assert_eq!(counter.get("icdm"), 2);
+    /// assert_eq!(counter.get("sigir"), 0);
+    /// ```
+    pub fn new<K>(keys: &[K]) -> Result<Self>
+    where
+        K: AsRef<[u8]>,
+    {
+        let records: Vec<_> = keys.iter().map(|k| (k, 1)).collect();
+        Self::from_counts(&records)
+    }
+
+    /// Creates a new [`CounterMap`] from pairs of keys and counts.
+    /// The counts of duplicate keys are summed up.
+    ///
+    /// # Arguments
+    ///
+    /// - `records`: List of key-count pairs, which may contain duplicate keys.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when `records` is empty.
+    ///
+    /// # Panics
+    ///
+    /// It panics in debug mode if the sum of the counts of a key overflows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::CounterMap;
+    ///
+    /// let records = vec![("icdm", 3), ("idce", 1), ("icdm", 2)];
+    /// let counter = CounterMap::from_counts(&records).unwrap();
+    /// assert_eq!(counter.get("icdm"), 5);
+    /// assert_eq!(counter.get("idce"), 1);
+    /// ```
+    pub fn from_counts<K>(records: &[(K, u64)]) -> Result<Self>
+    where
+        K: AsRef<[u8]>,
+    {
+        let map = HashMap::new_grouped(records, |acc, v| *acc += v)?;
+        Ok(Self { map })
+    }
+
+    /// Returns the count of the key, or `0` if the key is not found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::CounterMap;
+    ///
+    /// let keys = vec!["icdm", "idce", "icdm"];
+    /// let counter = CounterMap::new(&keys).unwrap();
+    /// assert_eq!(counter.get("idce"), 1);
+    /// assert_eq!(counter.get("sigir"), 0);
+    /// ```
+    #[inline(always)]
+    pub fn get<K>(&self, key: K) -> u64
+    where
+        K: AsRef<[u8]>,
+    {
+        self.map.get_or(key, 0)
+    }
+
+    /// Returns the `k` entries with the highest counts in descending order of counts.
+    /// Ties are broken by the lexicographical order of keys.
+    ///
+    /// It keeps a bounded heap of `k` entries while scanning the counts,
+    /// so the whole entries are not sorted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::CounterMap;
+    ///
+    /// let keys = vec!["icdm", "idce", "icdm", "sigmod", "idce", "icdm"];
+    /// let counter = CounterMap::new(&keys).unwrap();
+    /// assert_eq!(
+    ///     counter.top_k(2),
+    ///     vec![("icdm".as_bytes(), 3), ("idce".as_bytes(), 2)]
+    /// );
+    /// ```
+    pub fn top_k(&self, k: usize) -> Vec<(&[u8], u64)> {
+        if k == 0 {
+            return vec![];
+        }
+        // Min-heap of the best entries, where a larger count and then a smaller key is better.
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        for (key, node) in self.map.table.iter() {
+            let entry = Reverse((node.val, Reverse(key)));
+            if heap.len() < k {
+                heap.push(entry);
+            } else if entry < *heap.peek().unwrap() {
+                heap.pop();
+                heap.push(entry);
+            }
+        }
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((count, Reverse(key)))| (key, count))
+            .collect()
+    }
+
+    /// Adds the counts of `other` into this counter.
+    ///
+    /// The counts of keys already present are updated in place,
+    /// and only the keys not present yet are inserted,
+    /// so the cost is proportional to the size of `other`.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the counter cannot grow to hold the new keys,
+    /// in which case the keys merged so far are kept.
+    ///
+    /// # Panics
+    ///
+    /// It panics in debug mode if a count overflows.
+    /// Use [`CounterMap::merge_saturating`] to clamp counts instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::CounterMap;
+    ///
+    /// let mut counter = CounterMap::new(&["icdm", "idce", "icdm"]).unwrap();
+    /// let other = CounterMap::new(&["icdm", "sigir"]).unwrap();
+    /// counter.merge(&other).unwrap();
+    /// assert_eq!(counter.get("icdm"), 3);
+    /// assert_eq!(counter.get("idce"), 1);
+    /// assert_eq!(counter.get("sigir"), 1);
+    /// ```
+    pub fn merge(&mut self, other: &Self) -> Result<()> {
+        self.merge_with(other, |a, b| a + b)
+    }
+
+    /// Adds the counts of `other` into this counter like [`CounterMap::merge`],
+    /// but saturates counts at [`u64::MAX`] instead of overflowing.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned in the same cases as [`CounterMap::merge`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::CounterMap;
+    ///
+    /// let mut counter = CounterMap::from_counts(&[("icdm", u64::MAX)]).unwrap();
+    /// let other = CounterMap::new(&["icdm"]).unwrap();
+    /// counter.merge_saturating(&other).unwrap();
+    /// assert_eq!(counter.get("icdm"), u64::MAX);
+    /// ```
+    pub fn merge_saturating(&mut self, other: &Self) -> Result<()> {
+        self.merge_with(other, u64::saturating_add)
+    }
+
+    fn merge_with<F>(&mut self, other: &Self, add: F) -> Result<()>
+    where
+        F: Fn(u64, u64) -> u64,
+    {
+        for (key, node) in other.map.table.iter() {
+            if let Some(count) = self.map.get_mut(key) {
+                *count = add(*count, node.val);
+            } else {
+                self.map.insert(key, node.val)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the number of distinct keys in the counter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::CounterMap;
+    ///
+    /// let keys = vec!["icdm", "idce", "icdm"];
+    /// let counter = CounterMap::new(&keys).unwrap();
+    /// assert_eq!(counter.len(), 2);
+    /// ```
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns true if the counter contains no elements.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_basic() {
+        let keys = ["icdm", "idce", "", "icdm", "sigir", "", "icdm"];
+        let counter = CounterMap::new(&keys).unwrap();
+        assert_eq!(counter.len(), 4);
+        assert_eq!(counter.get("icdm"), 3);
+        assert_eq!(counter.get(""), 2);
+        assert_eq!(counter.get("idce"), 1);
+        assert_eq!(counter.get("sigkdd"), 0);
+    }
+
+    #[test]
+    fn test_top_k() {
+        let keys = ["b", "a", "c", "b", "a", "d", "b", "c"];
+        let counter = CounterMap::new(&keys).unwrap();
+        let expected: Vec<(&[u8], u64)> = vec![(b"b", 3), (b"a", 2), (b"c", 2), (b"d", 1)];
+        for k in 0..6 {
+            assert_eq!(counter.top_k(k), expected[..k.min(4)]);
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut counter = CounterMap::new(&["icdm", "idce", "", "icdm"]).unwrap();
+        let other = CounterMap::new(&["", "sigir", "icdm", "sigir"]).unwrap();
+        counter.merge(&other).unwrap();
+        assert_eq!(counter.len(), 4);
+        assert_eq!(counter.get("icdm"), 3);
+        assert_eq!(counter.get("idce"), 1);
+        assert_eq!(counter.get(""), 2);
+        assert_eq!(counter.get("sigir"), 2);
+
+        // No new keys
+        counter.merge(&other).unwrap();
+        assert_eq!(counter.len(), 4);
+        assert_eq!(counter.get("sigir"), 4);
+
+        // Many new keys grow the table in place.
+        let keys: Vec<_> = (0..100).map(|i| i.to_string()).collect();
+        counter.merge(&CounterMap::new(&keys).unwrap()).unwrap();
+        assert_eq!(counter.len(), 104);
+        assert_eq!(counter.get("icdm"), 4);
+        assert!(keys.iter().all(|k| counter.get(k) == 1));
+    }
+
+    #[test]
+    fn test_merge_saturating() {
+        let mut counter = CounterMap::from_counts(&[("icdm", u64::MAX - 1), ("idce", 1)]).unwrap();
+        let other = CounterMap::from_counts(&[("icdm", 2), ("sigir", 3)]).unwrap();
+        counter.merge_saturating(&other).unwrap();
+        assert_eq!(counter.get("icdm"), u64::MAX);
+        assert_eq!(counter.get("idce"), 1);
+        assert_eq!(counter.get("sigir"), 3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty() {
+        let keys = ["icdm"];
+        CounterMap::new(&keys[0..0]).unwrap();
+    }
+}
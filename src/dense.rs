@@ -0,0 +1,603 @@
+//! Simple fast hash map implementation storing values out of line.
+
+#[cfg(all(feature = "bytemuck", target_endian = "little"))]
+use std::io::{Read, Write};
+
+#[cfg(all(feature = "bytemuck", target_endian = "little"))]
+use crate::serial::{self, SectionKind, Sections};
+use std::cmp::Ordering;
+
+use crate::{HashMapBuilder, KeyLen, Node, Table};
+
+#[cfg(all(feature = "bytemuck", target_endian = "little"))]
+use anyhow::anyhow;
+use anyhow::Result;
+
+#[derive(Default, Clone)]
+pub(crate) struct DenseNode<L> {
+    ptr: usize,
+    len: L,
+    pub(crate) idx: u32,
+}
+
+impl<L> Node for DenseNode<L>
+where
+    L: KeyLen,
+{
+    type Len = L;
+
+    fn new(ptr: usize, len: L) -> Self {
+        Self { ptr, len, idx: 0 }
+    }
+
+    #[inline(always)]
+    fn ptr(&self) -> usize {
+        self.ptr
+    }
+
+    #[inline(always)]
+    fn set_ptr(&mut self, ptr: usize) {
+        self.ptr = ptr;
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.len.to_usize()
+    }
+}
+
+/// Simple fast hash map implementation storing values out of line.
+///
+/// Unlike [`HashMap`](crate::HashMap), which stores values in the slots,
+/// this stores values in a dense array in the input order and
+/// each slot only keeps a 32-bit index to its value.
+/// The slot size is therefore independent of the value size,
+/// which saves memory for large values since empty slots do not reserve space for them.
+///
+/// It is built by [`DenseHashMap::new`] or [`HashMapBuilder::build_dense`].
+#[derive(Clone)]
+pub struct DenseHashMap<V, L = usize>
+where
+    L: KeyLen,
+{
+    pub(crate) table: Table<DenseNode<L>>,
+    pub(crate) values: Vec<V>,
+}
+
+impl<V> DenseHashMap<V>
+where
+    V: Clone,
+{
+    /// Creates a new [`DenseHashMap`] from input records.
+    ///
+    /// # Arguments
+    ///
+    /// - `records`: List of key-value pairs.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `records` is empty,
+    ///  - `records` contains duplicate keys, or
+    ///  - `records` has more than 2^32 elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::DenseHashMap;
+    ///
+    /// let records = vec![("icdm", [0; 64]), ("idce", [1; 64]), ("sigmod", [2; 64])];
+    /// let map = DenseHashMap::new(&records).unwrap();
+    /// assert_eq!(map.get("idce"), Some(&[1; 64]));
+    /// assert_eq!(map.get("sigir"), None);
+    /// ```
+    pub fn new<K>(records: &[(K, V)]) -> Result<Self>
+    where
+        K: AsRef<[u8]>,
+    {
+        HashMapBuilder::new().build_dense(records)
+    }
+}
+
+impl<V, L> DenseHashMap<V, L>
+where
+    L: KeyLen,
+{
+    /// Returns true if the map contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::DenseHashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = DenseHashMap::new(&records).unwrap();
+    /// assert_eq!(map.contains_key("idce"), true);
+    /// assert_eq!(map.contains_key("sigir"), false);
+    /// ```
+    #[inline(always)]
+    pub fn contains_key<K>(&self, key: K) -> bool
+    where
+        K: AsRef<[u8]>,
+    {
+        self.table.get(key).is_some()
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::DenseHashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = DenseHashMap::new(&records).unwrap();
+    /// assert_eq!(map.get("idce"), Some(&1));
+    /// assert_eq!(map.get("sigir"), None);
+    /// ```
+    #[inline(always)]
+    pub fn get<K>(&self, key: K) -> Option<&V>
+    where
+        K: AsRef<[u8]>,
+    {
+        self.table.get(key).map(|nd| &self.values[nd.idx as usize])
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::DenseHashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let mut map = DenseHashMap::new(&records).unwrap();
+    /// *map.get_mut("idce").unwrap() = 3;
+    /// assert_eq!(map.get("idce"), Some(&3));
+    /// ```
+    #[inline(always)]
+    pub fn get_mut<K>(&mut self, key: K) -> Option<&mut V>
+    where
+        K: AsRef<[u8]>,
+    {
+        let idx = self.table.get(key)?.idx as usize;
+        Some(&mut self.values[idx])
+    }
+
+    /// Returns the values in the input order of the records.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::DenseHashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = DenseHashMap::new(&records).unwrap();
+    /// assert_eq!(map.values(), &[0, 1, 2]);
+    /// ```
+    #[inline(always)]
+    pub fn values(&self) -> &[V] {
+        &self.values
+    }
+
+    /// Iterates over the keys and values in ascending order of the values.
+    ///
+    /// Only an array of slot positions is sorted, so the keys are not cloned.
+    /// Entries with equal values are returned in the input order of the records.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::DenseHashMap;
+    ///
+    /// let records = vec![("icdm", 2), ("idce", 0), ("sigmod", 1)];
+    /// let map = DenseHashMap::new(&records).unwrap();
+    /// let entries: Vec<_> = map.iter_sorted_by_value().collect();
+    /// assert_eq!(
+    ///     entries,
+    ///     vec![(&b"idce"[..], &0), (&b"sigmod"[..], &1), (&b"icdm"[..], &2)]
+    /// );
+    /// ```
+    pub fn iter_sorted_by_value(&self) -> SortedByValue<'_, V, L>
+    where
+        V: Ord,
+    {
+        self.sorted_by(|a, b| a.cmp(b))
+    }
+
+    /// Iterates over the keys and values in descending order of the values,
+    /// such as for dumping a dictionary of frequencies from the most frequent key.
+    ///
+    /// Entries with equal values are returned in the input order of the records.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::DenseHashMap;
+    ///
+    /// let records = vec![("icdm", 2), ("idce", 0), ("sigmod", 1)];
+    /// let map = DenseHashMap::new(&records).unwrap();
+    /// let keys: Vec<_> = map.iter_sorted_by_value_desc().map(|(k, _)| k).collect();
+    /// assert_eq!(keys, vec![&b"icdm"[..], &b"sigmod"[..], &b"idce"[..]]);
+    /// ```
+    pub fn iter_sorted_by_value_desc(&self) -> SortedByValue<'_, V, L>
+    where
+        V: Ord,
+    {
+        self.sorted_by(|a, b| b.cmp(a))
+    }
+
+    fn sorted_by<F>(&self, mut cmp: F) -> SortedByValue<'_, V, L>
+    where
+        F: FnMut(&V, &V) -> Ordering,
+    {
+        let nodes = &self.table.nodes;
+        let mut positions: Vec<_> = (0..nodes.len())
+            .filter(|&pos| nodes[pos].is_occupied())
+            .collect();
+        positions.sort_unstable_by(|&a, &b| {
+            let (a, b) = (nodes[a].idx, nodes[b].idx);
+            cmp(&self.values[a as usize], &self.values[b as usize]).then(a.cmp(&b))
+        });
+        SortedByValue {
+            map: self,
+            positions: positions.into_iter(),
+        }
+    }
+
+    /// Returns the number of elements in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::DenseHashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = DenseHashMap::new(&records).unwrap();
+    /// assert_eq!(map.len(), 3);
+    /// ```
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.table.num_keys()
+    }
+
+    /// Returns true if the map contains no elements.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of slots allocated in the map.
+    #[inline(always)]
+    pub fn capacity(&self) -> usize {
+        self.table.capacity()
+    }
+
+    /// Returns the ratio of the number of elements to the number of slots.
+    #[inline(always)]
+    pub fn load_factor(&self) -> f64 {
+        self.table.load_factor()
+    }
+
+    /// Returns the statistics of the lookups recorded since the map was built
+    /// or the statistics were last reset.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::DenseHashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = DenseHashMap::new(&records).unwrap();
+    /// assert_eq!((map.get("idce"), map.get("sigir")), (Some(&1), None));
+    /// let stats = map.probe_stats();
+    /// assert_eq!((stats.hits, stats.misses), (1, 1));
+    /// assert!(stats.probes >= 2);
+    /// ```
+    #[cfg(feature = "instrument")]
+    pub fn probe_stats(&self) -> crate::ProbeStats {
+        self.table.probe_counters.snapshot()
+    }
+
+    /// Resets the statistics of the recorded lookups.
+    #[cfg(feature = "instrument")]
+    pub fn reset_probe_stats(&self) {
+        self.table.probe_counters.reset();
+    }
+}
+
+/// Serialization of plain-old-data values, which are written and read as a raw array
+/// instead of being encoded one by one, so that loading is bound by I/O.
+///
+/// The values are stored in the native byte order,
+/// so this is only available on little-endian targets
+/// to keep the [sectioned format](crate::serial) little endian.
+#[cfg(all(feature = "bytemuck", target_endian = "little"))]
+impl<V, L> DenseHashMap<V, L>
+where
+    V: bytemuck::Pod,
+    L: KeyLen,
+{
+    /// Serializes the map into a byte vector in the [sectioned format](crate::serial).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::DenseHashMap;
+    ///
+    /// let records = vec![("icdm", [0.5f32; 4]), ("idce", [1.5; 4]), ("sigmod", [2.5; 4])];
+    /// let map = DenseHashMap::new(&records).unwrap();
+    /// let data = map.serialize_to_vec();
+    /// let other = DenseHashMap::<[f32; 4]>::deserialize_from_slice(&data).unwrap();
+    /// assert_eq!(other.get("idce"), Some(&[1.5; 4]));
+    /// assert_eq!(other.values(), map.values());
+    /// ```
+    pub fn serialize_to_vec(&self) -> Vec<u8> {
+        let mut dest = vec![];
+        // Writing into a vector never fails.
+        self.serialize_into(&mut dest).unwrap();
+        dest
+    }
+
+    /// Serializes the map into `wtr` in the [sectioned format](crate::serial).
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when writing fails.
+    pub fn serialize_into<W>(&self, wtr: W) -> Result<()>
+    where
+        W: Write,
+    {
+        let (header, nodes) = self.table.serialize_sections();
+        let values: &[u8] = bytemuck::cast_slice(&self.values);
+        let indexes_len = (4 * self.values.len()).next_multiple_of(8);
+        let mut body = Vec::with_capacity(16 + indexes_len + values.len());
+        body.extend_from_slice(&(self.values.len() as u64).to_le_bytes());
+        body.extend_from_slice(&(std::mem::size_of::<V>() as u64).to_le_bytes());
+        for node in self.table.occupied() {
+            body.extend_from_slice(&node.idx.to_le_bytes());
+        }
+        body.resize(16 + indexes_len, 0);
+        body.extend_from_slice(values);
+        serial::write_sections(
+            wtr,
+            &[
+                (SectionKind::Header as u32, &header),
+                (SectionKind::Nodes as u32, &nodes),
+                (SectionKind::Keys as u32, &self.table.bytes),
+                (SectionKind::PodValues as u32, &body),
+            ],
+        )
+    }
+
+    /// Deserializes a map from `source` in the [sectioned format](crate::serial).
+    ///
+    /// The values are copied at once into an aligned array.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when `source` is broken,
+    /// its values have a different size from `V`,
+    /// or it contains a key whose length is out of the range of `L`.
+    pub fn deserialize_from_slice(source: &[u8]) -> Result<Self> {
+        let sections = Sections::parse(source)?;
+        let mut table = Table::<DenseNode<L>>::deserialize_sections(&sections)?;
+        let mut body = sections.require(SectionKind::PodValues)?;
+        let count = serial::to_usize(serial::read_u64(&mut body)?)?;
+        let size = serial::read_u64(&mut body)?;
+        if size != std::mem::size_of::<V>() as u64 {
+            return Err(anyhow!(
+                "The size of the values does not match the value type."
+            ));
+        }
+        if count != table.num_keys() {
+            return Err(anyhow!("The values section is broken."));
+        }
+        // The number of keys is bounded by the length of the nodes section.
+        let indexes_len = (4 * count).next_multiple_of(8);
+        let values_len = count.checked_mul(std::mem::size_of::<V>());
+        if values_len.and_then(|n| n.checked_add(indexes_len)) != Some(body.len()) {
+            return Err(anyhow!("The values section is broken."));
+        }
+        let (mut indexes, body) = body.split_at(indexes_len);
+        for node in table.occupied_mut() {
+            node.idx = serial::read_u32(&mut indexes)?;
+            if node.idx as usize >= count {
+                return Err(anyhow!("The values section is broken."));
+            }
+        }
+        let mut values: Vec<V> = vec![bytemuck::Zeroable::zeroed(); count];
+        bytemuck::cast_slice_mut::<V, u8>(&mut values).copy_from_slice(body);
+        Ok(Self { table, values })
+    }
+
+    /// Deserializes a map from `rdr` in the [sectioned format](crate::serial).
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when reading fails or the data is broken.
+    pub fn deserialize_from<R>(mut rdr: R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let mut source = vec![];
+        rdr.read_to_end(&mut source)?;
+        Self::deserialize_from_slice(&source)
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<V, L> Drop for DenseHashMap<V, L>
+where
+    L: KeyLen,
+{
+    fn drop(&mut self) {
+        crate::wipe(&mut self.values);
+    }
+}
+
+/// Iterator created by [`DenseHashMap::iter_sorted_by_value`]
+/// and [`DenseHashMap::iter_sorted_by_value_desc`].
+pub struct SortedByValue<'a, V, L>
+where
+    L: KeyLen,
+{
+    map: &'a DenseHashMap<V, L>,
+    positions: std::vec::IntoIter<usize>,
+}
+
+impl<'a, V, L> Iterator for SortedByValue<'a, V, L>
+where
+    L: KeyLen,
+{
+    type Item = (&'a [u8], &'a V);
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        let pos = self.positions.next()?;
+        let (key, nd) = self.map.table.entry_at(pos);
+        Some((key, &self.map.values[nd.idx as usize]))
+    }
+
+    #[inline(always)]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.positions.size_hint()
+    }
+}
+
+impl<V, L> DoubleEndedIterator for SortedByValue<'_, V, L>
+where
+    L: KeyLen,
+{
+    #[inline(always)]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let pos = self.positions.next_back()?;
+        let (key, nd) = self.map.table.entry_at(pos);
+        Some((key, &self.map.values[nd.idx as usize]))
+    }
+}
+
+impl<V, L> ExactSizeIterator for SortedByValue<'_, V, L> where L: KeyLen {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let map = DenseHashMap::new(&records).unwrap();
+        assert_eq!(map.len(), 6);
+        for &(k, v) in &records {
+            assert_eq!(*map.get(k).unwrap(), v);
+        }
+        assert_eq!(map.get("sigkdd"), None);
+        assert_eq!(map.values(), &[0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_get_mut() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let mut map = DenseHashMap::new(&records).unwrap();
+        for &(k, v) in &records {
+            *map.get_mut(k).unwrap() = v * 3;
+        }
+        for &(k, v) in &records {
+            assert_eq!(*map.get(k).unwrap(), v * 3);
+        }
+    }
+
+    #[test]
+    fn test_iter_sorted_by_value() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let freqs = [3, 1, 4, 1, 5, 9];
+        let records: Vec<_> = keys.iter().zip(freqs).collect();
+        let map = DenseHashMap::new(&records).unwrap();
+
+        let entries: Vec<_> = map.iter_sorted_by_value().collect();
+        let expected = [
+            ("idce", 1),
+            ("sigmod", 1),
+            ("icdm", 3),
+            ("", 4),
+            ("sigir", 5),
+            ("acl", 9),
+        ];
+        assert_eq!(entries.len(), expected.len());
+        for ((k, v), (ek, ev)) in entries.into_iter().zip(expected) {
+            assert_eq!((k, *v), (ek.as_bytes(), ev));
+        }
+
+        let mut iter = map.iter_sorted_by_value_desc();
+        assert_eq!(iter.len(), 6);
+        assert_eq!(iter.next(), Some((&b"acl"[..], &9)));
+        assert_eq!(iter.next_back(), Some((&b"sigmod"[..], &1)));
+        assert_eq!(iter.next_back(), Some((&b"idce"[..], &1)));
+        assert_eq!(iter.len(), 3);
+    }
+
+    #[test]
+    fn test_slot_size() {
+        use crate::map::MapNode;
+        use std::mem::size_of;
+
+        assert!(size_of::<DenseNode<u32>>() <= 16);
+        assert!(size_of::<DenseNode<u32>>() < size_of::<MapNode<[u8; 64], u32>>());
+    }
+
+    #[test]
+    fn test_non_default_value() {
+        #[derive(Clone)]
+        struct NoDefault(u32);
+        let records = [("icdm", NoDefault(3))];
+        let map = DenseHashMap::new(&records).unwrap();
+        assert_eq!(map.get("icdm").unwrap().0, 3);
+    }
+
+    #[cfg(all(feature = "bytemuck", target_endian = "little"))]
+    #[test]
+    fn test_serialize_pod() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, k)| (k, [i as u16; 3]))
+            .collect();
+        let map = HashMapBuilder::new()
+            .key_len::<u8>()
+            .build_dense(&records)
+            .unwrap();
+        let data = map.serialize_to_vec();
+        let other = DenseHashMap::<[u16; 3], u8>::deserialize_from(data.as_slice()).unwrap();
+        assert_eq!(other.values(), map.values());
+        for (k, v) in &records {
+            assert_eq!(other.get(k), Some(v));
+        }
+        assert_eq!(other.get("sigkdd"), None);
+
+        // The values must have the same size, and the data must be complete.
+        assert!(DenseHashMap::<u32, u8>::deserialize_from_slice(&data).is_err());
+        assert!(
+            DenseHashMap::<[u16; 3], u8>::deserialize_from_slice(&data[..data.len() - 2]).is_err()
+        );
+        let map = crate::HashMap::new(&[("icdm", 0u32)]).unwrap();
+        assert!(DenseHashMap::<u32>::deserialize_from_slice(&map.serialize_to_vec()).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty() {
+        let records: [(&str, u32); 0] = [];
+        DenseHashMap::new(&records).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_duplicate() {
+        let records = [("icdm", 0), ("icdm", 1)];
+        DenseHashMap::new(&records).unwrap();
+    }
+}
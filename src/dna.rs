@@ -0,0 +1,260 @@
+//! Packed encoding of DNA sequences into keys, enabled by the `dna` feature.
+//!
+//! Each of the bases `A`, `C`, `G`, and `T` is encoded into 2 bits, packing 4 bases per byte,
+//! so that dictionaries of k-mers use a quarter of the key memory.
+//! Bases are case-insensitive, and the first base is stored in the highest bits
+//! so that packed keys of the same length are ordered as their sequences.
+
+use crate::Keyable;
+
+use anyhow::{anyhow, Result};
+
+const INVALID: u8 = u8::MAX;
+const BASES: [u8; 4] = *b"ACGT";
+
+const CODES: [u8; 256] = {
+    let mut codes = [INVALID; 256];
+    let mut i = 0;
+    while i < BASES.len() {
+        codes[BASES[i] as usize] = i as u8;
+        codes[BASES[i].to_ascii_lowercase() as usize] = i as u8;
+        i += 1;
+    }
+    codes
+};
+
+/// Returns an error if `seq` contains a byte other than the bases.
+fn validate(seq: &[u8]) -> Result<()> {
+    match seq.iter().find(|&&b| CODES[b as usize] == INVALID) {
+        Some(&b) => Err(anyhow!(
+            "The base {:?} is not one of A, C, G, and T.",
+            b as char
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Appends the bases of `seq`, which must be valid, packed 4 per byte.
+#[inline(always)]
+fn pack(seq: &[u8], buf: &mut Vec<u8>) {
+    let mut chunks = seq.chunks_exact(4);
+    for c in &mut chunks {
+        buf.push(
+            CODES[c[0] as usize] << 6
+                | CODES[c[1] as usize] << 4
+                | CODES[c[2] as usize] << 2
+                | CODES[c[3] as usize],
+        );
+    }
+    let rem = chunks.remainder();
+    if !rem.is_empty() {
+        let mut byte = 0;
+        for (i, &b) in rem.iter().enumerate() {
+            byte |= CODES[b as usize] << (6 - 2 * i);
+        }
+        buf.push(byte);
+    }
+}
+
+/// Returns the first `len` bases packed in `packed`, which must have `len.div_ceil(4)` bytes.
+fn unpack(packed: &[u8], len: usize) -> Vec<u8> {
+    (0..len)
+        .map(|i| BASES[usize::from(packed[i / 4] >> (6 - 2 * (i % 4))) & 3])
+        .collect()
+}
+
+/// DNA sequence of any length that is encoded into a key of 2 bits per base.
+///
+/// The key ends with a byte holding the length modulo 4,
+/// so that sequences differing only in trailing `A`s do not collide.
+/// For keys of a fixed length, [`Kmer`] saves the byte.
+///
+/// # Examples
+///
+/// ```
+/// use simplearrayhash::dna::Dna;
+/// use simplearrayhash::HashMap;
+///
+/// let records = vec![
+///     (Dna::new("ACGTAC").unwrap(), 0),
+///     (Dna::new("ACGTACA").unwrap(), 1),
+///     (Dna::new("gattaca").unwrap(), 2),
+/// ];
+/// let map = HashMap::new_keyable(&records).unwrap();
+/// assert_eq!(map.get_keyable(&Dna::new("ACGTACA").unwrap()), Some(&1));
+/// assert_eq!(map.get_keyable(&Dna::new("GATTACA").unwrap()), Some(&2));
+/// assert_eq!(map.get_keyable(&Dna::new("ACGTACAA").unwrap()), None);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Dna<'a>(&'a [u8]);
+
+impl<'a> Dna<'a> {
+    /// Creates a sequence to be encoded.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when `seq` contains a byte other than `ACGTacgt`.
+    pub fn new<S>(seq: &'a S) -> Result<Self>
+    where
+        S: AsRef<[u8]> + ?Sized,
+    {
+        let seq = seq.as_ref();
+        validate(seq)?;
+        Ok(Self(seq))
+    }
+
+    /// Decodes a key encoded from a sequence into the sequence in upper case.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when `key` is not an encoded sequence.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::dna::Dna;
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let map = HashMap::new_keyable(&[(Dna::new("gattaca").unwrap(), 0)]).unwrap();
+    /// let (_, key, _) = map.slots().next().unwrap();
+    /// assert_eq!(key.len(), 3);
+    /// assert_eq!(Dna::decode(key).unwrap(), b"GATTACA");
+    /// ```
+    pub fn decode(key: &[u8]) -> Result<Vec<u8>> {
+        let (&rem, packed) = key
+            .split_last()
+            .filter(|&(&rem, packed)| rem < 4 && (rem == 0 || !packed.is_empty()))
+            .ok_or_else(|| anyhow!("The key is not an encoded sequence."))?;
+        let len = packed.len() * 4 - (4 - usize::from(rem)) % 4;
+        Ok(unpack(packed, len))
+    }
+}
+
+impl Keyable for Dna<'_> {
+    #[inline(always)]
+    fn encode_key(&self, buf: &mut Vec<u8>) {
+        pack(self.0, buf);
+        buf.push((self.0.len() % 4) as u8);
+    }
+}
+
+/// DNA sequence of exactly `K` bases that is encoded into a key of `K.div_ceil(4)` bytes.
+///
+/// Since all the keys have the same length, no length is stored,
+/// which makes this the fast path for dictionaries of k-mers.
+///
+/// # Examples
+///
+/// ```
+/// use simplearrayhash::dna::Kmer;
+/// use simplearrayhash::HashMap;
+///
+/// let records = vec![
+///     (Kmer::<5>::new("ACGTA").unwrap(), 0),
+///     (Kmer::<5>::new("CGTAC").unwrap(), 1),
+///     (Kmer::<5>::new("GTACG").unwrap(), 2),
+/// ];
+/// let map = HashMap::new_keyable(&records).unwrap();
+/// assert_eq!(map.get_keyable(&Kmer::<5>::new("cgtac").unwrap()), Some(&1));
+/// assert_eq!(map.get_keyable(&Kmer::<5>::new("TACGT").unwrap()), None);
+/// assert!(Kmer::<5>::new("ACGT").is_err());
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Kmer<'a, const K: usize>(&'a [u8]);
+
+impl<'a, const K: usize> Kmer<'a, K> {
+    /// Creates a k-mer to be encoded.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when `seq` does not have `K` bases
+    /// or contains a byte other than `ACGTacgt`.
+    pub fn new<S>(seq: &'a S) -> Result<Self>
+    where
+        S: AsRef<[u8]> + ?Sized,
+    {
+        let seq = seq.as_ref();
+        if seq.len() != K {
+            return Err(anyhow!("The k-mer must have {K} bases."));
+        }
+        validate(seq)?;
+        Ok(Self(seq))
+    }
+
+    /// Decodes a key encoded from a k-mer into the k-mer in upper case.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when `key` does not have `K.div_ceil(4)` bytes.
+    pub fn decode(key: &[u8]) -> Result<Vec<u8>> {
+        if key.len() != K.div_ceil(4) {
+            return Err(anyhow!("The key is not an encoded k-mer."));
+        }
+        Ok(unpack(key, K))
+    }
+}
+
+impl<const K: usize> Keyable for Kmer<'_, K> {
+    #[inline(always)]
+    fn encode_key(&self, buf: &mut Vec<u8>) {
+        pack(&self.0[..K], buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode<T>(key: &T) -> Vec<u8>
+    where
+        T: Keyable,
+    {
+        let mut buf = vec![];
+        key.encode_key(&mut buf);
+        buf
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode(&Dna::new("").unwrap()), vec![0]);
+        assert_eq!(encode(&Dna::new("A").unwrap()), vec![0, 1]);
+        assert_eq!(encode(&Dna::new("AAAA").unwrap()), vec![0, 0]);
+        assert_eq!(encode(&Dna::new("ACGT").unwrap()), vec![0b00011011, 0]);
+        assert_eq!(
+            encode(&Dna::new("acgtT").unwrap()),
+            vec![0b00011011, 0b11000000, 1]
+        );
+        assert_eq!(encode(&Kmer::<4>::new("ACGT").unwrap()), vec![0b00011011]);
+        assert_eq!(
+            encode(&Kmer::<5>::new("ACGTT").unwrap()),
+            vec![0b00011011, 0b11000000]
+        );
+        assert!(Dna::new("ACGN").is_err());
+        assert!(Kmer::<3>::new("AC-").is_err());
+        assert!(Kmer::<3>::new("ACGT").is_err());
+    }
+
+    #[test]
+    fn test_decode() {
+        for seq in ["", "A", "AA", "AAA", "AAAA", "GATTACA", "CCCCGGGGTT"] {
+            let key = encode(&Dna::new(seq).unwrap());
+            assert_eq!(Dna::decode(&key).unwrap(), seq.as_bytes());
+        }
+        assert!(Dna::decode(&[]).is_err());
+        assert!(Dna::decode(&[1]).is_err());
+        assert!(Dna::decode(&[0, 4]).is_err());
+        let key = encode(&Kmer::<7>::new("gattaca").unwrap());
+        assert_eq!(Kmer::<7>::decode(&key).unwrap(), b"GATTACA");
+        assert!(Kmer::<8>::decode(&[0]).is_err());
+    }
+
+    #[test]
+    fn test_order() {
+        let seqs = ["AAAC", "ACGT", "CAAA", "GGGG", "TTTT"];
+        let keys: Vec<_> = seqs
+            .iter()
+            .map(|s| encode(&Kmer::<4>::new(s).unwrap()))
+            .collect();
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+    }
+}
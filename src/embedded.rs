@@ -0,0 +1,297 @@
+//! Zero-copy hash map over a serialized dictionary, such as one embedded by [`include_bytes!`].
+
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::OnceLock;
+
+use crate::serial::{self, Header, SectionKind, Sections, Serializable, Slot, ValuesIndex};
+use crate::{constant_time_eq, hash_key, home_slot, next_slot};
+
+use anyhow::{anyhow, Result};
+
+/// Hash map looking up keys directly in the bytes of a serialized dictionary.
+///
+/// It opens data written by [`HashMap::serialize_to_vec`](crate::HashMap::serialize_to_vec)
+/// without copying the slots or keys: only a bitmap of the occupied slots is built,
+/// which locates the value of each slot.
+/// [`EmbeddedHashMap::get`] deserializes the value of the key on each call.
+///
+/// # Examples
+///
+/// ```
+/// use simplearrayhash::embedded::EmbeddedHashMap;
+/// use simplearrayhash::HashMap;
+///
+/// let records = vec![("icdm", 0u32), ("idce", 1), ("sigmod", 2)];
+/// let data = HashMap::new(&records).unwrap().serialize_to_vec();
+///
+/// let map = EmbeddedHashMap::<u32>::new(&data).unwrap();
+/// assert_eq!(map.get("idce").unwrap(), Some(1));
+/// assert_eq!(map.get("sigir").unwrap(), None);
+/// ```
+pub struct EmbeddedHashMap<'a, V> {
+    nodes: &'a [u8],
+    keys: &'a [u8],
+    values: ValuesIndex<'a>,
+    // Bits of the occupied slots, with the number of occupied slots before each word,
+    // since values are stored in slot order.
+    occupied: Vec<u64>,
+    ranks: Vec<usize>,
+    num_keys: usize,
+    capacity: usize,
+    seed: u64,
+    constant_time: bool,
+    stash_len: usize,
+    probe_limit: usize,
+    _val: PhantomData<fn() -> V>,
+}
+
+impl<'a, V> EmbeddedHashMap<'a, V> {
+    /// Opens a serialized dictionary in `data` without copying it.
+    ///
+    /// # Arguments
+    ///
+    /// - `data`: Data in the [sectioned format](crate::serial) written by a [`HashMap`](crate::HashMap).
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the data is broken or has no values.
+    pub fn new(data: &'a [u8]) -> Result<Self> {
+        let sections = Sections::parse(data)?;
+        let header = Header::parse(&sections)?;
+        let mut occupied = vec![0u64; header.capacity.div_ceil(64)];
+        let mut pos = 0;
+        serial::decode_slots(&header, &sections, |slot| {
+            if let Slot::Key { .. } = slot {
+                occupied[pos / 64] |= 1 << (pos % 64);
+            }
+            pos += 1;
+            Ok(())
+        })?;
+        let mut ranks = Vec::with_capacity(occupied.len());
+        let mut rank = 0;
+        for word in &occupied {
+            ranks.push(rank);
+            rank += word.count_ones() as usize;
+        }
+        let values = ValuesIndex::parse(sections.require(SectionKind::Values)?)?;
+        if values.len() != header.num_keys {
+            return Err(anyhow!("The values section is broken."));
+        }
+        Ok(Self {
+            nodes: sections.require(SectionKind::Nodes)?,
+            keys: sections.require(SectionKind::Keys)?,
+            values,
+            occupied,
+            ranks,
+            num_keys: header.num_keys,
+            capacity: header.capacity,
+            seed: header.seed,
+            constant_time: header.constant_time,
+            stash_len: header.stash_len,
+            probe_limit: header.probe_limit,
+            _val: PhantomData,
+        })
+    }
+
+    /// Deserializes and returns the value corresponding to the key.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the value is broken.
+    pub fn get<K>(&self, key: K) -> Result<Option<V>>
+    where
+        K: AsRef<[u8]>,
+        V: Serializable,
+    {
+        let Some(pos) = self.find(key.as_ref()) else {
+            return Ok(None);
+        };
+        let (q, r) = (pos / 64, pos % 64);
+        let idx = self.ranks[q] + (self.occupied[q] & ((1u64 << r) - 1)).count_ones() as usize;
+        self.values.get(idx).map(Some)
+    }
+
+    /// Returns true if the map contains the key.
+    pub fn contains_key<K>(&self, key: K) -> bool
+    where
+        K: AsRef<[u8]>,
+    {
+        self.find(key.as_ref()).is_some()
+    }
+
+    /// Returns the number of keys stored.
+    #[inline(always)]
+    pub const fn len(&self) -> usize {
+        self.num_keys
+    }
+
+    /// Returns true if the map contains no keys.
+    #[inline(always)]
+    pub const fn is_empty(&self) -> bool {
+        self.num_keys == 0
+    }
+
+    /// Returns the slot position of `key` in the same manner as the table it was serialized from.
+    fn find(&self, key: &[u8]) -> Option<usize> {
+        let primary_len = self.capacity - self.stash_len;
+        let mut pos = home_slot(hash_key(key, self.seed), primary_len);
+        let mut probes = 0;
+        while probes < self.probe_limit {
+            match Slot::decode(self.nodes, pos) {
+                Slot::Vacant => break,
+                Slot::Tombstone => {}
+                Slot::Key { ptr, len } => {
+                    if self.key_eq(key, &self.keys[ptr..ptr + len]) {
+                        return Some(pos);
+                    }
+                }
+            }
+            pos = next_slot(pos, primary_len);
+            probes += 1;
+        }
+        (primary_len..self.capacity).find(|&pos| match Slot::decode(self.nodes, pos) {
+            Slot::Key { ptr, len } => self.key_eq(key, &self.keys[ptr..ptr + len]),
+            _ => false,
+        })
+    }
+
+    #[inline(always)]
+    fn key_eq(&self, key: &[u8], stored: &[u8]) -> bool {
+        if self.constant_time {
+            constant_time_eq(key, stored)
+        } else {
+            key == stored
+        }
+    }
+}
+
+/// Lazily opened [`EmbeddedHashMap`] over static data, which can be stored in a `static` item.
+///
+/// The data is parsed on the first access through [`Deref`],
+/// so a binary shipping a dictionary spends nothing on it at startup
+/// and needs no filesystem access.
+/// [`include_dictionary!`](crate::include_dictionary) creates one from a file.
+///
+/// # Panics
+///
+/// The first access panics if the data is broken,
+/// which is a bug of the binary embedding it.
+///
+/// # Examples
+///
+/// ```
+/// use simplearrayhash::embedded::StaticHashMap;
+/// use simplearrayhash::HashMap;
+///
+/// let records = vec![("icdm", 0u32), ("idce", 1), ("sigmod", 2)];
+/// let data: &'static [u8] = HashMap::new(&records).unwrap().serialize_to_vec().leak();
+///
+/// let dict = StaticHashMap::<u32>::new(data);
+/// assert_eq!(dict.get("sigmod").unwrap(), Some(2));
+/// assert_eq!(dict.len(), 3);
+/// ```
+pub struct StaticHashMap<V> {
+    data: &'static [u8],
+    map: OnceLock<EmbeddedHashMap<'static, V>>,
+}
+
+impl<V> StaticHashMap<V> {
+    /// Creates a map that opens `data` on the first access.
+    pub const fn new(data: &'static [u8]) -> Self {
+        Self {
+            data,
+            map: OnceLock::new(),
+        }
+    }
+}
+
+impl<V> Deref for StaticHashMap<V> {
+    type Target = EmbeddedHashMap<'static, V>;
+
+    fn deref(&self) -> &Self::Target {
+        self.map.get_or_init(|| {
+            EmbeddedHashMap::new(self.data)
+                .unwrap_or_else(|e| panic!("The embedded dictionary is broken: {e}"))
+        })
+    }
+}
+
+/// Embeds a serialized dictionary file into the binary by [`include_bytes!`],
+/// creating a [`StaticHashMap`](crate::embedded::StaticHashMap) opening it lazily.
+///
+/// The path is relative to the file invoking the macro, as in [`include_bytes!`].
+///
+/// # Examples
+///
+/// ```ignore
+/// use simplearrayhash::embedded::StaticHashMap;
+/// use simplearrayhash::include_dictionary;
+///
+/// static DICT: StaticHashMap<u32> = include_dictionary!("dict.bin");
+///
+/// fn lookup(word: &str) -> Option<u32> {
+///     DICT.get(word).unwrap()
+/// }
+/// ```
+#[macro_export]
+macro_rules! include_dictionary {
+    ($path:expr) => {
+        $crate::embedded::StaticHashMap::new(include_bytes!($path))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HashMap, HashMapBuilder};
+
+    #[test]
+    fn test_get() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, k)| (k, i.to_string()))
+            .collect();
+        let mut map = HashMap::new(&records).unwrap();
+        map.remove("sigir").unwrap();
+        let data = map.serialize_to_vec();
+        let other = EmbeddedHashMap::<String>::new(&data).unwrap();
+        assert_eq!(other.len(), 5);
+        for (k, _) in &records {
+            assert_eq!(other.get(k).unwrap().as_ref(), map.get(k));
+        }
+        assert!(!other.contains_key("sigkdd"));
+
+        let map = HashMapBuilder::new()
+            .align_keys(true)
+            .stash(4, 1)
+            .constant_time(true)
+            .build(&records)
+            .unwrap();
+        let data = map.serialize_to_vec();
+        let other = EmbeddedHashMap::<String>::new(&data).unwrap();
+        for (k, v) in &records {
+            assert_eq!(other.get(k).unwrap().as_ref(), Some(v));
+        }
+    }
+
+    #[test]
+    fn test_static() {
+        static DICT: StaticHashMap<u32> = StaticHashMap::new(&[]);
+        let data = HashMap::new(&[("icdm", 0u32)]).unwrap().serialize_to_vec();
+        let dict = StaticHashMap::<u32>::new(data.leak());
+        assert_eq!(dict.get("icdm").unwrap(), Some(0));
+        assert!(std::panic::catch_unwind(|| DICT.len()).is_err());
+    }
+
+    #[test]
+    fn test_broken() {
+        let set = crate::HashSet::new(&["icdm"]).unwrap();
+        assert!(EmbeddedHashMap::<u32>::new(&set.serialize_to_vec()).is_err());
+        let mut data = HashMap::new(&[("icdm", 0u32)]).unwrap().serialize_to_vec();
+        data.truncate(data.len() - 1);
+        assert!(EmbeddedHashMap::<u32>::new(&data).is_err());
+    }
+}
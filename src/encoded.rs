@@ -0,0 +1,319 @@
+//! Hash map implementation storing values encoded in a shared byte arena.
+
+use std::marker::PhantomData;
+
+use crate::dense::DenseNode;
+use crate::serial::Serializable;
+use crate::{HashMapBuilder, KeyLen, Table};
+
+use anyhow::Result;
+
+/// Codec converting values to and from bytes for [`EncodedHashMap`].
+///
+/// A codec may keep state, such as a dictionary for compressing values,
+/// and is kept in the map to decode values on access.
+///
+/// # Examples
+///
+/// A codec storing non-negative integers in variable-length bytes:
+///
+/// ```
+/// use simplearrayhash::{EncodedHashMap, ValueCodec};
+///
+/// struct VByte;
+///
+/// impl ValueCodec for VByte {
+///     type Value = u64;
+///
+///     fn encode(&self, value: &u64, dest: &mut Vec<u8>) {
+///         let mut x = *value;
+///         while x >= 0x80 {
+///             dest.push(x as u8 | 0x80);
+///             x >>= 7;
+///         }
+///         dest.push(x as u8);
+///     }
+///
+///     fn decode(&self, bytes: &[u8]) -> u64 {
+///         bytes.iter().rev().fold(0, |x, &b| x << 7 | u64::from(b & 0x7f))
+///     }
+/// }
+///
+/// let records = vec![("icdm", 0), ("idce", 300), ("sigmod", u64::MAX)];
+/// let map = EncodedHashMap::new(&records, VByte).unwrap();
+/// assert_eq!(map.get("idce"), Some(300));
+/// assert_eq!(map.get("sigmod"), Some(u64::MAX));
+/// assert_eq!(map.encoded_len(), 1 + 2 + 10);
+/// ```
+pub trait ValueCodec {
+    /// Type of the values.
+    type Value;
+
+    /// Appends the encoded value to `dest`.
+    fn encode(&self, value: &Self::Value, dest: &mut Vec<u8>);
+
+    /// Decodes a value from `bytes`, which were written by [`ValueCodec::encode`].
+    fn decode(&self, bytes: &[u8]) -> Self::Value;
+}
+
+/// Codec of values in the binary format of [`Serializable`].
+pub struct SerialCodec<V> {
+    _val: PhantomData<fn() -> V>,
+}
+
+impl<V> SerialCodec<V> {
+    /// Creates a new codec.
+    pub const fn new() -> Self {
+        Self { _val: PhantomData }
+    }
+}
+
+// Implemented by hand since deriving them would require the values to implement them.
+impl<V> Clone for SerialCodec<V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<V> Copy for SerialCodec<V> {}
+
+impl<V> Default for SerialCodec<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> ValueCodec for SerialCodec<V>
+where
+    V: Serializable,
+{
+    type Value = V;
+
+    fn encode(&self, value: &V, dest: &mut Vec<u8>) {
+        value.serialize_to_vec(dest);
+    }
+
+    fn decode(&self, bytes: &[u8]) -> V {
+        // The bytes were written by `serialize_to_vec`, so they are always valid.
+        V::deserialize_from_slice(bytes).unwrap().0
+    }
+}
+
+/// Hash map implementation storing values encoded by a [`ValueCodec`] in a shared byte arena.
+///
+/// The values are encoded when the map is built and decoded on each access,
+/// so they need not implement [`Clone`] or [`Default`].
+/// Each slot only keeps a 32-bit index to its value as in [`DenseHashMap`](crate::DenseHashMap),
+/// and the encoded values are packed back to back,
+/// which saves memory for variable-size values such as strings or compressed data.
+///
+/// It is built by [`EncodedHashMap::new`] or [`HashMapBuilder::build_encoded`].
+#[derive(Clone)]
+pub struct EncodedHashMap<C, L = usize>
+where
+    L: KeyLen,
+{
+    pub(crate) table: Table<DenseNode<L>>,
+    // Starting offsets of the encoded values in the input order, followed by the total length.
+    pub(crate) offsets: Vec<usize>,
+    pub(crate) values: Vec<u8>,
+    pub(crate) codec: C,
+}
+
+impl<C> EncodedHashMap<C>
+where
+    C: ValueCodec,
+{
+    /// Creates a new [`EncodedHashMap`] from input records, encoding the values by `codec`.
+    ///
+    /// # Arguments
+    ///
+    /// - `records`: List of key-value pairs.
+    /// - `codec`: Codec of the values.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `records` is empty,
+    ///  - `records` contains duplicate keys, or
+    ///  - `records` has more than 2^32 elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::{EncodedHashMap, SerialCodec};
+    ///
+    /// let records = vec![("icdm", "Data Mining".to_string()), ("sigmod", "Databases".to_string())];
+    /// let map = EncodedHashMap::new(&records, SerialCodec::new()).unwrap();
+    /// assert_eq!(map.get("sigmod").as_deref(), Some("Databases"));
+    /// assert_eq!(map.get("sigir"), None);
+    /// ```
+    pub fn new<K>(records: &[(K, C::Value)], codec: C) -> Result<Self>
+    where
+        K: AsRef<[u8]>,
+    {
+        HashMapBuilder::new().build_encoded(records, codec)
+    }
+}
+
+impl<C, L> EncodedHashMap<C, L>
+where
+    C: ValueCodec,
+    L: KeyLen,
+{
+    /// Returns true if the map contains a value for the specified key.
+    #[inline(always)]
+    pub fn contains_key<K>(&self, key: K) -> bool
+    where
+        K: AsRef<[u8]>,
+    {
+        self.table.get(key).is_some()
+    }
+
+    /// Decodes and returns the value corresponding to the key.
+    #[inline(always)]
+    pub fn get<K>(&self, key: K) -> Option<C::Value>
+    where
+        K: AsRef<[u8]>,
+    {
+        self.get_encoded(key).map(|bytes| self.codec.decode(bytes))
+    }
+
+    /// Returns the encoded bytes of the value corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::{EncodedHashMap, SerialCodec};
+    ///
+    /// let records = vec![("icdm", 0u16), ("idce", 1), ("sigmod", 2)];
+    /// let map = EncodedHashMap::new(&records, SerialCodec::new()).unwrap();
+    /// assert_eq!(map.get_encoded("idce"), Some(&[1, 0][..]));
+    /// ```
+    #[inline(always)]
+    pub fn get_encoded<K>(&self, key: K) -> Option<&[u8]>
+    where
+        K: AsRef<[u8]>,
+    {
+        let idx = self.table.get(key)?.idx as usize;
+        Some(&self.values[self.offsets[idx]..self.offsets[idx + 1]])
+    }
+
+    /// Iterates over the keys and decoded values in arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::{EncodedHashMap, SerialCodec};
+    ///
+    /// let records = vec![("icdm", 0u32), ("idce", 1), ("sigmod", 2)];
+    /// let map = EncodedHashMap::new(&records, SerialCodec::new()).unwrap();
+    /// let total: u32 = map.iter().map(|(_, v)| v).sum();
+    /// assert_eq!(total, 3);
+    /// ```
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&[u8], C::Value)> + ExactSizeIterator {
+        self.table.occupied().map(|node| {
+            let idx = node.idx as usize;
+            let bytes = &self.values[self.offsets[idx]..self.offsets[idx + 1]];
+            (self.table.get_bytes(node), self.codec.decode(bytes))
+        })
+    }
+
+    /// Returns the codec of the values.
+    #[inline(always)]
+    pub const fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Returns the total number of bytes of the encoded values.
+    #[inline(always)]
+    pub const fn encoded_len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns the number of elements in the map.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.table.num_keys()
+    }
+
+    /// Returns true if the map contains no elements.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of slots allocated in the map.
+    #[inline(always)]
+    pub fn capacity(&self) -> usize {
+        self.table.capacity()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Value that cannot be cloned.
+    #[derive(Debug, PartialEq, Eq)]
+    struct Title(String);
+
+    struct TitleCodec;
+
+    impl ValueCodec for TitleCodec {
+        type Value = Title;
+
+        fn encode(&self, value: &Title, dest: &mut Vec<u8>) {
+            dest.extend_from_slice(value.0.as_bytes());
+        }
+
+        fn decode(&self, bytes: &[u8]) -> Title {
+            Title(String::from_utf8(bytes.to_vec()).unwrap())
+        }
+    }
+
+    #[test]
+    fn test_get() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().map(|k| (k, Title(k.repeat(2)))).collect();
+        let map = EncodedHashMap::new(&records, TitleCodec).unwrap();
+        assert_eq!(map.len(), keys.len());
+        assert_eq!(
+            map.encoded_len(),
+            keys.iter().map(|k| k.len() * 2).sum::<usize>()
+        );
+        for (k, v) in &records {
+            assert_eq!(map.get(k).as_ref(), Some(v));
+            assert_eq!(map.get_encoded(k), Some(v.0.as_bytes()));
+        }
+        assert_eq!(map.get("ijcai"), None);
+        assert!(!map.contains_key("ijcai"));
+        assert_eq!(map.get_encoded(""), Some(&b""[..]));
+
+        let mut entries: Vec<_> = map.iter().map(|(k, v)| (k.to_vec(), v.0)).collect();
+        entries.sort();
+        let mut expected: Vec<_> = keys
+            .iter()
+            .map(|k| (k.as_bytes().to_vec(), k.repeat(2)))
+            .collect();
+        expected.sort();
+        assert_eq!(entries, expected);
+    }
+
+    #[test]
+    fn test_builder() {
+        let records = [("icdm", vec![0u8; 100]), ("idce", vec![1])];
+        let map = HashMapBuilder::new()
+            .key_len::<u8>()
+            .build_encoded(&records, SerialCodec::new())
+            .unwrap();
+        assert_eq!(map.get("icdm"), Some(vec![0; 100]));
+        assert_eq!(map.get("idce"), Some(vec![1]));
+        assert!(HashMapBuilder::new()
+            .build_encoded(&[("icdm", 0u8), ("icdm", 1)], SerialCodec::new())
+            .is_err());
+        let records: [(&str, u8); 0] = [];
+        assert!(EncodedHashMap::new(&records, SerialCodec::new()).is_err());
+    }
+}
@@ -0,0 +1,45 @@
+//! Errors that callers may want to tell apart from others.
+//!
+//! The errors are returned wrapped in [`anyhow::Error`],
+//! from which they can be recovered by [`anyhow::Error::downcast_ref`].
+
+use std::fmt;
+
+/// Error of a key longer than the limit specified by
+/// [`HashMapBuilder::key_len_limit`](crate::HashMapBuilder::key_len_limit).
+///
+/// # Examples
+///
+/// ```
+/// use simplearrayhash::{HashMapBuilder, KeyTooLong};
+///
+/// let records = vec![("icdm", 0), ("sigmod", 1)];
+/// let err = HashMapBuilder::new()
+///     .key_len_limit(4)
+///     .build(&records)
+///     .err()
+///     .unwrap();
+/// assert_eq!(
+///     err.downcast_ref::<KeyTooLong>(),
+///     Some(&KeyTooLong { len: 6, limit: 4 })
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeyTooLong {
+    /// Length of the rejected key in bytes.
+    pub len: usize,
+    /// Maximum length of keys in bytes.
+    pub limit: usize,
+}
+
+impl fmt::Display for KeyTooLong {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The key length {} must not exceed the limit {}.",
+            self.len, self.limit
+        )
+    }
+}
+
+impl std::error::Error for KeyTooLong {}
@@ -0,0 +1,123 @@
+//! Deletion-neighborhood index for lookups within edit distance 1.
+//!
+//! Every key is indexed by the hashes of itself and its variants with one byte deleted.
+//! Two byte strings within edit distance 1 always share one of these variants,
+//! so the candidates of a query are the keys sharing a variant with the query,
+//! which are then verified by computing the distance.
+
+use crate::{hash_key, Node, Table};
+
+use anyhow::{anyhow, Result};
+
+impl<N> Table<N>
+where
+    N: Default + Clone + Node,
+{
+    /// Builds the pairs of the hash of each key or its deletion variant and
+    /// the slot position of the key, sorted by the hash.
+    pub(crate) fn deletion_index(&self) -> Vec<(u64, usize)> {
+        let mut deletions = vec![];
+        let mut variant = vec![];
+        for (pos, node) in self.nodes.iter().enumerate() {
+            if !node.is_occupied() {
+                continue;
+            }
+            let key = self.get_bytes(node);
+            deletions.push((hash_key(key, self.seed), pos));
+            for i in 0..key.len() {
+                // Deleting any byte in a run produces the same variant.
+                if i > 0 && key[i - 1] == key[i] {
+                    continue;
+                }
+                set_deletion(&mut variant, key, i);
+                deletions.push((hash_key(&variant, self.seed), pos));
+            }
+        }
+        deletions.sort_unstable();
+        deletions.dedup();
+        deletions
+    }
+
+    /// Returns the edit distances and slot positions of the keys within edit distance 1
+    /// from `key`, in the ascending order of the distance and then of the key.
+    pub(crate) fn fuzzy_positions(&self, key: &[u8]) -> Result<Vec<(usize, usize)>> {
+        let deletions = self
+            .deletions
+            .as_deref()
+            .ok_or_else(|| anyhow!("The fuzzy index must be built to support fuzzy lookups."))?;
+        let mut candidates = vec![];
+        let mut collect = |variant: &[u8]| {
+            let hash = hash_key(variant, self.seed);
+            let lo = deletions.partition_point(|&(h, _)| h < hash);
+            for &(h, pos) in &deletions[lo..] {
+                if h != hash {
+                    break;
+                }
+                candidates.push(pos);
+            }
+        };
+        collect(key);
+        let mut variant = vec![];
+        for i in 0..key.len() {
+            if i > 0 && key[i - 1] == key[i] {
+                continue;
+            }
+            set_deletion(&mut variant, key, i);
+            collect(&variant);
+        }
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        let mut matches: Vec<_> = candidates
+            .into_iter()
+            .filter_map(|pos| {
+                let (other, _) = self.entry_at(pos);
+                edit_distance_within_one(key, other).map(|dist| (dist, pos))
+            })
+            .collect();
+        matches.sort_unstable_by(|&(d1, p1), &(d2, p2)| {
+            d1.cmp(&d2)
+                .then_with(|| self.entry_at(p1).0.cmp(self.entry_at(p2).0))
+        });
+        Ok(matches)
+    }
+}
+
+/// Sets `key` with the `i`-th byte deleted to `variant`.
+fn set_deletion(variant: &mut Vec<u8>, key: &[u8], i: usize) {
+    variant.clear();
+    variant.extend_from_slice(&key[..i]);
+    variant.extend_from_slice(&key[i + 1..]);
+}
+
+/// Returns the edit distance between `a` and `b` if it is at most 1.
+fn edit_distance_within_one(a: &[u8], b: &[u8]) -> Option<usize> {
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    if long.len() - short.len() > 1 {
+        return None;
+    }
+    let prefix = short.iter().zip(long).take_while(|(x, y)| x == y).count();
+    if prefix == long.len() {
+        return Some(0);
+    }
+    let skip = usize::from(short.len() == long.len());
+    (short[prefix + skip..] == long[prefix + 1..]).then_some(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance_within_one() {
+        assert_eq!(edit_distance_within_one(b"", b""), Some(0));
+        assert_eq!(edit_distance_within_one(b"icdm", b"icdm"), Some(0));
+        assert_eq!(edit_distance_within_one(b"icdm", b"icm"), Some(1));
+        assert_eq!(edit_distance_within_one(b"icm", b"icdm"), Some(1));
+        assert_eq!(edit_distance_within_one(b"icdm", b"icpm"), Some(1));
+        assert_eq!(edit_distance_within_one(b"", b"a"), Some(1));
+        assert_eq!(edit_distance_within_one(b"icdm", b"cidm"), None);
+        assert_eq!(edit_distance_within_one(b"icdm", b"ic"), None);
+        assert_eq!(edit_distance_within_one(b"icdm", b"xcdx"), None);
+    }
+}
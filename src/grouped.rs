@@ -0,0 +1,235 @@
+//! Two-level hash map implementation for string keys grouped into sections by a delimiter.
+
+use crate::{HashMap, HashMapBuilder, KeyLen};
+
+use anyhow::Result;
+
+/// Two-level hash map implementation for string keys grouped into sections by a delimiter,
+/// such as `"section.key"` in configuration files and locale bundles.
+///
+/// Each key is split at the first occurrence of the delimiter into a section and a key within it.
+/// An outer table maps sections to inner tables, which map keys to values,
+/// so that a whole section can be looked up and iterated at once.
+///
+/// It is built by [`GroupedHashMap::new`] or [`HashMapBuilder::build_delimited`].
+#[derive(Clone)]
+pub struct GroupedHashMap<V, L = usize>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    pub(crate) sections: HashMap<usize, L>,
+    pub(crate) groups: Vec<HashMap<V, L>>,
+    pub(crate) delimiter: Vec<u8>,
+}
+
+impl<V> GroupedHashMap<V>
+where
+    V: Default + Clone,
+{
+    /// Creates a new [`GroupedHashMap`] from input records.
+    ///
+    /// # Arguments
+    ///
+    /// - `records`: List of key-value pairs, whose keys contain `delimiter`.
+    /// - `delimiter`: Non-empty delimiter between a section and a key.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `records` is empty,
+    ///  - `records` contains duplicate keys,
+    ///  - `records` contains a key without `delimiter`, or
+    ///  - `delimiter` is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::GroupedHashMap;
+    ///
+    /// let records = vec![("conf.icdm", 0), ("conf.idce", 1), ("journal.tods", 2)];
+    /// let map = GroupedHashMap::new(&records, ".").unwrap();
+    /// assert_eq!(map.get("conf", "idce"), Some(&1));
+    /// assert_eq!(map.get("journal", "idce"), None);
+    /// ```
+    pub fn new<K, D>(records: &[(K, V)], delimiter: D) -> Result<Self>
+    where
+        K: AsRef<[u8]>,
+        D: AsRef<[u8]>,
+    {
+        HashMapBuilder::new().build_delimited(records, delimiter)
+    }
+}
+
+impl<V, L> GroupedHashMap<V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    /// Returns a reference to the value corresponding to the key in the section.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::GroupedHashMap;
+    ///
+    /// let records = vec![("conf.icdm", 0), ("conf.idce", 1), ("journal.tods", 2)];
+    /// let map = GroupedHashMap::new(&records, ".").unwrap();
+    /// assert_eq!(map.get("journal", "tods"), Some(&2));
+    /// assert_eq!(map.get("conf", "tods"), None);
+    /// ```
+    #[inline(always)]
+    pub fn get<S, K>(&self, section: S, key: K) -> Option<&V>
+    where
+        S: AsRef<[u8]>,
+        K: AsRef<[u8]>,
+    {
+        self.section(section)?.get(key)
+    }
+
+    /// Returns a reference to the value corresponding to the key joined with its section
+    /// by the delimiter, or `None` if the key has no delimiter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::GroupedHashMap;
+    ///
+    /// let records = vec![("conf.icdm", 0), ("conf.idce", 1), ("journal.tods", 2)];
+    /// let map = GroupedHashMap::new(&records, ".").unwrap();
+    /// assert_eq!(map.get_joined("conf.idce"), Some(&1));
+    /// assert_eq!(map.get_joined("confidce"), None);
+    /// ```
+    #[inline(always)]
+    pub fn get_joined<K>(&self, key: K) -> Option<&V>
+    where
+        K: AsRef<[u8]>,
+    {
+        let (section, key) = split(key.as_ref(), &self.delimiter)?;
+        self.get(section, key)
+    }
+
+    /// Returns the inner map of the keys in the section.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::GroupedHashMap;
+    ///
+    /// let records = vec![("conf.icdm", 0), ("conf.idce", 1), ("journal.tods", 2)];
+    /// let map = GroupedHashMap::new(&records, ".").unwrap();
+    /// assert_eq!(map.section("conf").unwrap().len(), 2);
+    /// assert!(map.section("book").is_none());
+    /// ```
+    #[inline(always)]
+    pub fn section<S>(&self, section: S) -> Option<&HashMap<V, L>>
+    where
+        S: AsRef<[u8]>,
+    {
+        self.sections.get(section).map(|&i| &self.groups[i])
+    }
+
+    /// Iterates over the sections and their inner maps in arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::GroupedHashMap;
+    ///
+    /// let records = vec![("conf.icdm", 0), ("conf.idce", 1), ("journal.tods", 2)];
+    /// let map = GroupedHashMap::new(&records, ".").unwrap();
+    /// let mut sections: Vec<_> = map.sections().map(|(s, m)| (s, m.len())).collect();
+    /// sections.sort();
+    /// assert_eq!(sections, vec![(&b"conf"[..], 2), (&b"journal"[..], 1)]);
+    /// ```
+    pub fn sections(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (&[u8], &HashMap<V, L>)> + ExactSizeIterator {
+        self.sections
+            .slots()
+            .map(|(_, section, &i)| (section, &self.groups[i]))
+    }
+
+    /// Returns the delimiter between a section and a key.
+    #[inline(always)]
+    pub fn delimiter(&self) -> &[u8] {
+        &self.delimiter
+    }
+
+    /// Returns the number of keys in all the sections.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.groups.iter().map(HashMap::len).sum()
+    }
+
+    /// Returns true if the map contains no keys.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of sections.
+    #[inline(always)]
+    pub const fn num_sections(&self) -> usize {
+        self.groups.len()
+    }
+}
+
+/// Splits `key` at the first occurrence of `delimiter` into a section and a key.
+#[inline(always)]
+pub(crate) fn split<'a>(key: &'a [u8], delimiter: &[u8]) -> Option<(&'a [u8], &'a [u8])> {
+    let pos = key.windows(delimiter.len()).position(|w| w == delimiter)?;
+    Some((&key[..pos], &key[pos + delimiter.len()..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get() {
+        let keys = [
+            "conf.icdm",
+            "conf.idce",
+            "conf.",
+            ".sigmod",
+            "conf.sigir.short",
+            "acl.acl",
+        ];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let map = GroupedHashMap::new(&records, ".").unwrap();
+        assert_eq!(map.len(), 6);
+        assert_eq!(map.num_sections(), 3);
+        for &(k, v) in &records {
+            assert_eq!(map.get_joined(k), Some(&v));
+        }
+        assert_eq!(map.get("conf", ""), Some(&2));
+        assert_eq!(map.get("", "sigmod"), Some(&3));
+        assert_eq!(map.get("conf", "sigir.short"), Some(&4));
+        assert_eq!(map.get("conf.sigir", "short"), None);
+        assert_eq!(map.get("acl", "icdm"), None);
+        assert_eq!(map.get_joined("conf"), None);
+    }
+
+    #[test]
+    fn test_delimiter() {
+        let records = [("conf::icdm", 0), ("journal::tods", 1)];
+        let map = HashMapBuilder::new()
+            .key_len::<u8>()
+            .build_delimited(&records, "::")
+            .unwrap();
+        assert_eq!(map.delimiter(), b"::");
+        assert_eq!(map.get("conf", "icdm"), Some(&0));
+        assert_eq!(map.get_joined("journal::tods"), Some(&1));
+        assert_eq!(map.get_joined("journal:tods"), None);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(GroupedHashMap::new(&[("conf.icdm", 0), ("conf.icdm", 1)], ".").is_err());
+        assert!(GroupedHashMap::new(&[("conf.icdm", 0), ("icdm", 1)], ".").is_err());
+        assert!(GroupedHashMap::new(&[("conf.icdm", 0)], "").is_err());
+        assert!(GroupedHashMap::<u32>::new::<&str, _>(&[], ".").is_err());
+    }
+}
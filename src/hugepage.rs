@@ -0,0 +1,59 @@
+//! Advice to back large arrays with transparent huge pages, enabled by the `hugepages` feature.
+//!
+//! Probing a map of many gigabytes touches a random page per lookup,
+//! so the misses of the translation lookaside buffer can dominate the latency.
+//! Huge pages cover the same memory with far fewer entries.
+
+use anyhow::Result;
+
+/// Advises the kernel to back the whole pages within `data` with transparent huge pages.
+///
+/// Only the pages lying entirely within `data` are advised, so that no other allocation is affected.
+/// On targets other than Linux, or with the `safe` feature, this does nothing.
+///
+/// # Errors
+///
+/// An error will be returned when the kernel rejects the advice,
+/// such as when it does not support transparent huge pages.
+pub fn advise<T>(data: &[T]) -> Result<()> {
+    #[cfg(all(target_os = "linux", not(feature = "safe")))]
+    {
+        // SAFETY: `sysconf` only queries a system parameter.
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        let page_size = usize::try_from(page_size)
+            .map_err(|_| anyhow::anyhow!("The page size cannot be determined."))?;
+        let start = (data.as_ptr() as usize).next_multiple_of(page_size);
+        let end = (data.as_ptr() as usize + std::mem::size_of_val(data)) / page_size * page_size;
+        if start >= end {
+            return Ok(());
+        }
+        // SAFETY: The range consists of whole pages within the allocation of `data`,
+        // and the advice changes how the pages are backed but not their contents.
+        let ret =
+            unsafe { libc::madvise(start as *mut libc::c_void, end - start, libc::MADV_HUGEPAGE) };
+        if ret != 0 {
+            return Err(anyhow::anyhow!(
+                "The advice of huge pages was rejected: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+    }
+    #[cfg(any(not(target_os = "linux"), feature = "safe"))]
+    let _ = data;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advise() {
+        // An array smaller than a page has no whole page to advise.
+        advise(&[0u8; 16]).unwrap();
+        advise::<u64>(&[]).unwrap();
+        // Transparent huge pages may be disabled in the kernel.
+        let data = vec![0u64; 1 << 20];
+        let _ = advise(&data);
+    }
+}
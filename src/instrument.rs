@@ -0,0 +1,119 @@
+//! Instrumentation of lookups, enabled by the `instrument` feature.
+//!
+//! Every lookup into a map records whether the key was found
+//! and how many slots it examined, so that the probe distribution
+//! of a map under real queries can be monitored.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Number of buckets in [`ProbeStats::histogram`].
+pub const PROBE_HISTOGRAM_LEN: usize = 16;
+
+/// Snapshot of the lookup statistics recorded by a map.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProbeStats {
+    /// Number of lookups that found the key.
+    pub hits: u64,
+    /// Number of lookups that did not find the key.
+    pub misses: u64,
+    /// Total number of slots examined by the lookups,
+    /// including the empty slot terminating each miss.
+    pub probes: u64,
+    /// Number of lookups by the number of examined slots,
+    /// where `histogram[i]` counts lookups examining `i + 1` slots
+    /// and the last bucket also counts longer lookups.
+    pub histogram: [u64; PROBE_HISTOGRAM_LEN],
+}
+
+impl ProbeStats {
+    /// Returns the number of recorded lookups.
+    #[inline(always)]
+    pub const fn lookups(&self) -> u64 {
+        self.hits + self.misses
+    }
+
+    /// Returns the average number of slots examined by a lookup,
+    /// or 0 if no lookup is recorded.
+    pub fn mean_probes(&self) -> f64 {
+        if self.lookups() == 0 {
+            return 0.0;
+        }
+        self.probes as f64 / self.lookups() as f64
+    }
+}
+
+/// Counters of lookups shared by concurrent readers.
+///
+/// A cloned map starts with fresh counters.
+#[derive(Default)]
+pub(crate) struct ProbeCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    probes: AtomicU64,
+    histogram: [AtomicU64; PROBE_HISTOGRAM_LEN],
+}
+
+impl Clone for ProbeCounters {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl ProbeCounters {
+    /// Records a lookup that examined `probes` slots.
+    #[inline(always)]
+    pub(crate) fn record(&self, hit: bool, probes: usize) {
+        let tally = if hit { &self.hits } else { &self.misses };
+        tally.fetch_add(1, Ordering::Relaxed);
+        self.probes.fetch_add(probes as u64, Ordering::Relaxed);
+        let bucket = probes.clamp(1, PROBE_HISTOGRAM_LEN) - 1;
+        self.histogram[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the current values of the counters.
+    pub(crate) fn snapshot(&self) -> ProbeStats {
+        ProbeStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            probes: self.probes.load(Ordering::Relaxed),
+            histogram: std::array::from_fn(|i| self.histogram[i].load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Resets the counters to zero.
+    pub(crate) fn reset(&self) {
+        for counter in [&self.hits, &self.misses, &self.probes]
+            .into_iter()
+            .chain(&self.histogram)
+        {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let counters = ProbeCounters::default();
+        counters.record(true, 1);
+        counters.record(true, 3);
+        counters.record(false, 100);
+        let stats = counters.snapshot();
+        assert_eq!(stats.hits, 2);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.lookups(), 3);
+        assert_eq!(stats.probes, 104);
+        assert_eq!(stats.histogram[0], 1);
+        assert_eq!(stats.histogram[2], 1);
+        assert_eq!(stats.histogram[PROBE_HISTOGRAM_LEN - 1], 1);
+        let cloned = counters.clone();
+        assert_eq!(cloned.snapshot(), ProbeStats::default());
+
+        counters.reset();
+        assert_eq!(counters.snapshot(), ProbeStats::default());
+        assert_eq!(counters.snapshot().mean_probes(), 0.0);
+    }
+}
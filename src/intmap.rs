@@ -0,0 +1,230 @@
+//! Simple fast hash map implementation for fixed-width integer keys.
+
+use crate::{capacity_for, hash_key, DEFAULT_LOAD_FACTOR, DEFAULT_SEED};
+
+use anyhow::{anyhow, Result};
+
+/// Fixed-width integer type that can be used as a key of [`IntHashMap`].
+pub trait IntKey: Copy + Eq {
+    /// Computes the hash value of the key with `seed`.
+    fn hash(self, seed: u64) -> usize;
+}
+
+macro_rules! impl_int_key {
+    ($($t:ty),*) => {
+        $(
+            impl IntKey for $t {
+                #[inline(always)]
+                fn hash(self, seed: u64) -> usize {
+                    hash_key(&self.to_le_bytes(), seed) as usize
+                }
+            }
+        )*
+    };
+}
+
+impl_int_key!(u32, u64, u128, usize);
+
+/// Simple fast hash map implementation for fixed-width integer keys.
+///
+/// Keys are stored inline in the slots without the key bytes,
+/// and are placed by the same hashing and linear probing as [`HashMap`](crate::HashMap).
+#[derive(Clone)]
+pub struct IntHashMap<I, V>
+where
+    I: IntKey,
+    V: Clone,
+{
+    slots: Vec<Option<(I, V)>>,
+    capacity_mask: usize,
+    num_keys: usize,
+    seed: u64,
+}
+
+impl<I, V> IntHashMap<I, V>
+where
+    I: IntKey,
+    V: Clone,
+{
+    /// Creates a new [`IntHashMap`] from input records.
+    ///
+    /// # Arguments
+    ///
+    /// - `records`: List of key-value pairs.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `records` is empty, or
+    ///  - `records` contains duplicate keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::IntHashMap;
+    ///
+    /// let records = vec![(3u32, "icdm"), (14, "idce"), (15, "sigmod")];
+    /// let map = IntHashMap::new(&records).unwrap();
+    /// assert_eq!(map.get(14), Some(&"idce"));
+    /// assert_eq!(map.get(92), None);
+    /// ```
+    pub fn new(records: &[(I, V)]) -> Result<Self> {
+        if records.is_empty() {
+            return Err(anyhow!("The input records must not be empty."));
+        }
+        let num_keys = records.len();
+        let capacity = capacity_for(num_keys, DEFAULT_LOAD_FACTOR)?;
+        let capacity_mask = capacity - 1;
+        let seed = DEFAULT_SEED;
+        let mut slots = vec![None; capacity];
+        for (k, v) in records {
+            let mut pos = k.hash(seed) & capacity_mask;
+            while let Some((other, _)) = &slots[pos] {
+                if other == k {
+                    return Err(anyhow!(
+                        "The input records must not contain duplicated keys."
+                    ));
+                }
+                pos = (pos + 1) & capacity_mask;
+            }
+            slots[pos] = Some((*k, v.clone()));
+        }
+        Ok(Self {
+            slots,
+            capacity_mask,
+            num_keys,
+            seed,
+        })
+    }
+
+    /// Returns true if the map contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::IntHashMap;
+    ///
+    /// let records = vec![(3u32, "icdm"), (14, "idce"), (15, "sigmod")];
+    /// let map = IntHashMap::new(&records).unwrap();
+    /// assert_eq!(map.contains_key(14), true);
+    /// assert_eq!(map.contains_key(92), false);
+    /// ```
+    #[inline(always)]
+    pub fn contains_key(&self, key: I) -> bool {
+        self.get_pos(key).is_some()
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::IntHashMap;
+    ///
+    /// let records = vec![(3u32, "icdm"), (14, "idce"), (15, "sigmod")];
+    /// let map = IntHashMap::new(&records).unwrap();
+    /// assert_eq!(map.get(14), Some(&"idce"));
+    /// assert_eq!(map.get(92), None);
+    /// ```
+    #[inline(always)]
+    pub fn get(&self, key: I) -> Option<&V> {
+        self.get_pos(key)
+            .and_then(|pos| self.slots[pos].as_ref())
+            .map(|(_, v)| v)
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::IntHashMap;
+    ///
+    /// let records = vec![(3u32, "icdm"), (14, "idce"), (15, "sigmod")];
+    /// let mut map = IntHashMap::new(&records).unwrap();
+    /// *map.get_mut(14).unwrap() = "sigir";
+    /// assert_eq!(map.get(14), Some(&"sigir"));
+    /// ```
+    #[inline(always)]
+    pub fn get_mut(&mut self, key: I) -> Option<&mut V> {
+        self.get_pos(key)
+            .and_then(|pos| self.slots[pos].as_mut())
+            .map(|(_, v)| v)
+    }
+
+    #[inline(always)]
+    fn get_pos(&self, key: I) -> Option<usize> {
+        let mut pos = key.hash(self.seed) & self.capacity_mask;
+        while let Some((other, _)) = &self.slots[pos] {
+            if *other == key {
+                return Some(pos);
+            }
+            pos = (pos + 1) & self.capacity_mask;
+        }
+        None
+    }
+
+    /// Returns the number of elements in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::IntHashMap;
+    ///
+    /// let records = vec![(3u32, "icdm"), (14, "idce"), (15, "sigmod")];
+    /// let map = IntHashMap::new(&records).unwrap();
+    /// assert_eq!(map.len(), 3);
+    /// ```
+    #[inline(always)]
+    pub const fn len(&self) -> usize {
+        self.num_keys
+    }
+
+    /// Returns true if the map contains no elements.
+    #[inline(always)]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get() {
+        let records: Vec<_> = (0..1000u64).map(|i| (i * 7919, i)).collect();
+        let map = IntHashMap::new(&records).unwrap();
+        assert_eq!(map.len(), 1000);
+        for &(k, v) in &records {
+            assert_eq!(map.get(k), Some(&v));
+        }
+        assert_eq!(map.get(1), None);
+        assert_eq!(map.get(u64::MAX), None);
+    }
+
+    #[test]
+    fn test_get_mut() {
+        let records: Vec<_> = (0..100u128).map(|i| (i << 64, i)).collect();
+        let mut map = IntHashMap::new(&records).unwrap();
+        for &(k, v) in &records {
+            *map.get_mut(k).unwrap() = v * 3;
+        }
+        for &(k, v) in &records {
+            assert_eq!(map.get(k), Some(&(v * 3)));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty() {
+        IntHashMap::<u32, u32>::new(&[]).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_duplicate() {
+        IntHashMap::new(&[(1u32, 0), (2, 1), (1, 2)]).unwrap();
+    }
+}
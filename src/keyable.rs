@@ -0,0 +1,196 @@
+//! Encoding of non-byte types into keys.
+
+use std::cell::RefCell;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Type that can be encoded into a key.
+///
+/// Implementing this trait allows a type to be used as a key directly
+/// via methods such as [`HashMap::get_keyable`](crate::HashMap::get_keyable).
+/// Different values must be encoded into different byte sequences.
+///
+/// # Examples
+///
+/// ```
+/// use simplearrayhash::{HashMap, Keyable};
+///
+/// struct Point {
+///     x: u16,
+///     y: u16,
+/// }
+///
+/// impl Keyable for Point {
+///     fn encode_key(&self, buf: &mut Vec<u8>) {
+///         self.x.encode_key(buf);
+///         self.y.encode_key(buf);
+///     }
+/// }
+///
+/// let records = vec![(Point { x: 1, y: 2 }, "a"), (Point { x: 2, y: 1 }, "b")];
+/// let map = HashMap::new_keyable(&records).unwrap();
+/// assert_eq!(map.get_keyable(&Point { x: 2, y: 1 }), Some(&"b"));
+/// assert_eq!(map.get_keyable(&Point { x: 2, y: 2 }), None);
+/// ```
+pub trait Keyable {
+    /// Appends the encoded key to `buf`.
+    fn encode_key(&self, buf: &mut Vec<u8>);
+}
+
+macro_rules! impl_keyable_int {
+    ($($t:ty),*) => {
+        $(
+            impl Keyable for $t {
+                #[inline(always)]
+                fn encode_key(&self, buf: &mut Vec<u8>) {
+                    buf.extend_from_slice(&self.to_be_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_keyable_int!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+// Pointer-sized integers are widened to 64 bits,
+// so that their keys are the same on 32-bit and 64-bit targets.
+impl Keyable for usize {
+    #[inline(always)]
+    fn encode_key(&self, buf: &mut Vec<u8>) {
+        (*self as u64).encode_key(buf);
+    }
+}
+
+impl Keyable for isize {
+    #[inline(always)]
+    fn encode_key(&self, buf: &mut Vec<u8>) {
+        (*self as i64).encode_key(buf);
+    }
+}
+
+impl Keyable for Ipv4Addr {
+    #[inline(always)]
+    fn encode_key(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.octets());
+    }
+}
+
+impl Keyable for Ipv6Addr {
+    #[inline(always)]
+    fn encode_key(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.octets());
+    }
+}
+
+impl Keyable for IpAddr {
+    /// Encodes the address into 4 or 16 bytes,
+    /// so an IPv4 address never collides with an IPv6 one.
+    #[inline(always)]
+    fn encode_key(&self, buf: &mut Vec<u8>) {
+        match self {
+            Self::V4(addr) => addr.encode_key(buf),
+            Self::V6(addr) => addr.encode_key(buf),
+        }
+    }
+}
+
+impl<const N: usize> Keyable for [u8; N] {
+    #[inline(always)]
+    fn encode_key(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self);
+    }
+}
+
+impl Keyable for [u8] {
+    #[inline(always)]
+    fn encode_key(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self);
+    }
+}
+
+impl Keyable for Vec<u8> {
+    #[inline(always)]
+    fn encode_key(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self);
+    }
+}
+
+impl Keyable for str {
+    #[inline(always)]
+    fn encode_key(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl Keyable for String {
+    #[inline(always)]
+    fn encode_key(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl<T> Keyable for &T
+where
+    T: Keyable + ?Sized,
+{
+    #[inline(always)]
+    fn encode_key(&self, buf: &mut Vec<u8>) {
+        (*self).encode_key(buf);
+    }
+}
+
+thread_local! {
+    static SCRATCH: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Encodes `key` into a thread-local scratch buffer and passes the encoded bytes to `f`.
+///
+/// The buffer is taken out during the call, so nested calls are safe
+/// (although they allocate a new buffer).
+#[inline(always)]
+pub(crate) fn with_encoded<T, F, R>(key: &T, f: F) -> R
+where
+    T: Keyable + ?Sized,
+    F: FnOnce(&[u8]) -> R,
+{
+    let mut buf = SCRATCH.with(|scratch| std::mem::take(&mut *scratch.borrow_mut()));
+    buf.clear();
+    key.encode_key(&mut buf);
+    let ret = f(&buf);
+    SCRATCH.with(|scratch| *scratch.borrow_mut() = buf);
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode<T>(key: &T) -> Vec<u8>
+    where
+        T: Keyable + ?Sized,
+    {
+        let mut buf = vec![];
+        key.encode_key(&mut buf);
+        buf
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode(&0x0102u16), vec![1, 2]);
+        assert_eq!(encode(&Ipv4Addr::new(192, 168, 0, 1)), vec![192, 168, 0, 1]);
+        assert_eq!(encode(&IpAddr::V6(Ipv6Addr::LOCALHOST)).len(), 16);
+        assert_eq!(encode("abc"), b"abc");
+        assert_eq!(encode(&[1u8; 16]), vec![1; 16]);
+        assert_eq!(encode(&0x0102usize), encode(&0x0102u64));
+        assert_eq!(encode(&-2isize), encode(&-2i64));
+    }
+
+    #[test]
+    fn test_with_encoded() {
+        let len = with_encoded(&1u32, |outer| {
+            let inner = with_encoded(&2u64, |inner| inner.to_vec());
+            assert_eq!(inner, vec![0, 0, 0, 0, 0, 0, 0, 2]);
+            outer.len()
+        });
+        assert_eq!(len, 4);
+    }
+}
@@ -0,0 +1,302 @@
+//! Hash map loading values on demand from a serialized dictionary.
+
+use std::io::{Read, Seek, SeekFrom};
+use std::marker::PhantomData;
+
+use crate::dense::DenseNode;
+use crate::serial::{self, SectionKind, Sections, Serializable};
+use crate::{KeyLen, Table};
+
+use anyhow::{anyhow, Result};
+
+/// Hash map keeping only the keys in memory and
+/// fetching values on demand from a serialized dictionary.
+///
+/// It opens data written by [`HashMap::serialize_into`](crate::HashMap::serialize_into),
+/// loading the header, nodes, keys, and the offsets of the values,
+/// while the payload of the values stays in the reader.
+/// [`LazyHashMap::get`] seeks to the value of the key and deserializes it,
+/// so it is suited to dictionaries with huge values such as embeddings or definitions.
+/// A memory-mapped file can be used by wrapping it with [`std::io::Cursor`].
+pub struct LazyHashMap<V, R, L = usize>
+where
+    L: KeyLen,
+{
+    table: Table<DenseNode<L>>,
+    offsets: Vec<u64>,
+    payload_start: u64,
+    rdr: R,
+    buf: Vec<u8>,
+    _val: PhantomData<V>,
+}
+
+impl<V, R, L> LazyHashMap<V, R, L>
+where
+    V: Serializable,
+    R: Read + Seek,
+    L: KeyLen,
+{
+    /// Opens a serialized dictionary starting at the current position of `rdr`.
+    ///
+    /// # Arguments
+    ///
+    /// - `rdr`: Reader of the data in the [sectioned format](crate::serial).
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - reading fails,
+    ///  - the data is broken, or
+    ///  - the data contains a key whose length is out of the range of `L`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    ///
+    /// use simplearrayhash::{HashMap, LazyHashMap};
+    ///
+    /// let records = vec![("icdm", "data mining".to_string()), ("sigmod", "database".to_string())];
+    /// let data = HashMap::new(&records).unwrap().serialize_to_vec();
+    ///
+    /// let mut map = LazyHashMap::<String, _>::open(Cursor::new(data)).unwrap();
+    /// assert_eq!(map.get("icdm").unwrap(), Some("data mining".to_string()));
+    /// assert_eq!(map.get("sigir").unwrap(), None);
+    /// ```
+    pub fn open(mut rdr: R) -> Result<Self> {
+        let base = rdr.stream_position()?;
+        let entries = serial::read_section_table(&mut rdr)?;
+        let find = |kind: SectionKind| {
+            entries
+                .iter()
+                .find(|&&(k, _, _)| k == kind as u32)
+                .map(|&(_, offset, len)| (offset, len))
+                .ok_or_else(|| anyhow!("The section {kind:?} is missing."))
+        };
+
+        let mut bodies = vec![];
+        for kind in [SectionKind::Header, SectionKind::Nodes, SectionKind::Keys] {
+            let (offset, len) = find(kind)?;
+            bodies.push((kind, read_at(&mut rdr, base, offset, len)?));
+        }
+        let sections = Sections::from_entries(
+            bodies
+                .iter()
+                .map(|(kind, body)| (*kind as u32, body.as_slice()))
+                .collect(),
+        );
+        let mut table = Table::<DenseNode<L>>::deserialize_sections(&sections)?;
+        // Values are stored in slot order.
+        for (idx, node) in table.occupied_mut().enumerate() {
+            node.idx = u32::try_from(idx)
+                .map_err(|_| anyhow!("The number of keys must not exceed 2^32."))?;
+        }
+
+        let (offset, len) = find(SectionKind::Values)?;
+        let mut head = read_at(&mut rdr, base, offset, 8)?;
+        let count = serial::read_u64(&mut head.as_slice())?;
+        if count != table.num_keys() as u64 {
+            return Err(anyhow!("The values section is broken."));
+        }
+        let offsets_len = count
+            .checked_add(1)
+            .and_then(|n| n.checked_mul(8))
+            .ok_or_else(|| anyhow!("The values section is broken."))?;
+        // The offsets and lengths are untrusted, so every sum is checked.
+        let truncated = || anyhow!("The values section is truncated.");
+        let payload_len = len
+            .checked_sub(8)
+            .and_then(|n| n.checked_sub(offsets_len))
+            .ok_or_else(truncated)?;
+        let payload_start = base
+            .checked_add(offset)
+            .and_then(|pos| pos.checked_add(8 + offsets_len))
+            .ok_or_else(truncated)?;
+        let payload_end = payload_start
+            .checked_add(payload_len)
+            .ok_or_else(truncated)?;
+        if payload_end > rdr.seek(SeekFrom::End(0))? {
+            return Err(truncated());
+        }
+        head = read_at(&mut rdr, base, offset + 8, offsets_len)?;
+        let mut source = head.as_slice();
+        let mut offsets = Vec::with_capacity(count as usize + 1);
+        for _ in 0..=count {
+            offsets.push(serial::read_u64(&mut source)?);
+        }
+        if offsets.windows(2).any(|w| w[0] > w[1]) || offsets[count as usize] > payload_len {
+            return Err(anyhow!("The values section is broken."));
+        }
+
+        Ok(Self {
+            table,
+            offsets,
+            payload_start,
+            rdr,
+            buf: vec![],
+            _val: PhantomData,
+        })
+    }
+
+    /// Reads and returns the value corresponding to the key.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when reading fails or the value is broken.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    ///
+    /// use simplearrayhash::{HashMap, LazyHashMap};
+    ///
+    /// let records = vec![("icdm", vec![0.5f32; 4]), ("idce", vec![1.5; 4])];
+    /// let data = HashMap::new(&records).unwrap().serialize_to_vec();
+    ///
+    /// let mut map = LazyHashMap::<Vec<f32>, _>::open(Cursor::new(data)).unwrap();
+    /// assert_eq!(map.get("idce").unwrap(), Some(vec![1.5; 4]));
+    /// ```
+    pub fn get<K>(&mut self, key: K) -> Result<Option<V>>
+    where
+        K: AsRef<[u8]>,
+    {
+        let Some(node) = self.table.get(key) else {
+            return Ok(None);
+        };
+        let idx = node.idx as usize;
+        let (start, end) = (self.offsets[idx], self.offsets[idx + 1]);
+        self.rdr.seek(SeekFrom::Start(self.payload_start + start))?;
+        self.buf.resize(serial::to_usize(end - start)?, 0);
+        self.rdr.read_exact(&mut self.buf)?;
+        serial::decode_exact(&self.buf).map(Some)
+    }
+}
+
+impl<V, R, L> LazyHashMap<V, R, L>
+where
+    L: KeyLen,
+{
+    /// Returns true if the map contains a value for the specified key,
+    /// without reading the value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    ///
+    /// use simplearrayhash::{HashMap, LazyHashMap};
+    ///
+    /// let records = vec![("icdm", 0u32), ("idce", 1), ("sigmod", 2)];
+    /// let data = HashMap::new(&records).unwrap().serialize_to_vec();
+    ///
+    /// let map = LazyHashMap::<u32, _>::open(Cursor::new(data)).unwrap();
+    /// assert_eq!(map.contains_key("idce"), true);
+    /// assert_eq!(map.contains_key("sigir"), false);
+    /// ```
+    #[inline(always)]
+    pub fn contains_key<K>(&self, key: K) -> bool
+    where
+        K: AsRef<[u8]>,
+    {
+        self.table.get(key).is_some()
+    }
+
+    /// Returns the number of elements in the map.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.table.num_keys()
+    }
+
+    /// Returns true if the map contains no elements.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Consumes the map, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.rdr
+    }
+}
+
+/// Reads `len` bytes at `base + offset` of `rdr`.
+fn read_at<R>(rdr: &mut R, base: u64, offset: u64, len: u64) -> Result<Vec<u8>>
+where
+    R: Read + Seek,
+{
+    let pos = base
+        .checked_add(offset)
+        .ok_or_else(|| anyhow!("A section is out of bounds."))?;
+    rdr.seek(SeekFrom::Start(pos))?;
+    // Reads through `take` not to allocate a broken length in advance.
+    let mut buf = vec![];
+    rdr.take(len).read_to_end(&mut buf)?;
+    if buf.len() as u64 != len {
+        return Err(anyhow!("A section is out of bounds."));
+    }
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Cursor;
+
+    use crate::HashMap;
+
+    #[test]
+    fn test_get() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().map(|&k| (k, k.repeat(3))).collect();
+        let data = HashMap::new(&records).unwrap().serialize_to_vec();
+
+        // Embeds the data after a prefix to check that offsets are relative to the start position.
+        let mut embedded = vec![0xff; 5];
+        embedded.extend_from_slice(&data);
+        let mut rdr = Cursor::new(embedded);
+        rdr.set_position(5);
+
+        let mut map = LazyHashMap::<String, _>::open(rdr).unwrap();
+        assert_eq!(map.len(), 6);
+        for (k, v) in &records {
+            assert!(map.contains_key(k));
+            assert_eq!(map.get(k).unwrap().as_ref(), Some(v));
+        }
+        assert_eq!(map.get("sigkdd").unwrap(), None);
+        assert!(!map.contains_key("sigkdd"));
+    }
+
+    #[test]
+    fn test_key_len() {
+        let records = [("icdm", 0u32), ("idce", 1)];
+        let data = HashMap::new(&records).unwrap().serialize_to_vec();
+        let mut map = LazyHashMap::<u32, _, u8>::open(Cursor::new(&data)).unwrap();
+        assert_eq!(map.get("idce").unwrap(), Some(1));
+    }
+
+    #[test]
+    fn test_broken() {
+        let records = [("icdm", 0u32), ("idce", 1)];
+        let data = HashMap::new(&records).unwrap().serialize_to_vec();
+        let truncated = &data[..data.len() - 1];
+        assert!(LazyHashMap::<u32, _>::open(Cursor::new(truncated)).is_err());
+        assert!(LazyHashMap::<u32, _>::open(Cursor::new(&data[..10])).is_err());
+
+        // An overflowing length of the values section is rejected.
+        let entries = u32::from_le_bytes(data[12..16].try_into().unwrap()) as usize;
+        let mut broken = data.clone();
+        for entry in broken[16..16 + 24 * entries].chunks_exact_mut(24) {
+            if u32::from_le_bytes(entry[..4].try_into().unwrap()) == SectionKind::Values as u32 {
+                entry[16..].copy_from_slice(&u64::MAX.to_le_bytes());
+            }
+        }
+        assert!(LazyHashMap::<u32, _>::open(Cursor::new(&broken)).is_err());
+
+        // A wrong value type is detected on access.
+        let mut map = LazyHashMap::<u64, _>::open(Cursor::new(&data)).unwrap();
+        assert!(map.get("idce").is_err());
+    }
+}
@@ -1,21 +1,177 @@
 //! # simplearrayhash
 //!
 //! A simple fast implementation of an open addressing hash table for string keys.
+//!
+//! With the `safe` feature, the crate is compiled with `#![forbid(unsafe_code)]`,
+//! taking only safe code paths even at some cost of performance.
 #![deny(missing_docs)]
+#![cfg_attr(feature = "safe", forbid(unsafe_code))]
+
+/// Traces the rest of the enclosing block as a construction phase
+/// with a span of the given name and fields, if the `tracing` feature is enabled.
+#[cfg(feature = "tracing")]
+macro_rules! phase {
+    ($name:literal $(, $($field:tt)*)?) => {
+        let _phase = crate::trace::Phase::enter(tracing::debug_span!($name $(, $($field)*)?));
+    };
+}
 
+#[cfg(not(feature = "tracing"))]
+macro_rules! phase {
+    ($($arg:tt)*) => {};
+}
+
+pub mod aliased;
+mod cache;
+pub mod composite;
+pub mod counter;
+pub mod dense;
+#[cfg(feature = "dna")]
+pub mod dna;
+pub mod embedded;
+pub mod encoded;
+pub mod error;
+mod fuzzy;
+pub mod grouped;
+#[cfg(feature = "hugepages")]
+mod hugepage;
+#[cfg(feature = "instrument")]
+pub mod instrument;
+pub mod intmap;
+pub mod keyable;
+pub mod lazy;
 pub mod map;
+pub mod overlay;
+#[cfg(feature = "rayon")]
+mod par;
+pub mod partitioned;
+pub mod persistent;
+pub mod prehashed;
+pub mod progress;
+pub mod raw;
+#[cfg(feature = "serde")]
+mod serde_impl;
+pub mod serial;
 pub mod set;
+pub mod small;
+pub mod storage;
+pub mod strmap;
+mod suffix;
+#[cfg(feature = "tracing")]
+mod trace;
+mod utf16;
+pub mod view;
+
+pub use aliased::AliasedHashMap;
+pub use composite::{CompositeKey, KeyEncoder};
+pub use counter::CounterMap;
+pub use dense::DenseHashMap;
+pub use encoded::{EncodedHashMap, SerialCodec, ValueCodec};
+pub use error::KeyTooLong;
+pub use grouped::GroupedHashMap;
+#[cfg(feature = "instrument")]
+pub use instrument::ProbeStats;
+pub use intmap::IntHashMap;
+pub use keyable::Keyable;
+pub use lazy::LazyHashMap;
+pub use map::{HashMap, HashMapBuilder};
+pub use overlay::Overlay;
+pub use partitioned::PartitionedHashMap;
+pub use persistent::PersistentHashMap;
+pub use prehashed::PrehashedKey;
+pub use progress::{BuildPhase, Progress};
+pub use raw::{RawSlot, RawTable};
+pub use set::{HashSet, HashSetBuilder};
+pub use small::SmallMap;
+pub use storage::KeyStorage;
+pub use strmap::StrHashMap;
+pub use view::{PrefixedView, View};
 
-pub use map::HashMap;
-pub use set::HashSet;
+use std::ops::Bound;
 
-const MAX_LOAD_FACTOR: f64 = 0.8;
-const WORD_BITS: usize = std::mem::size_of::<usize>() * 8;
+use crate::storage::{KeyBytes, Nodes};
+
+use anyhow::{anyhow, Result};
+
+const DEFAULT_LOAD_FACTOR: Ratio = Ratio::new(4, 5);
+const DEFAULT_SEED: u64 = 0;
+const DEFAULT_GROWTH_FACTOR: Ratio = Ratio::new(2, 1);
+const DEFAULT_TOMBSTONE_RATIO: Ratio = Ratio::new(1, 5);
+/// Alignment of stored keys when they are aligned for vector comparisons.
+const KEY_ALIGN: usize = 16;
+
+/// Unsigned integer type used to store the length of each key.
+///
+/// A narrower type shrinks the metadata of every node,
+/// at the cost of limiting the maximum key length.
+pub trait KeyLen: Default + Copy {
+    /// Converts `len` into this type, returning `None` if it is out of range.
+    fn from_usize(len: usize) -> Option<Self>;
+
+    /// Converts this value into `usize`.
+    fn to_usize(self) -> usize;
+}
 
-trait Node {
-    fn new(ptr: usize, len: usize) -> Self;
+macro_rules! impl_key_len {
+    ($($t:ty),*) => {
+        $(
+            impl KeyLen for $t {
+                #[inline(always)]
+                fn from_usize(len: usize) -> Option<Self> {
+                    Self::try_from(len).ok()
+                }
+
+                #[inline(always)]
+                fn to_usize(self) -> usize {
+                    self as usize
+                }
+            }
+        )*
+    };
+}
+
+impl_key_len!(u8, u16, u32, usize);
+
+/// Pointer of a vacant node, which never refers to actual key bytes.
+const VACANT_PTR: usize = usize::MAX;
+/// Pointer of a tombstone left by a removed key, which never refers to actual key bytes.
+const TOMBSTONE_PTR: usize = usize::MAX - 1;
+
+trait Node: Sized {
+    type Len: KeyLen;
+    fn new(ptr: usize, len: Self::Len) -> Self;
     fn ptr(&self) -> usize;
+    fn set_ptr(&mut self, ptr: usize);
     fn len(&self) -> usize;
+
+    /// Creates a node representing an empty slot.
+    #[inline(always)]
+    fn vacant() -> Self {
+        Self::new(VACANT_PTR, Self::Len::default())
+    }
+
+    #[inline(always)]
+    fn is_vacant(&self) -> bool {
+        self.ptr() == VACANT_PTR
+    }
+
+    /// Creates a node representing a removed key,
+    /// which continues probe sequences passing through its slot.
+    #[inline(always)]
+    fn tombstone() -> Self {
+        Self::new(TOMBSTONE_PTR, Self::Len::default())
+    }
+
+    #[inline(always)]
+    fn is_tombstone(&self) -> bool {
+        self.ptr() == TOMBSTONE_PTR
+    }
+
+    /// Returns true if the node stores a key.
+    #[inline(always)]
+    fn is_occupied(&self) -> bool {
+        self.ptr() < TOMBSTONE_PTR
+    }
 }
 
 #[derive(Clone)]
@@ -23,58 +179,1019 @@ struct Table<N>
 where
     N: Default + Clone + Node,
 {
-    nodes: Vec<Option<N>>,
-    bytes: Vec<u8>,
-    capacity_mask: usize,
+    // Empty slots are encoded by vacant nodes instead of `Option`
+    // to avoid spending a discriminant and padding on each slot.
+    nodes: Nodes<N>,
+    bytes: KeyBytes,
     num_keys: usize,
+    // Slots of removed keys, which are empty for insertions but not for probing.
+    num_tombstones: usize,
+    // Incremented whenever a slot may come to store another key, which invalidates handles.
+    generation: u64,
+    max_key_len: usize,
+    seed: u64,
+    // 1, or KEY_ALIGN if each key starts at a multiple of KEY_ALIGN
+    // and the key bytes are zero-padded to a multiple of KEY_ALIGN.
+    key_align: usize,
+    // If true, a key that is a suffix of another key refers to the bytes of the other.
+    share_suffixes: bool,
+    // If true, keys are compared in time independent of how long a prefix matches.
+    constant_time: bool,
+    // Number of slots following the primary slots that store keys colliding too much,
+    // which are scanned after a failed probe of the primary slots.
+    stash_len: usize,
+    // A key is placed in the stash instead of this many slots or more away from its home slot,
+    // unless the stash is full.
+    max_probe: usize,
+    // Number of primary slots probed at most by a lookup, which exceeds `max_probe`
+    // only if keys were placed further while the stash was full.
+    probe_limit: usize,
+    // Slot positions in the ascending order of keys, if the key bytes are sorted.
+    order: Option<Vec<usize>>,
+    // Pairs of the hash of each key or its deletion variant and the slot position of the key,
+    // sorted by the hash, if the table supports fuzzy lookups.
+    deletions: Option<Vec<(u64, usize)>>,
+    // Slot positions in the ascending order of the reversed keys,
+    // if the table supports enumerating keys by suffixes.
+    suffixes: Option<Vec<usize>>,
+    resize: ResizePolicy,
+    // Keys longer than this are rejected by insertions and never found by lookups,
+    // which bounds the memory and time spent on untrusted keys.
+    key_len_limit: usize,
+    // Cache of recent lookups, if enabled for workloads repeating the same queries.
+    lookup_cache: Option<cache::LookupCache>,
+    // If true, the slots and key bytes are advised to be backed by huge pages,
+    // which is repeated whenever they are reallocated by rebuilding.
+    #[cfg(feature = "hugepages")]
+    huge_pages: bool,
+    #[cfg(feature = "instrument")]
+    probe_counters: instrument::ProbeCounters,
+}
+
+/// Ratio of two integers, in which load factors and other resizing parameters are kept
+/// so that capacities are computed exactly without floating-point arithmetic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Ratio {
+    num: u64,
+    den: u64,
+}
+
+impl Ratio {
+    /// Denominator of ratios converted from floating-point values.
+    const F64_DEN: u64 = 1_000_000;
+
+    const fn new(num: u64, den: u64) -> Self {
+        Self { num, den }
+    }
+
+    /// Converts `x` into a ratio rounded to six decimal places.
+    const fn from_f64(x: f64) -> Self {
+        // The cast saturates, converting NaN and negative values into zero.
+        Self::new((x * Self::F64_DEN as f64 + 0.5) as u64, Self::F64_DEN)
+    }
+
+    /// Returns true if the ratio is in `(0, 1]`.
+    const fn in_unit_interval(self) -> bool {
+        self.num > 0 && self.num <= self.den
+    }
+
+    /// Returns true if the ratio is greater than 1, which a zero denominator is not.
+    const fn exceeds_one(self) -> bool {
+        self.den > 0 && self.num > self.den
+    }
+
+    /// Returns true if `n` is greater than `m` multiplied by the ratio.
+    const fn is_exceeded(self, n: usize, m: usize) -> bool {
+        n as u128 * self.den as u128 > m as u128 * self.num as u128
+    }
+
+    /// Returns `n` multiplied by the ratio and rounded up, saturating at `usize::MAX`.
+    fn mul_ceil(self, n: usize) -> usize {
+        saturate((n as u128 * u128::from(self.num)).div_ceil(u128::from(self.den)))
+    }
+
+    /// Returns `n` divided by the ratio and rounded up, saturating at `usize::MAX`.
+    fn div_ceil(self, n: usize) -> usize {
+        saturate((n as u128 * u128::from(self.den)).div_ceil(u128::from(self.num)))
+    }
+
+    /// Returns `n` divided by the ratio and rounded down, saturating at `usize::MAX`.
+    fn div_floor(self, n: usize) -> usize {
+        saturate(n as u128 * u128::from(self.den) / u128::from(self.num))
+    }
+}
+
+/// Converts `n` into `usize`, saturating at `usize::MAX`.
+#[inline(always)]
+fn saturate(n: u128) -> usize {
+    usize::try_from(n).unwrap_or(usize::MAX)
+}
+
+/// Policy of rebuilding a table when keys are inserted or removed.
+#[derive(Clone, Copy, Debug)]
+struct ResizePolicy {
+    // The table grows when the ratio of keys to slots would exceed this.
+    load_factor: Ratio,
+    // Multiplier of the number of slots on growth.
+    growth_factor: Ratio,
+    // The table does not grow beyond this number of slots.
+    max_capacity: usize,
+    // The table is compacted when the ratio of tombstones to slots exceeds this.
+    tombstone_ratio: Ratio,
+}
+
+impl ResizePolicy {
+    const fn new(load_factor: Ratio) -> Self {
+        Self {
+            load_factor,
+            growth_factor: DEFAULT_GROWTH_FACTOR,
+            max_capacity: usize::MAX,
+            tombstone_ratio: DEFAULT_TOMBSTONE_RATIO,
+        }
+    }
 }
 
 impl<N> Table<N>
 where
     N: Default + Clone + Node,
 {
-    fn build<K>(keys: &[K]) -> Self
+    /// Builds a table from `records` in a single pass,
+    /// initializing the node of each key with `fill` and detecting duplicate keys while probing.
+    ///
+    /// The capacity is computed from the length of `records`,
+    /// so the records are consumed without being buffered.
+    /// `num_bytes` is the total length of the keys if it is known in advance, or 0,
+    /// so that the key bytes can be allocated exactly once without reallocation.
+    fn build_from_iter<I, K, T, F>(
+        records: I,
+        num_bytes: usize,
+        load_factor: Ratio,
+        seed: u64,
+        fill: F,
+    ) -> Result<Self>
     where
+        I: ExactSizeIterator<Item = (K, T)>,
         K: AsRef<[u8]>,
+        F: FnMut(&mut N, T) -> Result<()>,
     {
-        let num_keys = keys.len();
-        let capacity = ceil_two((num_keys as f64 / MAX_LOAD_FACTOR) as usize);
-        let capacity_mask = capacity - 1;
-        let mut mapping = vec![None; capacity];
-        for (i, key) in keys.iter().enumerate() {
-            let mut pos = hash_key(key.as_ref()) & capacity_mask;
-            while mapping[pos].is_some() {
-                pos = (pos + 1) & capacity_mask;
-            }
-            mapping[pos] = Some(i);
-        }
-
-        let mut nodes = vec![None; mapping.len()];
-        let mut bytes = vec![];
-        for (i, map) in mapping.iter().enumerate() {
-            if let Some(j) = map {
-                let ptr = bytes.len();
-                let key = keys[*j].as_ref();
-                bytes.extend_from_slice(key);
-                nodes[i] = Some(N::new(ptr, key.len()));
-            }
-        }
-        bytes.shrink_to_fit();
-        Self {
+        let records = records.map(|(key, x)| {
+            let hash = hash_key(key.as_ref(), seed);
+            (key, hash, x)
+        });
+        Self::build_from_hashed(records, num_bytes, load_factor, seed, true, fill)
+    }
+
+    /// Builds a table in the same manner as [`Table::build_from_iter`]
+    /// from `records` with the precomputed hashes of their keys.
+    ///
+    /// If `check_duplicates` is false, the keys must be known to be distinct.
+    fn build_from_hashed<I, K, T, F>(
+        records: I,
+        num_bytes: usize,
+        load_factor: Ratio,
+        seed: u64,
+        check_duplicates: bool,
+        fill: F,
+    ) -> Result<Self>
+    where
+        I: ExactSizeIterator<Item = (K, u64, T)>,
+        K: AsRef<[u8]>,
+        F: FnMut(&mut N, T) -> Result<()>,
+    {
+        let mut nodes = vec![];
+        let mut bytes = Vec::with_capacity(num_bytes);
+        let duplicates: Duplicates<fn(&mut N, T) -> Result<()>> = if check_duplicates {
+            Duplicates::Rejected
+        } else {
+            Duplicates::Unchecked
+        };
+        let mut table = Self::build_in(
+            records,
+            &mut nodes,
+            &mut bytes,
+            load_factor,
+            seed,
+            duplicates,
+            fill,
+        )?;
+        // This does nothing if `num_bytes` is exact.
+        table.shrink_bytes();
+        Ok(table)
+    }
+
+    /// Builds a table in the same manner as [`Table::build_from_iter`],
+    /// reusing the allocations of `nodes` and `bytes`, which are taken on success.
+    fn build_from_iter_in<I, K, T, F>(
+        records: I,
+        nodes: &mut Vec<N>,
+        bytes: &mut Vec<u8>,
+        load_factor: Ratio,
+        seed: u64,
+        fill: F,
+    ) -> Result<Self>
+    where
+        I: ExactSizeIterator<Item = (K, T)>,
+        K: AsRef<[u8]>,
+        F: FnMut(&mut N, T) -> Result<()>,
+    {
+        let records = records.map(|(key, x)| {
+            let hash = hash_key(key.as_ref(), seed);
+            (key, hash, x)
+        });
+        Self::build_in(
+            records,
             nodes,
             bytes,
-            capacity_mask,
+            load_factor,
+            seed,
+            Duplicates::<fn(&mut N, T) -> Result<()>>::Rejected,
+            fill,
+        )
+    }
+
+    /// Builds a table in the same manner as [`Table::build_from_iter`]
+    /// from `records` that may contain duplicate keys.
+    ///
+    /// The payload of the first occurrence of each key is given to `fill`,
+    /// and those of later occurrences are given to `merge` with the node of the key,
+    /// which is found while probing for a slot.
+    /// The table is rearranged into a smaller capacity if duplicates leave it sparser
+    /// than the capacity computed from the number of records.
+    fn build_merged<I, K, T, F, G>(
+        records: I,
+        num_bytes: usize,
+        load_factor: Ratio,
+        seed: u64,
+        fill: F,
+        merge: G,
+    ) -> Result<Self>
+    where
+        I: ExactSizeIterator<Item = (K, T)>,
+        K: AsRef<[u8]>,
+        F: FnMut(&mut N, T) -> Result<()>,
+        G: FnMut(&mut N, T) -> Result<()>,
+    {
+        let records = records.map(|(key, x)| {
+            let hash = hash_key(key.as_ref(), seed);
+            (key, hash, x)
+        });
+        let mut nodes = vec![];
+        let mut bytes = Vec::with_capacity(num_bytes);
+        let mut table = Self::build_in(
+            records,
+            &mut nodes,
+            &mut bytes,
+            load_factor,
+            seed,
+            Duplicates::Merged(merge),
+            fill,
+        )?;
+        let capacity = capacity_for(table.num_keys, load_factor)?;
+        if capacity < table.primary_len() {
+            table.rearrange(capacity);
+        } else {
+            table.shrink_bytes();
+        }
+        Ok(table)
+    }
+
+    /// Builds a table in the same manner as [`Table::build_from_hashed`],
+    /// reusing the allocations of `nodes` and `bytes`, which are taken on success.
+    fn build_in<I, K, T, F, G>(
+        records: I,
+        nodes: &mut Vec<N>,
+        bytes: &mut Vec<u8>,
+        load_factor: Ratio,
+        seed: u64,
+        mut duplicates: Duplicates<G>,
+        mut fill: F,
+    ) -> Result<Self>
+    where
+        I: ExactSizeIterator<Item = (K, u64, T)>,
+        K: AsRef<[u8]>,
+        F: FnMut(&mut N, T) -> Result<()>,
+        G: FnMut(&mut N, T) -> Result<()>,
+    {
+        let check_duplicates = !matches!(duplicates, Duplicates::Unchecked);
+        let capacity = capacity_for(records.len(), load_factor)?;
+        // Hashing and duplicate detection are interleaved with slot assignment,
+        // unless the hashes are precomputed and the keys are known to be distinct.
+        phase!(
+            "slot_assignment",
+            num_keys = records.len(),
+            capacity,
+            check_duplicates
+        );
+        nodes.clear();
+        nodes.resize(capacity, N::vacant());
+        bytes.clear();
+        let mut num_keys = 0;
+        let mut max_key_len = 0;
+        for (key, hash, x) in records {
+            let key = key.as_ref();
+            // Guards against iterators yielding more records than their length,
+            // which could fill all the slots.
+            if num_keys + 1 == capacity {
+                return Err(anyhow!(
+                    "The input must not yield more records than its length."
+                ));
+            }
+            let len = N::Len::from_usize(key.len()).ok_or_else(|| {
+                anyhow!("The key length must not exceed the range of the length type.")
+            })?;
+            let mut pos = home_slot(hash, capacity);
+            let mut found = false;
+            while !nodes[pos].is_vacant() {
+                let node = &nodes[pos];
+                if check_duplicates && &bytes[node.ptr()..node.ptr() + node.len()] == key {
+                    found = true;
+                    break;
+                }
+                pos = next_slot(pos, capacity);
+            }
+            if found {
+                let Duplicates::Merged(merge) = &mut duplicates else {
+                    return Err(anyhow!("The input must not contain duplicated keys."));
+                };
+                merge(&mut nodes[pos], x)?;
+                continue;
+            }
+            nodes[pos] = N::new(bytes.len(), len);
+            fill(&mut nodes[pos], x)?;
+            bytes.extend_from_slice(key);
+            num_keys += 1;
+            max_key_len = max_key_len.max(key.len());
+        }
+        Ok(Self {
+            nodes: std::mem::take(nodes).into(),
+            bytes: std::mem::take(bytes).into(),
             num_keys,
+            num_tombstones: 0,
+            generation: 0,
+            max_key_len,
+            seed,
+            key_align: 1,
+            share_suffixes: false,
+            constant_time: false,
+            stash_len: 0,
+            max_probe: usize::MAX,
+            probe_limit: usize::MAX,
+            order: None,
+            deletions: None,
+            suffixes: None,
+            resize: ResizePolicy::new(load_factor),
+            key_len_limit: usize::MAX,
+            lookup_cache: None,
+            #[cfg(feature = "hugepages")]
+            huge_pages: false,
+            #[cfg(feature = "instrument")]
+            probe_counters: Default::default(),
+        })
+    }
+
+    /// Repacks the key bytes in the given layout.
+    ///
+    /// - `key_align`: Alignment of each key, which is 1 or [`KEY_ALIGN`].
+    ///   If it is [`KEY_ALIGN`], the key bytes are also zero-padded
+    ///   so that keys can be compared in full vector lanes.
+    /// - `share_suffixes`: Whether a key that is a suffix of another key
+    ///   refers to the tail of the other instead of having its own copy.
+    /// - `sorted`: Whether the key bytes are sorted to support ordered operations.
+    fn layout_keys(&mut self, key_align: usize, share_suffixes: bool, sorted: bool) {
+        self.key_align = key_align;
+        self.share_suffixes = share_suffixes;
+        self.order = sorted.then(Vec::new);
+        self.rearrange(self.primary_len());
+    }
+
+    /// Drops the sort order and the fuzzy and suffix indexes and gives each key its own bytes,
+    /// rebuilding the table if it has any of them, so that keys can be inserted and removed.
+    fn drop_indexes(&mut self) {
+        if self.share_suffixes || self.has_index() {
+            self.deletions = None;
+            self.suffixes = None;
+            self.layout_keys(self.key_align, false, false);
+        }
+    }
+
+    /// Keeps the order of keys without rebuilding the table,
+    /// whose key bytes must be packed in key order, as when it is built from sorted keys.
+    fn keep_key_order(&mut self) {
+        self.order = Some(self.sorted_positions());
+    }
+
+    /// Adds `stash_len` slots storing the keys that would be placed `max_probe` or more slots
+    /// away from their home slots, which must be positive.
+    fn enable_stash(&mut self, stash_len: usize, max_probe: usize) {
+        let capacity = self.primary_len();
+        self.stash_len = stash_len;
+        self.max_probe = max_probe;
+        self.rearrange(capacity);
+    }
+
+    /// Advises the kernel to back the slots and key bytes with huge pages,
+    /// also after they are reallocated by rebuilding.
+    #[cfg(feature = "hugepages")]
+    fn advise_huge_pages(&mut self) -> Result<()> {
+        // Shared slots and key bytes belong to their backend, such as a memory-mapped file.
+        if !self.nodes.is_shared() {
+            hugepage::advise(&self.nodes)?;
+        }
+        if !self.bytes.is_shared() {
+            hugepage::advise(&self.bytes[..])?;
+        }
+        self.huge_pages = true;
+        Ok(())
+    }
+
+    /// Rebuilds the table by placing the keys in the lexicographic order,
+    /// keeping the capacity.
+    ///
+    /// The slots and key bytes then depend only on the keys and the options,
+    /// not on the order in which the keys were inserted.
+    // The nodes are drained instead of consumed so that their allocation can be wiped.
+    #[allow(clippy::iter_with_drain)]
+    fn canonicalize(&mut self) {
+        let capacity = self.primary_len();
+        let mut old = self.nodes.take();
+        let mut nodes: Vec<_> = old.drain(..).filter(|node| node.is_occupied()).collect();
+        // Keys are distinct, so the unstable sort is deterministic.
+        nodes.sort_unstable_by(|a, b| self.get_bytes(a).cmp(self.get_bytes(b)));
+        self.place(nodes.drain(..), capacity, false);
+        wipe(&mut old);
+        wipe(&mut nodes);
+    }
+
+    /// Rebuilds the table by placing the keys in the descending order of `counts`,
+    /// the numbers of accesses indexed by slot position, keeping the capacity.
+    ///
+    /// Frequent keys then come earliest in their probe sequences,
+    /// and their key bytes are packed together unless the keys are sorted or share suffixes.
+    fn reorder_by_counts(&mut self, counts: &[u64]) {
+        let capacity = self.primary_len();
+        let mut positions: Vec<_> = (0..self.nodes.len())
+            .filter(|&pos| self.nodes[pos].is_occupied())
+            .collect();
+        // The stable sort keeps keys of the same count in slot order.
+        positions.sort_by_key(|&pos| std::cmp::Reverse(counts[pos]));
+        let mut old = self.nodes.take();
+        let mut nodes: Vec<_> = positions
+            .iter()
+            .map(|&pos| std::mem::take(&mut old[pos]))
+            .collect();
+        self.place(nodes.drain(..), capacity, true);
+        wipe(&mut old);
+        wipe(&mut nodes);
+    }
+
+    /// Rebuilds the table with the given load factor.
+    fn optimize(&mut self, load_factor: Ratio) -> Result<()> {
+        let capacity = capacity_for(self.num_keys, load_factor)?;
+        self.rearrange(capacity);
+        Ok(())
+    }
+
+    /// Rebuilds the table with the given seed, keeping the capacity.
+    fn rehash(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rearrange(self.primary_len());
+    }
+
+    /// Places the nodes into `capacity` primary slots and the stash, repacking the key bytes.
+    // The nodes are drained instead of consumed so that their allocation can be wiped.
+    #[allow(clippy::iter_with_drain)]
+    fn rearrange(&mut self, capacity: usize) {
+        let mut old = self.nodes.take();
+        self.place(
+            old.drain(..).filter(|node| node.is_occupied()),
+            capacity,
+            false,
+        );
+        wipe(&mut old);
+    }
+
+    /// Places the occupied `nodes` into `capacity` primary slots and the stash
+    /// in the iteration order, repacking the key bytes.
+    /// If `in_order` is true, the key bytes are packed in the iteration order (see [`Table::arrange`]).
+    fn place<I>(&mut self, nodes: I, capacity: usize, in_order: bool)
+    where
+        I: Iterator<Item = N>,
+    {
+        let (nodes, bytes, probe_limit) = self.arrange(nodes, capacity, in_order);
+        self.nodes = nodes.into();
+        self.probe_limit = probe_limit;
+        self.bytes.wipe();
+        self.bytes = bytes.into();
+        #[cfg(feature = "hugepages")]
+        if self.huge_pages {
+            // The advice was accepted for the previous arrays, so it is expected to be accepted again.
+            let _ = self.advise_huge_pages();
+        }
+        self.num_tombstones = 0;
+        self.generation = self.generation.wrapping_add(1);
+        self.rebuild_indexes();
+    }
+
+    /// Rebuilds the enabled auxiliary indexes after the slots or key bytes are changed.
+    fn rebuild_indexes(&mut self) {
+        if self.order.is_none() && self.deletions.is_none() && self.suffixes.is_none() {
+            return;
+        }
+        phase!("indexes", num_keys = self.num_keys);
+        if self.order.is_some() {
+            self.order = Some(self.sorted_positions());
+        }
+        if self.deletions.is_some() {
+            self.deletions = Some(self.deletion_index());
+        }
+        if self.suffixes.is_some() {
+            self.suffixes = Some(self.suffix_index());
         }
     }
 
+    /// Creates a new table consisting of the nodes satisfying `pred`,
+    /// copying the packed key bytes of the kept nodes.
+    fn filter<F>(&self, mut pred: F) -> Self
+    where
+        F: FnMut(&[u8], &N) -> bool,
+    {
+        let nodes = self
+            .iter()
+            .filter(|(key, node)| pred(key, node))
+            .map(|(_, node)| node.clone())
+            .collect();
+        self.with_nodes(nodes)
+    }
+
+    /// Splits the table into the nodes satisfying `pred` and the others in one pass,
+    /// copying the packed key bytes of each part.
+    fn partition<F>(mut self, mut pred: F) -> (Self, Self)
+    where
+        F: FnMut(&[u8], &N) -> bool,
+    {
+        let (mut left, mut right) = (vec![], vec![]);
+        for node in self.nodes.take() {
+            if !node.is_occupied() {
+                continue;
+            }
+            if pred(self.get_bytes(&node), &node) {
+                left.push(node);
+            } else {
+                right.push(node);
+            }
+        }
+        (self.with_nodes(left), self.with_nodes(right))
+    }
+
+    /// Applies the options of `self` on the key layout, lookups, indexes, and resizing
+    /// to `table` built with the default options, rearranging it.
+    fn copy_options_to(&self, table: &mut Self) {
+        let capacity = table.primary_len();
+        table.key_align = self.key_align;
+        table.share_suffixes = self.share_suffixes;
+        table.constant_time = self.constant_time;
+        table.stash_len = self.stash_len;
+        table.max_probe = self.max_probe;
+        table.order = self.order.as_ref().map(|_| vec![]);
+        table.deletions = self.deletions.as_ref().map(|_| vec![]);
+        table.suffixes = self.suffixes.as_ref().map(|_| vec![]);
+        table.resize = self.resize;
+        table.key_len_limit = self.key_len_limit;
+        table.lookup_cache = self.lookup_cache.clone();
+        table.rearrange(capacity);
+        #[cfg(feature = "hugepages")]
+        if self.huge_pages {
+            // The advice was accepted for this table, so it is expected to be accepted again.
+            let _ = table.advise_huge_pages();
+        }
+    }
+
+    /// Creates a new table consisting of `nodes` referring to keys in `self.bytes`
+    /// in the same layout, with the default load factor.
+    fn with_nodes(&self, nodes: Vec<N>) -> Self {
+        let num_keys = nodes.len();
+        let max_key_len = nodes.iter().map(|node| node.len()).max().unwrap_or(0);
+        // The default load factor is always valid.
+        let capacity = capacity_for(num_keys, DEFAULT_LOAD_FACTOR).unwrap();
+        let (nodes, bytes, probe_limit) = self.arrange(nodes.into_iter(), capacity, false);
+        let mut table = Self {
+            nodes: nodes.into(),
+            bytes: bytes.into(),
+            num_keys,
+            num_tombstones: 0,
+            generation: 0,
+            max_key_len,
+            seed: self.seed,
+            key_align: self.key_align,
+            share_suffixes: self.share_suffixes,
+            constant_time: self.constant_time,
+            stash_len: self.stash_len,
+            max_probe: self.max_probe,
+            probe_limit,
+            order: self.order.as_ref().map(|_| vec![]),
+            deletions: self.deletions.as_ref().map(|_| vec![]),
+            suffixes: self.suffixes.as_ref().map(|_| vec![]),
+            resize: self.resize,
+            key_len_limit: self.key_len_limit,
+            lookup_cache: self.lookup_cache.clone(),
+            #[cfg(feature = "hugepages")]
+            huge_pages: false,
+            #[cfg(feature = "instrument")]
+            probe_counters: Default::default(),
+        };
+        #[cfg(feature = "hugepages")]
+        if self.huge_pages {
+            // The advice was accepted for this table, so it is expected to be accepted again.
+            let _ = table.advise_huge_pages();
+        }
+        table.rebuild_indexes();
+        table
+    }
+
+    /// Places `nodes` referring to keys in `self.bytes` into `capacity` primary slots
+    /// followed by `self.stash_len` stash slots,
+    /// returning the slots, the repacked key bytes, and the probe limit of lookups.
+    ///
+    /// The key bytes are packed in slot order, or in the iteration order if `in_order` is true,
+    /// or in key order if `self.order` is set, and aligned to `self.key_align` bytes.
+    /// They are packed with shared suffixes instead if `self.share_suffixes` is set.
+    fn arrange<I>(&self, nodes: I, capacity: usize, in_order: bool) -> (Vec<N>, Vec<u8>, usize)
+    where
+        I: Iterator<Item = N>,
+    {
+        let key_of = |node: &N| &self.bytes[node.ptr()..node.ptr() + node.len()];
+        let mut slots = vec![N::vacant(); capacity + self.stash_len];
+        let mut num_bytes = 0;
+        let mut probe_limit = self.max_probe;
+        // Slot positions in the iteration order, if the key bytes are packed in it.
+        let mut placed = vec![];
+        {
+            phase!("slot_assignment", num_keys = self.num_keys, capacity);
+            for node in nodes {
+                let hash = self.hash(key_of(&node));
+                let (pos, dist) = free_slot(&slots, capacity, self.max_probe, hash);
+                if let Some(dist) = dist {
+                    probe_limit = probe_limit.max(dist + 1);
+                }
+                num_bytes += node.len().next_multiple_of(self.key_align);
+                slots[pos] = node;
+                if in_order {
+                    placed.push(pos);
+                }
+            }
+        }
+        phase!(
+            "byte_packing",
+            num_bytes,
+            key_align = self.key_align,
+            share_suffixes = self.share_suffixes,
+            sorted = self.order.is_some()
+        );
+
+        let mut bytes = Vec::with_capacity(num_bytes);
+        if self.share_suffixes {
+            let mut nodes: Vec<_> = slots.iter_mut().filter(|node| !node.is_vacant()).collect();
+            // In the descending order of the reversed keys, a key that is a suffix of
+            // another key immediately follows a key having it as a suffix,
+            // so it is a suffix of the last appended key.
+            nodes.sort_unstable_by(|a, b| {
+                let (a, b) = (key_of(a), key_of(b));
+                b.iter().rev().cmp(a.iter().rev())
+            });
+            let mut last: &[u8] = &[];
+            for node in nodes {
+                let key = key_of(node);
+                if last.ends_with(key) {
+                    node.set_ptr(bytes.len() - key.len());
+                } else {
+                    node.set_ptr(bytes.len());
+                    bytes.extend_from_slice(key);
+                    last = key;
+                }
+            }
+            bytes.shrink_to_fit();
+        } else {
+            let mut positions: Vec<_> = if in_order {
+                placed
+            } else {
+                (0..slots.len())
+                    .filter(|&pos| !slots[pos].is_vacant())
+                    .collect()
+            };
+            if self.order.is_some() {
+                positions.sort_unstable_by(|&a, &b| key_of(&slots[a]).cmp(key_of(&slots[b])));
+            }
+            for pos in positions {
+                let node = &mut slots[pos];
+                let ptr = bytes.len();
+                bytes.extend_from_slice(key_of(node));
+                bytes.resize(bytes.len().next_multiple_of(self.key_align), 0);
+                node.set_ptr(ptr);
+            }
+        }
+        (slots, bytes, probe_limit)
+    }
+
+    /// Returns the slot positions in the ascending order of keys,
+    /// assuming that the key bytes are packed in key order.
+    fn sorted_positions(&self) -> Vec<usize> {
+        let mut order: Vec<_> = (0..self.nodes.len())
+            .filter(|&pos| self.nodes[pos].is_occupied())
+            .collect();
+        // The empty key shares its position with the next key.
+        order.sort_unstable_by_key(|&pos| {
+            let node = &self.nodes[pos];
+            (node.ptr(), node.len())
+        });
+        order
+    }
+
+    /// Returns the keys and nodes in the ascending order of keys.
+    #[cfg(feature = "fst")]
+    fn sorted_entries(&self) -> Vec<(&[u8], &N)> {
+        if let Some(order) = &self.order {
+            return order.iter().map(|&pos| self.entry_at(pos)).collect();
+        }
+        let mut entries: Vec<_> = self.iter().collect();
+        entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        entries
+    }
+
+    /// Returns true if the order of keys, the fuzzy index, or the suffix index is kept,
+    /// which would be invalidated by inserting or removing keys.
+    #[inline(always)]
+    const fn has_index(&self) -> bool {
+        self.order.is_some() || self.deletions.is_some() || self.suffixes.is_some()
+    }
+
+    /// Returns the slot positions in the ascending order of keys,
+    /// or an error if the key bytes are not sorted.
+    fn order(&self) -> Result<&[usize]> {
+        self.order
+            .as_deref()
+            .ok_or_else(|| anyhow!("The keys must be sorted to support ordered operations."))
+    }
+
+    /// Returns the key and node of the `pos`-th slot, which must be occupied.
+    #[inline(always)]
+    fn entry_at(&self, pos: usize) -> (&[u8], &N) {
+        let node = &self.nodes[pos];
+        (self.get_bytes(node), node)
+    }
+
+    /// Returns the number of keys less than `key`.
+    fn rank(&self, key: &[u8]) -> Result<usize> {
+        let order = self.order()?;
+        Ok(order.partition_point(|&pos| self.entry_at(pos).0 < key))
+    }
+
+    /// Returns the slot positions of the keys within the bounds in the ascending order.
+    /// If the lower bound exceeds the upper bound, no position is returned.
+    fn range_positions(&self, start: Bound<&[u8]>, end: Bound<&[u8]>) -> Result<&[usize]> {
+        let order = self.order()?;
+        let bound = |bound, unbounded| match bound {
+            Bound::Included(key) => order.partition_point(|&pos| self.entry_at(pos).0 < key),
+            Bound::Excluded(key) => order.partition_point(|&pos| self.entry_at(pos).0 <= key),
+            Bound::Unbounded => unbounded,
+        };
+        let lo = bound(start, 0);
+        let hi = match end {
+            Bound::Included(key) => bound(Bound::Excluded(key), order.len()),
+            Bound::Excluded(key) => bound(Bound::Included(key), order.len()),
+            Bound::Unbounded => order.len(),
+        };
+        Ok(&order[lo..hi.max(lo)])
+    }
+
+    /// Returns the slot positions of the keys starting with `prefix` in the ascending order.
+    fn prefix_positions(&self, prefix: &[u8]) -> Result<&[usize]> {
+        let order = self.order()?;
+        // Keys starting with `prefix` are contiguous in the order.
+        let lo = order.partition_point(|&pos| self.entry_at(pos).0 < prefix);
+        let hi = lo + order[lo..].partition_point(|&pos| self.entry_at(pos).0.starts_with(prefix));
+        Ok(&order[lo..hi])
+    }
+
+    /// Returns the `i`-th smallest key and its node.
+    fn select(&self, i: usize) -> Result<Option<(&[u8], &N)>> {
+        let order = self.order()?;
+        Ok(order.get(i).map(|&pos| self.entry_at(pos)))
+    }
+
+    /// Inserts `key` if it is not stored, growing the table by `self.resize` if needed,
+    /// and returns the slot position of the key and whether it is newly inserted.
+    ///
+    /// The node of a new key has the default fields except for the key reference.
+    fn insert(&mut self, key: &[u8]) -> Result<(usize, bool)> {
+        self.insert_hashed(key, self.hash(key))
+    }
+
+    /// Inserts `key` in the same manner as [`Table::insert`], given its hash value.
+    fn insert_hashed(&mut self, key: &[u8], hash: u64) -> Result<(usize, bool)> {
+        check_key_len(key.len(), self.key_len_limit)?;
+        if let Some(pos) = self.get_pos_hashed(key, hash) {
+            return Ok((pos, false));
+        }
+        if self.share_suffixes || self.has_index() {
+            return Err(anyhow!(
+                "Keys cannot be inserted when suffixes are shared, keys are sorted, or the fuzzy or suffix index is enabled."
+            ));
+        }
+        let len = N::Len::from_usize(key.len()).ok_or_else(|| {
+            anyhow!("The key length must not exceed the range of the length type.")
+        })?;
+        let num_keys = self.num_keys + 1;
+        // At least one slot must remain vacant to terminate probing even if the load factor is 1.
+        if num_keys >= self.primary_len()
+            || self
+                .resize
+                .load_factor
+                .is_exceeded(num_keys, self.primary_len())
+        {
+            self.grow(num_keys)?;
+        }
+        if num_keys + self.num_tombstones >= self.primary_len() {
+            self.rearrange(self.primary_len());
+        }
+        // The key is absent, so the first tombstone on its probe sequence can be reused.
+        let (pos, dist) = free_slot(&self.nodes, self.primary_len(), self.max_probe, hash);
+        if let Some(dist) = dist {
+            self.probe_limit = self.probe_limit.max(dist + 1);
+        }
+        if self.nodes[pos].is_tombstone() {
+            self.num_tombstones -= 1;
+        }
+        self.nodes[pos] = N::new(self.bytes.len(), len);
+        #[cfg(feature = "zeroize")]
+        self.reserve_bytes(key.len().next_multiple_of(self.key_align));
+        let bytes = self.bytes.to_mut();
+        bytes.extend_from_slice(key);
+        bytes.resize(bytes.len().next_multiple_of(self.key_align), 0);
+        self.num_keys = num_keys;
+        self.max_key_len = self.max_key_len.max(key.len());
+        Ok((pos, true))
+    }
+
+    /// Grows the table so that it can store `num_keys` keys.
+    ///
+    /// The number of slots is multiplied by the growth factor, or more if the load factor requires,
+    /// but does not exceed the maximum capacity.
+    /// Once the maximum capacity is reached, keys are stored beyond the load factor
+    /// while at least one slot remains empty.
+    fn grow(&mut self, num_keys: usize) -> Result<()> {
+        let capacity = self.primary_len();
+        let max_capacity = self
+            .resize
+            .max_capacity
+            .min(Self::max_slots())
+            .max(capacity);
+        if capacity == max_capacity {
+            if num_keys < capacity {
+                return Ok(());
+            }
+            return Err(anyhow!(
+                "The number of keys must be less than the maximum capacity."
+            ));
+        }
+        let grown = self.resize.growth_factor.mul_ceil(capacity);
+        let required = self.resize.load_factor.div_ceil(num_keys);
+        let new_capacity = grown.max(required).max(num_keys + 1).min(max_capacity);
+        self.rearrange(new_capacity);
+        Ok(())
+    }
+
+    /// Removes `key` and returns its node, or `None` if it is not stored.
+    ///
+    /// The slot of the key becomes a tombstone unless it ends a probe sequence,
+    /// and the table is compacted when the ratio of tombstones exceeds `self.resize`.
+    fn remove(&mut self, key: &[u8]) -> Result<Option<N>> {
+        if self.share_suffixes || self.has_index() {
+            return Err(anyhow!(
+                "Keys cannot be removed when suffixes are shared, keys are sorted, or the fuzzy or suffix index is enabled."
+            ));
+        }
+        let Some(pos) = self.get_pos(key) else {
+            return Ok(None);
+        };
+        Ok(Some(self.remove_at(pos)))
+    }
+
+    /// Removes the key of the `pos`-th slot, which must be occupied, and returns its node,
+    /// assuming that keys can be removed.
+    fn remove_at(&mut self, pos: usize) -> N {
+        let capacity = self.primary_len();
+        // Stash slots are on no probe sequence, so they become vacant at once.
+        let stashed = pos >= capacity;
+        let empty = if stashed { N::vacant() } else { N::tombstone() };
+        let node = std::mem::replace(&mut self.nodes[pos], empty);
+        // Suffixes are not shared, so the bytes are referred to only by the removed key.
+        // Shared bytes belong to their backend, so they are left as they are.
+        #[cfg(feature = "zeroize")]
+        if let KeyBytes::Owned(bytes) = &mut self.bytes {
+            zeroize::Zeroize::zeroize(&mut bytes[node.ptr()..node.ptr() + node.len()]);
+        }
+        self.num_keys -= 1;
+        self.generation = self.generation.wrapping_add(1);
+        if stashed {
+            return node;
+        }
+        self.num_tombstones += 1;
+        // Tombstones followed by a vacant slot end no probe sequence of stored keys.
+        if self.nodes[next_slot(pos, capacity)].is_vacant() {
+            let mut pos = pos;
+            while self.nodes[pos].is_tombstone() {
+                self.nodes[pos] = N::vacant();
+                self.num_tombstones -= 1;
+                pos = (pos + capacity - 1) % capacity;
+            }
+        }
+        if self
+            .resize
+            .tombstone_ratio
+            .is_exceeded(self.num_tombstones, capacity)
+        {
+            self.rearrange(capacity);
+        }
+        node
+    }
+
+    /// Grows the table at once so that `additional` more keys can be inserted
+    /// without exceeding the load factor, up to the maximum capacity.
+    fn reserve(&mut self, additional: usize) -> Result<()> {
+        let max_capacity = self.resize.max_capacity.min(Self::max_slots());
+        let num_keys = self
+            .num_keys
+            .checked_add(additional)
+            .filter(|&n| n < max_capacity)
+            .ok_or_else(|| anyhow!("The number of keys must be less than the maximum capacity."))?;
+        if num_keys < self.primary_len()
+            && !self
+                .resize
+                .load_factor
+                .is_exceeded(num_keys, self.primary_len())
+        {
+            return Ok(());
+        }
+        let required = self.resize.load_factor.div_ceil(num_keys);
+        let new_capacity = required.max(num_keys + 1).min(max_capacity);
+        self.rearrange(new_capacity);
+        Ok(())
+    }
+
+    /// Returns the largest number of slots whose allocation does not exceed `isize::MAX` bytes,
+    /// which growth can reach on 32-bit targets.
+    const fn max_slots() -> usize {
+        isize::MAX as usize / std::mem::size_of::<N>()
+    }
+
+    /// Removes all the keys, keeping the allocated slots and key bytes for reuse.
+    fn clear(&mut self) {
+        let capacity = self.nodes.len();
+        self.nodes.wipe();
+        self.nodes.to_mut().resize(capacity, N::vacant());
+        self.bytes.wipe();
+        self.num_keys = 0;
+        self.num_tombstones = 0;
+        self.generation = self.generation.wrapping_add(1);
+        self.max_key_len = 0;
+        self.probe_limit = self.max_probe;
+        self.rebuild_indexes();
+    }
+
+    /// Shrinks the allocation of the key bytes to fit.
+    fn shrink_bytes(&mut self) {
+        let KeyBytes::Owned(bytes) = &mut self.bytes else {
+            return;
+        };
+        if cfg!(feature = "zeroize") && bytes.capacity() > bytes.len() {
+            // The reallocation by `shrink_to_fit` would leave the old bytes behind.
+            let shrunk = bytes.clone();
+            wipe(bytes);
+            *bytes = shrunk;
+        } else {
+            bytes.shrink_to_fit();
+        }
+    }
+
+    /// Reserves `additional` key bytes, wiping the previous allocation if it is moved.
+    #[cfg(feature = "zeroize")]
+    fn reserve_bytes(&mut self, additional: usize) {
+        let old = self.bytes.to_mut();
+        if old.capacity() - old.len() >= additional {
+            return;
+        }
+        let mut bytes = Vec::with_capacity((old.len() + additional).max(2 * old.capacity()));
+        bytes.extend_from_slice(old);
+        wipe(old);
+        *old = bytes;
+    }
+
     #[inline(always)]
     fn get<K>(&self, key: K) -> Option<&N>
     where
         K: AsRef<[u8]>,
     {
-        self.get_pos(key.as_ref())
-            .and_then(|pos| self.nodes[pos].as_ref())
+        self.get_pos(key.as_ref()).map(|pos| &self.nodes[pos])
     }
 
     #[inline(always)]
@@ -82,8 +1199,7 @@ where
     where
         K: AsRef<[u8]>,
     {
-        self.get_pos(key.as_ref())
-            .and_then(|pos| self.nodes[pos].as_mut())
+        self.get_pos(key.as_ref()).map(|pos| &mut self.nodes[pos])
     }
 
     #[inline(always)]
@@ -92,33 +1208,416 @@ where
         K: AsRef<[u8]>,
     {
         let key = key.as_ref();
-        let mut pos = hash_key(key) & self.capacity_mask;
-        while let Some(node) = &self.nodes[pos] {
-            if key == self.get_bytes(node) {
+        if key.len() > self.key_len_limit {
+            return None;
+        }
+        let find = || self.get_pos_hashed(key, self.hash(key));
+        self.lookup_cache.as_ref().map_or_else(find, |cache| {
+            cache.get_or_find(key, self.cache_stamp(), find)
+        })
+    }
+
+    /// Returns the slot position of `key`, using its precomputed hash value
+    /// if the table has the default seed.
+    ///
+    /// The lookup cache is bypassed, since it would hash the key.
+    #[inline(always)]
+    fn get_pos_prehashed(&self, key: &PrehashedKey) -> Option<usize> {
+        let bytes = key.as_bytes();
+        if self.seed != DEFAULT_SEED {
+            return self.get_pos(bytes);
+        }
+        if bytes.len() > self.key_len_limit {
+            return None;
+        }
+        self.get_pos_hashed(bytes, key.hash())
+    }
+
+    /// Returns the stamp of the current slots, which changes whenever a key is inserted
+    /// or a slot may come to store another key, invalidating cached lookups.
+    #[inline(always)]
+    const fn cache_stamp(&self) -> (u64, usize) {
+        // Keys are removed only with a new generation,
+        // so the number of keys grows by every insertion within a generation.
+        (self.generation, self.num_keys)
+    }
+
+    /// Enables the cache of the `capacity` most recent lookups, or disables it if 0.
+    fn set_lookup_cache(&mut self, capacity: usize) {
+        self.lookup_cache = (capacity != 0).then(|| cache::LookupCache::new(capacity));
+    }
+
+    /// Returns the slot position of `key` given its hash value.
+    #[inline(always)]
+    fn get_pos_hashed(&self, key: &[u8], hash: u64) -> Option<usize> {
+        let capacity = self.primary_len();
+        let mut pos = home_slot(hash, capacity);
+        let mut probes = 0;
+        while probes < self.probe_limit {
+            let Some(node) = self.node(pos) else {
+                break;
+            };
+            if !node.is_tombstone() && self.key_eq(key, node) {
+                #[cfg(feature = "instrument")]
+                self.probe_counters.record(true, probes + 1);
                 return Some(pos);
             }
-            pos = (pos + 1) & self.capacity_mask;
+            pos = next_slot(pos, capacity);
+            probes += 1;
+        }
+        let pos = self.stash_pos(key);
+        #[cfg(feature = "instrument")]
+        self.probe_counters.record(pos.is_some(), probes + 1);
+        pos
+    }
+
+    /// Iterates over the slot positions of the keys whose hash values equal `hash`,
+    /// probing the slots on which such keys can be stored and then the stash.
+    ///
+    /// The hash values are not stored, so they are recomputed from the probed keys.
+    fn hashed_positions(&self, hash: u64) -> impl Iterator<Item = usize> + '_ {
+        let capacity = self.primary_len();
+        let home = home_slot(hash, capacity);
+        let probed = (0..capacity.min(self.probe_limit))
+            .map(move |i| (home + i) % capacity)
+            .take_while(|&pos| !self.nodes[pos].is_vacant());
+        probed
+            .chain(capacity..self.nodes.len())
+            .filter(move |&pos| {
+                let node = &self.nodes[pos];
+                node.is_occupied() && self.hash(self.get_bytes(node)) == hash
+            })
+    }
+
+    /// Returns the slot position of `key` in the stash, scanning all the stash slots.
+    #[inline(always)]
+    fn stash_pos(&self, key: &[u8]) -> Option<usize> {
+        let capacity = self.primary_len();
+        self.nodes[capacity..]
+            .iter()
+            .position(|node| node.is_occupied() && self.key_eq(key, node))
+            .map(|i| capacity + i)
+    }
+
+    /// Finds the longest key that is a prefix of `haystack`,
+    /// returning its length and node.
+    #[inline(always)]
+    fn longest_prefix(&self, haystack: &[u8]) -> Option<(usize, &N)> {
+        for len in (0..=self.max_key_len.min(haystack.len())).rev() {
+            if let Some(node) = self.get(&haystack[..len]) {
+                return Some((len, node));
+            }
         }
         None
     }
 
+    #[inline(always)]
+    fn key_eq(&self, key: &[u8], node: &N) -> bool {
+        if self.constant_time {
+            return constant_time_eq(key, self.get_bytes(node));
+        }
+        if self.key_align == 1 {
+            return key == self.get_bytes(node);
+        }
+        if key.len() != node.len() {
+            return false;
+        }
+        // The stored key is followed by zeros up to the lane boundary,
+        // so only the query tail needs padding.
+        let lanes = &self.bytes[node.ptr()..];
+        let mut chunks = key.chunks_exact(KEY_ALIGN);
+        let mut offset = 0;
+        for chunk in &mut chunks {
+            if chunk != &lanes[offset..offset + KEY_ALIGN] {
+                return false;
+            }
+            offset += KEY_ALIGN;
+        }
+        let rem = chunks.remainder();
+        if rem.is_empty() {
+            return true;
+        }
+        let mut tail = [0; KEY_ALIGN];
+        tail[..rem.len()].copy_from_slice(rem);
+        tail == lanes[offset..offset + KEY_ALIGN]
+    }
+
+    #[inline(always)]
+    fn hash(&self, key: &[u8]) -> u64 {
+        hash_key(key, self.seed)
+    }
+
     #[inline(always)]
     fn get_bytes(&self, node: &N) -> &[u8] {
         &self.bytes[node.ptr()..node.ptr() + node.len()]
     }
 
+    /// Returns the node of the `pos`-th slot, or `None` if the slot is vacant.
+    /// The node may be a tombstone.
+    #[inline(always)]
+    fn node(&self, pos: usize) -> Option<&N> {
+        let node = &self.nodes[pos];
+        (!node.is_vacant()).then_some(node)
+    }
+
+    /// Iterates over the occupied nodes in slot order.
+    fn occupied(&self) -> impl DoubleEndedIterator<Item = &N> + ExactSizeIterator {
+        self.occupied_slots().map(|(_, node)| node)
+    }
+
+    /// Iterates over the slot positions and nodes of the occupied slots in slot order.
+    fn occupied_slots(&self) -> impl DoubleEndedIterator<Item = (usize, &N)> + ExactSizeIterator {
+        let slots = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.is_occupied());
+        Counted::new(slots, self.num_keys)
+    }
+
+    /// Iterates mutably over the occupied nodes in slot order.
+    fn occupied_mut(&mut self) -> impl Iterator<Item = &mut N> {
+        self.nodes.iter_mut().filter(|node| node.is_occupied())
+    }
+
+    /// Iterates over the stored keys and nodes in slot order.
+    fn iter(&self) -> impl DoubleEndedIterator<Item = (&[u8], &N)> + ExactSizeIterator {
+        self.occupied()
+            .map(move |node| (self.get_bytes(node), node))
+    }
+
     #[inline(always)]
     #[allow(clippy::missing_const_for_fn)]
     fn num_keys(&self) -> usize {
         self.num_keys
     }
+
+    /// Returns the number of slots including the stash.
+    #[inline(always)]
+    #[allow(clippy::missing_const_for_fn)]
+    fn capacity(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns the number of slots excluding the stash, on which keys are probed.
+    #[inline(always)]
+    #[allow(clippy::missing_const_for_fn)]
+    fn primary_len(&self) -> usize {
+        self.nodes.len() - self.stash_len
+    }
+
+    /// Returns the number of keys stored in the stash.
+    fn num_stashed(&self) -> usize {
+        self.nodes[self.primary_len()..]
+            .iter()
+            .filter(|node| node.is_occupied())
+            .count()
+    }
+
+    #[inline(always)]
+    fn load_factor(&self) -> f64 {
+        self.num_keys as f64 / self.capacity() as f64
+    }
 }
 
+#[cfg(feature = "zeroize")]
+impl<N> Drop for Table<N>
+where
+    N: Default + Clone + Node,
+{
+    fn drop(&mut self) {
+        self.nodes.wipe();
+        self.bytes.wipe();
+    }
+}
+
+/// Clears `v`, also overwriting its whole allocation with zeros if the `zeroize` feature is enabled,
+/// so that no copy of sensitive keys or values remains in the memory to be freed or reused.
+///
+/// Values owning heap memory, such as strings, must zeroize it on their own drop,
+/// for example by being wrapped in `zeroize::Zeroizing`.
+fn wipe<T>(v: &mut Vec<T>) {
+    v.clear();
+    #[cfg(feature = "zeroize")]
+    zeroize::Zeroize::zeroize(v.spare_capacity_mut());
+}
+
+/// Computes the hash value of `key` with `seed` in exactly the same manner as
+/// the tables of this crate, so that keys can be partitioned consistently
+/// across several maps or processes, for example by `hash(key, seed) % num_shards`.
+///
+/// The seed of a map is given by [`HashMapBuilder::seed`] and returned by [`HashMap::seed`].
+/// The values are stable across targets and versions of the serialization format.
+///
+/// # Examples
+///
+/// ```
+/// use simplearrayhash::HashMapBuilder;
+///
+/// let map = HashMapBuilder::new().seed(42).build(&[("icdm", 0), ("idce", 1)]).unwrap();
+/// assert_eq!(simplearrayhash::hash("idce", map.seed()), map.hash("idce"));
+///
+/// let shard = simplearrayhash::hash("sigmod", 42) % 4;
+/// assert!(shard < 4);
+/// ```
 #[inline(always)]
-fn hash_key(k: &[u8]) -> usize {
-    fasthash::city::hash64(k) as usize
+pub fn hash<K>(key: K, seed: u64) -> u64
+where
+    K: AsRef<[u8]>,
+{
+    hash_key(key.as_ref(), seed)
 }
 
-const fn ceil_two(n: usize) -> usize {
-    1 << (WORD_BITS - n.leading_zeros() as usize)
+/// Computes the hash value of `k`, which has 64 bits on every target
+/// so that the slots of a table do not depend on the width of `usize`.
+#[inline(always)]
+fn hash_key(k: &[u8], seed: u64) -> u64 {
+    fasthash::city::hash64_with_seed(k, seed)
+}
+
+/// Returns the first slot to probe for `hash` in `capacity` slots.
+///
+/// Built tables have power-of-two capacities, which avoid the division.
+/// Other capacities arise only from growth by non-power-of-two factors.
+/// The remainder is taken on the whole hash value,
+/// so that the slot is the same on 32-bit and 64-bit targets.
+#[inline(always)]
+const fn home_slot(hash: u64, capacity: usize) -> usize {
+    if capacity.is_power_of_two() {
+        (hash & (capacity as u64 - 1)) as usize
+    } else {
+        (hash % capacity as u64) as usize
+    }
+}
+
+/// Returns the position of a slot in `slots` to store a new key with `hash`
+/// and its distance from the home slot, or `None` if it is in the stash.
+///
+/// The first `capacity` slots are probed and the others form the stash.
+/// The first slot that is not occupied on the probe sequence is taken
+/// if it is less than `max_probe` slots away or the stash is full,
+/// and otherwise a vacant stash slot.
+#[inline(always)]
+fn free_slot<N>(slots: &[N], capacity: usize, max_probe: usize, hash: u64) -> (usize, Option<usize>)
+where
+    N: Node,
+{
+    let mut pos = home_slot(hash, capacity);
+    let mut dist = 0;
+    while slots[pos].is_occupied() {
+        pos = next_slot(pos, capacity);
+        dist += 1;
+        if dist == max_probe {
+            if let Some(i) = slots[capacity..].iter().position(|node| node.is_vacant()) {
+                return (capacity + i, None);
+            }
+        }
+    }
+    (pos, Some(dist))
+}
+
+/// Returns the slot to probe after `pos` in `capacity` slots.
+#[inline(always)]
+const fn next_slot(pos: usize, capacity: usize) -> usize {
+    if pos + 1 == capacity {
+        0
+    } else {
+        pos + 1
+    }
+}
+
+/// Returns true if `key` equals `stored`, taking time that depends only on the length of `key`.
+///
+/// Every byte of `key` is compared even after a mismatch, and a difference of the lengths
+/// is folded into the result instead of returning early,
+/// so that the timing does not leak how much of a guessed key matched.
+#[inline(never)]
+fn constant_time_eq(key: &[u8], stored: &[u8]) -> bool {
+    let Some(last) = stored.len().checked_sub(1) else {
+        return key.is_empty();
+    };
+    let mut diff = key.len() ^ stored.len();
+    for (i, &b) in key.iter().enumerate() {
+        diff |= usize::from(b ^ stored[i.min(last)]);
+    }
+    std::hint::black_box(diff) == 0
+}
+
+/// Treatment of a key that is already placed when building a table.
+enum Duplicates<G> {
+    /// The keys are known to be distinct, so they are not compared.
+    Unchecked,
+    /// A duplicate key is an error.
+    Rejected,
+    /// The payload of a duplicate key is merged into the node of the key.
+    Merged(G),
+}
+
+/// Computes the number of slots to store `num_keys` keys within `load_factor`.
+fn capacity_for(num_keys: usize, load_factor: Ratio) -> Result<usize> {
+    if !load_factor.in_unit_interval() {
+        return Err(anyhow!("The load factor must be in (0, 1]."));
+    }
+    // The smallest power of two greater than the required number,
+    // which may not be representable in a 32-bit `usize`.
+    load_factor
+        .div_floor(num_keys)
+        .checked_add(1)
+        .and_then(usize::checked_next_power_of_two)
+        .ok_or_else(|| anyhow!("The number of slots must not exceed the range of usize."))
+}
+
+/// Iterator adapter yielding the items of `iter`, which are known to be `remaining` in total,
+/// so that iterators filtering the occupied slots are exact-sized.
+struct Counted<I> {
+    iter: I,
+    remaining: usize,
+}
+
+impl<I> Counted<I> {
+    const fn new(iter: I, remaining: usize) -> Self {
+        Self { iter, remaining }
+    }
+}
+
+impl<I> Iterator for Counted<I>
+where
+    I: Iterator,
+{
+    type Item = I::Item;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<I::Item> {
+        let item = self.iter.next()?;
+        self.remaining -= 1;
+        Some(item)
+    }
+
+    #[inline(always)]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<I> DoubleEndedIterator for Counted<I>
+where
+    I: DoubleEndedIterator,
+{
+    #[inline(always)]
+    fn next_back(&mut self) -> Option<I::Item> {
+        let item = self.iter.next_back()?;
+        self.remaining -= 1;
+        Some(item)
+    }
+}
+
+impl<I> ExactSizeIterator for Counted<I> where I: Iterator {}
+
+/// Returns an error of [`KeyTooLong`] if a key of `len` bytes exceeds `limit`.
+#[inline(always)]
+fn check_key_len(len: usize, limit: usize) -> Result<()> {
+    if len > limit {
+        return Err(KeyTooLong { len, limit }.into());
+    }
+    Ok(())
 }
@@ -1,21 +1,57 @@
 //! Simple fast hash map implementation for string kyes.
 
-use crate::{Node, Table};
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::marker::PhantomData;
+use std::ops::{Range, RangeBounds};
+use std::sync::Arc;
+
+use crate::dense::DenseNode;
+use crate::encoded::{EncodedHashMap, ValueCodec};
+use crate::grouped::{self, GroupedHashMap};
+use crate::keyable::with_encoded;
+use crate::progress::{BuildPhase, Progress, ProgressFn, Tracked};
+use crate::serial::{self, SectionKind, Sections, Serializable, ValuesIndex};
+use crate::storage::{KeyBytes, KeyStorage};
+use crate::utf16::with_utf8;
+use crate::{
+    check_key_len, hash_key, wipe, CompositeKey, DenseHashMap, KeyLen, Keyable, Node, PrehashedKey,
+    Ratio, ResizePolicy, Table, DEFAULT_GROWTH_FACTOR, DEFAULT_LOAD_FACTOR, DEFAULT_SEED,
+    DEFAULT_TOMBSTONE_RATIO, KEY_ALIGN,
+};
 
 use anyhow::{anyhow, Result};
 
+/// Seed of the digests in [`HashMap::fingerprint`], which must never change.
+const FINGERPRINT_SEED: u128 = 0x7361_6861_7368_5f66_696e_6765_7270_7269;
+
+// The fields are kept in order so that the slots of a set, where `V` is `()` and `L` is `usize`,
+// have the layout of serialized slots and can be borrowed from a storage (see `storage::Nodes`).
 #[derive(Default, Clone)]
-struct MapNode<V> {
+#[repr(C)]
+pub(crate) struct MapNode<V, L> {
     ptr: usize,
-    len: usize,
-    val: V,
+    len: L,
+    pub(crate) val: V,
 }
 
-impl<V> Node for MapNode<V>
+// A serialized slot is the pointer and length of a key in eight little-endian bytes each.
+#[cfg(all(target_endian = "little", target_pointer_width = "64"))]
+const _: () = {
+    assert!(std::mem::size_of::<MapNode<(), usize>>() == 16);
+    assert!(std::mem::offset_of!(MapNode<(), usize>, ptr) == 0);
+    assert!(std::mem::offset_of!(MapNode<(), usize>, len) == 8);
+};
+
+impl<V, L> Node for MapNode<V, L>
 where
     V: Default,
+    L: KeyLen,
 {
-    fn new(ptr: usize, len: usize) -> Self {
+    type Len = L;
+
+    fn new(ptr: usize, len: L) -> Self {
         let val = V::default();
         Self { ptr, len, val }
     }
@@ -25,19 +61,28 @@ where
         self.ptr
     }
 
+    #[inline(always)]
+    fn set_ptr(&mut self, ptr: usize) {
+        self.ptr = ptr;
+    }
+
     #[inline(always)]
     fn len(&self) -> usize {
-        self.len
+        self.len.to_usize()
     }
 }
 
 /// Simple fast hash map implementation for string kyes.
+///
+/// The type parameter `L` specifies the integer type to store key lengths
+/// (see [`HashMapBuilder::key_len`]).
 #[derive(Clone)]
-pub struct HashMap<V>
+pub struct HashMap<V, L = usize>
 where
     V: Default + Clone,
+    L: KeyLen,
 {
-    table: Table<MapNode<V>>,
+    pub(crate) table: Table<MapNode<V, L>>,
 }
 
 impl<V> HashMap<V>
@@ -71,25 +116,196 @@ where
     where
         K: AsRef<[u8]>,
     {
-        if records.is_empty() {
-            return Err(anyhow!("The input records must not be empty."));
-        }
-        let keys: Vec<_> = records.iter().map(|(k, _)| k).collect();
-        let mut table = Table::<MapNode<V>>::build(&keys);
-        let mut flags = vec![false; table.nodes.len()]; // to check duplication
-        for (k, v) in records {
-            let pos = table.get_pos(k).unwrap();
-            if flags[pos] {
-                return Err(anyhow!(
-                    "The input records must not contain duplicated keys."
-                ));
-            }
-            table.nodes[pos].as_mut().unwrap().val = v.clone();
-            flags[pos] = true;
-        }
-        Ok(Self { table })
+        HashMapBuilder::new().build(records)
+    }
+
+    /// Creates a new [`HashMap`] from an iterator of records with a known length,
+    /// consuming the records without buffering them.
+    ///
+    /// See [`HashMapBuilder::build_from_iter`] for the details.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `records` is empty,
+    ///  - `records` contains duplicate keys, or
+    ///  - `records` yields more records than its length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let lines = ["icdm\t0", "idce\t1", "sigmod\t2"];
+    /// let records = lines.iter().map(|line| {
+    ///     let (key, val) = line.split_once('\t').unwrap();
+    ///     (key.to_string(), val.parse::<u32>().unwrap())
+    /// });
+    /// let map = HashMap::new_from_iter(records).unwrap();
+    /// assert_eq!(map.get("idce"), Some(&1));
+    /// ```
+    pub fn new_from_iter<I, K>(records: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        I::IntoIter: ExactSizeIterator,
+        K: AsRef<[u8]>,
+    {
+        HashMapBuilder::new().build_from_iter(records)
+    }
+
+    /// Creates a new [`HashMap`] from input records,
+    /// hashing the keys and detecting duplicate keys in parallel.
+    ///
+    /// See [`HashMapBuilder::build_par`] for the details.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `records` is empty, or
+    ///  - `records` contains duplicate keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new_par(&records).unwrap();
+    /// assert_eq!(map.get("idce"), Some(&1));
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn new_par<K>(records: &[(K, V)]) -> Result<Self>
+    where
+        K: AsRef<[u8]> + Sync,
+        V: Sync,
+    {
+        HashMapBuilder::new().build_par(records)
+    }
+
+    /// Creates a new [`HashMap`] from keys, computing the value of each key with `f`.
+    ///
+    /// See [`HashMapBuilder::build_with`] for the details.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `keys` is empty,
+    ///  - `keys` contains duplicate keys, or
+    ///  - `f` returns an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let keys = vec!["1", "10", "100"];
+    /// let map = HashMap::new_with(&keys, |key| Ok(std::str::from_utf8(key)?.parse::<u32>()?))
+    ///     .unwrap();
+    /// assert_eq!(map.get("10"), Some(&10));
+    ///
+    /// let keys = vec!["1", "one"];
+    /// assert!(HashMap::new_with(&keys, |key| Ok(std::str::from_utf8(key)?.parse::<u32>()?)).is_err());
+    /// ```
+    pub fn new_with<K, F>(keys: &[K], f: F) -> Result<Self>
+    where
+        K: AsRef<[u8]>,
+        F: FnMut(&[u8]) -> Result<V>,
+    {
+        HashMapBuilder::new().build_with(keys, f)
+    }
+
+    /// Creates a new [`HashMap`] from parallel lists of keys and values,
+    /// where `values[i]` is the value of `keys[i]`.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `keys` is empty,
+    ///  - `keys` and `values` have different lengths, or
+    ///  - `keys` contains duplicate keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let keys = vec!["icdm", "idce", "sigmod"];
+    /// let map = HashMap::from_keys_and_values(&keys, vec![0, 1, 2]).unwrap();
+    /// assert_eq!(map.get("idce"), Some(&1));
+    ///
+    /// assert!(HashMap::from_keys_and_values(&keys, vec![0, 1]).is_err());
+    /// ```
+    pub fn from_keys_and_values<K>(keys: &[K], values: Vec<V>) -> Result<Self>
+    where
+        K: AsRef<[u8]>,
+    {
+        HashMapBuilder::new().build_from_keys_and_values(keys, values)
+    }
+
+    /// Creates a new [`HashMap`] from input records whose keys implement [`Keyable`].
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `records` is empty, or
+    ///  - `records` contains duplicate keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::net::Ipv4Addr;
+    ///
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![(Ipv4Addr::new(10, 0, 0, 1), "a"), (Ipv4Addr::LOCALHOST, "b")];
+    /// let map = HashMap::new_keyable(&records).unwrap();
+    /// assert_eq!(map.get_keyable(&Ipv4Addr::LOCALHOST), Some(&"b"));
+    /// ```
+    pub fn new_keyable<T>(records: &[(T, V)]) -> Result<Self>
+    where
+        T: Keyable,
+    {
+        HashMapBuilder::new().build_keyable(records)
+    }
+
+    /// Creates a new [`HashMap`] from input records that may contain duplicate keys,
+    /// folding the values of each key into one with `fold`.
+    ///
+    /// See [`HashMapBuilder::build_grouped`] for the details.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when `records` is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 1), ("idce", 2), ("icdm", 3)];
+    /// let map = HashMap::new_grouped(&records, |acc, v| *acc += v).unwrap();
+    /// assert_eq!(map.get("icdm"), Some(&4));
+    /// assert_eq!(map.get("idce"), Some(&2));
+    /// ```
+    pub fn new_grouped<K, F>(records: &[(K, V)], fold: F) -> Result<Self>
+    where
+        K: AsRef<[u8]>,
+        F: FnMut(&mut V, &V),
+    {
+        HashMapBuilder::new().build_grouped(records, fold)
     }
+}
 
+impl<V, L> HashMap<V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
     /// Returns true if the map contains a value for the specified key.
     ///
     /// # Examples
@@ -130,6 +346,93 @@ where
         self.table.get(key).map(|nd| &nd.val)
     }
 
+    /// Returns a reference to the value corresponding to the key in the same manner as
+    /// [`HashMap::get`], but fails for a key longer than the limit of key lengths
+    /// (see [`HashMapBuilder::key_len_limit`]) without hashing it.
+    ///
+    /// # Errors
+    ///
+    /// An error of [`KeyTooLong`](crate::KeyTooLong) will be returned
+    /// when `key` exceeds the limit of key lengths.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::{HashMapBuilder, KeyTooLong};
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1)];
+    /// let map = HashMapBuilder::new().key_len_limit(4).build(&records).unwrap();
+    /// assert_eq!(map.try_get("idce").unwrap(), Some(&1));
+    /// assert_eq!(map.try_get("kdd").unwrap(), None);
+    /// let err = map.try_get("sigmod").unwrap_err();
+    /// assert!(err.is::<KeyTooLong>());
+    /// ```
+    #[inline(always)]
+    pub fn try_get<K>(&self, key: K) -> Result<Option<&V>>
+    where
+        K: AsRef<[u8]>,
+    {
+        let key = key.as_ref();
+        check_key_len(key.len(), self.table.key_len_limit)?;
+        Ok(self.get(key))
+    }
+
+    /// Returns the seed of the hash function,
+    /// with which [`hash`](crate::hash) computes the same hash values as the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::{HashMap, HashMapBuilder};
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let mut map = HashMapBuilder::new().seed(7).build(&records).unwrap();
+    /// assert_eq!(map.seed(), 7);
+    /// map.rehash(42);
+    /// assert_eq!(map.seed(), 42);
+    /// ```
+    #[inline(always)]
+    pub const fn seed(&self) -> u64 {
+        self.table.seed
+    }
+
+    /// Computes the hash value of `key` with the seed of the map,
+    /// which [`HashMap::get_by_hash`] takes.
+    #[inline(always)]
+    pub fn hash<K>(&self, key: K) -> u64
+    where
+        K: AsRef<[u8]>,
+    {
+        self.table.hash(key.as_ref())
+    }
+
+    /// Iterates over the keys whose hash values equal `hash` and their values,
+    /// for callers verifying the keys on their own, such as when the keys were verified upstream.
+    ///
+    /// The key of `hash` computed by [`HashMap::hash`] is yielded if it is stored,
+    /// possibly with other keys of the same hash value, which are rare.
+    /// The bytes of the key are not passed, while the probed keys are hashed
+    /// since their hash values are not stored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let hash = map.hash("idce");
+    /// let candidates: Vec<_> = map.get_by_hash(hash).collect();
+    /// assert_eq!(candidates, vec![(&b"idce"[..], &1)]);
+    /// assert_eq!(map.get_by_hash(map.hash("sigir")).count(), 0);
+    /// ```
+    pub fn get_by_hash(&self, hash: u64) -> impl Iterator<Item = (&[u8], &V)> {
+        self.table
+            .hashed_positions(hash)
+            .map(|pos| self.table.entry_at(pos))
+            .map(|(key, nd)| (key, &nd.val))
+    }
+
     /// Returns a mutable reference to the value corresponding to the key.
     ///
     /// # Examples
@@ -150,7 +453,8 @@ where
         self.table.get_mut(key).map(|nd| &mut nd.val)
     }
 
-    /// Returns the number of elements in the map.
+    /// Returns a handle to the slot of the key, which accesses its value
+    /// without hashing and probing by [`HashMap::get_by_handle`] and [`HashMap::get_mut_by_handle`].
     ///
     /// # Examples
     ///
@@ -158,62 +462,5308 @@ where
     /// use simplearrayhash::HashMap;
     ///
     /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
-    /// let map = HashMap::new(&records).unwrap();
-    /// assert_eq!(map.len(), 3);
+    /// let mut map = HashMap::new(&records).unwrap();
+    /// let handle = map.get_handle("idce").unwrap();
+    /// for _ in 0..10 {
+    ///     *map.get_mut_by_handle(handle).unwrap() += 1;
+    /// }
+    /// assert_eq!(map.get_by_handle(handle), Some(&11));
+    /// assert_eq!(map.get_handle("sigir"), None);
     /// ```
     #[inline(always)]
-    pub fn len(&self) -> usize {
-        self.table.num_keys()
+    pub fn get_handle<K>(&self, key: K) -> Option<Handle>
+    where
+        K: AsRef<[u8]>,
+    {
+        let pos = self.table.get_pos(key)?;
+        Some(Handle {
+            pos,
+            generation: self.table.generation,
+        })
     }
 
-    /// Returns true if the map contains no elements.
+    /// Returns a reference to the value of the key that `handle` was obtained for.
+    ///
+    /// A handle is invalidated when its slot may come to store another key,
+    /// that is, when a key is removed or the map is rebuilt or cleared;
+    /// then `None` is returned.
+    /// Inserting keys without growing the map keeps handles valid.
+    /// The handle must be obtained from this map;
+    /// a handle from another map may refer to an arbitrary value or panic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let mut map = HashMap::new(&records).unwrap();
+    /// let handle = map.get_handle("idce").unwrap();
+    /// assert_eq!(map.get_by_handle(handle), Some(&1));
+    /// map.remove("icdm").unwrap();
+    /// assert_eq!(map.get_by_handle(handle), None);
+    /// ```
     #[inline(always)]
-    pub fn is_empty(&self) -> bool {
-        self.len() == 0
+    pub fn get_by_handle(&self, handle: Handle) -> Option<&V> {
+        (handle.generation == self.table.generation).then(|| &self.table.nodes[handle.pos].val)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Returns a mutable reference to the value of the key that `handle` was obtained for,
+    /// or `None` if the handle is invalidated as described in [`HashMap::get_by_handle`].
+    #[inline(always)]
+    pub fn get_mut_by_handle(&mut self, handle: Handle) -> Option<&mut V> {
+        if handle.generation != self.table.generation {
+            return None;
+        }
+        Some(&mut self.table.nodes[handle.pos].val)
+    }
 
-    #[test]
-    fn test_basic() {
-        let keys = vec!["icdm", "idce", "", "sigmod", "sigir", "acl"];
-        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
-        let map = HashMap::new(&records).unwrap();
-        assert_eq!(map.len(), 6);
+    /// Inserts a key-value pair into the map, returning the old value if the key was present.
+    ///
+    /// The map grows when the insertion would exceed the load factor,
+    /// as configured by [`HashMapBuilder::growth`] and [`HashMapBuilder::max_capacity`].
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - the map shares suffixes, sorts keys, or has the fuzzy or suffix index,
+    ///  - the length of `key` is out of the range of `L`, or
+    ///  - the map reaches its maximum capacity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let mut map = HashMap::new(&records).unwrap();
+    /// assert_eq!(map.insert("sigir", 3).unwrap(), None);
+    /// assert_eq!(map.insert("idce", 4).unwrap(), Some(1));
+    /// assert_eq!(map.get("sigir"), Some(&3));
+    /// assert_eq!(map.get("idce"), Some(&4));
+    /// assert_eq!(map.len(), 4);
+    /// ```
+    pub fn insert<K>(&mut self, key: K, val: V) -> Result<Option<V>>
+    where
+        K: AsRef<[u8]>,
+    {
+        let (pos, inserted) = self.table.insert(key.as_ref())?;
+        let old = std::mem::replace(&mut self.table.nodes[pos].val, val);
+        Ok((!inserted).then_some(old))
     }
 
-    #[test]
-    fn test_get() {
-        let keys = vec!["icdm", "idce", "", "sigmod", "sigir", "acl"];
-        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
-        let map = HashMap::new(&records).unwrap();
-        for &(k, v) in &records {
-            assert_eq!(*map.get(k).unwrap(), v);
-        }
-        assert_eq!(map.get("sigkdd"), None);
-        assert_eq!(map.get("idml"), None);
+    /// Removes a key from the map, returning its value if the key was present.
+    ///
+    /// The slot of the key is left as a tombstone,
+    /// and the map is rebuilt when tombstones exceed [`HashMapBuilder::max_tombstones`].
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the map shares suffixes, sorts keys,
+    /// or has the fuzzy or suffix index.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let mut map = HashMap::new(&records).unwrap();
+    /// assert_eq!(map.remove("idce").unwrap(), Some(1));
+    /// assert_eq!(map.remove("idce").unwrap(), None);
+    /// assert_eq!(map.get("idce"), None);
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    pub fn remove<K>(&mut self, key: K) -> Result<Option<V>>
+    where
+        K: AsRef<[u8]>,
+    {
+        Ok(self.table.remove(key.as_ref())?.map(|nd| nd.val))
     }
 
-    #[test]
-    fn test_get_mut() {
-        let keys = vec!["icdm", "idce", "", "sigmod", "sigir", "acl"];
-        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
-        let mut map = HashMap::new(&records).unwrap();
-        for &(k, v) in &records {
+    /// Iterates over the occupied slots in slot order,
+    /// yielding the index of each slot and its key and value.
+    ///
+    /// Slot indices are less than [`HashMap::capacity`], so they can index
+    /// auxiliary arrays parallel to the slots.
+    /// They are stable until the map is rebuilt, such as by [`HashMap::insert`]
+    /// growing it, [`HashMap::remove`] compacting it, [`HashMap::optimize`], or [`HashMap::rehash`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let mut lens = vec![0; map.capacity()];
+    /// for (pos, key, _) in map.slots() {
+    ///     lens[pos] = key.len();
+    /// }
+    /// assert_eq!(lens.iter().sum::<usize>(), 14);
+    /// ```
+    pub fn slots(&self) -> Slots<'_, V, L> {
+        Slots {
+            map: self,
+            positions: 0..self.table.capacity(),
+            remaining: self.table.num_keys(),
+        }
+    }
+
+    /// Returns all the key-value pairs as records in slot order,
+    /// which can be edited and passed to [`HashMap::new`] to rebuild the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let mut records = map.to_vec();
+    /// records.retain(|(key, _)| key != b"idce");
+    /// let map = HashMap::new(&records).unwrap();
+    /// assert_eq!(map.len(), 2);
+    /// assert_eq!(map.get("idce"), None);
+    /// ```
+    pub fn to_vec(&self) -> Vec<(Vec<u8>, V)> {
+        self.slots()
+            .map(|(_, key, val)| (key.to_vec(), val.clone()))
+            .collect()
+    }
+
+    /// Returns all the key-value pairs as records with string keys in slot order,
+    /// borrowing the keys from the map.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when a key is not valid UTF-8.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let mut records = map.to_str_vec().unwrap();
+    /// records.sort();
+    /// assert_eq!(records, vec![("icdm", 0), ("idce", 1), ("sigmod", 2)]);
+    /// ```
+    pub fn to_str_vec(&self) -> Result<Vec<(&str, V)>> {
+        self.slots()
+            .map(|(_, key, val)| {
+                let key = std::str::from_utf8(key)
+                    .map_err(|_| anyhow!("The keys must be valid UTF-8."))?;
+                Ok((key, val.clone()))
+            })
+            .collect()
+    }
+
+    /// Creates a new map consisting of the key-value pairs satisfying `pred`.
+    ///
+    /// The packed key bytes of the kept keys are copied directly in the layout of this map,
+    /// without collecting and rebuilding from records.
+    /// The new map is built with the default load factor.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let filtered = map.clone_filtered(|key, &val| key.starts_with(b"i") && val > 0);
+    /// assert_eq!(filtered.len(), 1);
+    /// assert_eq!(filtered.get("idce"), Some(&1));
+    /// assert_eq!(filtered.get("icdm"), None);
+    /// ```
+    pub fn clone_filtered<F>(&self, mut pred: F) -> Self
+    where
+        F: FnMut(&[u8], &V) -> bool,
+    {
+        let table = self.table.filter(|key, nd| pred(key, &nd.val));
+        Self { table }
+    }
+
+    /// Splits the map into the key-value pairs satisfying `pred` and the others,
+    /// consuming the map.
+    ///
+    /// Each key is visited once and hashed once for the part it goes to,
+    /// and the values are moved without being cloned.
+    /// The new maps are built with the default load factor.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let (short, long) = map.partition(|key, _| key.len() == 4);
+    /// assert_eq!(short.len(), 2);
+    /// assert_eq!(short.get("idce"), Some(&1));
+    /// assert_eq!(long.len(), 1);
+    /// assert_eq!(long.get("sigmod"), Some(&2));
+    /// ```
+    pub fn partition<F>(self, mut pred: F) -> (Self, Self)
+    where
+        F: FnMut(&[u8], &V) -> bool,
+    {
+        let (left, right) = self.table.partition(|key, nd| pred(key, &nd.val));
+        (Self { table: left }, Self { table: right })
+    }
+
+    /// Creates a new map whose keys are transformed by `f`, consuming the map.
+    ///
+    /// The values are moved without being cloned,
+    /// and the new map is built with the options of this map,
+    /// such as the load factor, seed, and key layout.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - two keys are transformed into the same key,
+    ///  - a transformed key is longer than the maximum of `L`, or
+    ///  - a transformed key exceeds the limit of key lengths
+    ///    (see [`HashMapBuilder::key_len_limit`]), with an error of [`KeyTooLong`](crate::KeyTooLong).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("ICDM", 0), ("IDCE", 1), ("SIGMOD", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let map = map.map_keys(|key| key.to_ascii_lowercase()).unwrap();
+    /// assert_eq!(map.get("idce"), Some(&1));
+    /// assert_eq!(map.get("IDCE"), None);
+    ///
+    /// let map = HashMap::new(&[("icdm", 0), ("ICDM", 1)]).unwrap();
+    /// assert!(map.map_keys(|key| key.to_ascii_lowercase()).is_err());
+    /// ```
+    pub fn map_keys<F>(self, mut f: F) -> Result<Self>
+    where
+        F: FnMut(&[u8]) -> Vec<u8>,
+    {
+        let mut table = self.table;
+        let records = table
+            .nodes
+            .take()
+            .into_iter()
+            .filter(|node| node.is_occupied())
+            .map(|node| {
+                let key = f(table.get_bytes(&node));
+                // The new map keeps the limit, under which every stored key must be found.
+                check_key_len(key.len(), table.key_len_limit)?;
+                Ok((key, node.val))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let num_bytes = records.iter().map(|(key, _)| key.len()).sum();
+        let mut mapped = Table::build_from_iter(
+            records.into_iter(),
+            num_bytes,
+            table.resize.load_factor,
+            table.seed,
+            |nd: &mut MapNode<V, L>, val| {
+                nd.val = val;
+                Ok(())
+            },
+        )?;
+        table.copy_options_to(&mut mapped);
+        Ok(Self { table: mapped })
+    }
+
+    /// Grows the map at once so that `additional` more keys can be inserted
+    /// without rebuilding it on the way.
+    ///
+    /// The number of slots is computed from the load factor regardless of
+    /// [`HashMapBuilder::growth`], but does not exceed [`HashMapBuilder::max_capacity`].
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the number of keys would reach the maximum capacity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let mut map = HashMap::new(&records).unwrap();
+    /// map.reserve(1000).unwrap();
+    /// let capacity = map.capacity();
+    /// for i in 0..1000 {
+    ///     map.insert(i.to_string(), i).unwrap();
+    /// }
+    /// assert_eq!(map.capacity(), capacity);
+    /// ```
+    pub fn reserve(&mut self, additional: usize) -> Result<()> {
+        self.table.reserve(additional)
+    }
+
+    /// Removes all the key-value pairs from the map,
+    /// keeping the allocated slots and key bytes for reuse.
+    ///
+    /// If the `zeroize` feature is enabled, the slots and key bytes are overwritten with zeros,
+    /// as they also are when the map is dropped or reallocated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let mut map = HashMap::new(&records).unwrap();
+    /// let capacity = map.capacity();
+    /// map.clear();
+    /// assert!(map.is_empty());
+    /// assert_eq!(map.get("idce"), None);
+    /// assert_eq!(map.capacity(), capacity);
+    /// ```
+    pub fn clear(&mut self) {
+        self.table.clear();
+    }
+
+    /// Removes all the key-value pairs from the map, returning them as an iterator in slot order.
+    ///
+    /// The map keeps its allocated slots and key bytes for reuse,
+    /// and is empty when the iterator is dropped even if it is not fully consumed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let mut map = HashMap::new(&records).unwrap();
+    /// let mut drained: Vec<_> = map.drain().collect();
+    /// drained.sort();
+    /// assert_eq!(drained[1], (b"idce".to_vec(), 1));
+    /// assert!(map.is_empty());
+    /// map.insert("sigir", 3).unwrap();
+    /// assert_eq!(map.get("sigir"), Some(&3));
+    /// ```
+    pub fn drain(&mut self) -> Drain<'_, V, L> {
+        let positions = 0..self.table.nodes.len();
+        let remaining = self.table.num_keys();
+        Drain {
+            table: &mut self.table,
+            positions,
+            remaining,
+        }
+    }
+
+    /// Returns a reference to the value corresponding to the composite key
+    /// encoded from `fields` by [`KeyEncoder`](crate::KeyEncoder).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::{HashMap, KeyEncoder};
+    ///
+    /// let records = vec![
+    ///     (KeyEncoder::encode(("ja", "東京")), 0),
+    ///     (KeyEncoder::encode(("en", "Tokyo")), 1),
+    /// ];
+    /// let map = HashMap::new(&records).unwrap();
+    /// assert_eq!(map.get_composite(("en", "Tokyo")), Some(&1));
+    /// assert_eq!(map.get_composite(("ja", "Tokyo")), None);
+    /// ```
+    #[inline(always)]
+    pub fn get_composite<C>(&self, fields: C) -> Option<&V>
+    where
+        C: CompositeKey,
+    {
+        let mut key = vec![];
+        fields.encode_into(&mut key);
+        self.get(key)
+    }
+
+    /// Returns a reference to the value corresponding to the key implementing [`Keyable`].
+    ///
+    /// The key is encoded into a thread-local scratch buffer,
+    /// so no allocation is needed after the first call.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::net::Ipv4Addr;
+    ///
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![(Ipv4Addr::new(10, 0, 0, 1), "a"), (Ipv4Addr::LOCALHOST, "b")];
+    /// let map = HashMap::new_keyable(&records).unwrap();
+    /// assert_eq!(map.get_keyable(&Ipv4Addr::new(10, 0, 0, 1)), Some(&"a"));
+    /// assert_eq!(map.get_keyable(&Ipv4Addr::BROADCAST), None);
+    /// ```
+    #[inline(always)]
+    pub fn get_keyable<T>(&self, key: &T) -> Option<&V>
+    where
+        T: Keyable + ?Sized,
+    {
+        with_encoded(key, |key| self.get(key))
+    }
+
+    /// Returns a reference to the value corresponding to the key given in UTF-16,
+    /// such as a string from Windows APIs or JavaScript.
+    ///
+    /// The key is transcoded into UTF-8 on the fly,
+    /// on the stack unless it is long, instead of converting it into a [`String`].
+    /// A key containing an unpaired surrogate has no UTF-8 form and is never found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("情報", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let key: Vec<u16> = "情報".encode_utf16().collect();
+    /// assert_eq!(map.get_utf16(&key), Some(&1));
+    /// assert_eq!(map.get_utf16(&[0xd800]), None);
+    /// ```
+    #[inline(always)]
+    pub fn get_utf16(&self, key: &[u16]) -> Option<&V> {
+        with_utf8(key, |key| self.get(key)).flatten()
+    }
+
+    /// Returns a reference to the value corresponding to a key hashed in advance,
+    /// typically by the [`key!`](crate::key!) macro at compile time.
+    ///
+    /// The precomputed hash value is used if the map has the default seed,
+    /// and otherwise the key is hashed as in [`HashMap::get`].
+    /// The lookup cache (see [`HashMapBuilder::lookup_cache`]) is bypassed for the default seed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::{key, HashMap};
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// assert_eq!(map.get_prehashed(&key!("idce")), Some(&1));
+    /// assert_eq!(map.get_prehashed(&key!("sigir")), None);
+    /// ```
+    #[inline(always)]
+    pub fn get_prehashed(&self, key: &PrehashedKey) -> Option<&V> {
+        self.table
+            .get_pos_prehashed(key)
+            .map(|pos| &self.table.nodes[pos].val)
+    }
+
+    /// Returns a copy of the value corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// assert_eq!(map.get_copied("idce"), Some(1));
+    /// assert_eq!(map.get_copied("sigir"), None);
+    /// ```
+    #[inline(always)]
+    pub fn get_copied<K>(&self, key: K) -> Option<V>
+    where
+        K: AsRef<[u8]>,
+        V: Copy,
+    {
+        self.get(key).copied()
+    }
+
+    /// Returns a clone of the value corresponding to the key,
+    /// or `default` if the map does not contain the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// assert_eq!(map.get_or("idce", 0), 1);
+    /// assert_eq!(map.get_or("sigir", 0), 0);
+    /// ```
+    #[inline(always)]
+    pub fn get_or<K>(&self, key: K, default: V) -> V
+    where
+        K: AsRef<[u8]>,
+    {
+        self.get(key).cloned().unwrap_or(default)
+    }
+
+    /// Looks up a batch of keys, partitioning them into the keys found in the map
+    /// paired with their values and the keys not found.
+    ///
+    /// Both lists keep the order of `keys`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let (hits, misses) = map.get_batch(["sigmod", "sigir", "icdm", "acl"]);
+    /// assert_eq!(hits, vec![("sigmod", &2), ("icdm", &0)]);
+    /// assert_eq!(misses, vec!["sigir", "acl"]);
+    /// ```
+    pub fn get_batch<I, K>(&self, keys: I) -> (Vec<(K, &V)>, Vec<K>)
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<[u8]>,
+    {
+        let mut hits = vec![];
+        let mut misses = vec![];
+        for key in keys {
+            match self.get(&key) {
+                Some(val) => hits.push((key, val)),
+                None => misses.push(key),
+            }
+        }
+        (hits, misses)
+    }
+
+    /// Splits `text` by `delimiter` and looks up each token,
+    /// yielding its byte range in `text` and the corresponding value.
+    ///
+    /// Tokens are produced in the same manner as [`slice::split`],
+    /// so consecutive delimiters produce empty tokens.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let mut it = map.lookup_tokens("idce at sigmod", b' ');
+    /// assert_eq!(it.next(), Some((0..4, Some(&1))));
+    /// assert_eq!(it.next(), Some((5..7, None)));
+    /// assert_eq!(it.next(), Some((8..14, Some(&2))));
+    /// assert_eq!(it.next(), None);
+    /// ```
+    pub fn lookup_tokens<'a, 't, T>(
+        &'a self,
+        text: &'t T,
+        delimiter: u8,
+    ) -> LookupTokens<'a, 't, V, L>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        LookupTokens {
+            map: self,
+            text: text.as_ref(),
+            delimiter,
+            start: 0,
+            finished: false,
+        }
+    }
+
+    /// Finds the longest key that matches `haystack[start..]` as a prefix,
+    /// returning its byte range in `haystack` and the corresponding value.
+    ///
+    /// The empty key also matches if it is stored in the map.
+    /// `None` is returned if no key matches or `start` is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("東京", 0), ("東京都", 1), ("京都", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let text = "東京都に行く";
+    /// assert_eq!(map.match_at(text, 0), Some((0..9, &1)));
+    /// assert_eq!(map.match_at(text, 3), Some((3..9, &2)));
+    /// assert_eq!(map.match_at(text, 9), None);
+    /// ```
+    #[inline(always)]
+    pub fn match_at<T>(&self, haystack: &T, start: usize) -> Option<(Range<usize>, &V)>
+    where
+        T: AsRef<[u8]> + ?Sized,
+    {
+        let haystack = haystack.as_ref().get(start..)?;
+        self.table
+            .longest_prefix(haystack)
+            .map(|(len, nd)| (start..start + len, &nd.val))
+    }
+
+    /// Returns the number of elements in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// assert_eq!(map.len(), 3);
+    /// ```
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.table.num_keys()
+    }
+
+    /// Returns true if the map contains no elements.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of slots allocated in the map, including the stash slots.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// assert_eq!(map.capacity(), 4);
+    /// ```
+    #[inline(always)]
+    pub fn capacity(&self) -> usize {
+        self.table.capacity()
+    }
+
+    /// Returns the number of keys stored in the stash slots specified by [`HashMapBuilder::stash`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMapBuilder::new().stash(2, 1).build(&records).unwrap();
+    /// assert!(map.num_stashed() <= 2);
+    /// ```
+    #[inline(always)]
+    pub fn num_stashed(&self) -> usize {
+        self.table.num_stashed()
+    }
+
+    /// Enables the cache of the `capacity` most recent lookups, or disables it if `0`,
+    /// discarding the cached lookups (see [`HashMapBuilder::lookup_cache`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let mut map = HashMap::new(&records).unwrap();
+    /// map.set_lookup_cache(8);
+    /// assert_eq!(map.get("sigir"), None);
+    /// map.insert("sigir", 3).unwrap();
+    /// assert_eq!(map.get("sigir"), Some(&3));
+    /// ```
+    pub fn set_lookup_cache(&mut self, capacity: usize) {
+        self.table.set_lookup_cache(capacity);
+    }
+
+    /// Returns the maximum length of keys in bytes (see [`HashMapBuilder::key_len_limit`]),
+    /// which is `usize::MAX` if unlimited.
+    #[inline(always)]
+    pub const fn key_len_limit(&self) -> usize {
+        self.table.key_len_limit
+    }
+
+    /// Changes the maximum length of keys in bytes (see [`HashMapBuilder::key_len_limit`]).
+    ///
+    /// The limit is not serialized, so it must be set again on deserialized maps.
+    /// Keys already stored beyond a lowered limit are no longer found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let mut map = HashMap::new(&records).unwrap();
+    /// map.set_key_len_limit(5);
+    /// assert!(map.insert("sigkdd", 3).is_err());
+    /// assert_eq!(map.get("sigmod"), None);
+    /// assert_eq!(map.get("icdm"), Some(&0));
+    /// ```
+    pub fn set_key_len_limit(&mut self, limit: usize) {
+        self.table.key_len_limit = limit;
+    }
+
+    /// Moves the key bytes into shared memory, so that clones of the map share them
+    /// until either is modified.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let mut map = HashMap::new(&records).unwrap();
+    /// map.share_keys();
+    /// let mut other = map.clone();
+    /// assert!(other.shares_keys());
+    /// *other.get_mut("idce").unwrap() = 10;
+    /// assert!(other.shares_keys());
+    /// assert_eq!(map.get("idce"), Some(&1));
+    /// ```
+    pub fn share_keys(&mut self) {
+        if let KeyBytes::Owned(bytes) = &mut self.table.bytes {
+            let len = bytes.len();
+            let storage: Arc<dyn KeyStorage> = Arc::new(std::mem::take(bytes));
+            self.table.bytes = KeyBytes::shared(storage, 0, len);
+        }
+    }
+
+    /// Returns true if the key bytes are borrowed from a shared backend
+    /// (see [`HashMap::deserialize_from_storage`] and [`HashMap::share_keys`]).
+    #[inline(always)]
+    pub const fn shares_keys(&self) -> bool {
+        self.table.bytes.is_shared()
+    }
+
+    /// Returns the ratio of the number of elements to the number of slots.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// assert_eq!(map.load_factor(), 0.75);
+    /// ```
+    #[inline(always)]
+    pub fn load_factor(&self) -> f64 {
+        self.table.load_factor()
+    }
+
+    /// Returns the statistics of the lookups recorded since the map was built
+    /// or the statistics were last reset.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// assert_eq!((map.get("idce"), map.get("sigir")), (Some(&1), None));
+    /// let stats = map.probe_stats();
+    /// assert_eq!((stats.hits, stats.misses), (1, 1));
+    /// assert!(stats.probes >= 2);
+    /// ```
+    #[cfg(feature = "instrument")]
+    pub fn probe_stats(&self) -> crate::ProbeStats {
+        self.table.probe_counters.snapshot()
+    }
+
+    /// Resets the statistics of the recorded lookups.
+    #[cfg(feature = "instrument")]
+    pub fn reset_probe_stats(&self) {
+        self.table.probe_counters.reset();
+    }
+
+    /// Rebuilds the map so that its load factor does not exceed the fraction `num / den`,
+    /// repacking the key bytes in the new slot order.
+    ///
+    /// The capacity is computed by integer arithmetic, so it is exactly determined on any target.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when `num / den` is not in `(0, 1]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let mut map = HashMap::new(&records).unwrap();
+    /// map.optimize_to(1, 2).unwrap();
+    /// assert_eq!(map.capacity(), 8);
+    /// assert_eq!(map.get("idce"), Some(&1));
+    /// assert!(map.optimize_to(1, 0).is_err());
+    /// ```
+    pub fn optimize_to(&mut self, num: u32, den: u32) -> Result<()> {
+        self.table.optimize(Ratio::new(num as u64, den as u64))
+    }
+
+    /// Rebuilds the map like [`HashMap::optimize_to`] with the maximum load factor
+    /// given as a floating-point value, which is rounded to six decimal places.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when `load_factor` is not in `(0, 1]`.
+    pub fn optimize(&mut self, load_factor: f64) -> Result<()> {
+        self.table.optimize(Ratio::from_f64(load_factor))
+    }
+
+    /// Rebuilds the map with a new seed of the hash function,
+    /// keeping the contents and the capacity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let mut map = HashMap::new(&records).unwrap();
+    /// map.rehash(42);
+    /// assert_eq!(map.get("idce"), Some(&1));
+    /// ```
+    pub fn rehash(&mut self, seed: u64) {
+        self.table.rehash(seed);
+    }
+
+    /// Advises the kernel to back the slots and key bytes with transparent huge pages
+    /// on Linux, as [`HashMapBuilder::huge_pages`] does for built maps.
+    ///
+    /// This suits deserialized maps, whose already populated pages
+    /// are collapsed into huge pages in the background.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the kernel rejects the advice,
+    /// such as when it does not support transparent huge pages.
+    #[cfg(feature = "hugepages")]
+    pub fn advise_huge_pages(&mut self) -> Result<()> {
+        self.table.advise_huge_pages()
+    }
+
+    /// Rebuilds the map by placing the keys in the lexicographic order,
+    /// keeping the contents and the capacity.
+    ///
+    /// This restores the layout of [`HashMapBuilder::canonical`] after insertions and removals.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let builder = HashMapBuilder::new().canonical(true);
+    /// let mut map = builder.build(&[("sigmod", 2u32), ("icdm", 0)]).unwrap();
+    /// map.insert("idce", 1).unwrap();
+    /// map.canonicalize();
+    /// let other = builder.build(&[("icdm", 0u32), ("idce", 1), ("sigmod", 2)]).unwrap();
+    /// assert_eq!(map.serialize_to_vec(), other.serialize_to_vec());
+    /// ```
+    pub fn canonicalize(&mut self) {
+        self.table.canonicalize();
+    }
+
+    /// Rebuilds the map for the query distribution of `access_log`, a sample of queried keys,
+    /// keeping the contents and the capacity.
+    ///
+    /// Keys are placed in the descending order of their frequencies in `access_log`,
+    /// so frequently queried keys come earliest in their probe sequences
+    /// and are found with fewer probes under skewed distributions.
+    /// Their key bytes are also packed together to reduce cache misses,
+    /// unless the keys are sorted or share suffixes.
+    /// Keys in `access_log` that are not stored are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let mut map = HashMap::new(&records).unwrap();
+    /// map.optimize_for(["sigmod", "sigmod", "idce", "sigir"]);
+    /// assert_eq!(map.get("sigmod"), Some(&2));
+    /// assert_eq!(map.len(), 3);
+    /// ```
+    pub fn optimize_for<I, K>(&mut self, access_log: I)
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<[u8]>,
+    {
+        let mut counts = vec![0u64; self.table.capacity()];
+        for key in access_log {
+            if let Some(pos) = self.table.get_pos(key) {
+                counts[pos] += 1;
+            }
+        }
+        self.table.reorder_by_counts(&counts);
+    }
+
+    /// Consumes the map and leaks it, returning a reference valid for the rest of the process.
+    ///
+    /// This suits dictionaries that live as long as the process,
+    /// which can then be stored in statics and shared without reference counting.
+    /// The memory is never freed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::OnceLock;
+    ///
+    /// use simplearrayhash::HashMap;
+    ///
+    /// static DICT: OnceLock<&'static HashMap<u32>> = OnceLock::new();
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let dict = *DICT.get_or_init(|| HashMap::new(&records).unwrap().leak());
+    /// assert_eq!(dict.get("idce"), Some(&1));
+    /// ```
+    pub fn leak(self) -> &'static Self
+    where
+        V: 'static,
+        L: 'static,
+    {
+        Box::leak(Box::new(self))
+    }
+
+    /// Returns the number of keys lexicographically less than `key`.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the map is not built with [`HashMapBuilder::sort_keys`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMapBuilder::new().sort_keys(true).build(&records).unwrap();
+    /// assert_eq!(map.rank("icdm").unwrap(), 0);
+    /// assert_eq!(map.rank("idce").unwrap(), 1);
+    /// assert_eq!(map.rank("sigir").unwrap(), 2);
+    /// assert_eq!(map.rank("zzz").unwrap(), 3);
+    /// ```
+    pub fn rank<K>(&self, key: K) -> Result<usize>
+    where
+        K: AsRef<[u8]>,
+    {
+        self.table.rank(key.as_ref())
+    }
+
+    /// Returns the `i`-th smallest key in the lexicographic order and its value.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the map is not built with [`HashMapBuilder::sort_keys`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("sigmod", 2), ("icdm", 0), ("idce", 1)];
+    /// let map = HashMapBuilder::new().sort_keys(true).build(&records).unwrap();
+    /// assert_eq!(map.select(1).unwrap(), Some((&b"idce"[..], &1)));
+    /// assert_eq!(map.select(3).unwrap(), None);
+    /// ```
+    pub fn select(&self, i: usize) -> Result<Option<(&[u8], &V)>> {
+        Ok(self.table.select(i)?.map(|(key, nd)| (key, &nd.val)))
+    }
+
+    /// Returns the greatest key less than `key` and its value.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the map is not built with [`HashMapBuilder::sort_keys`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMapBuilder::new().sort_keys(true).build(&records).unwrap();
+    /// assert_eq!(map.predecessor("idce").unwrap(), Some((&b"icdm"[..], &0)));
+    /// assert_eq!(map.predecessor("icdm").unwrap(), None);
+    /// ```
+    pub fn predecessor<K>(&self, key: K) -> Result<Option<(&[u8], &V)>>
+    where
+        K: AsRef<[u8]>,
+    {
+        match self.rank(key)? {
+            0 => Ok(None),
+            r => self.select(r - 1),
+        }
+    }
+
+    /// Returns the smallest key greater than `key` and its value.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the map is not built with [`HashMapBuilder::sort_keys`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMapBuilder::new().sort_keys(true).build(&records).unwrap();
+    /// assert_eq!(map.successor("idce").unwrap(), Some((&b"sigmod"[..], &2)));
+    /// assert_eq!(map.successor("ide").unwrap(), Some((&b"sigmod"[..], &2)));
+    /// assert_eq!(map.successor("sigmod").unwrap(), None);
+    /// ```
+    pub fn successor<K>(&self, key: K) -> Result<Option<(&[u8], &V)>>
+    where
+        K: AsRef<[u8]>,
+    {
+        let key = key.as_ref();
+        let r = self.rank(key)? + usize::from(self.contains_key(key));
+        self.select(r)
+    }
+
+    /// Returns an iterator over the entries whose keys are within `range`
+    /// in the lexicographic order of keys.
+    ///
+    /// Unlike [`std::collections::BTreeMap::range`],
+    /// this yields no entry if the start of `range` is greater than its end.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the map is not built with [`HashMapBuilder::sort_keys`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2), ("sigir", 3)];
+    /// let map = HashMapBuilder::new().sort_keys(true).build(&records).unwrap();
+    ///
+    /// let entries: Vec<_> = map.range("icdm".."sigir").unwrap().collect();
+    /// assert_eq!(entries, vec![(&b"icdm"[..], &0), (&b"idce"[..], &1)]);
+    ///
+    /// let keys: Vec<_> = map.range("s"..).unwrap().map(|(k, _)| k).collect();
+    /// assert_eq!(keys, vec![&b"sigir"[..], &b"sigmod"[..]]);
+    /// ```
+    pub fn range<K, R>(&self, range: R) -> Result<RangeIter<'_, V, L>>
+    where
+        K: AsRef<[u8]>,
+        R: RangeBounds<K>,
+    {
+        let start = range.start_bound().map(|key| key.as_ref());
+        let end = range.end_bound().map(|key| key.as_ref());
+        let positions = self.table.range_positions(start, end)?;
+        Ok(RangeIter {
+            map: self,
+            positions: positions.iter(),
+        })
+    }
+
+    /// Returns the entries whose keys are within edit distance 1 from `key`,
+    /// in the ascending order of the distance and then of the key.
+    ///
+    /// Edits are insertions, deletions, and substitutions of single bytes,
+    /// so a multibyte character counts as several edits.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the map is not built with [`HashMapBuilder::fuzzy_index`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("icde", 2)];
+    /// let map = HashMapBuilder::new().fuzzy_index(true).build(&records).unwrap();
+    /// assert_eq!(
+    ///     map.get_fuzzy("icde").unwrap(),
+    ///     vec![(&b"icde"[..], &2), (&b"icdm"[..], &0)]
+    /// );
+    /// assert!(map.get_fuzzy("sigmod").unwrap().is_empty());
+    /// ```
+    pub fn get_fuzzy<K>(&self, key: K) -> Result<Vec<(&[u8], &V)>>
+    where
+        K: AsRef<[u8]>,
+    {
+        Ok(self
+            .table
+            .fuzzy_positions(key.as_ref())?
+            .into_iter()
+            .map(|(_, pos)| {
+                let (key, nd) = self.table.entry_at(pos);
+                (key, &nd.val)
+            })
+            .collect())
+    }
+
+    /// Returns an iterator over the entries whose keys start with `prefix`
+    /// in the lexicographic order of keys, such as for autocompletion.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the map is not built with [`HashMapBuilder::sort_keys`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2), ("sigir", 3)];
+    /// let map = HashMapBuilder::new().sort_keys(true).build(&records).unwrap();
+    ///
+    /// let entries: Vec<_> = map.keys_with_prefix("sig").unwrap().collect();
+    /// assert_eq!(entries, vec![(&b"sigir"[..], &3), (&b"sigmod"[..], &2)]);
+    /// ```
+    pub fn keys_with_prefix<K>(&self, prefix: K) -> Result<RangeIter<'_, V, L>>
+    where
+        K: AsRef<[u8]>,
+    {
+        let positions = self.table.prefix_positions(prefix.as_ref())?;
+        Ok(RangeIter {
+            map: self,
+            positions: positions.iter(),
+        })
+    }
+
+    /// Iterates over the keys ending with `suffix` and their values
+    /// in the lexicographic order of the reversed keys,
+    /// such as for inflectional endings or domain names.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the map is not built with [`HashMapBuilder::suffix_index`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("walked", 0), ("talked", 1), ("walking", 2), ("baked", 3)];
+    /// let map = HashMapBuilder::new().suffix_index(true).build(&records).unwrap();
+    ///
+    /// let entries: Vec<_> = map.keys_with_suffix("lked").unwrap().collect();
+    /// assert_eq!(entries, vec![(&b"talked"[..], &1), (&b"walked"[..], &0)]);
+    /// ```
+    pub fn keys_with_suffix<K>(&self, suffix: K) -> Result<RangeIter<'_, V, L>>
+    where
+        K: AsRef<[u8]>,
+    {
+        let positions = self.table.suffix_positions(suffix.as_ref())?;
+        Ok(RangeIter {
+            map: self,
+            positions: positions.iter(),
+        })
+    }
+
+    /// Creates an [`fst::Map`] with the same key-value pairs,
+    /// so that ordered and prefix queries can be served by the fst
+    /// while point lookups are served by this map.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when building the fst fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0u32), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let fst = map.to_fst_map().unwrap();
+    /// assert_eq!(fst.get("idce"), Some(1));
+    /// ```
+    #[cfg(feature = "fst")]
+    pub fn to_fst_map(&self) -> Result<fst::Map<Vec<u8>>>
+    where
+        V: Copy + Into<u64>,
+    {
+        let mut builder = fst::MapBuilder::memory();
+        for (key, nd) in self.table.sorted_entries() {
+            builder.insert(key, nd.val.into())?;
+        }
+        Ok(builder.into_map())
+    }
+}
+
+impl<V, L> HashMap<V, L>
+where
+    V: Default + Clone + Serializable,
+    L: KeyLen,
+{
+    /// Returns a 128-bit digest of the key-value pairs,
+    /// where the values are digested in their serialized bytes.
+    ///
+    /// The digest is independent of the slot layout and of the build options,
+    /// such as the load factor, seed, and key layout,
+    /// so two maps with the same contents have the same fingerprint.
+    /// It is stable across platforms and releases of this crate,
+    /// but is not cryptographic.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::{HashMap, HashMapBuilder};
+    ///
+    /// let records = vec![("icdm", 0u32), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let other = HashMapBuilder::new()
+    ///     .load_factor(0.9)
+    ///     .sort_keys(true)
+    ///     .build(&records)
+    ///     .unwrap();
+    /// assert_eq!(map.fingerprint(), other.fingerprint());
+    ///
+    /// let records = vec![("icdm", 0u32), ("idce", 1), ("sigmod", 3)];
+    /// let other = HashMap::new(&records).unwrap();
+    /// assert_ne!(map.fingerprint(), other.fingerprint());
+    /// ```
+    pub fn fingerprint(&self) -> u128 {
+        let mut buf = vec![];
+        // Summing the digests of the pairs makes the result independent of their order.
+        let mut sum = 0u128;
+        for (key, nd) in self.table.iter() {
+            buf.clear();
+            (key.len() as u64).serialize_to_vec(&mut buf);
+            buf.extend_from_slice(key);
+            nd.val.serialize_to_vec(&mut buf);
+            sum = sum.wrapping_add(fasthash::city::hash128_with_seed(&buf, FINGERPRINT_SEED));
+        }
+        buf.clear();
+        sum.serialize_to_vec(&mut buf);
+        (self.len() as u64).serialize_to_vec(&mut buf);
+        fasthash::city::hash128_with_seed(&buf, FINGERPRINT_SEED)
+    }
+
+    /// Serializes the map into a byte vector in the [sectioned format](crate::serial).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0u32), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let data = map.serialize_to_vec();
+    /// let other = HashMap::<u32>::deserialize_from_slice(&data).unwrap();
+    /// assert_eq!(other.get("idce"), Some(&1));
+    /// ```
+    pub fn serialize_to_vec(&self) -> Vec<u8> {
+        let mut dest = vec![];
+        // Writing into a vector never fails.
+        self.serialize_into(&mut dest).unwrap();
+        dest
+    }
+
+    /// Serializes the map into `wtr` in the [sectioned format](crate::serial).
+    ///
+    /// A map of valueless types such as `()` is serialized without values,
+    /// in the same format as a [`HashSet`](crate::HashSet).
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when writing fails.
+    pub fn serialize_into<W>(&self, wtr: W) -> Result<()>
+    where
+        W: Write,
+    {
+        if V::VALUELESS {
+            return self.serialize_keys_into(wtr);
+        }
+        let (header, nodes) = self.table.serialize_sections();
+        let values = serial::serialize_values(self.table.occupied().map(|nd| &nd.val));
+        serial::write_sections(
+            wtr,
+            &[
+                (SectionKind::Header as u32, &header),
+                (SectionKind::Nodes as u32, &nodes),
+                (SectionKind::Keys as u32, &self.table.bytes),
+                (SectionKind::Values as u32, &values),
+            ],
+        )
+    }
+
+    /// Deserializes a map from `source` in the [sectioned format](crate::serial).
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when `source` is broken,
+    /// or contains a key whose length is out of the range of `L`.
+    pub fn deserialize_from_slice(source: &[u8]) -> Result<Self> {
+        Self::from_sections(&Sections::parse(source)?)
+    }
+
+    /// Deserializes a map from the bytes of `storage` in the [sectioned format](crate::serial),
+    /// borrowing the key bytes from `storage` instead of copying them.
+    ///
+    /// The slots and values are still decoded into memory owned by the map,
+    /// since the slots embed the values, unlike those of a set
+    /// (see [`HashSet::deserialize_from_storage`](crate::HashSet::deserialize_from_storage)),
+    /// while the key bytes, usually the bulk of a dictionary, stay in `storage`,
+    /// such as a memory-mapped file, a static slice embedded by [`include_bytes!`],
+    /// or an `Arc<[u8]>` shared with other maps.
+    /// A modification of the map copies the key bytes into its own memory first.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the data is broken,
+    /// or contains a key whose length is out of the range of `L`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0u32), ("idce", 1), ("sigmod", 2)];
+    /// let data: &'static [u8] = HashMap::new(&records).unwrap().serialize_to_vec().leak();
+    ///
+    /// let mut map = HashMap::<u32>::deserialize_from_storage(data).unwrap();
+    /// assert!(map.shares_keys());
+    /// assert_eq!(map.get("idce"), Some(&1));
+    ///
+    /// map.insert("sigir", 3).unwrap();
+    /// assert!(!map.shares_keys());
+    /// assert_eq!(map.get("sigmod"), Some(&2));
+    /// ```
+    pub fn deserialize_from_storage<S>(storage: S) -> Result<Self>
+    where
+        S: KeyStorage,
+    {
+        let storage: Arc<dyn KeyStorage> = Arc::new(storage);
+        let sections = Sections::parse(storage.as_key_bytes())?;
+        let table = Table::deserialize_sections_with(&sections, Some(&storage))?;
+        Self::from_table(table, &sections)
+    }
+
+    /// Serializes the key structure of the map, that is, the header, nodes, and keys sections,
+    /// into `wtr` in the [sectioned format](crate::serial) without the values.
+    ///
+    /// The values are serialized apart by [`HashMap::serialize_values_into`],
+    /// and [`HashMap::deserialize_from_split`] combines them,
+    /// so that one key structure can be shipped with several sets of values.
+    /// Maps sharing a key structure are obtained by cloning a map and updating its values,
+    /// which keeps the slots.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when writing fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0u32), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let mut other = map.clone();
+    /// *other.get_mut("idce").unwrap() = 10;
+    ///
+    /// let mut keys = vec![];
+    /// map.serialize_keys_into(&mut keys).unwrap();
+    /// let (mut values, mut other_values) = (vec![], vec![]);
+    /// map.serialize_values_into(&mut values).unwrap();
+    /// other.serialize_values_into(&mut other_values).unwrap();
+    ///
+    /// let map = HashMap::<u32>::deserialize_from_split(&keys, &values).unwrap();
+    /// let other = HashMap::<u32>::deserialize_from_split(&keys, &other_values).unwrap();
+    /// assert_eq!(map.get("idce"), Some(&1));
+    /// assert_eq!(other.get("idce"), Some(&10));
+    /// ```
+    pub fn serialize_keys_into<W>(&self, wtr: W) -> Result<()>
+    where
+        W: Write,
+    {
+        let (header, nodes) = self.table.serialize_sections();
+        serial::write_sections(
+            wtr,
+            &[
+                (SectionKind::Header as u32, &header),
+                (SectionKind::Nodes as u32, &nodes),
+                (SectionKind::Keys as u32, &self.table.bytes),
+            ],
+        )
+    }
+
+    /// Serializes the values of the map into `wtr` in the [sectioned format](crate::serial)
+    /// with the digest of its key structure, which is serialized by [`HashMap::serialize_keys_into`].
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when writing fails.
+    pub fn serialize_values_into<W>(&self, wtr: W) -> Result<()>
+    where
+        W: Write,
+    {
+        let (header, nodes) = self.table.serialize_sections();
+        let keys = Sections::from_entries(vec![
+            (SectionKind::Header as u32, &header),
+            (SectionKind::Nodes as u32, &nodes),
+            (SectionKind::Keys as u32, &self.table.bytes),
+        ]);
+        // All the sections of the key structure are given.
+        let digest = serial::keys_digest(&keys).unwrap();
+        let values = serial::serialize_values(self.table.occupied().map(|nd| &nd.val));
+        serial::write_sections(
+            wtr,
+            &[
+                (SectionKind::KeysDigest as u32, &digest),
+                (SectionKind::Values as u32, &values),
+            ],
+        )
+    }
+
+    /// Deserializes a map from its key structure in `keys` serialized by
+    /// [`HashMap::serialize_keys_into`] and its values in `values`
+    /// serialized by [`HashMap::serialize_values_into`].
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `keys` or `values` is broken,
+    ///  - `values` was serialized from a map with another key structure, or
+    ///  - `keys` contains a key whose length is out of the range of `L`.
+    pub fn deserialize_from_split(keys: &[u8], values: &[u8]) -> Result<Self> {
+        let keys = Sections::parse(keys)?;
+        let values = Sections::parse(values)?;
+        if values.require(SectionKind::KeysDigest)? != serial::keys_digest(&keys)? {
+            return Err(anyhow!(
+                "The values must be serialized from a map with the same key structure."
+            ));
+        }
+        let mut entries: Vec<_> = keys.iter().collect();
+        entries.push((
+            SectionKind::Values as u32,
+            values.require(SectionKind::Values)?,
+        ));
+        Self::from_sections(&Sections::from_entries(entries))
+    }
+
+    /// Serializes the map into `buf` in the [sectioned format](crate::serial),
+    /// such as a [`bytes::BytesMut`] holding a frame of a network protocol.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when `buf` has no room for the serialized map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bytes::BytesMut;
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0u32), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let mut buf = BytesMut::new();
+    /// map.serialize_into_buf(&mut buf).unwrap();
+    /// assert_eq!(buf.len(), map.serialize_to_vec().len());
+    /// ```
+    #[cfg(feature = "bytes")]
+    pub fn serialize_into_buf<B>(&self, buf: &mut B) -> Result<()>
+    where
+        B: bytes::BufMut,
+    {
+        self.serialize_into(bytes::BufMut::writer(buf))
+    }
+
+    /// Deserializes a map at the front of `buf` in the [sectioned format](crate::serial),
+    /// advancing `buf` past it so that the following data can be read.
+    ///
+    /// If `buf` is [`bytes::Bytes`], the sections are read without copying the underlying
+    /// buffer, and the key bytes are borrowed from it as in [`HashMap::deserialize_from_storage`],
+    /// while the slots and values are still deserialized into memory owned by the map.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when `buf` is broken or truncated,
+    /// or contains a key whose length is out of the range of `L`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bytes::{Buf, BufMut, BytesMut};
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0u32), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let mut buf = BytesMut::new();
+    /// map.serialize_into_buf(&mut buf).unwrap();
+    /// buf.put_u32(42);
+    ///
+    /// let mut buf = buf.freeze();
+    /// let other = HashMap::<u32>::deserialize_from_buf(&mut buf).unwrap();
+    /// assert!(other.shares_keys());
+    /// assert_eq!(other.get("idce"), Some(&1));
+    /// assert_eq!(buf.get_u32(), 42);
+    /// ```
+    #[cfg(feature = "bytes")]
+    pub fn deserialize_from_buf<B>(buf: &mut B) -> Result<Self>
+    where
+        B: bytes::Buf,
+    {
+        serial::read_buf(buf, |sections, storage| {
+            let table = Table::deserialize_sections_with(sections, Some(storage))?;
+            Self::from_table(table, sections)
+        })
+    }
+
+    /// Decodes a map from parsed sections.
+    fn from_sections(sections: &Sections) -> Result<Self> {
+        Self::from_table(Table::deserialize_sections(sections)?, sections)
+    }
+
+    /// Fills the values of `table` deserialized from `sections`.
+    fn from_table(mut table: Table<MapNode<V, L>>, sections: &Sections) -> Result<Self> {
+        // Maps of valueless types are serialized without the values section, as sets are.
+        if V::VALUELESS && sections.get(SectionKind::Values).is_none() {
+            return Ok(Self { table });
+        }
+        let values = sections
+            .get(SectionKind::Values)
+            .ok_or_else(|| anyhow!("The section Values is missing."))?;
+        let values = ValuesIndex::parse(values)?;
+        if values.len() != table.num_keys() {
+            return Err(anyhow!("The values section is broken."));
+        }
+        for (i, node) in table.occupied_mut().enumerate() {
+            node.val = values.get(i)?;
+        }
+        Ok(Self { table })
+    }
+
+    /// Deserializes a map from `rdr` in the [sectioned format](crate::serial).
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when reading fails or the data is broken.
+    pub fn deserialize_from<R>(mut rdr: R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let mut source = vec![];
+        rdr.read_to_end(&mut source)?;
+        Self::deserialize_from_slice(&source)
+    }
+
+    /// Serializes the map into the asynchronous writer `wtr`
+    /// in the [sectioned format](crate::serial).
+    ///
+    /// The map is serialized in memory when this is called,
+    /// and the returned future writes it without blocking the runtime thread on slow writes.
+    /// Like [`Self::serialize_into`], `wtr` is not flushed.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when writing fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use simplearrayhash::HashMap;
+    ///
+    /// let records = vec![("icdm", 0u32), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let mut data = vec![];
+    /// map.serialize_into_async(&mut data).await.unwrap();
+    /// let other = HashMap::<u32>::deserialize_from_async(data.as_slice()).await.unwrap();
+    /// assert_eq!(other.get("idce"), Some(&1));
+    /// # }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub fn serialize_into_async<W>(
+        &self,
+        mut wtr: W,
+    ) -> impl std::future::Future<Output = Result<()>>
+    where
+        W: tokio::io::AsyncWrite + Unpin,
+    {
+        use tokio::io::AsyncWriteExt;
+        // The future does not borrow `self`, so it is `Send` whenever `W` is.
+        let data = self.serialize_to_vec();
+        async move {
+            wtr.write_all(&data).await?;
+            Ok(())
+        }
+    }
+
+    /// Deserializes a map from the asynchronous reader `rdr`
+    /// in the [sectioned format](crate::serial).
+    ///
+    /// The data is read without blocking the runtime thread and then decoded in memory.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when reading fails or the data is broken.
+    #[cfg(feature = "tokio")]
+    pub async fn deserialize_from_async<R>(mut rdr: R) -> Result<Self>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+    {
+        use tokio::io::AsyncReadExt;
+        let mut source = vec![];
+        rdr.read_to_end(&mut source).await?;
+        Self::deserialize_from_slice(&source)
+    }
+}
+
+/// Iterator created by [`HashMap::lookup_tokens`].
+pub struct LookupTokens<'a, 't, V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    map: &'a HashMap<V, L>,
+    text: &'t [u8],
+    delimiter: u8,
+    start: usize,
+    finished: bool,
+}
+
+impl<'a, 't, V, L> Iterator for LookupTokens<'a, 't, V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    type Item = (Range<usize>, Option<&'a V>);
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let end = self.text[self.start..]
+            .iter()
+            .position(|&c| c == self.delimiter)
+            .map_or_else(
+                || {
+                    self.finished = true;
+                    self.text.len()
+                },
+                |i| self.start + i,
+            );
+        let range = self.start..end;
+        self.start = end + 1;
+        let val = self.map.get(&self.text[range.clone()]);
+        Some((range, val))
+    }
+}
+
+/// Iterator created by [`HashMap::range`], [`HashMap::keys_with_prefix`],
+/// and [`HashMap::keys_with_suffix`].
+pub struct RangeIter<'a, V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    map: &'a HashMap<V, L>,
+    positions: std::slice::Iter<'a, usize>,
+}
+
+impl<'a, V, L> Iterator for RangeIter<'a, V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    type Item = (&'a [u8], &'a V);
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        let &pos = self.positions.next()?;
+        let (key, nd) = self.map.table.entry_at(pos);
+        Some((key, &nd.val))
+    }
+
+    #[inline(always)]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.positions.size_hint()
+    }
+}
+
+impl<V, L> DoubleEndedIterator for RangeIter<'_, V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    #[inline(always)]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let &pos = self.positions.next_back()?;
+        let (key, nd) = self.map.table.entry_at(pos);
+        Some((key, &nd.val))
+    }
+}
+
+impl<V, L> ExactSizeIterator for RangeIter<'_, V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+}
+
+/// Handle to the slot of a key, created by [`HashMap::get_handle`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Handle {
+    pos: usize,
+    generation: u64,
+}
+
+/// Iterator created by [`HashMap::slots`].
+pub struct Slots<'a, V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    map: &'a HashMap<V, L>,
+    positions: Range<usize>,
+    remaining: usize,
+}
+
+impl<'a, V, L> Iterator for Slots<'a, V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    type Item = (usize, &'a [u8], &'a V);
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        let table = &self.map.table;
+        let pos = self.positions.find(|&pos| table.nodes[pos].is_occupied())?;
+        self.remaining -= 1;
+        let (key, nd) = table.entry_at(pos);
+        Some((pos, key, &nd.val))
+    }
+
+    #[inline(always)]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<V, L> DoubleEndedIterator for Slots<'_, V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    #[inline(always)]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let table = &self.map.table;
+        let pos = self
+            .positions
+            .rfind(|&pos| table.nodes[pos].is_occupied())?;
+        self.remaining -= 1;
+        let (key, nd) = table.entry_at(pos);
+        Some((pos, key, &nd.val))
+    }
+}
+
+impl<V, L> ExactSizeIterator for Slots<'_, V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+}
+
+/// Freezes a [`BTreeMap`] into a [`HashMap`] with the default options,
+/// in the same manner as [`HashMapBuilder::build_from_btree_map`].
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+///
+/// use simplearrayhash::HashMap;
+///
+/// let state = BTreeMap::from([(b"icdm".to_vec(), 0), (b"idce".to_vec(), 1)]);
+/// let map = HashMap::<i32>::try_from(&state).unwrap();
+/// assert_eq!(map.get("idce"), Some(&1));
+/// assert_eq!(BTreeMap::from(&map), state);
+/// ```
+impl<V, L> TryFrom<&BTreeMap<Vec<u8>, V>> for HashMap<V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    type Error = anyhow::Error;
+
+    fn try_from(map: &BTreeMap<Vec<u8>, V>) -> Result<Self> {
+        HashMapBuilder::new()
+            .key_len::<L>()
+            .build_from_btree_map(map)
+    }
+}
+
+/// Copies the key-value pairs of a [`HashMap`] into a [`BTreeMap`].
+impl<V, L> From<&HashMap<V, L>> for BTreeMap<Vec<u8>, V>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    fn from(map: &HashMap<V, L>) -> Self {
+        map.slots()
+            .map(|(_, key, val)| (key.to_vec(), val.clone()))
+            .collect()
+    }
+}
+
+/// Iterator created by [`HashMap::drain`].
+pub struct Drain<'a, V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    table: &'a mut Table<MapNode<V, L>>,
+    positions: Range<usize>,
+    remaining: usize,
+}
+
+impl<V, L> Iterator for Drain<'_, V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    type Item = (Vec<u8>, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let nodes = &self.table.nodes;
+        let pos = self.positions.find(|&pos| nodes[pos].is_occupied())?;
+        Some(self.take(pos))
+    }
+
+    #[inline(always)]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<V, L> DoubleEndedIterator for Drain<'_, V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let nodes = &self.table.nodes;
+        let pos = self.positions.rfind(|&pos| nodes[pos].is_occupied())?;
+        Some(self.take(pos))
+    }
+}
+
+impl<V, L> Drain<'_, V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    /// Takes the key and value out of the occupied slot at `pos`, leaving it vacant.
+    fn take(&mut self, pos: usize) -> (Vec<u8>, V) {
+        let node = std::mem::replace(&mut self.table.nodes[pos], MapNode::vacant());
+        self.remaining -= 1;
+        (self.table.get_bytes(&node).to_vec(), node.val)
+    }
+}
+
+impl<V, L> ExactSizeIterator for Drain<'_, V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+}
+
+impl<V, L> Drop for Drain<'_, V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    fn drop(&mut self) {
+        self.table.clear();
+    }
+}
+
+/// Buffers whose allocations are reused across repeated builds
+/// with [`HashMapBuilder::build_with_buffers`].
+pub struct BuildBuffers<V, L = usize> {
+    nodes: Vec<MapNode<V, L>>,
+    bytes: Vec<u8>,
+}
+
+impl<V, L> BuildBuffers<V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    /// Creates empty buffers, which allocate nothing until the first build.
+    pub const fn new() -> Self {
+        Self {
+            nodes: vec![],
+            bytes: vec![],
+        }
+    }
+
+    /// Takes the allocations of a map that is no longer needed,
+    /// keeping whichever of the current and taken allocations are larger.
+    ///
+    /// # Arguments
+    ///
+    /// - `map`: Map to be dropped.
+    pub fn recycle(&mut self, mut map: HashMap<V, L>) {
+        let mut nodes = map.table.nodes.take();
+        // Drops the values now rather than at the next build.
+        wipe(&mut nodes);
+        if nodes.capacity() > self.nodes.capacity() {
+            self.nodes = nodes;
+        }
+        // Shared key bytes belong to their backend.
+        if let KeyBytes::Owned(mut bytes) = std::mem::take(&mut map.table.bytes) {
+            wipe(&mut bytes);
+            if bytes.capacity() > self.bytes.capacity() {
+                self.bytes = bytes;
+            }
+        }
+    }
+
+    /// Returns the numbers of slots and key bytes that can be built without allocation.
+    pub const fn capacity(&self) -> (usize, usize) {
+        (self.nodes.capacity(), self.bytes.capacity())
+    }
+}
+
+impl<V, L> Default for BuildBuffers<V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builder of [`HashMap`] with configurable options.
+///
+/// # Examples
+///
+/// ```
+/// use simplearrayhash::HashMapBuilder;
+///
+/// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+/// let map = HashMapBuilder::new().key_len::<u8>().build(&records).unwrap();
+/// assert_eq!(map.get("idce"), Some(&1));
+/// ```
+#[derive(Clone, Debug)]
+pub struct HashMapBuilder<L = usize> {
+    load_factor: Ratio,
+    pub(crate) seed: u64,
+    align_keys: bool,
+    share_suffixes: bool,
+    sort_keys: bool,
+    fuzzy_index: bool,
+    suffix_index: bool,
+    canonical: bool,
+    constant_time: bool,
+    #[cfg(feature = "hugepages")]
+    huge_pages: bool,
+    stash_len: usize,
+    max_probe: usize,
+    lookup_cache: usize,
+    growth_factor: Ratio,
+    max_capacity: usize,
+    tombstone_ratio: Ratio,
+    key_len_limit: usize,
+    progress: Option<ProgressFn>,
+    _len: PhantomData<L>,
+}
+
+impl HashMapBuilder {
+    /// Creates a new builder with the default options.
+    pub const fn new() -> Self {
+        Self {
+            load_factor: DEFAULT_LOAD_FACTOR,
+            seed: DEFAULT_SEED,
+            align_keys: false,
+            share_suffixes: false,
+            sort_keys: false,
+            fuzzy_index: false,
+            suffix_index: false,
+            canonical: false,
+            constant_time: false,
+            #[cfg(feature = "hugepages")]
+            huge_pages: false,
+            stash_len: 0,
+            max_probe: 0,
+            lookup_cache: 0,
+            growth_factor: DEFAULT_GROWTH_FACTOR,
+            max_capacity: usize::MAX,
+            tombstone_ratio: DEFAULT_TOMBSTONE_RATIO,
+            key_len_limit: usize::MAX,
+            progress: None,
+            _len: PhantomData,
+        }
+    }
+}
+
+impl Default for HashMapBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<L> HashMapBuilder<L>
+where
+    L: KeyLen,
+{
+    /// Specifies the integer type to store key lengths, one of
+    /// `u8`, `u16`, `u32`, and `usize` (default).
+    ///
+    /// A narrower type reduces the memory usage of each node,
+    /// but rejects keys longer than its maximum value.
+    pub fn key_len<M>(self) -> HashMapBuilder<M>
+    where
+        M: KeyLen,
+    {
+        HashMapBuilder {
+            load_factor: self.load_factor,
+            seed: self.seed,
+            align_keys: self.align_keys,
+            share_suffixes: self.share_suffixes,
+            sort_keys: self.sort_keys,
+            fuzzy_index: self.fuzzy_index,
+            suffix_index: self.suffix_index,
+            canonical: self.canonical,
+            constant_time: self.constant_time,
+            #[cfg(feature = "hugepages")]
+            huge_pages: self.huge_pages,
+            stash_len: self.stash_len,
+            max_probe: self.max_probe,
+            lookup_cache: self.lookup_cache,
+            growth_factor: self.growth_factor,
+            max_capacity: self.max_capacity,
+            tombstone_ratio: self.tombstone_ratio,
+            key_len_limit: self.key_len_limit,
+            progress: self.progress,
+            _len: PhantomData,
+        }
+    }
+
+    /// Specifies the maximum ratio of the number of keys to the number of slots
+    /// as the fraction `num / den`, which must be in `(0, 1]` (default: `4 / 5`).
+    ///
+    /// A smaller value shortens probe sequences but allocates more slots.
+    /// Capacities are computed by integer arithmetic,
+    /// so they are exactly determined on any target.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMapBuilder::new().max_load(3, 4).build(&records).unwrap();
+    /// assert_eq!(map.capacity(), 8);
+    /// assert!(HashMapBuilder::new().max_load(5, 4).build(&records).is_err());
+    /// ```
+    pub const fn max_load(mut self, num: u32, den: u32) -> Self {
+        self.load_factor = Ratio::new(num as u64, den as u64);
+        self
+    }
+
+    /// Specifies the maximum load like [`Self::max_load`] as a floating-point value
+    /// in `(0, 1]`, which is rounded to six decimal places (default: `0.8`).
+    pub const fn load_factor(mut self, load_factor: f64) -> Self {
+        self.load_factor = Ratio::from_f64(load_factor);
+        self
+    }
+
+    /// Specifies the seed of the hash function (default: `0`).
+    pub const fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Specifies whether to align each stored key to 16 bytes
+    /// and zero-pad the key bytes (default: `false`).
+    ///
+    /// Keys are then compared in full 16-byte lanes without handling the tail of each stored key,
+    /// at the cost of up to 15 padding bytes per key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMapBuilder::new().align_keys(true).build(&records).unwrap();
+    /// assert_eq!(map.get("idce"), Some(&1));
+    /// assert_eq!(map.get("sigir"), None);
+    /// ```
+    pub const fn align_keys(mut self, yes: bool) -> Self {
+        self.align_keys = yes;
+        self
+    }
+
+    /// Specifies whether to share the bytes of keys that are suffixes of other keys
+    /// (default: `false`).
+    ///
+    /// A key that is a suffix of another key refers to the tail of the other
+    /// instead of storing its own copy, which shrinks the key bytes of
+    /// dictionaries where many keys share tails, such as inflected forms.
+    /// The sharing is kept when the map is rebuilt.
+    /// It cannot be combined with [`HashMapBuilder::align_keys`] or [`HashMapBuilder::sort_keys`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("walking", 0), ("talking", 1), ("king", 2)];
+    /// let map = HashMapBuilder::new().share_suffixes(true).build(&records).unwrap();
+    /// assert_eq!(map.get("king"), Some(&2));
+    /// assert_eq!(map.get("walking"), Some(&0));
+    /// ```
+    pub const fn share_suffixes(mut self, yes: bool) -> Self {
+        self.share_suffixes = yes;
+        self
+    }
+
+    /// Specifies whether to store the key bytes in the lexicographic order of keys
+    /// (default: `false`).
+    ///
+    /// Lookups still use hashing, while an auxiliary index over the sorted keys
+    /// enables ordered operations such as [`HashMap::rank`] and [`HashMap::successor`]
+    /// by binary search, at the cost of a slot position per key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMapBuilder::new().sort_keys(true).build(&records).unwrap();
+    /// assert_eq!(map.get("idce"), Some(&1));
+    /// assert_eq!(map.rank("ide").unwrap(), 2);
+    /// ```
+    pub const fn sort_keys(mut self, yes: bool) -> Self {
+        self.sort_keys = yes;
+        self
+    }
+
+    /// Specifies whether to build a deletion-neighborhood index for [`HashMap::get_fuzzy`]
+    /// (default: `false`).
+    ///
+    /// The index holds a hash and a slot position for each key and
+    /// each of its variants with one byte deleted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMapBuilder::new().fuzzy_index(true).build(&records).unwrap();
+    /// assert_eq!(map.get_fuzzy("sigmd").unwrap(), vec![(&b"sigmod"[..], &2)]);
+    /// ```
+    pub const fn fuzzy_index(mut self, yes: bool) -> Self {
+        self.fuzzy_index = yes;
+        self
+    }
+
+    /// Specifies whether to build a reverse-key index for [`HashMap::keys_with_suffix`]
+    /// (default: `false`).
+    ///
+    /// The index holds a slot position for each key,
+    /// sorted in the lexicographic order of the reversed keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("example.com", 0), ("example.org", 1), ("test.com", 2)];
+    /// let map = HashMapBuilder::new().suffix_index(true).build(&records).unwrap();
+    /// assert_eq!(map.keys_with_suffix(".com").unwrap().count(), 2);
+    /// ```
+    pub const fn suffix_index(mut self, yes: bool) -> Self {
+        self.suffix_index = yes;
+        self
+    }
+
+    /// Specifies whether to place the keys in the lexicographic order (default: `false`).
+    ///
+    /// The built map is then determined only by its contents and the options,
+    /// so the same records in any order are serialized into identical bytes,
+    /// as required for reproducible builds.
+    /// Otherwise, colliding keys are placed in the order of the input records.
+    /// Later insertions and removals break this guarantee until [`HashMap::canonicalize`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let builder = HashMapBuilder::new().canonical(true);
+    /// let map = builder.build(&[("icdm", 0u32), ("idce", 1), ("sigmod", 2)]).unwrap();
+    /// let other = builder.build(&[("sigmod", 2u32), ("icdm", 0), ("idce", 1)]).unwrap();
+    /// assert_eq!(map.serialize_to_vec(), other.serialize_to_vec());
+    /// ```
+    pub const fn canonical(mut self, yes: bool) -> Self {
+        self.canonical = yes;
+        self
+    }
+
+    /// Specifies whether to advise the kernel to back the slots and key bytes
+    /// with transparent huge pages on Linux (default: `false`).
+    ///
+    /// Lookups into a map of many gigabytes are dominated by TLB misses,
+    /// which huge pages reduce.
+    /// The advice is repeated whenever the arrays are reallocated by rebuilding the map.
+    /// Building fails when the kernel rejects the advice.
+    /// With the `safe` feature, which forbids the system calls, no advice is given.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let builder = HashMapBuilder::new().huge_pages(true);
+    /// if let Ok(map) = builder.build(&records) {
+    ///     assert_eq!(map.get("idce"), Some(&1));
+    /// }
+    /// ```
+    #[cfg(feature = "hugepages")]
+    pub const fn huge_pages(mut self, yes: bool) -> Self {
+        self.huge_pages = yes;
+        self
+    }
+
+    /// Specifies whether to compare keys in constant time while probing (default: `false`).
+    ///
+    /// By default, a comparison stops at the first mismatched byte,
+    /// so the lookup time may leak how long a prefix of a guessed key matches a stored key.
+    /// For maps keyed by secrets such as tokens or API keys,
+    /// this compares every byte of the query, including when the lengths differ,
+    /// at the cost of slower lookups.
+    /// The option is kept when the map is rebuilt or serialized.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMapBuilder::new().constant_time(true).build(&records).unwrap();
+    /// assert_eq!(map.get("idce"), Some(&1));
+    /// assert_eq!(map.get("idc"), None);
+    /// ```
+    pub const fn constant_time(mut self, yes: bool) -> Self {
+        self.constant_time = yes;
+        self
+    }
+
+    /// Specifies the multiplier of the number of slots when the map grows on insertion
+    /// as the fraction `num / den`, which must be greater than 1 (default: `2 / 1`).
+    ///
+    /// A smaller value such as `3 / 2` wastes fewer empty slots after each growth,
+    /// at the cost of more frequent rebuilds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let mut map = HashMapBuilder::new().growth(3, 2).build(&records).unwrap();
+    /// assert_eq!(map.capacity(), 4);
+    /// map.insert("sigir", 3).unwrap();
+    /// assert_eq!(map.capacity(), 6);
+    /// assert!(HashMapBuilder::new().growth(1, 1).build(&records).is_err());
+    /// ```
+    pub const fn growth(mut self, num: u32, den: u32) -> Self {
+        self.growth_factor = Ratio::new(num as u64, den as u64);
+        self
+    }
+
+    /// Specifies the growth like [`Self::growth`] as a floating-point value
+    /// greater than 1, which is rounded to six decimal places (default: `2.0`).
+    pub const fn growth_factor(mut self, growth_factor: f64) -> Self {
+        self.growth_factor = Ratio::from_f64(growth_factor);
+        self
+    }
+
+    /// Specifies the maximum number of slots the map grows to on insertion
+    /// (default: unlimited).
+    ///
+    /// Once the map reaches this capacity, insertions exceed the load factor
+    /// until all but one slot are filled, and then fail.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let mut map = HashMapBuilder::new().max_capacity(5).build(&records).unwrap();
+    /// map.insert("sigir", 3).unwrap();
+    /// assert_eq!(map.capacity(), 5);
+    /// assert!(map.insert("acl", 4).is_err());
+    /// ```
+    pub const fn max_capacity(mut self, max_capacity: usize) -> Self {
+        self.max_capacity = max_capacity;
+        self
+    }
+
+    /// Specifies the maximum ratio of tombstones to slots as the fraction `num / den`,
+    /// which must be in `(0, 1]` (default: `1 / 5`).
+    ///
+    /// [`HashMap::remove`] leaves a tombstone in the slot of a removed key,
+    /// which lengthens probe sequences until the slot is reused by an insertion.
+    /// When tombstones exceed this ratio, the map is rebuilt without them,
+    /// so lookups do not slow down in maps with many insertions and removals.
+    /// A smaller value keeps probe sequences shorter at the cost of more frequent rebuilds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMapBuilder::new().max_tombstones(1, 4).build(&records);
+    /// assert!(map.is_ok());
+    /// assert!(HashMapBuilder::new().max_tombstones(0, 4).build(&records).is_err());
+    /// ```
+    pub const fn max_tombstones(mut self, num: u32, den: u32) -> Self {
+        self.tombstone_ratio = Ratio::new(num as u64, den as u64);
+        self
+    }
+
+    /// Specifies the maximum ratio of tombstones like [`Self::max_tombstones`]
+    /// as a floating-point value in `(0, 1]`,
+    /// which is rounded to six decimal places (default: `0.2`).
+    pub const fn tombstone_ratio(mut self, tombstone_ratio: f64) -> Self {
+        self.tombstone_ratio = Ratio::from_f64(tombstone_ratio);
+        self
+    }
+
+    /// Specifies the number of stash slots and the maximum probe length (default: no stash).
+    ///
+    /// A key that would be placed `max_probe` or more slots away from its home slot
+    /// is placed in one of `stash_len` extra slots instead,
+    /// which a lookup scans after probing at most `max_probe` slots in vain.
+    /// This bounds the lookup cost under pathological collisions without rebuilding the map.
+    /// Once the stash is full, keys are placed further away as without it.
+    ///
+    /// The stash slots are counted in [`HashMap::capacity`],
+    /// and [`HashMap::num_stashed`] reports how many keys they store.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMapBuilder::new().stash(2, 1).build(&records).unwrap();
+    /// assert_eq!(map.capacity(), 6);
+    /// assert_eq!(map.get("sigmod"), Some(&2));
+    /// ```
+    pub const fn stash(mut self, stash_len: usize, max_probe: usize) -> Self {
+        self.stash_len = stash_len;
+        self.max_probe = max_probe;
+        self
+    }
+
+    /// Specifies the number of recent lookups cached in front of the probe path
+    /// (default: `0`, no cache).
+    ///
+    /// The cache remembers the results of the most recent queries, including misses,
+    /// evicting the least recently used one when full.
+    /// It pays off for workloads repeatedly querying a small set of keys,
+    /// such as missing keys whose probe sequences are long, but costs a lock per lookup.
+    /// Concurrent lookups bypass the cache instead of waiting for each other.
+    /// It can be changed later by [`HashMap::set_lookup_cache`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMapBuilder::new().lookup_cache(16).build(&records).unwrap();
+    /// assert_eq!(map.get("sigir"), None);
+    /// assert_eq!(map.get("sigir"), None);
+    /// assert_eq!(map.get("sigmod"), Some(&2));
+    /// ```
+    pub const fn lookup_cache(mut self, capacity: usize) -> Self {
+        self.lookup_cache = capacity;
+        self
+    }
+
+    /// Specifies the maximum length of keys in bytes (default: `usize::MAX`, unlimited).
+    ///
+    /// Building and insertions fail with an error of [`KeyTooLong`](crate::KeyTooLong)
+    /// on a longer key before copying it, and lookups of a longer key find nothing
+    /// without hashing it, so that absurd keys from untrusted input cannot blow up
+    /// the memory or time.
+    /// [`HashMap::try_get`] tells such keys apart from missing ones.
+    /// It can be changed later by [`HashMap::set_key_len_limit`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let builder = HashMapBuilder::new().key_len_limit(6);
+    /// let mut map = builder.build(&records).unwrap();
+    /// assert!(map.insert("sigkdd", 3).is_ok());
+    /// assert!(map.insert("sigmetrics", 4).is_err());
+    /// assert_eq!(map.get("sigmetrics"), None);
+    ///
+    /// let records = vec![("icdm", 0), ("sigmetrics", 1)];
+    /// assert!(builder.build(&records).is_err());
+    /// ```
+    pub const fn key_len_limit(mut self, limit: usize) -> Self {
+        self.key_len_limit = limit;
+        self
+    }
+
+    /// Specifies a callback receiving the progress of constructions (default: none),
+    /// such as to report long-running builds of hundreds of millions of keys.
+    ///
+    /// The callback is called every 65536 inserted keys and after the last one
+    /// in [`BuildPhase::Insertion`], once when entering [`BuildPhase::Layout`],
+    /// and once in [`BuildPhase::Finished`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::{Arc, Mutex};
+    /// use simplearrayhash::{BuildPhase, HashMapBuilder};
+    ///
+    /// let reports = Arc::new(Mutex::new(vec![]));
+    /// let sink = Arc::clone(&reports);
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMapBuilder::new()
+    ///     .progress(move |p| sink.lock().unwrap().push((p.phase, p.processed)))
+    ///     .build(&records)
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     *reports.lock().unwrap(),
+    ///     vec![
+    ///         (BuildPhase::Insertion, 3),
+    ///         (BuildPhase::Layout, 0),
+    ///         (BuildPhase::Finished, 3),
+    ///     ]
+    /// );
+    /// ```
+    pub fn progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(Progress) + Send + Sync + 'static,
+    {
+        self.progress = Some(ProgressFn::new(callback));
+        self
+    }
+
+    /// Applies the options on the layout of key bytes and auxiliary indexes to a built table,
+    /// whose key bytes are packed in key order if `sorted` is true.
+    fn finish_table<N>(&self, table: &mut Table<N>, sorted: bool) -> Result<()>
+    where
+        N: Default + Clone + Node,
+    {
+        phase!("layout", num_keys = table.num_keys());
+        if let Some(progress) = &self.progress {
+            progress.report(BuildPhase::Layout, 0, table.num_keys());
+        }
+        if self.share_suffixes && (self.align_keys || self.sort_keys) {
+            return Err(anyhow!(
+                "Sharing suffixes cannot be combined with aligning or sorting keys."
+            ));
+        }
+        if !self.growth_factor.exceeds_one() {
+            return Err(anyhow!("The growth factor must be greater than 1."));
+        }
+        if self.max_capacity < table.capacity() {
+            return Err(anyhow!(
+                "The maximum capacity must not be less than the initial capacity."
+            ));
+        }
+        if !self.tombstone_ratio.in_unit_interval() {
+            return Err(anyhow!("The tombstone ratio must be in (0, 1]."));
+        }
+        if self.stash_len != 0 && self.max_probe == 0 {
+            return Err(anyhow!("The maximum probe length must be positive."));
+        }
+        table.resize = ResizePolicy {
+            load_factor: self.load_factor,
+            growth_factor: self.growth_factor,
+            max_capacity: self.max_capacity,
+            tombstone_ratio: self.tombstone_ratio,
+        };
+        if self.stash_len != 0 {
+            table.enable_stash(self.stash_len, self.max_probe);
+        }
+        if sorted && self.sort_keys && !self.align_keys && self.stash_len == 0 {
+            // The key bytes are already packed in the layout of sorted keys.
+            table.keep_key_order();
+        } else if self.align_keys || self.share_suffixes || self.sort_keys {
+            let key_align = if self.align_keys { KEY_ALIGN } else { 1 };
+            table.layout_keys(key_align, self.share_suffixes, self.sort_keys);
+        }
+        if self.canonical {
+            table.canonicalize();
+        }
+        table.constant_time = self.constant_time;
+        table.set_lookup_cache(self.lookup_cache);
+        table.key_len_limit = self.key_len_limit;
+        #[cfg(feature = "hugepages")]
+        if self.huge_pages {
+            table.advise_huge_pages()?;
+        }
+        if self.fuzzy_index {
+            table.deletions = Some(table.deletion_index());
+        }
+        if self.suffix_index {
+            table.suffixes = Some(table.suffix_index());
+        }
+        if let Some(progress) = &self.progress {
+            progress.report(BuildPhase::Finished, table.num_keys(), table.num_keys());
+        }
+        Ok(())
+    }
+
+    /// Builds a table from `records` with the options, filling the node of each key by `fill`.
+    pub(crate) fn build_table<N, I, K, T, F>(
+        &self,
+        records: I,
+        num_bytes: usize,
+        mut fill: F,
+    ) -> Result<Table<N>>
+    where
+        N: Default + Clone + Node,
+        I: ExactSizeIterator<Item = (K, T)>,
+        K: AsRef<[u8]>,
+        F: FnMut(&mut N, T) -> Result<()>,
+    {
+        let mut table = Table::<N>::build_from_iter(
+            Tracked::new(records, self.progress.as_ref()),
+            num_bytes,
+            self.load_factor,
+            self.seed,
+            |nd, x| {
+                check_key_len(nd.len(), self.key_len_limit)?;
+                fill(nd, x)
+            },
+        )?;
+        self.finish_table(&mut table, false)?;
+        Ok(table)
+    }
+
+    /// Builds a table in the same manner as [`HashMapBuilder::build_table`]
+    /// from `records` whose keys are distinct and sorted,
+    /// skipping the detection of duplicate keys and the sorting of key bytes.
+    fn build_table_sorted<N, I, K, T, F>(
+        &self,
+        records: I,
+        num_bytes: usize,
+        mut fill: F,
+    ) -> Result<Table<N>>
+    where
+        N: Default + Clone + Node,
+        I: ExactSizeIterator<Item = (K, T)>,
+        K: AsRef<[u8]>,
+        F: FnMut(&mut N, T) -> Result<()>,
+    {
+        let records = Tracked::new(records, self.progress.as_ref()).map(|(key, x)| {
+            let hash = hash_key(key.as_ref(), self.seed);
+            (key, hash, x)
+        });
+        let mut table = Table::<N>::build_from_hashed(
+            records,
+            num_bytes,
+            self.load_factor,
+            self.seed,
+            false,
+            |nd, x| {
+                check_key_len(nd.len(), self.key_len_limit)?;
+                fill(nd, x)
+            },
+        )?;
+        self.finish_table(&mut table, true)?;
+        Ok(table)
+    }
+
+    /// Builds a table in the same manner as [`HashMapBuilder::build_table`]
+    /// from the keys of `items` and the corresponding `extras` in parallel.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn build_table_par<N, S, G, I, T, F>(
+        &self,
+        items: &[S],
+        key_of: G,
+        extras: I,
+        mut fill: F,
+    ) -> Result<Table<N>>
+    where
+        N: Default + Clone + Node,
+        S: Sync,
+        G: Fn(&S) -> &[u8] + Sync,
+        I: ExactSizeIterator<Item = T>,
+        F: FnMut(&mut N, T) -> Result<()>,
+    {
+        let mut table = Table::<N>::build_par(
+            items,
+            key_of,
+            Tracked::new(extras, self.progress.as_ref()),
+            self.load_factor,
+            self.seed,
+            |nd, x| {
+                check_key_len(nd.len(), self.key_len_limit)?;
+                fill(nd, x)
+            },
+        )?;
+        self.finish_table(&mut table, false)?;
+        Ok(table)
+    }
+
+    /// Builds a new [`HashMap`] from input records.
+    ///
+    /// # Arguments
+    ///
+    /// - `records`: List of key-value pairs.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `records` is empty,
+    ///  - `records` contains duplicate keys,
+    ///  - `records` contains a key whose length is out of the range of `L`, or
+    ///  - the load factor is not in `(0, 1]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let long_key = "a".repeat(256);
+    /// let records = vec![("icdm", 0), (long_key.as_str(), 1)];
+    /// assert!(HashMapBuilder::new().key_len::<u16>().build(&records).is_ok());
+    /// assert!(HashMapBuilder::new().key_len::<u8>().build(&records).is_err());
+    /// ```
+    pub fn build<K, V>(&self, records: &[(K, V)]) -> Result<HashMap<V, L>>
+    where
+        K: AsRef<[u8]>,
+        V: Default + Clone,
+    {
+        let num_bytes = records.iter().map(|(k, _)| k.as_ref().len()).sum();
+        self.build_records(records.iter().map(|(k, v)| (k, v.clone())), num_bytes)
+    }
+
+    /// Builds a new [`HashMap`] from input records in the same manner as [`Self::build`],
+    /// reusing the slots and key bytes allocated in `buffers`.
+    ///
+    /// When a large map is rebuilt repeatedly, recycling the retired map with
+    /// [`BuildBuffers::recycle`] lets the next build skip allocating its largest arrays.
+    /// The buffers are left empty after a successful build, and their allocations
+    /// are kept after a failed one.
+    /// Aligning, sharing, or sorting keys repacks them into new allocations.
+    ///
+    /// # Arguments
+    ///
+    /// - `records`: List of key-value pairs.
+    /// - `buffers`: Buffers whose allocations are reused.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned in the same cases as [`Self::build`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::map::BuildBuffers;
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let builder = HashMapBuilder::new();
+    /// let mut buffers = BuildBuffers::new();
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let mut map = builder.build_with_buffers(&records, &mut buffers).unwrap();
+    ///
+    /// let records = vec![("icdm", 3), ("idce", 4), ("sigmod", 5)];
+    /// let next = builder.build_with_buffers(&records, &mut buffers).unwrap();
+    /// buffers.recycle(std::mem::replace(&mut map, next));
+    /// assert_eq!(map.get("idce"), Some(&4));
+    /// ```
+    pub fn build_with_buffers<K, V>(
+        &self,
+        records: &[(K, V)],
+        buffers: &mut BuildBuffers<V, L>,
+    ) -> Result<HashMap<V, L>>
+    where
+        K: AsRef<[u8]>,
+        V: Default + Clone,
+    {
+        if records.is_empty() {
+            return Err(anyhow!("The input records must not be empty."));
+        }
+        let num_bytes = records.iter().map(|(k, _)| k.as_ref().len()).sum();
+        buffers.bytes.clear();
+        buffers.bytes.reserve(num_bytes);
+        let mut table = Table::<MapNode<V, L>>::build_from_iter_in(
+            Tracked::new(
+                records.iter().map(|(k, v)| (k, v.clone())),
+                self.progress.as_ref(),
+            ),
+            &mut buffers.nodes,
+            &mut buffers.bytes,
+            self.load_factor,
+            self.seed,
+            |nd, v| {
+                check_key_len(nd.len(), self.key_len_limit)?;
+                nd.val = v;
+                Ok(())
+            },
+        )?;
+        self.finish_table(&mut table, false)?;
+        Ok(HashMap { table })
+    }
+
+    /// Builds a new [`HashMap`] from an iterator of records with a known length,
+    /// such as records decoded from a file or network stream.
+    ///
+    /// The number of slots is computed from the length of the iterator,
+    /// and each record is placed as it is consumed, without buffering the records.
+    ///
+    /// # Arguments
+    ///
+    /// - `records`: Iterator of key-value pairs.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `records` is empty,
+    ///  - `records` contains duplicate keys,
+    ///  - `records` yields more records than its length,
+    ///  - `records` contains a key whose length is out of the range of `L`, or
+    ///  - the load factor is not in `(0, 1]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let keys = vec!["icdm", "idce", "sigmod"];
+    /// let map = HashMapBuilder::new()
+    ///     .build_from_iter(keys.iter().map(|k| (k, k.len())))
+    ///     .unwrap();
+    /// assert_eq!(map.get("sigmod"), Some(&6));
+    /// ```
+    pub fn build_from_iter<I, K, V>(&self, records: I) -> Result<HashMap<V, L>>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        I::IntoIter: ExactSizeIterator,
+        K: AsRef<[u8]>,
+        V: Default + Clone,
+    {
+        self.build_records(records.into_iter(), 0)
+    }
+
+    /// Builds a new [`HashMap`] from a [`BTreeMap`],
+    /// such as ordered mutable state frozen for serving.
+    ///
+    /// The keys of a [`BTreeMap`] are distinct and sorted, so duplicate keys are not searched for,
+    /// and [`HashMapBuilder::sort_keys`] keeps the key bytes as packed
+    /// unless keys are aligned or the stash is enabled.
+    ///
+    /// # Arguments
+    ///
+    /// - `map`: Map whose keys are ordered as their bytes by [`Borrow<[u8]>`](Borrow).
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `map` is empty,
+    ///  - `map` contains a key whose length is out of the range of `L`, or
+    ///  - the options are invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    ///
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let mut state = BTreeMap::new();
+    /// state.insert(b"sigmod".to_vec(), 2);
+    /// state.insert(b"icdm".to_vec(), 0);
+    /// state.insert(b"idce".to_vec(), 1);
+    /// let map = HashMapBuilder::new()
+    ///     .sort_keys(true)
+    ///     .build_from_btree_map(&state)
+    ///     .unwrap();
+    /// assert_eq!(map.get("idce"), Some(&1));
+    /// assert_eq!(map.rank("sigmod").unwrap(), 2);
+    /// ```
+    pub fn build_from_btree_map<K, V>(&self, map: &BTreeMap<K, V>) -> Result<HashMap<V, L>>
+    where
+        K: Borrow<[u8]>,
+        V: Default + Clone,
+    {
+        if map.is_empty() {
+            return Err(anyhow!("The input records must not be empty."));
+        }
+        let records = map.iter().map(|(k, v)| (k.borrow(), v.clone()));
+        let num_bytes = map.keys().map(|k| k.borrow().len()).sum();
+        let table = self.build_table_sorted(records, num_bytes, |nd: &mut MapNode<V, L>, v| {
+            nd.val = v;
+            Ok(())
+        })?;
+        Ok(HashMap { table })
+    }
+
+    /// Builds a new [`HashMap`] from input records,
+    /// hashing the keys and detecting duplicate keys in parallel.
+    ///
+    /// This is equivalent to [`HashMapBuilder::build`], but it speeds up
+    /// the construction from large inputs on multi-threaded machines.
+    ///
+    /// # Arguments
+    ///
+    /// - `records`: List of key-value pairs.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `records` is empty,
+    ///  - `records` contains duplicate keys,
+    ///  - `records` contains a key whose length is out of the range of `L`, or
+    ///  - the load factor is not in `(0, 1]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMapBuilder::new().build_par(&records).unwrap();
+    /// assert_eq!(map.get("idce"), Some(&1));
+    ///
+    /// let records = vec![("icdm", 0), ("icdm", 1)];
+    /// assert!(HashMapBuilder::new().build_par(&records).is_err());
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn build_par<K, V>(&self, records: &[(K, V)]) -> Result<HashMap<V, L>>
+    where
+        K: AsRef<[u8]> + Sync,
+        V: Default + Clone + Sync,
+    {
+        if records.is_empty() {
+            return Err(anyhow!("The input records must not be empty."));
+        }
+        let table = self.build_table_par(
+            records,
+            |(k, _)| k.as_ref(),
+            records.iter().map(|(_, v)| v.clone()),
+            |nd: &mut MapNode<V, L>, v| {
+                nd.val = v;
+                Ok(())
+            },
+        )?;
+        Ok(HashMap { table })
+    }
+
+    /// Builds a new [`HashMap`] from the key-value pairs of an [`fst::Map`].
+    ///
+    /// # Arguments
+    ///
+    /// - `fst`: Map whose pairs are copied.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `fst` is empty,
+    ///  - `fst` contains a key whose length is out of the range of `L`, or
+    ///  - the load factor is not in `(0, 1]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let fst = fst::Map::from_iter([("icdm", 0), ("idce", 1), ("sigmod", 2)]).unwrap();
+    /// let map = HashMapBuilder::new().build_from_fst_map(&fst).unwrap();
+    /// assert_eq!(map.get("idce"), Some(&1));
+    /// ```
+    #[cfg(feature = "fst")]
+    pub fn build_from_fst_map<D>(&self, fst: &fst::Map<D>) -> Result<HashMap<u64, L>>
+    where
+        D: AsRef<[u8]>,
+    {
+        let records = fst.stream().into_byte_vec();
+        let num_bytes = records.iter().map(|(k, _)| k.len()).sum();
+        self.build_records(records.into_iter(), num_bytes)
+    }
+
+    /// Builds a new [`HashMap`] from an Arrow column of keys and a column of values,
+    /// such as a [`StringArray`](arrow_array::StringArray)
+    /// or a [`LargeBinaryArray`](arrow_array::LargeBinaryArray) of keys.
+    ///
+    /// The keys are borrowed from the data buffer of `keys` through its offsets,
+    /// and the values are read from the value buffer of `values`,
+    /// without extracting or allocating each row.
+    ///
+    /// # Arguments
+    ///
+    /// - `keys`: Column of keys.
+    /// - `values`: Column of the values corresponding to `keys`.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `keys` is empty,
+    ///  - `keys` and `values` have different lengths,
+    ///  - `keys` or `values` contains nulls,
+    ///  - `keys` contains duplicate keys,
+    ///  - `keys` contains a key whose length is out of the range of `L`, or
+    ///  - the load factor is not in `(0, 1]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use arrow_array::{StringArray, UInt32Array};
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let keys = StringArray::from(vec!["icdm", "idce", "sigmod"]);
+    /// let values = UInt32Array::from(vec![0, 1, 2]);
+    /// let map = HashMapBuilder::new().build_from_arrow(&keys, &values).unwrap();
+    /// assert_eq!(map.get("idce"), Some(&1));
+    /// ```
+    #[cfg(feature = "arrow")]
+    pub fn build_from_arrow<T, P>(
+        &self,
+        keys: &arrow_array::GenericByteArray<T>,
+        values: &arrow_array::PrimitiveArray<P>,
+    ) -> Result<HashMap<P::Native, L>>
+    where
+        T: arrow_array::types::ByteArrayType,
+        T::Native: AsRef<[u8]>,
+        P: arrow_array::ArrowPrimitiveType,
+    {
+        use arrow_array::Array;
+
+        if keys.len() != values.len() {
+            return Err(anyhow!(
+                "The key and value columns must have the same length."
+            ));
+        }
+        if keys.null_count() != 0 || values.null_count() != 0 {
+            return Err(anyhow!("The key and value columns must not contain nulls."));
+        }
+        let num_bytes = keys.offsets().lengths().sum();
+        let records = values
+            .values()
+            .iter()
+            .enumerate()
+            .map(|(i, &v)| (keys.value(i), v));
+        self.build_records(records, num_bytes)
+    }
+
+    /// Builds a new [`HashMap`] from an iterator of records
+    /// whose keys have `num_bytes` bytes in total if known, or 0.
+    fn build_records<I, K, V>(&self, records: I, num_bytes: usize) -> Result<HashMap<V, L>>
+    where
+        I: ExactSizeIterator<Item = (K, V)>,
+        K: AsRef<[u8]>,
+        V: Default + Clone,
+    {
+        if records.len() == 0 {
+            return Err(anyhow!("The input records must not be empty."));
+        }
+        let table = self.build_table(records, num_bytes, |nd: &mut MapNode<V, L>, v| {
+            nd.val = v;
+            Ok(())
+        })?;
+        Ok(HashMap { table })
+    }
+
+    /// Builds a new [`DenseHashMap`] from input records,
+    /// which stores values out of line in a dense array.
+    ///
+    /// # Arguments
+    ///
+    /// - `records`: List of key-value pairs.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `records` is empty,
+    ///  - `records` contains duplicate keys,
+    ///  - `records` has more than 2^32 elements,
+    ///  - `records` contains a key whose length is out of the range of `L`, or
+    ///  - the load factor is not in `(0, 1]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("icdm", vec![0; 100]), ("idce", vec![1; 100])];
+    /// let map = HashMapBuilder::new()
+    ///     .key_len::<u8>()
+    ///     .build_dense(&records)
+    ///     .unwrap();
+    /// assert_eq!(map.get("idce"), Some(&vec![1; 100]));
+    /// ```
+    pub fn build_dense<K, V>(&self, records: &[(K, V)]) -> Result<DenseHashMap<V, L>>
+    where
+        K: AsRef<[u8]>,
+        V: Clone,
+    {
+        if records.is_empty() {
+            return Err(anyhow!("The input records must not be empty."));
+        }
+        if u32::try_from(records.len() - 1).is_err() {
+            return Err(anyhow!("The number of input records must not exceed 2^32."));
+        }
+        let records_with_idx = records.iter().enumerate().map(|(i, (k, _))| (k, i));
+        let num_bytes = records.iter().map(|(k, _)| k.as_ref().len()).sum();
+        let table = self.build_table(records_with_idx, num_bytes, |nd: &mut DenseNode<L>, i| {
+            nd.idx = i as u32;
+            Ok(())
+        })?;
+        let values = records.iter().map(|(_, v)| v.clone()).collect();
+        Ok(DenseHashMap { table, values })
+    }
+
+    /// Builds a new [`EncodedHashMap`] from input records, encoding the values by `codec`.
+    ///
+    /// # Arguments
+    ///
+    /// - `records`: List of key-value pairs.
+    /// - `codec`: Codec of the values.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `records` is empty,
+    ///  - `records` contains duplicate keys,
+    ///  - `records` contains a key whose length is out of the range of `L`,
+    ///  - `records` has more than 2^32 elements, or
+    ///  - the load factor is not in `(0, 1]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::{HashMapBuilder, SerialCodec};
+    ///
+    /// let records = vec![("icdm", "Data Mining".to_string()), ("sigmod", "Databases".to_string())];
+    /// let map = HashMapBuilder::new()
+    ///     .key_len::<u8>()
+    ///     .build_encoded(&records, SerialCodec::new())
+    ///     .unwrap();
+    /// assert_eq!(map.get("icdm").as_deref(), Some("Data Mining"));
+    /// ```
+    pub fn build_encoded<K, C>(
+        &self,
+        records: &[(K, C::Value)],
+        codec: C,
+    ) -> Result<EncodedHashMap<C, L>>
+    where
+        K: AsRef<[u8]>,
+        C: ValueCodec,
+    {
+        if records.is_empty() {
+            return Err(anyhow!("The input records must not be empty."));
+        }
+        if u32::try_from(records.len() - 1).is_err() {
+            return Err(anyhow!("The number of input records must not exceed 2^32."));
+        }
+        let records_with_idx = records.iter().enumerate().map(|(i, (k, _))| (k, i));
+        let num_bytes = records.iter().map(|(k, _)| k.as_ref().len()).sum();
+        let table = self.build_table(records_with_idx, num_bytes, |nd: &mut DenseNode<L>, i| {
+            nd.idx = i as u32;
+            Ok(())
+        })?;
+        let mut offsets = Vec::with_capacity(records.len() + 1);
+        let mut values = vec![];
+        for (_, v) in records {
+            offsets.push(values.len());
+            codec.encode(v, &mut values);
+        }
+        offsets.push(values.len());
+        Ok(EncodedHashMap {
+            table,
+            offsets,
+            values,
+            codec,
+        })
+    }
+
+    /// Builds a new [`HashMap`] from input records that may contain duplicate keys,
+    /// folding the values of each key into one with `fold`.
+    ///
+    /// For each distinct key, the value of its first occurrence is used as the initial
+    /// accumulator, and `fold(&mut acc, v)` is called for the values of later occurrences
+    /// in the input order.
+    ///
+    /// # Arguments
+    ///
+    /// - `records`: List of key-value pairs.
+    /// - `fold`: Function to merge a value into the accumulator.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `records` is empty,
+    ///  - `records` contains a key whose length is out of the range of `L`, or
+    ///  - the load factor is not in `(0, 1]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("icdm", 1), ("idce", 2), ("icdm", 3)];
+    /// let map = HashMapBuilder::new()
+    ///     .build_grouped(&records, |acc, v| *acc += v)
+    ///     .unwrap();
+    /// assert_eq!(map.len(), 2);
+    /// assert_eq!(map.get("icdm"), Some(&4));
+    /// ```
+    pub fn build_grouped<K, V, F>(&self, records: &[(K, V)], mut fold: F) -> Result<HashMap<V, L>>
+    where
+        K: AsRef<[u8]>,
+        V: Default + Clone,
+        F: FnMut(&mut V, &V),
+    {
+        if records.is_empty() {
+            return Err(anyhow!("The input records must not be empty."));
+        }
+        // Duplicates are folded where probing finds the slot of the key,
+        // so the number of bytes is an upper bound trimmed after the build.
+        let num_bytes = records.iter().map(|(k, _)| k.as_ref().len()).sum();
+        let records = records.iter().map(|(k, v)| (k, v));
+        let mut table = Table::<MapNode<V, L>>::build_merged(
+            Tracked::new(records, self.progress.as_ref()),
+            num_bytes,
+            self.load_factor,
+            self.seed,
+            |nd, v| {
+                check_key_len(nd.len(), self.key_len_limit)?;
+                nd.val = v.clone();
+                Ok(())
+            },
+            |nd, v| {
+                fold(&mut nd.val, v);
+                Ok(())
+            },
+        )?;
+        self.finish_table(&mut table, false)?;
+        Ok(HashMap { table })
+    }
+
+    /// Builds a new [`GroupedHashMap`] from input records,
+    /// splitting each key at the first occurrence of `delimiter` into a section and a key.
+    ///
+    /// The outer table of sections and the inner table of each section are built with the options.
+    ///
+    /// # Arguments
+    ///
+    /// - `records`: List of key-value pairs, whose keys contain `delimiter`.
+    /// - `delimiter`: Non-empty delimiter between a section and a key.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `records` is empty,
+    ///  - `records` contains duplicate keys,
+    ///  - `records` contains a key without `delimiter`,
+    ///  - `records` contains a section or key whose length is out of the range of `L`,
+    ///  - `delimiter` is empty, or
+    ///  - the load factor is not in `(0, 1]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![("conf/icdm", 0), ("conf/idce", 1), ("journal/tods", 2)];
+    /// let map = HashMapBuilder::new()
+    ///     .key_len::<u8>()
+    ///     .build_delimited(&records, "/")
+    ///     .unwrap();
+    /// assert_eq!(map.get("conf", "icdm"), Some(&0));
+    /// assert_eq!(map.get_joined("journal/tods"), Some(&2));
+    /// ```
+    pub fn build_delimited<K, V, D>(
+        &self,
+        records: &[(K, V)],
+        delimiter: D,
+    ) -> Result<GroupedHashMap<V, L>>
+    where
+        K: AsRef<[u8]>,
+        V: Default + Clone,
+        D: AsRef<[u8]>,
+    {
+        let delimiter = delimiter.as_ref();
+        if delimiter.is_empty() {
+            return Err(anyhow!("The delimiter must not be empty."));
+        }
+        if records.is_empty() {
+            return Err(anyhow!("The input records must not be empty."));
+        }
+        let mut indices = std::collections::HashMap::new();
+        let mut sections = vec![];
+        let mut groups: Vec<Vec<_>> = vec![];
+        for (k, v) in records {
+            let (section, key) = grouped::split(k.as_ref(), delimiter)
+                .ok_or_else(|| anyhow!("Every key must contain the delimiter."))?;
+            let i = *indices.entry(section).or_insert_with(|| {
+                sections.push((section, groups.len()));
+                groups.push(vec![]);
+                groups.len() - 1
+            });
+            groups[i].push((key, v.clone()));
+        }
+        Ok(GroupedHashMap {
+            sections: self.build(&sections)?,
+            groups: groups
+                .iter()
+                .map(|records| self.build(records))
+                .collect::<Result<_>>()?,
+            delimiter: delimiter.to_vec(),
+        })
+    }
+
+    /// Builds a new [`HashMap`] from input records whose keys implement [`Keyable`].
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `records` is empty,
+    ///  - `records` contains duplicate keys,
+    ///  - `records` contains a key whose encoded length is out of the range of `L`, or
+    ///  - the load factor is not in `(0, 1]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let records = vec![(1u64, "a"), (2u64, "b")];
+    /// let map = HashMapBuilder::new().key_len::<u8>().build_keyable(&records).unwrap();
+    /// assert_eq!(map.get_keyable(&2u64), Some(&"b"));
+    /// ```
+    pub fn build_keyable<T, V>(&self, records: &[(T, V)]) -> Result<HashMap<V, L>>
+    where
+        T: Keyable,
+        V: Default + Clone,
+    {
+        if records.is_empty() {
+            return Err(anyhow!("The input records must not be empty."));
+        }
+        let mut bytes = vec![];
+        let mut ends = Vec::with_capacity(records.len());
+        for (k, _) in records {
+            k.encode_key(&mut bytes);
+            ends.push(bytes.len());
+        }
+        let keys: Vec<_> = ends
+            .iter()
+            .scan(0, |start, &end| {
+                let key = &bytes[*start..end];
+                *start = end;
+                Some(key)
+            })
+            .collect();
+        self.build_parts(&keys, records.iter().map(|(_, v)| Ok(v.clone())))
+    }
+
+    /// Builds a new [`HashMap`] from keys, computing the value of each key with `f`.
+    ///
+    /// `f` is called for each key in the input order,
+    /// and the construction is aborted at the first error returned by `f`.
+    ///
+    /// # Arguments
+    ///
+    /// - `keys`: List of keys.
+    /// - `f`: Function to compute the value from a key.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `keys` is empty,
+    ///  - `keys` contains duplicate keys,
+    ///  - `keys` contains a key whose length is out of the range of `L`,
+    ///  - the load factor is not in `(0, 1]`, or
+    ///  - `f` returns an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let keys = vec!["icdm", "idce", "sigmod"];
+    /// let map = HashMapBuilder::new()
+    ///     .build_with(&keys, |key| Ok(key.len()))
+    ///     .unwrap();
+    /// assert_eq!(map.get("sigmod"), Some(&6));
+    /// ```
+    pub fn build_with<K, V, F>(&self, keys: &[K], mut f: F) -> Result<HashMap<V, L>>
+    where
+        K: AsRef<[u8]>,
+        V: Default + Clone,
+        F: FnMut(&[u8]) -> Result<V>,
+    {
+        if keys.is_empty() {
+            return Err(anyhow!("The input keys must not be empty."));
+        }
+        self.build_parts(keys, keys.iter().map(|k| f(k.as_ref())))
+    }
+
+    /// Builds a new [`HashMap`] from parallel lists of keys and values,
+    /// where `values[i]` is the value of `keys[i]`.
+    ///
+    /// # Arguments
+    ///
+    /// - `keys`: List of keys.
+    /// - `values`: List of values.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `keys` is empty,
+    ///  - `keys` and `values` have different lengths,
+    ///  - `keys` contains duplicate keys,
+    ///  - `keys` contains a key whose length is out of the range of `L`, or
+    ///  - the load factor is not in `(0, 1]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashMapBuilder;
+    ///
+    /// let keys = vec!["icdm", "idce", "sigmod"];
+    /// let values = vec![0, 1, 2];
+    /// let map = HashMapBuilder::new()
+    ///     .build_from_keys_and_values(&keys, values)
+    ///     .unwrap();
+    /// assert_eq!(map.get("idce"), Some(&1));
+    /// ```
+    pub fn build_from_keys_and_values<K, V>(
+        &self,
+        keys: &[K],
+        values: Vec<V>,
+    ) -> Result<HashMap<V, L>>
+    where
+        K: AsRef<[u8]>,
+        V: Default + Clone,
+    {
+        if keys.is_empty() {
+            return Err(anyhow!("The input keys must not be empty."));
+        }
+        if keys.len() != values.len() {
+            return Err(anyhow!(
+                "The input keys and values must have the same length."
+            ));
+        }
+        self.build_parts(keys, values.into_iter().map(Ok))
+    }
+
+    /// Builds a new [`HashMap`] from non-empty keys and the corresponding values
+    /// in a single pass, placing each value and detecting duplicate keys while probing.
+    fn build_parts<K, V, I>(&self, keys: &[K], vals: I) -> Result<HashMap<V, L>>
+    where
+        K: AsRef<[u8]>,
+        V: Default + Clone,
+        I: IntoIterator<Item = Result<V>>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let records = keys.iter().zip(vals);
+        let num_bytes = keys.iter().map(|k| k.as_ref().len()).sum();
+        let table = self.build_table(records, num_bytes, |nd: &mut MapNode<V, L>, v| {
+            nd.val = v?;
+            Ok(())
+        })?;
+        Ok(HashMap { table })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_basic() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let map = HashMap::new(&records).unwrap();
+        assert_eq!(map.len(), 6);
+    }
+
+    #[test]
+    fn test_get() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let map = HashMap::new(&records).unwrap();
+        for &(k, v) in &records {
+            assert_eq!(*map.get(k).unwrap(), v);
+        }
+        assert_eq!(map.get("sigkdd"), None);
+        assert_eq!(map.get("idml"), None);
+    }
+
+    #[test]
+    fn test_get_mut() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let mut map = HashMap::new(&records).unwrap();
+        for &(k, v) in &records {
             *map.get_mut(k).unwrap() = v * 3;
         }
         for &(k, v) in &records {
-            assert_eq!(*map.get(k).unwrap(), v * 3);
+            assert_eq!(*map.get(k).unwrap(), v * 3);
+        }
+    }
+
+    #[test]
+    fn test_get_copied() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let map = HashMap::new(&records).unwrap();
+        for &(k, v) in &records {
+            assert_eq!(map.get_copied(k), Some(v));
+            assert_eq!(map.get_or(k, 100), v);
+        }
+        assert_eq!(map.get_copied("sigkdd"), None);
+        assert_eq!(map.get_or("sigkdd", 100), 100);
+    }
+
+    #[test]
+    fn test_get_composite() {
+        let fields = [("a", "bc"), ("ab", "c"), ("", "abc"), ("abc", "")];
+        let records: Vec<_> = fields
+            .iter()
+            .enumerate()
+            .map(|(i, f)| (crate::KeyEncoder::encode(f), i))
+            .collect();
+        let map = HashMap::new(&records).unwrap();
+        for (i, f) in fields.iter().enumerate() {
+            assert_eq!(map.get_composite(f), Some(&i));
+        }
+        assert_eq!(map.get_composite(("a", "b", "c")), None);
+        assert_eq!(map.get_composite(("abc",)), None);
+    }
+
+    #[test]
+    fn test_lookup_tokens() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let map = HashMap::new(&records).unwrap();
+        let text = "icdm\tsigkdd\t\tacl";
+        let tokens: Vec<_> = map.lookup_tokens(text, b'\t').collect();
+        assert_eq!(
+            tokens,
+            vec![
+                (0..4, Some(&0)),
+                (5..11, None),
+                (12..12, Some(&2)),
+                (13..16, Some(&5)),
+            ]
+        );
+        let tokens: Vec<_> = map.lookup_tokens("", b'\t').collect();
+        assert_eq!(tokens, vec![(0..0, Some(&2))]);
+    }
+
+    #[test]
+    fn test_match_at() {
+        let keys = ["a", "ab", "abcd", "bc"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let map = HashMap::new(&records).unwrap();
+        let text = "abcde";
+        assert_eq!(map.match_at(text, 0), Some((0..4, &2)));
+        assert_eq!(map.match_at(text, 1), Some((1..3, &3)));
+        assert_eq!(map.match_at(text, 2), None);
+        assert_eq!(map.match_at(text, 5), None);
+        assert_eq!(map.match_at(text, 6), None);
+        assert_eq!(map.match_at("abc", 0), Some((0..2, &1)));
+
+        let records = [("", 0), ("x", 1)];
+        let map = HashMap::new(&records).unwrap();
+        assert_eq!(map.match_at("xy", 0), Some((0..1, &1)));
+        assert_eq!(map.match_at("xy", 1), Some((1..1, &0)));
+    }
+
+    #[test]
+    fn test_key_len() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let map = HashMapBuilder::new()
+            .key_len::<u8>()
+            .build(&records)
+            .unwrap();
+        for &(k, v) in &records {
+            assert_eq!(*map.get(k).unwrap(), v);
+        }
+        assert_eq!(map.get("sigkdd"), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_key_len_overflow() {
+        let long_key = "a".repeat(256);
+        let records = [(long_key.as_str(), 0)];
+        HashMapBuilder::new()
+            .key_len::<u8>()
+            .build(&records)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_capacity() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let map = HashMap::new(&records).unwrap();
+        assert!(map.capacity().is_power_of_two());
+        assert!(map.capacity() >= map.len());
+        assert_eq!(map.load_factor(), map.len() as f64 / map.capacity() as f64);
+        assert!(!crate::DEFAULT_LOAD_FACTOR.is_exceeded(map.len(), map.capacity()));
+    }
+
+    #[test]
+    fn test_optimize() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let mut map = HashMapBuilder::new()
+            .load_factor(0.1)
+            .build(&records)
+            .unwrap();
+        assert_eq!(map.capacity(), 64);
+        map.optimize(1.0).unwrap();
+        assert_eq!(map.capacity(), 8);
+        for &(k, v) in &records {
+            assert_eq!(*map.get(k).unwrap(), v);
+        }
+        assert_eq!(map.get("sigkdd"), None);
+        assert!(map.optimize(0.0).is_err());
+
+        map.optimize_to(1, 8).unwrap();
+        assert_eq!(map.capacity(), 64);
+        map.optimize_to(1, 1).unwrap();
+        assert_eq!(map.capacity(), 8);
+        assert!(map.optimize_to(9, 8).is_err());
+        assert!(map.optimize_to(1, 0).is_err());
+    }
+
+    #[test]
+    fn test_rehash() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let mut map = HashMapBuilder::new().seed(1).build(&records).unwrap();
+        for seed in 2..10 {
+            map.rehash(seed);
+            assert_eq!(map.len(), 6);
+            for &(k, v) in &records {
+                assert_eq!(*map.get(k).unwrap(), v);
+            }
+            assert_eq!(map.get("sigkdd"), None);
+        }
+    }
+
+    #[test]
+    fn test_align_keys() {
+        let keys = [
+            "icdm",
+            "idce",
+            "",
+            "sigmod",
+            "abcdefghijklmnop",
+            "abcdefghijklmnopq",
+            "abcdefghijklmnopqrstuvwxyz012345",
+            "a\0",
+        ];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let mut map = HashMapBuilder::new()
+            .align_keys(true)
+            .build(&records)
+            .unwrap();
+        for _ in 0..2 {
+            assert_eq!(map.table.bytes.len() % crate::KEY_ALIGN, 0);
+            for (_, node) in map.table.iter() {
+                assert_eq!(node.ptr() % crate::KEY_ALIGN, 0);
+            }
+            for &(k, v) in &records {
+                assert_eq!(*map.get(k).unwrap(), v);
+            }
+            for k in [
+                "a",
+                "a\0\0",
+                "abcdefghijklmnoq",
+                "abcdefghijklmnopr",
+                "sigkdd",
+            ] {
+                assert_eq!(map.get(k), None);
+            }
+            map.optimize(0.5).unwrap();
+        }
+
+        let other = HashMap::<usize>::deserialize_from_slice(&map.serialize_to_vec()).unwrap();
+        assert_eq!(other.table.key_align, crate::KEY_ALIGN);
+        for &(k, v) in &records {
+            assert_eq!(*other.get(k).unwrap(), v);
+        }
+    }
+
+    #[test]
+    fn test_share_suffixes() {
+        let keys = [
+            "walking", "talking", "king", "ing", "", "g", "walk", "alk", "sigmod",
+        ];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let mut map = HashMapBuilder::new()
+            .share_suffixes(true)
+            .build(&records)
+            .unwrap();
+        // "walking" + "talking" + "walk" + "sigmod"
+        let num_bytes = 7 + 7 + 4 + 6;
+        for seed in 0..3 {
+            map.rehash(seed);
+            assert_eq!(map.table.bytes.len(), num_bytes);
+            for &(k, v) in &records {
+                assert_eq!(*map.get(k).unwrap(), v);
+            }
+            assert_eq!(map.get("lking"), None);
+        }
+
+        let mut other = HashMap::<usize>::deserialize_from_slice(&map.serialize_to_vec()).unwrap();
+        other.optimize(0.5).unwrap();
+        assert_eq!(other.table.bytes.len(), num_bytes);
+        for &(k, v) in &records {
+            assert_eq!(*other.get(k).unwrap(), v);
+        }
+
+        assert!(HashMapBuilder::new()
+            .align_keys(true)
+            .share_suffixes(true)
+            .build(&records)
+            .is_err());
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let mut sorted = keys;
+        sorted.sort_unstable();
+
+        let mut map = HashMapBuilder::new()
+            .sort_keys(true)
+            .align_keys(true)
+            .build(&records)
+            .unwrap();
+        for _ in 0..2 {
+            for &(k, v) in &records {
+                assert_eq!(*map.get(k).unwrap(), v);
+            }
+            for (i, k) in sorted.iter().enumerate() {
+                assert_eq!(map.rank(k).unwrap(), i);
+                assert_eq!(map.select(i).unwrap().unwrap().0, k.as_bytes());
+            }
+            assert_eq!(map.select(6).unwrap(), None);
+            assert_eq!(map.rank("b").unwrap(), 2);
+            assert_eq!(map.predecessor("").unwrap(), None);
+            assert_eq!(map.predecessor("b").unwrap(), Some((&b"acl"[..], &5)));
+            assert_eq!(map.successor("").unwrap(), Some((&b"acl"[..], &5)));
+            assert_eq!(map.successor("sigmod").unwrap(), None);
+            map.rehash(42);
+        }
+
+        let other = HashMap::<usize>::deserialize_from_slice(&map.serialize_to_vec()).unwrap();
+        assert_eq!(other.rank("sigir").unwrap(), 4);
+
+        let map = HashMap::new(&records).unwrap();
+        assert!(map.rank("icdm").is_err());
+        assert!(map.select(0).is_err());
+        assert!(HashMapBuilder::new()
+            .sort_keys(true)
+            .share_suffixes(true)
+            .build(&records)
+            .is_err());
+    }
+
+    #[test]
+    fn test_btree_map() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let state: BTreeMap<_, _> = records
+            .iter()
+            .map(|&(k, v)| (k.as_bytes().to_vec(), v))
+            .collect();
+        let mut sorted = keys;
+        sorted.sort_unstable();
+
+        let map = HashMap::<usize>::try_from(&state).unwrap();
+        for &(k, v) in &records {
+            assert_eq!(map.get(k), Some(&v));
+        }
+        assert_eq!(BTreeMap::from(&map), state);
+
+        for builder in [
+            HashMapBuilder::new().sort_keys(true),
+            HashMapBuilder::new().sort_keys(true).align_keys(true),
+            HashMapBuilder::new().sort_keys(true).stash(2, 1),
+        ] {
+            let mut map = builder.build_from_btree_map(&state).unwrap();
+            for _ in 0..2 {
+                for (i, k) in sorted.iter().enumerate() {
+                    assert_eq!(map.rank(k).unwrap(), i);
+                    assert_eq!(map.select(i).unwrap().unwrap().0, k.as_bytes());
+                }
+                map.rehash(42);
+            }
+        }
+
+        let builder = HashMapBuilder::new().canonical(true);
+        let map = builder.build_from_btree_map(&state).unwrap();
+        let other = builder.build(&records).unwrap();
+        assert_eq!(map.serialize_to_vec(), other.serialize_to_vec());
+
+        let long = BTreeMap::from([(vec![0; 256], 0)]);
+        assert!(HashMap::<i32, u8>::try_from(&long).is_err());
+        assert!(HashMap::<i32>::try_from(&BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_range() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let map = HashMapBuilder::new()
+            .sort_keys(true)
+            .build(&records)
+            .unwrap();
+        fn keys_in(it: RangeIter<'_, usize, usize>) -> Vec<&[u8]> {
+            it.map(|(k, _)| k).collect()
+        }
+
+        assert_eq!(
+            keys_in(map.range::<&str, _>(..).unwrap()),
+            vec![&b""[..], b"acl", b"icdm", b"idce", b"sigir", b"sigmod"]
+        );
+        assert_eq!(
+            keys_in(map.range("icdm".."sigir").unwrap()),
+            vec![b"icdm", b"idce"]
+        );
+        assert_eq!(
+            keys_in(map.range("icdm"..="sigir").unwrap()),
+            vec![&b"icdm"[..], b"idce", b"sigir"]
+        );
+        assert_eq!(
+            keys_in(map.range(..="acl").unwrap()),
+            vec![&b""[..], b"acl"]
+        );
+        assert_eq!(keys_in(map.range("ice"..).unwrap()).len(), 3);
+        assert!(keys_in(map.range("x"..).unwrap()).is_empty());
+        assert!(keys_in(map.range("sigir".."icdm").unwrap()).is_empty());
+
+        let mut it = map.range("acl".."sigmod").unwrap();
+        assert_eq!(it.len(), 4);
+        assert_eq!(it.next_back(), Some((&b"sigir"[..], &4)));
+        assert_eq!(it.next(), Some((&b"acl"[..], &5)));
+        assert_eq!(it.len(), 2);
+
+        assert!(HashMap::new(&records).unwrap().range("a".."b").is_err());
+    }
+
+    #[test]
+    fn test_keys_with_prefix() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl", "i", "sig"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let map = HashMapBuilder::new()
+            .sort_keys(true)
+            .build(&records)
+            .unwrap();
+        let keys_with_prefix = |prefix: &str| -> Vec<Vec<u8>> {
+            map.keys_with_prefix(prefix)
+                .unwrap()
+                .map(|(k, _)| k.to_vec())
+                .collect()
+        };
+        assert_eq!(keys_with_prefix("").len(), 8);
+        assert_eq!(keys_with_prefix("i"), vec![&b"i"[..], b"icdm", b"idce"]);
+        assert_eq!(
+            keys_with_prefix("sig"),
+            vec![&b"sig"[..], b"sigir", b"sigmod"]
+        );
+        assert_eq!(keys_with_prefix("sigm"), vec![b"sigmod"]);
+        assert!(keys_with_prefix("sigmodx").is_empty());
+        assert!(keys_with_prefix("b").is_empty());
+        assert!(keys_with_prefix("z").is_empty());
+
+        assert!(HashMap::new(&records)
+            .unwrap()
+            .keys_with_prefix("i")
+            .is_err());
+    }
+
+    #[test]
+    fn test_keys_with_suffix() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl", "m", "dm"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        for builder in [
+            HashMapBuilder::new().suffix_index(true),
+            HashMapBuilder::new()
+                .share_suffixes(true)
+                .suffix_index(true),
+        ] {
+            let mut map = builder.build(&records).unwrap();
+            let keys_with_suffix = |map: &HashMap<usize>, suffix: &str| -> Vec<Vec<u8>> {
+                map.keys_with_suffix(suffix)
+                    .unwrap()
+                    .map(|(k, _)| k.to_vec())
+                    .collect()
+            };
+            assert_eq!(keys_with_suffix(&map, "").len(), 8);
+            assert_eq!(keys_with_suffix(&map, "m"), vec![&b"m"[..], b"dm", b"icdm"]);
+            assert_eq!(keys_with_suffix(&map, "dm"), vec![&b"dm"[..], b"icdm"]);
+            assert_eq!(keys_with_suffix(&map, "d"), vec![b"sigmod"]);
+            assert!(keys_with_suffix(&map, "xicdm").is_empty());
+            assert!(keys_with_suffix(&map, "b").is_empty());
+            assert!(keys_with_suffix(&map, "z").is_empty());
+
+            let other = HashMap::<usize>::deserialize_from_slice(&map.serialize_to_vec()).unwrap();
+            assert_eq!(keys_with_suffix(&other, "m"), keys_with_suffix(&map, "m"));
+            map.rehash(42);
+            assert_eq!(keys_with_suffix(&map, "dm"), vec![&b"dm"[..], b"icdm"]);
+            assert!(map.insert("kdd", 8).is_err());
+        }
+
+        assert!(HashMap::new(&records)
+            .unwrap()
+            .keys_with_suffix("m")
+            .is_err());
+    }
+
+    #[test]
+    fn test_get_fuzzy() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl", "icde", "aacl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let mut map = HashMapBuilder::new()
+            .fuzzy_index(true)
+            .build(&records)
+            .unwrap();
+        for _ in 0..2 {
+            let fuzzy_keys = |key: &str| -> Vec<Vec<u8>> {
+                map.get_fuzzy(key)
+                    .unwrap()
+                    .into_iter()
+                    .map(|(k, _)| k.to_vec())
+                    .collect()
+            };
+            assert_eq!(fuzzy_keys("icdm"), vec![&b"icdm"[..], b"icde"]);
+            assert_eq!(fuzzy_keys("idc"), vec![b"idce"]);
+            assert_eq!(fuzzy_keys("sigmoid"), vec![b"sigmod"]);
+            assert_eq!(fuzzy_keys("sigkr"), vec![b"sigir"]);
+            assert_eq!(fuzzy_keys("acl"), vec![&b"acl"[..], b"aacl"]);
+            assert_eq!(fuzzy_keys("x"), vec![b""]);
+            assert!(fuzzy_keys("cidm").is_empty());
+            assert!(fuzzy_keys("sigkdd").is_empty());
+            map.rehash(42);
+        }
+
+        let other = HashMap::<usize>::deserialize_from_slice(&map.serialize_to_vec()).unwrap();
+        assert_eq!(other.get_fuzzy("icdmx").unwrap(), vec![(&b"icdm"[..], &0)]);
+
+        assert!(HashMap::new(&records).unwrap().get_fuzzy("icdm").is_err());
+    }
+
+    #[test]
+    fn test_bytes_capacity() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let map = HashMap::new(&records).unwrap();
+        assert_eq!(map.table.bytes.len(), 22);
+        assert_eq!(map.table.bytes.capacity(), 22);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_new_par() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let map = HashMap::new_par(&records).unwrap();
+        assert_eq!(map.len(), 6);
+        for &(k, v) in &records {
+            assert_eq!(map.get(k), Some(&v));
+        }
+        assert_eq!(map.get("sigkdd"), None);
+
+        let map = HashMapBuilder::new()
+            .sort_keys(true)
+            .build_par(&records)
+            .unwrap();
+        assert_eq!(map.rank("icdm").unwrap(), 2);
+
+        let records = [("icdm", 0), ("icdm", 1)];
+        assert!(HashMap::new_par(&records).is_err());
+        let records: [(&str, u32); 0] = [];
+        assert!(HashMap::new_par(&records).is_err());
+    }
+
+    #[test]
+    fn test_get_by_hash() {
+        let keys: Vec<_> = (0..1000).map(|i| i.to_string()).collect();
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        for builder in [
+            HashMapBuilder::new().load_factor(1.0),
+            HashMapBuilder::new().seed(42).stash(8, 2),
+        ] {
+            let mut map = builder.build(&records).unwrap();
+            for k in &keys[..100] {
+                map.remove(k).unwrap();
+            }
+            for (k, v) in &records[100..] {
+                let candidates: Vec<_> = map.get_by_hash(map.hash(k)).collect();
+                assert_eq!(candidates, vec![(k.as_bytes(), v)]);
+            }
+            for k in &keys[..100] {
+                assert_eq!(map.get_by_hash(map.hash(k)).count(), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_hash_sharding() {
+        let keys: Vec<_> = (0..1000).map(|i| i.to_string()).collect();
+        let seed = 42;
+        let mut shards = vec![vec![]; 4];
+        for (i, k) in keys.iter().enumerate() {
+            shards[(crate::hash(k, seed) % 4) as usize].push((k, i));
+        }
+        let maps: Vec<_> = shards
+            .iter()
+            .map(|records| HashMapBuilder::new().seed(seed).build(records).unwrap())
+            .collect();
+        for (i, k) in keys.iter().enumerate() {
+            let hash = crate::hash(k, seed);
+            let map = &maps[(hash % 4) as usize];
+            assert_eq!(map.hash(k), hash);
+            assert_eq!(map.get(k), Some(&i));
+        }
+
+        let mut map =
+            HashMap::<usize>::deserialize_from_slice(&maps[0].serialize_to_vec()).unwrap();
+        assert_eq!(map.seed(), seed);
+        map.rehash(7);
+        assert_eq!(map.hash("0"), crate::hash("0", 7));
+    }
+
+    #[test]
+    fn test_get_prehashed() {
+        const IDCE: PrehashedKey = crate::key!("idce");
+        const SIGMOD: PrehashedKey = crate::key!("sigmod");
+        let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+
+        let map = HashMapBuilder::new()
+            .lookup_cache(4)
+            .build(&records)
+            .unwrap();
+        assert_eq!(map.get_prehashed(&IDCE), Some(&1));
+        assert_eq!(map.get_prehashed(&crate::key!("sigir")), None);
+
+        let map = HashMapBuilder::new().seed(42).build(&records).unwrap();
+        assert_eq!(map.get_prehashed(&IDCE), Some(&1));
+        assert_eq!(map.get_prehashed(&SIGMOD), Some(&2));
+
+        let map = HashMapBuilder::new()
+            .key_len_limit(4)
+            .build(&records[..2])
+            .unwrap();
+        assert_eq!(map.get_prehashed(&IDCE), Some(&1));
+        assert_eq!(map.get_prehashed(&SIGMOD), None);
+    }
+
+    #[test]
+    fn test_get_batch() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let map = HashMap::new(&records).unwrap();
+        let queries = ["acl", "sigkdd", "", "www", "icdm"];
+        let (hits, misses) = map.get_batch(queries.iter().map(|q| q.to_string()));
+        let hits: Vec<_> = hits.iter().map(|(k, &v)| (k.as_str(), v)).collect();
+        assert_eq!(hits, vec![("acl", 5), ("", 2), ("icdm", 0)]);
+        assert_eq!(misses, vec!["sigkdd", "www"]);
+
+        let (hits, misses) = map.get_batch(Vec::<&str>::new());
+        assert!(hits.is_empty() && misses.is_empty());
+    }
+
+    #[cfg(feature = "instrument")]
+    #[test]
+    fn test_probe_stats() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let map = HashMap::new(&records).unwrap();
+        assert_eq!(map.probe_stats(), crate::ProbeStats::default());
+        for k in keys {
+            map.get(k);
+        }
+        map.get("sigkdd");
+        let stats = map.probe_stats();
+        assert_eq!((stats.hits, stats.misses), (6, 1));
+        assert!(stats.probes >= 7);
+        assert_eq!(stats.histogram.iter().sum::<u64>(), 7);
+        assert!(stats.mean_probes() >= 1.0);
+        map.reset_probe_stats();
+        assert_eq!(map.probe_stats().lookups(), 0);
+    }
+
+    #[test]
+    fn test_insert() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        for growth_factor in [1.1, 1.5, 2.0, 4.0] {
+            for align_keys in [false, true] {
+                let mut map = HashMapBuilder::new()
+                    .growth_factor(growth_factor)
+                    .align_keys(align_keys)
+                    .build(&records[..1])
+                    .unwrap();
+                for &(k, v) in &records[1..] {
+                    assert_eq!(map.insert(k, v).unwrap(), None);
+                }
+                for i in 0..100 {
+                    let key = format!("key{i}");
+                    assert_eq!(map.insert(&key, i + 6).unwrap(), None);
+                    assert!(map.load_factor() <= 0.8);
+                }
+                assert_eq!(map.len(), 106);
+                for &(k, v) in &records {
+                    assert_eq!(map.get(k), Some(&v));
+                    assert_eq!(map.insert(k, v + 1).unwrap(), Some(v));
+                    assert_eq!(map.get(k), Some(&(v + 1)));
+                }
+                for i in 0..100 {
+                    assert_eq!(map.get(format!("key{i}")), Some(&(i + 6)));
+                }
+                assert_eq!(map.get("sigkdd"), None);
+                assert_eq!(map.len(), 106);
+
+                let other =
+                    HashMap::<usize>::deserialize_from_slice(&map.serialize_to_vec()).unwrap();
+                assert_eq!(other.get("key99"), Some(&105));
+            }
+        }
+    }
+
+    #[test]
+    fn test_remove() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        for tombstone_ratio in [0.01, 0.2, 1.0] {
+            let mut map = HashMapBuilder::new()
+                .tombstone_ratio(tombstone_ratio)
+                .load_factor(1.0)
+                .build(&records)
+                .unwrap();
+            for round in 0..50 {
+                for i in 0..20 {
+                    let key = format!("key{}", round * 20 + i);
+                    assert_eq!(map.insert(&key, i).unwrap(), None);
+                }
+                for i in 0..20 {
+                    let key = format!("key{}", round * 20 + i);
+                    assert_eq!(map.remove(&key).unwrap(), Some(i));
+                    assert_eq!(map.remove(&key).unwrap(), None);
+                    assert_eq!(map.get(&key), None);
+                }
+                assert!(map.table.num_tombstones as f64 <= map.capacity() as f64 * tombstone_ratio);
+                assert!(map.len() + map.table.num_tombstones < map.capacity());
+            }
+            assert_eq!(map.len(), 6);
+            for &(k, v) in &records {
+                assert_eq!(map.get(k), Some(&v));
+            }
+
+            let other = HashMap::<usize>::deserialize_from_slice(&map.serialize_to_vec()).unwrap();
+            assert_eq!(other.table.num_tombstones, map.table.num_tombstones);
+            for &(k, v) in &records {
+                assert_eq!(other.get(k), Some(&v));
+            }
+        }
+
+        assert!(HashMapBuilder::new()
+            .tombstone_ratio(0.0)
+            .build(&records)
+            .is_err());
+        let mut map = HashMapBuilder::new()
+            .sort_keys(true)
+            .build(&records)
+            .unwrap();
+        assert!(map.remove("icdm").is_err());
+    }
+
+    #[test]
+    fn test_clear() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let mut map = HashMap::new(&records).unwrap();
+        let (capacity, bytes_capacity) = (map.capacity(), map.table.bytes.capacity());
+        for _ in 0..3 {
+            map.clear();
+            assert!(map.is_empty());
+            assert_eq!(map.capacity(), capacity);
+            assert_eq!(map.table.bytes.capacity(), bytes_capacity);
+            for k in keys {
+                assert_eq!(map.get(k), None);
+            }
+            for &(k, v) in &records {
+                assert_eq!(map.insert(k, v).unwrap(), None);
+            }
+            for &(k, v) in &records {
+                assert_eq!(map.get(k), Some(&v));
+            }
+            assert_eq!(map.capacity(), capacity);
+            assert_eq!(map.table.bytes.capacity(), bytes_capacity);
+        }
+    }
+
+    #[test]
+    fn test_reserve() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        for load_factor in [0.5, 0.8, 1.0] {
+            let mut map = HashMapBuilder::new()
+                .load_factor(load_factor)
+                .growth_factor(1.5)
+                .build(&records)
+                .unwrap();
+            map.reserve(0).unwrap();
+            let capacity = map.capacity();
+            map.reserve(1).unwrap();
+            assert!(map.capacity() >= capacity);
+            map.reserve(100).unwrap();
+            let capacity = map.capacity();
+            for i in 0..100 {
+                map.insert(i.to_string(), i).unwrap();
+            }
+            assert_eq!(map.capacity(), capacity);
+            assert!(map.load_factor() <= load_factor);
+            for &(k, v) in &records {
+                assert_eq!(map.get(k), Some(&v));
+            }
+        }
+
+        let mut map = HashMapBuilder::new()
+            .max_capacity(16)
+            .build(&records)
+            .unwrap();
+        assert!(map.reserve(10).is_err());
+        map.reserve(9).unwrap();
+        assert_eq!(map.capacity(), 16);
+        assert!(map.reserve(usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_clone_filtered() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        for builder in [
+            HashMapBuilder::new(),
+            HashMapBuilder::new().align_keys(true),
+            HashMapBuilder::new().share_suffixes(true),
+            HashMapBuilder::new().sort_keys(true),
+        ] {
+            let map = builder.build(&records).unwrap();
+            let filtered = map.clone_filtered(|key, &val| key.starts_with(b"s") || val == 2);
+            assert_eq!(filtered.len(), 3);
+            for &(k, v) in &records {
+                let expected = (k.starts_with('s') || v == 2).then_some(&v);
+                assert_eq!(filtered.get(k), expected);
+            }
+            assert!(filtered.table.bytes.len() <= map.table.bytes.len());
+            assert_eq!(map.len(), 6);
+        }
+        assert!(HashMap::new(&records)
+            .unwrap()
+            .clone_filtered(|_, _| false)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_partition() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        for builder in [
+            HashMapBuilder::new(),
+            HashMapBuilder::new().align_keys(true),
+            HashMapBuilder::new().share_suffixes(true),
+            HashMapBuilder::new().sort_keys(true),
+        ] {
+            let map = builder.build(&records).unwrap();
+            let (left, right) = map.partition(|key, &val| key.starts_with(b"s") || val == 2);
+            assert_eq!(left.len(), 3);
+            assert_eq!(right.len(), 3);
+            for &(k, v) in &records {
+                let (yes, no) = if k.starts_with('s') || v == 2 {
+                    (&left, &right)
+                } else {
+                    (&right, &left)
+                };
+                assert_eq!(yes.get(k), Some(&v));
+                assert_eq!(no.get(k), None);
+            }
+        }
+
+        let (left, right) = HashMap::new(&records).unwrap().partition(|_, _| true);
+        assert_eq!(left.len(), 6);
+        assert!(right.is_empty());
+        assert_eq!(right.get(""), None);
+    }
+
+    #[test]
+    fn test_home_slot() {
+        // The whole 64-bit hash is used, so the slot does not depend on the width of usize.
+        assert_eq!(crate::home_slot(1 << 32, 3), 1);
+        assert_eq!(crate::home_slot((1 << 32) | 5, 8), 5);
+        assert_eq!(crate::capacity_for(0, Ratio::new(1, 1)).unwrap(), 1);
+        assert_eq!(crate::capacity_for(4, Ratio::new(4, 5)).unwrap(), 8);
+        assert!(crate::capacity_for(usize::MAX, Ratio::new(1, 1)).is_err());
+    }
+
+    #[test]
+    fn test_max_load() {
+        assert_eq!(Ratio::from_f64(0.8), Ratio::new(800_000, 1_000_000));
+        assert_eq!(Ratio::from_f64(f64::NAN).num, 0);
+        assert_eq!(Ratio::from_f64(-1.0).num, 0);
+        // 5 keys fit in 8 slots exactly at the load factor of 5/8.
+        let keys = ["icdm", "idce", "", "sigmod", "sigir"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        for (num, den, capacity) in [(5, 8, 16), (4, 5, 8), (1, 1, 8), (1, 2, 16)] {
+            let mut map = HashMapBuilder::new()
+                .max_load(num, den)
+                .build(&records)
+                .unwrap();
+            assert_eq!(map.capacity(), capacity);
+            map.insert("acl", 5).unwrap();
+            assert!(map.len() * den as usize <= map.capacity() * num as usize);
+        }
+        let map = HashMapBuilder::new()
+            .load_factor(0.8)
+            .build(&records)
+            .unwrap();
+        assert_eq!(map.capacity(), HashMap::new(&records).unwrap().capacity());
+        for (num, den) in [(0, 1), (1, 0), (3, 2)] {
+            assert!(HashMapBuilder::new()
+                .max_load(num, den)
+                .build(&records)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_to_vec() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let mut map = HashMap::new(&records).unwrap();
+        map.remove("sigir").unwrap();
+        let exported = map.to_vec();
+        assert_eq!(exported.len(), 5);
+        let other = HashMap::new(&exported).unwrap();
+        for &(k, v) in &records {
+            assert_eq!(other.get(k), map.get(k));
+            assert_eq!(other.get(k), (*k != "sigir").then_some(&v));
+        }
+        let exported: Vec<_> = exported
+            .iter()
+            .map(|(k, v)| (std::str::from_utf8(k).unwrap(), *v))
+            .collect();
+        assert_eq!(map.to_str_vec().unwrap(), exported);
+
+        let map = HashMap::new(&[(&b"\xff"[..], 0), (&b"icdm"[..], 1)]).unwrap();
+        assert_eq!(map.to_vec().len(), 2);
+        assert!(map.to_str_vec().is_err());
+    }
+
+    #[test]
+    fn test_slots() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let mut map = HashMap::new(&records).unwrap();
+        map.remove("sigir").unwrap();
+        let slots = map.slots();
+        assert_eq!(slots.len(), 5);
+        let mut entries: Vec<_> = slots.collect();
+        assert!(entries.windows(2).all(|w| w[0].0 < w[1].0));
+        let backward: Vec<_> = map.slots().rev().collect();
+        assert_eq!(backward.len(), 5);
+        assert!(backward.iter().rev().eq(entries.iter()));
+        for &(pos, key, val) in &entries {
+            assert!(pos < map.capacity());
+            assert_eq!(keys[*val].as_bytes(), key);
+            assert_eq!(map.table.get_pos(key), Some(pos));
+        }
+        entries.sort_by_key(|&(_, _, &v)| v);
+        let vals: Vec<_> = entries.iter().map(|&(_, _, &v)| v).collect();
+        assert_eq!(vals, vec![0, 1, 2, 3, 5]);
+
+        let mut slots = map.slots();
+        slots.next();
+        slots.next_back();
+        assert_eq!(slots.len(), 3);
+        assert_eq!(slots.count(), 3);
+    }
+
+    #[test]
+    fn test_handle() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let mut map = HashMapBuilder::new()
+            .load_factor(1.0)
+            .build(&records)
+            .unwrap();
+        let handles: Vec<_> = keys.iter().map(|k| map.get_handle(k).unwrap()).collect();
+        for (i, &handle) in handles.iter().enumerate() {
+            assert_eq!(map.get_by_handle(handle), Some(&i));
+            *map.get_mut_by_handle(handle).unwrap() += 10;
+        }
+        assert_eq!(map.get_handle("sigkdd"), None);
+
+        // Insertions without growth keep handles valid.
+        assert_eq!(map.capacity(), 8);
+        map.insert("sigkdd", 6).unwrap();
+        assert_eq!(map.capacity(), 8);
+        for (i, &handle) in handles.iter().enumerate() {
+            assert_eq!(map.get_by_handle(handle), Some(&(i + 10)));
+        }
+
+        // Removals, rebuilds, and clears invalidate handles.
+        map.remove("sigkdd").unwrap();
+        assert_eq!(map.get_by_handle(handles[0]), None);
+        assert_eq!(map.get_mut_by_handle(handles[0]), None);
+        let handle = map.get_handle("icdm").unwrap();
+        map.rehash(42);
+        assert_eq!(map.get_by_handle(handle), None);
+        let handle = map.get_handle("icdm").unwrap();
+        assert_eq!(map.get_by_handle(handle), Some(&10));
+        map.clear();
+        assert_eq!(map.get_by_handle(handle), None);
+    }
+
+    #[test]
+    fn test_build_with_buffers() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let builder = HashMapBuilder::new();
+        let mut buffers = BuildBuffers::new();
+        assert!(builder
+            .build_with_buffers(&records[..0], &mut buffers)
+            .is_err());
+        let map = builder.build_with_buffers(&records, &mut buffers).unwrap();
+        assert_eq!(buffers.capacity(), (0, 0));
+        let (nodes_ptr, bytes_ptr) = (map.table.nodes.as_ptr(), map.table.bytes.as_ptr());
+        buffers.recycle(map);
+        assert_eq!(buffers.capacity().0, 8);
+
+        // The next build of the same size allocates nothing.
+        let records: Vec<_> = keys.iter().rev().enumerate().map(|(i, k)| (k, i)).collect();
+        let map = builder.build_with_buffers(&records, &mut buffers).unwrap();
+        assert_eq!(map.table.nodes.as_ptr(), nodes_ptr);
+        assert_eq!(map.table.bytes.as_ptr(), bytes_ptr);
+        for (k, v) in &records {
+            assert_eq!(map.get(k), Some(v));
+        }
+        assert_eq!(map.len(), keys.len());
+        assert_eq!(map.get("sigkdd"), None);
+
+        // A failed build keeps the allocations.
+        buffers.recycle(map);
+        let dups = [("icdm", 0), ("icdm", 1)];
+        assert!(builder.build_with_buffers(&dups, &mut buffers).is_err());
+        assert_eq!(buffers.capacity().0, 8);
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, k)| (k, i as u32))
+            .collect();
+        let map = HashMap::new(&records).unwrap();
+        let fingerprint = map.fingerprint();
+
+        let shared = HashMapBuilder::new()
+            .seed(42)
+            .share_suffixes(true)
+            .build(&records)
+            .unwrap();
+        assert_eq!(shared.fingerprint(), fingerprint);
+
+        let mut inserted = HashMap::new(&records[..1]).unwrap();
+        for (k, v) in records[1..].iter().rev() {
+            inserted.insert(k, *v).unwrap();
+        }
+        inserted.insert("sigkdd", 6).unwrap();
+        assert_ne!(inserted.fingerprint(), fingerprint);
+        inserted.remove("sigkdd").unwrap();
+        assert_eq!(inserted.fingerprint(), fingerprint);
+
+        let deserialized = HashMap::<u32>::deserialize_from_slice(&map.serialize_to_vec()).unwrap();
+        assert_eq!(deserialized.fingerprint(), fingerprint);
+
+        // Moving a byte between a key and its value changes the digest.
+        let map = HashMap::new(&[("ab", vec![0u8])]).unwrap();
+        let other = HashMap::new(&[("a", vec![b'b', 0])]).unwrap();
+        assert_ne!(map.fingerprint(), other.fingerprint());
+    }
+
+    #[test]
+    fn test_canonical() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, k)| (k, i as u32))
+            .collect();
+        let reversed: Vec<_> = records.iter().rev().cloned().collect();
+        for builder in [
+            HashMapBuilder::new(),
+            HashMapBuilder::new().load_factor(1.0),
+            HashMapBuilder::new().share_suffixes(true),
+            HashMapBuilder::new().align_keys(true).sort_keys(true),
+        ] {
+            let builder = builder.canonical(true);
+            let map = builder.build(&records).unwrap();
+            let other = builder.build(&reversed).unwrap();
+            assert_eq!(map.serialize_to_vec(), other.serialize_to_vec());
+            for (k, v) in &records {
+                assert_eq!(map.get(k), Some(v));
+            }
+        }
+
+        let builder = HashMapBuilder::new().load_factor(1.0).canonical(true);
+        let map = builder.build(&records).unwrap();
+        let mut other = builder.build(&reversed[..3]).unwrap();
+        for (k, v) in &reversed[3..] {
+            other.insert(k, *v).unwrap();
+        }
+        other.insert("sigkdd", 6).unwrap();
+        other.remove("sigkdd").unwrap();
+        other.canonicalize();
+        assert_eq!(map.serialize_to_vec(), other.serialize_to_vec());
+    }
+
+    #[test]
+    fn test_optimize_for() {
+        let keys: Vec<_> = (0..1000).map(|i| i.to_string()).collect();
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        for builder in [
+            HashMapBuilder::new().load_factor(1.0),
+            HashMapBuilder::new().align_keys(true),
+            HashMapBuilder::new().share_suffixes(true),
+            HashMapBuilder::new().sort_keys(true),
+            HashMapBuilder::new().stash(8, 4),
+        ] {
+            let mut map = builder.build(&records).unwrap();
+            let capacity = map.capacity();
+            let log = ["999", "999", "500", "999", "500", "2000"];
+            map.optimize_for(log);
+            assert_eq!(map.capacity(), capacity);
+            assert_eq!(map.len(), keys.len());
+            for (k, v) in &records {
+                assert_eq!(map.get(k), Some(v));
+            }
+            // The most frequent key is placed first, at its home slot.
+            let pos = map.table.get_pos("999").unwrap();
+            let hash = map.table.hash(b"999");
+            assert_eq!(pos, crate::home_slot(hash, map.table.primary_len()));
+            if !map.table.share_suffixes && map.table.order.is_none() {
+                assert_eq!(map.table.nodes[pos].ptr, 0);
+                let pos = map.table.get_pos("500").unwrap();
+                assert_eq!(map.table.nodes[pos].ptr, map.table.key_align.max(3));
+            }
+        }
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_zeroize() {
+        #[cfg(not(feature = "safe"))]
+        fn allocation(bytes: &Vec<u8>) -> &[u8] {
+            // SAFETY: The spare capacity is initialized by zeroization.
+            unsafe { std::slice::from_raw_parts(bytes.as_ptr(), bytes.capacity()) }
+        }
+
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let mut map = HashMap::new(&records).unwrap();
+        map.remove("sigmod").unwrap();
+        assert!(!map.table.bytes.windows(6).any(|w| w == b"sigmod"));
+        assert_eq!(map.get("sigir"), Some(&4));
+
+        for i in 0..100 {
+            map.insert(format!("key{i}"), i).unwrap();
+        }
+        map.clear();
+        #[cfg(not(feature = "safe"))]
+        assert!(allocation(map.table.bytes.to_mut()).iter().all(|&b| b == 0));
+        map.insert("icdm", 0).unwrap();
+        assert_eq!(map.get("icdm"), Some(&0));
+    }
+
+    #[test]
+    fn test_constant_time() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        for builder in [
+            HashMapBuilder::new(),
+            HashMapBuilder::new().align_keys(true),
+        ] {
+            let mut map = builder.constant_time(true).build(&records).unwrap();
+            for &(k, v) in &records {
+                assert_eq!(map.get(k), Some(&v));
+            }
+            for k in ["icd", "icdmx", "sigmoe", "a"] {
+                assert_eq!(map.get(k), None);
+            }
+            for i in 0..20 {
+                map.insert(format!("key{i}"), i + 6).unwrap();
+            }
+            assert!(map.table.constant_time);
+            assert_eq!(map.get("key3"), Some(&9));
+
+            let other = HashMap::<usize>::deserialize_from_slice(&map.serialize_to_vec()).unwrap();
+            assert!(other.table.constant_time);
+            assert_eq!(other.get("sigir"), Some(&4));
+        }
+        assert!(!HashMap::new(&records).unwrap().table.constant_time);
+    }
+
+    #[cfg(feature = "fst")]
+    #[test]
+    fn test_fst_map() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, k)| (k, i as u32))
+            .collect();
+        for builder in [HashMapBuilder::new(), HashMapBuilder::new().sort_keys(true)] {
+            let map = builder.build(&records).unwrap();
+            let fst = map.to_fst_map().unwrap();
+            assert_eq!(fst.len(), keys.len());
+            for &(k, v) in &records {
+                assert_eq!(fst.get(k), Some(u64::from(v)));
+            }
+            let other = HashMapBuilder::new().build_from_fst_map(&fst).unwrap();
+            assert_eq!(other.len(), keys.len());
+            for &(k, v) in &records {
+                assert_eq!(other.get(k), Some(&u64::from(v)));
+            }
+        }
+        let empty = fst::Map::default();
+        assert!(HashMapBuilder::new().build_from_fst_map(&empty).is_err());
+        let long_key = "a".repeat(256);
+        let fst = fst::Map::from_iter([(long_key.as_str(), 0)]).unwrap();
+        let builder = HashMapBuilder::new().key_len::<u8>();
+        assert!(builder.build_from_fst_map(&fst).is_err());
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn test_build_from_arrow() {
+        use arrow_array::{Int64Array, LargeBinaryArray, StringArray, UInt16Array};
+
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let column = StringArray::from(keys.to_vec());
+        let values = UInt16Array::from_iter_values(0..keys.len() as u16);
+        let map = HashMapBuilder::new()
+            .key_len::<u8>()
+            .build_from_arrow(&column, &values)
+            .unwrap();
+        assert_eq!(map.len(), keys.len());
+        for (i, k) in keys.iter().enumerate() {
+            assert_eq!(map.get(k), Some(&(i as u16)));
+        }
+        assert_eq!(map.get("sigkdd"), None);
+
+        // Sliced columns refer to the middle of the buffers.
+        let column = LargeBinaryArray::from_iter_values(keys.iter().map(|k| k.as_bytes()));
+        let values = Int64Array::from_iter_values(0..keys.len() as i64);
+        let map = HashMapBuilder::new()
+            .build_from_arrow(&column.slice(2, 3), &values.slice(2, 3))
+            .unwrap();
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get(""), Some(&2));
+        assert_eq!(map.get("sigir"), Some(&4));
+        assert_eq!(map.get("idce"), None);
+
+        let builder = HashMapBuilder::new();
+        assert!(builder
+            .build_from_arrow(&column, &values.slice(0, 5))
+            .is_err());
+        let column = StringArray::from(vec![Some("icdm"), None]);
+        let values = Int64Array::from(vec![0, 1]);
+        assert!(builder.build_from_arrow(&column, &values).is_err());
+        let column = StringArray::from(vec!["icdm", "icdm"]);
+        assert!(builder.build_from_arrow(&column, &values).is_err());
+        assert!(builder
+            .build_from_arrow(&column.slice(0, 0), &values.slice(0, 0))
+            .is_err());
+    }
+
+    #[test]
+    fn test_drain_rev() {
+        let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2), ("sigir", 3)];
+        let map = HashMap::new(&records).unwrap();
+        let forward: Vec<_> = map.clone().drain().collect();
+        let mut map = map;
+        let mut drain = map.drain();
+        let last = drain.next_back().unwrap();
+        assert_eq!(drain.len(), 3);
+        let mut rest: Vec<_> = drain.collect();
+        rest.push(last);
+        assert_eq!(rest, forward);
+    }
+
+    #[test]
+    fn test_drain() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let mut map = HashMap::new(&records).unwrap();
+        let (capacity, bytes_capacity) = (map.capacity(), map.table.bytes.capacity());
+        let mut drain = map.drain();
+        assert_eq!(drain.len(), 6);
+        drain.next().unwrap();
+        assert_eq!(drain.len(), 5);
+        let mut drained: Vec<_> = drain.collect();
+        assert_eq!(drained.len(), 5);
+        drained.sort();
+        assert!(drained.windows(2).all(|w| w[0].0 < w[1].0));
+        for (k, v) in &drained {
+            assert_eq!(keys[*v].as_bytes(), k.as_slice());
+        }
+        assert!(map.is_empty());
+        assert_eq!(map.capacity(), capacity);
+        assert_eq!(map.table.bytes.capacity(), bytes_capacity);
+        for k in keys {
+            assert_eq!(map.get(k), None);
+        }
+
+        // Dropping a partially consumed iterator empties the map.
+        for &(k, v) in &records {
+            map.insert(k, v).unwrap();
+        }
+        map.drain().next();
+        assert!(map.is_empty());
+        assert_eq!(map.get("icdm"), None);
+
+        let mut map = HashMapBuilder::new()
+            .sort_keys(true)
+            .build(&records)
+            .unwrap();
+        assert_eq!(map.drain().count(), 6);
+        assert_eq!(map.select(0).unwrap(), None);
+    }
+
+    #[test]
+    fn test_map_keys() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let map = HashMapBuilder::new()
+            .sort_keys(true)
+            .seed(7)
+            .build(&records)
+            .unwrap();
+        let mapped = map.map_keys(|key| [b"conf/", key].concat()).unwrap();
+        assert_eq!(mapped.len(), keys.len());
+        assert_eq!(mapped.table.seed, 7);
+        for &(k, v) in &records {
+            assert_eq!(mapped.get(format!("conf/{k}")), Some(&v));
+            assert_eq!(mapped.get(k), None);
+        }
+        // The sorted layout is kept.
+        assert_eq!(mapped.rank("conf/icdm").unwrap(), 2);
+
+        let map = HashMap::new(&records).unwrap();
+        assert!(map
+            .map_keys(|key| key[..key.len().min(1)].to_vec())
+            .is_err());
+        let map = HashMapBuilder::new()
+            .key_len::<u8>()
+            .build(&records)
+            .unwrap();
+        assert!(map.map_keys(|key| key.repeat(64)).is_err());
+
+        let map = HashMapBuilder::new()
+            .key_len_limit(6)
+            .build(&records)
+            .unwrap();
+        let err = map.clone().map_keys(|key| key.repeat(3)).err().unwrap();
+        assert!(err.is::<crate::KeyTooLong>());
+        let mapped = map.map_keys(|key| key.to_ascii_uppercase()).unwrap();
+        assert_eq!(mapped.key_len_limit(), 6);
+        assert_eq!(mapped.try_get("SIGMOD").unwrap(), Some(&3));
+    }
+
+    #[test]
+    fn test_lookup_cache() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let mut map = HashMapBuilder::new()
+            .lookup_cache(2)
+            .build(&records[..3])
+            .unwrap();
+        for _ in 0..2 {
+            for (i, k) in keys.iter().enumerate() {
+                assert_eq!(map.get(k), (i < 3).then_some(&i));
+            }
+        }
+        // Inserting a cached miss, with or without growth, must be visible.
+        for &(k, v) in &records[3..] {
+            assert_eq!(map.get(k), None);
+            map.insert(k, v).unwrap();
+            assert_eq!(map.get(k), Some(&v));
+        }
+        assert_eq!(map.remove("idce").unwrap(), Some(1));
+        assert_eq!(map.get("idce"), None);
+        assert_eq!(map.get("acl"), Some(&5));
+
+        let mut other = map.clone_filtered(|k, _| k != b"acl");
+        assert!(other.table.lookup_cache.is_some());
+        assert_eq!(other.get("acl"), None);
+        other.set_lookup_cache(0);
+        assert!(other.table.lookup_cache.is_none());
+        assert_eq!(other.get("sigmod"), Some(&3));
+    }
+
+    #[test]
+    fn test_progress() {
+        use std::sync::{Arc, Mutex};
+
+        let interval = crate::progress::PROGRESS_INTERVAL;
+        let num_keys = 2 * interval + 10;
+        let keys: Vec<_> = (0..num_keys).map(|i| i.to_string()).collect();
+        let reports = Arc::new(Mutex::new(vec![]));
+        let sink = Arc::clone(&reports);
+        let builder = HashMapBuilder::new()
+            .progress(move |p| sink.lock().unwrap().push(p))
+            .key_len::<u8>()
+            .sort_keys(true);
+        let map = builder
+            .build_from_keys_and_values(&keys, (0..num_keys).collect())
+            .unwrap();
+        assert_eq!(map.len(), num_keys);
+
+        let processed: Vec<_> = reports
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|p| {
+                assert_eq!(p.total, num_keys);
+                (p.phase, p.processed)
+            })
+            .collect();
+        assert_eq!(
+            processed,
+            vec![
+                (BuildPhase::Insertion, interval),
+                (BuildPhase::Insertion, 2 * interval),
+                (BuildPhase::Insertion, num_keys),
+                (BuildPhase::Layout, 0),
+                (BuildPhase::Finished, num_keys),
+            ]
+        );
+
+        // Failed constructions never finish.
+        reports.lock().unwrap().clear();
+        assert!(builder.build(&[("icdm", 0), ("icdm", 1)]).is_err());
+        assert!(reports
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|p| p.phase == BuildPhase::Insertion));
+    }
+
+    #[test]
+    fn test_key_len_limit() {
+        use crate::KeyTooLong;
+
+        let records = [("icdm", 0), ("idce", 1), ("", 2), ("sigmod", 3)];
+        let builder = HashMapBuilder::new().key_len_limit(6);
+        let mut map = builder.build(&records).unwrap();
+        assert_eq!(map.key_len_limit(), 6);
+        assert_eq!(map.try_get("sigmod").unwrap(), Some(&3));
+        assert_eq!(map.try_get("sigir").unwrap(), None);
+        let err = map.try_get("sigmetrics").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<KeyTooLong>(),
+            Some(&KeyTooLong { len: 10, limit: 6 })
+        );
+        let err = map.insert("sigmetrics", 4).unwrap_err();
+        assert!(err.is::<KeyTooLong>());
+        assert_eq!(map.get("sigmetrics"), None);
+        assert_eq!(map.len(), 4);
+
+        // The limit survives rebuilding but not serialization.
+        for i in 0..100 {
+            map.insert(i.to_string(), i).unwrap();
+        }
+        assert_eq!(map.key_len_limit(), 6);
+        let other = map.clone_filtered(|_, &v| v < 50);
+        assert_eq!(other.key_len_limit(), 6);
+        let other = HashMap::<i32>::deserialize_from_slice(&map.serialize_to_vec()).unwrap();
+        assert_eq!(other.key_len_limit(), usize::MAX);
+
+        let long = [("icdm", 0), ("sigmetrics", 1)];
+        for result in [
+            builder.build(&long).map(|_| ()),
+            builder.build_dense(&long).map(|_| ()),
+            builder.build_from_iter(long).map(|_| ()),
+            builder
+                .build_from_keys_and_values(&["icdm", "sigmetrics"], vec![0, 1])
+                .map(|_| ()),
+        ] {
+            assert!(result.unwrap_err().is::<KeyTooLong>());
+        }
+
+        map.set_key_len_limit(2);
+        assert_eq!(map.get("icdm"), None);
+        assert_eq!(map.get("10"), Some(&10));
+        map.set_key_len_limit(usize::MAX);
+        assert_eq!(map.get("icdm"), Some(&0));
+    }
+
+    #[test]
+    fn test_stash() {
+        let records: Vec<_> = (0..100).map(|i| (i.to_string(), i)).collect();
+        let mut map = HashMapBuilder::new()
+            .load_factor(1.0)
+            .stash(8, 2)
+            .build(&records)
+            .unwrap();
+        let stash_len = map.capacity() - map.table.primary_len();
+        assert_eq!(stash_len, 8);
+        // With the full load factor, more than 8 keys collide beyond two slots.
+        assert_eq!(map.num_stashed(), 8);
+        assert!(map.table.probe_limit > 2);
+        for (k, v) in &records {
+            assert_eq!(map.get(k), Some(v));
+        }
+        assert_eq!(map.get("100"), None);
+
+        let stashed: Vec<_> = map
+            .slots()
+            .filter(|&(pos, _, _)| pos >= map.table.primary_len())
+            .map(|(_, k, &v)| (k.to_vec(), v))
+            .collect();
+        for (k, v) in &stashed {
+            assert_eq!(map.remove(k).unwrap(), Some(*v));
+            assert_eq!(map.get(k), None);
+        }
+        assert_eq!(map.num_stashed(), 0);
+        for (k, v) in &stashed {
+            assert_eq!(map.insert(k, *v).unwrap(), None);
+        }
+        for (k, v) in &records {
+            assert_eq!(map.get(k), Some(v));
+        }
+
+        let other = HashMap::<i32>::deserialize_from_slice(&map.serialize_to_vec()).unwrap();
+        assert_eq!(other.num_stashed(), map.num_stashed());
+        for (k, v) in &records {
+            assert_eq!(other.get(k), Some(v));
+        }
+
+        // The probe limit stays at the maximum while the stash has room.
+        let map = HashMapBuilder::new().stash(100, 1).build(&records).unwrap();
+        assert_eq!(map.table.probe_limit, 1);
+        assert!(map.num_stashed() > 0);
+        for (k, v) in &records {
+            assert_eq!(map.get(k), Some(v));
+        }
+        assert!(HashMapBuilder::new().stash(1, 0).build(&records).is_err());
+    }
+
+    #[test]
+    fn test_insert_max_capacity() {
+        let records = [("icdm", 0)];
+        let mut map = HashMapBuilder::new()
+            .max_capacity(4)
+            .build(&records)
+            .unwrap();
+        map.insert("idce", 1).unwrap();
+        map.insert("sigmod", 2).unwrap();
+        assert_eq!(map.capacity(), 4);
+        assert!(map.insert("sigir", 3).is_err());
+        assert_eq!(map.insert("icdm", 4).unwrap(), Some(0));
+        assert_eq!(map.len(), 3);
+
+        let mut map = HashMapBuilder::new()
+            .load_factor(1.0)
+            .build(&records)
+            .unwrap();
+        for i in 0..100 {
+            map.insert(i.to_string(), i).unwrap();
+            assert!(map.len() < map.capacity());
+        }
+
+        assert!(HashMapBuilder::new()
+            .max_capacity(1)
+            .build(&records)
+            .is_err());
+        assert!(HashMapBuilder::new()
+            .growth_factor(1.0)
+            .build(&records)
+            .is_err());
+        assert!(HashMapBuilder::new()
+            .growth_factor(f64::NAN)
+            .build(&records)
+            .is_err());
+        assert!(HashMapBuilder::new().growth(2, 2).build(&records).is_err());
+        assert!(HashMapBuilder::new().growth(3, 0).build(&records).is_err());
+
+        // The fractions are kept exactly, so they agree with the rounded floating-point values.
+        let builder = HashMapBuilder::new().growth(3, 2).max_tombstones(1, 4);
+        let map = builder.build(&records).unwrap();
+        let other = HashMapBuilder::new()
+            .growth_factor(1.5)
+            .tombstone_ratio(0.25)
+            .build(&records)
+            .unwrap();
+        assert_eq!(map.table.resize.growth_factor, Ratio::new(3, 2));
+        assert_eq!(map.table.resize.tombstone_ratio, Ratio::new(1, 4));
+        assert_eq!(map.serialize_to_vec(), other.serialize_to_vec());
+        assert!(HashMapBuilder::new()
+            .max_tombstones(5, 4)
+            .build(&records)
+            .is_err());
+    }
+
+    #[test]
+    fn test_insert_unsupported() {
+        let records = [("icdm", 0)];
+        let mut map = HashMapBuilder::new()
+            .sort_keys(true)
+            .build(&records)
+            .unwrap();
+        assert!(map.insert("idce", 1).is_err());
+        assert_eq!(map.insert("icdm", 1).unwrap(), Some(0));
+        let mut map = HashMapBuilder::new()
+            .key_len::<u8>()
+            .build(&records)
+            .unwrap();
+        assert!(map.insert("a".repeat(256), 1).is_err());
+    }
+
+    #[test]
+    fn test_new_from_iter() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records = keys.iter().enumerate().map(|(i, k)| (k.to_string(), i));
+        let map = HashMap::new_from_iter(records).unwrap();
+        assert_eq!(map.len(), 6);
+        for (i, k) in keys.iter().enumerate() {
+            assert_eq!(map.get(k), Some(&i));
+        }
+        assert_eq!(map.get("sigkdd"), None);
+
+        let map = HashMapBuilder::new()
+            .key_len::<u8>()
+            .sort_keys(true)
+            .build_from_iter(keys.iter().map(|k| (k, ())))
+            .unwrap();
+        assert_eq!(map.rank("icdm").unwrap(), 2);
+
+        assert!(HashMap::new_from_iter([("icdm", 0), ("icdm", 1)]).is_err());
+        assert!(HashMap::<u32>::new_from_iter(Vec::<(&str, u32)>::new()).is_err());
+    }
+
+    #[test]
+    fn test_grouped() {
+        let keys = ["icdm", "idce", "icdm", "", "sigmod", "", "icdm"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let map = HashMap::new_grouped(&records, |acc, v| *acc += v).unwrap();
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.get("icdm"), Some(&8));
+        assert_eq!(map.get("idce"), Some(&1));
+        assert_eq!(map.get(""), Some(&8));
+        assert_eq!(map.get("sigmod"), Some(&4));
+        assert_eq!(map.get("sigir"), None);
+
+        // Values are folded in the input order, and the capacity fits the distinct keys.
+        let records: Vec<_> = (0..1000)
+            .map(|i| (["a", "b"][i % 2], i.to_string()))
+            .collect();
+        let map = HashMapBuilder::new()
+            .sort_keys(true)
+            .build_grouped(&records, |acc, v| acc.push_str(v))
+            .unwrap();
+        assert_eq!(map.len(), 2);
+        assert_eq!(
+            map.capacity(),
+            HashMap::new(&[("a", 0), ("b", 1)]).unwrap().capacity()
+        );
+        assert!(map.get("a").unwrap().starts_with("024"));
+        assert!(map.get("b").unwrap().starts_with("135"));
+        assert_eq!(map.rank("b").unwrap(), 1);
+
+        let records = [("icdm", 0), ("sigmod", 1), ("icdm", 2)];
+        let builder = HashMapBuilder::new().key_len_limit(4);
+        assert!(builder.build_grouped(&records, |acc, v| *acc += v).is_err());
+    }
+
+    #[test]
+    fn test_new_with() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let map = HashMap::new_with(&keys, |key| Ok(key.to_vec())).unwrap();
+        for &k in &keys {
+            assert_eq!(map.get(k).unwrap(), k.as_bytes());
+        }
+
+        let mut calls = vec![];
+        let result = HashMap::new_with(&keys, |key| {
+            calls.push(key.to_vec());
+            if key == b"sigmod" {
+                Err(anyhow!("failed"))
+            } else {
+                Ok(0)
+            }
+        });
+        assert!(result.is_err());
+        assert_eq!(calls.len(), 4);
+    }
+
+    #[test]
+    fn test_from_keys_and_values() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let values: Vec<_> = (0..keys.len()).collect();
+        let map = HashMap::from_keys_and_values(&keys, values.clone()).unwrap();
+        for (k, v) in keys.iter().zip(values.iter()) {
+            assert_eq!(map.get(k), Some(v));
+        }
+        assert!(HashMap::from_keys_and_values(&keys, vec![0; 5]).is_err());
+        assert!(HashMap::from_keys_and_values(&keys, vec![0; 7]).is_err());
+    }
+
+    #[test]
+    fn test_serialize() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, k)| (k, k.repeat(i)))
+            .collect();
+        let map = HashMapBuilder::new()
+            .key_len::<u8>()
+            .seed(42)
+            .build(&records)
+            .unwrap();
+        let mut data = vec![];
+        map.serialize_into(&mut data).unwrap();
+        assert_eq!(data, map.serialize_to_vec());
+
+        let other = HashMap::<String, u8>::deserialize_from(data.as_slice()).unwrap();
+        assert_eq!(other.len(), map.len());
+        assert_eq!(other.capacity(), map.capacity());
+        for (k, v) in &records {
+            assert_eq!(other.get(k), Some(v));
+        }
+        assert_eq!(other.get("sigkdd"), None);
+        assert_eq!(
+            other.match_at("sigmodx", 0),
+            Some((0..6, &"sigmod".repeat(3)))
+        );
+
+        assert!(HashMap::<String, u8>::deserialize_from_slice(&data[..data.len() - 1]).is_err());
+        assert!(HashMap::<u32, u8>::deserialize_from_slice(&data).is_err());
+    }
+
+    #[test]
+    fn test_serialize_split() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, k)| (k, i as u32))
+            .collect();
+        let map = HashMapBuilder::new()
+            .key_len::<u8>()
+            .seed(42)
+            .build(&records)
+            .unwrap();
+        let mut other = map.clone();
+        for v in other.table.occupied_mut() {
+            v.val *= 10;
+        }
+        let mut data = vec![];
+        map.serialize_keys_into(&mut data).unwrap();
+        let (mut values, mut other_values) = (vec![], vec![]);
+        map.serialize_values_into(&mut values).unwrap();
+        other.serialize_values_into(&mut other_values).unwrap();
+
+        let map = HashMap::<u32, u8>::deserialize_from_split(&data, &values).unwrap();
+        let other = HashMap::<u32, u8>::deserialize_from_split(&data, &other_values).unwrap();
+        for (k, v) in &records {
+            assert_eq!(map.get(k), Some(v));
+            assert_eq!(other.get(k), Some(&(v * 10)));
+        }
+        let mut values_again = vec![];
+        map.serialize_values_into(&mut values_again).unwrap();
+        assert_eq!(values, values_again);
+
+        let mut rehashed = map.clone();
+        rehashed.rehash(7);
+        let mut rehashed_values = vec![];
+        rehashed
+            .serialize_values_into(&mut rehashed_values)
+            .unwrap();
+        assert!(HashMap::<u32, u8>::deserialize_from_split(&data, &rehashed_values).is_err());
+        let full = map.serialize_to_vec();
+        assert!(HashMap::<u32, u8>::deserialize_from_split(&data, &full).is_err());
+        assert!(
+            HashMap::<u32, u8>::deserialize_from_split(&data[..data.len() - 1], &values).is_err()
+        );
+    }
+
+    #[test]
+    fn test_deserialize_from_storage() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, k)| (k, i as u32))
+            .collect();
+        for builder in [
+            HashMapBuilder::new(),
+            HashMapBuilder::new().align_keys(true).seed(42),
+            HashMapBuilder::new().share_suffixes(true),
+            HashMapBuilder::new().sort_keys(true).fuzzy_index(true),
+        ] {
+            let data: Arc<[u8]> = builder.build(&records).unwrap().serialize_to_vec().into();
+            let map = HashMap::<u32>::deserialize_from_storage(Arc::clone(&data)).unwrap();
+            assert!(map.shares_keys());
+            let keys = map.table.bytes.as_ptr_range();
+            assert!(data.as_ptr_range().contains(&keys.start));
+            for (k, v) in &records {
+                assert_eq!(map.get(k), Some(v));
+            }
+            assert_eq!(map.get("sigkdd"), None);
+            assert_eq!(map.serialize_to_vec(), &data[..]);
+        }
+
+        let data = HashMap::new(&records).unwrap().serialize_to_vec();
+        let mut map = HashMap::<u32>::deserialize_from_storage(data.clone()).unwrap();
+        let other = map.clone();
+        map.insert("sigkdd", 6).unwrap();
+        assert!(!map.shares_keys());
+        assert!(other.shares_keys());
+        assert_eq!(map.get("sigkdd"), Some(&6));
+        assert_eq!(other.get("sigkdd"), None);
+        map.share_keys();
+        assert!(map.shares_keys());
+        assert_eq!(map.get("sigmod"), Some(&3));
+        map.clear();
+        assert!(!map.shares_keys());
+        assert_eq!(map.get("sigmod"), None);
+
+        assert!(HashMap::<u32>::deserialize_from_storage(data[..data.len() - 1].to_vec()).is_err());
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn test_serialize_buf() {
+        use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, k)| (k, i as u32))
+            .collect();
+        let map = HashMap::new(&records).unwrap();
+        let data = map.serialize_to_vec();
+        let mut buf = BytesMut::new();
+        buf.put_u8(7);
+        map.serialize_into_buf(&mut buf).unwrap();
+        assert_eq!(&buf[1..], data.as_slice());
+        map.serialize_into_buf(&mut buf).unwrap();
+
+        let mut buf = buf.freeze();
+        assert_eq!(buf.get_u8(), 7);
+        let ptr = buf.as_ptr();
+        for _ in 0..2 {
+            let other = HashMap::<u32>::deserialize_from_buf(&mut buf).unwrap();
+            // The key bytes are borrowed from the frame.
+            assert!(other.shares_keys());
+            let keys = other.table.bytes.as_ptr();
+            assert!(ptr <= keys && keys < ptr.wrapping_add(2 * data.len()));
+            for (k, v) in &records {
+                assert_eq!(other.get(k), Some(v));
+            }
+        }
+        assert!(buf.is_empty());
+        assert_eq!(ptr.wrapping_add(2 * data.len()), buf.as_ptr());
+
+        // Non-contiguous buffers are also supported.
+        let (head, tail) = data.split_at(data.len() / 2);
+        let mut chain = Bytes::copy_from_slice(head).chain(Bytes::copy_from_slice(tail));
+        let other = HashMap::<u32>::deserialize_from_buf(&mut chain).unwrap();
+        assert_eq!(other.get("sigmod"), Some(&3));
+
+        let mut truncated = &data[..data.len() - 1];
+        assert!(HashMap::<u32>::deserialize_from_buf(&mut truncated).is_err());
+        let mut small = [0; 16];
+        assert!(map.serialize_into_buf(&mut &mut small[..]).is_err());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_serialize_async() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, k)| (k, i as u32))
+            .collect();
+        let map = HashMap::new(&records).unwrap();
+        let data = map.serialize_to_vec();
+
+        // A small pipe makes the reads and writes interleave.
+        let (mut wtr, rdr) = tokio::io::duplex(16);
+        let (written, other) = tokio::join!(
+            async {
+                map.serialize_into_async(&mut wtr).await.unwrap();
+                drop(wtr);
+            },
+            HashMap::<u32>::deserialize_from_async(rdr)
+        );
+        let () = written;
+        let other = other.unwrap();
+        for (k, v) in &records {
+            assert_eq!(other.get(k), Some(v));
         }
+        let truncated = &data[..data.len() - 1];
+        assert!(HashMap::<u32>::deserialize_from_async(truncated)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_serialize_key_len_overflow() {
+        let long_key = "a".repeat(256);
+        let records = [(long_key.as_str(), 0u32)];
+        let data = HashMap::new(&records).unwrap().serialize_to_vec();
+        assert!(HashMap::<u32, u8>::deserialize_from_slice(&data).is_err());
+        assert!(HashMap::<u32, u16>::deserialize_from_slice(&data).is_ok());
     }
 
     #[test]
     #[should_panic]
     fn test_empty() {
-        let keys = vec!["icdm"];
+        let keys = ["icdm"];
         let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
         HashMap::new(&records[0..0]).unwrap();
     }
@@ -221,7 +5771,7 @@ mod tests {
     #[test]
     #[should_panic]
     fn test_duplicate() {
-        let keys = vec!["icdm", "icdm"];
+        let keys = ["icdm", "icdm"];
         let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
         HashMap::new(&records).unwrap();
     }
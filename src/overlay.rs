@@ -0,0 +1,194 @@
+//! Chains of hash maps answering queries from the first map containing the key.
+
+use crate::{HashMap, KeyLen};
+
+/// Chain of [`HashMap`]s in which a key is looked up in order,
+/// and the first map containing it answers.
+///
+/// It lets a small map override or extend large ones without merging and rebuilding them,
+/// such as a user dictionary over a system dictionary of a tokenizer.
+/// Each map keeps its own storage and options; the overlay only holds references to them.
+///
+/// # Examples
+///
+/// ```
+/// use simplearrayhash::{HashMap, Overlay};
+///
+/// let system = HashMap::new(&[("icdm", 0), ("idce", 1), ("sigmod", 2)]).unwrap();
+/// let user = HashMap::new(&[("idce", 10), ("sigir", 11)]).unwrap();
+/// let overlay = Overlay::new([&user, &system]);
+/// assert_eq!(overlay.get("idce"), Some(&10));
+/// assert_eq!(overlay.get("icdm"), Some(&0));
+/// assert_eq!(overlay.get("sigir"), Some(&11));
+/// assert_eq!(overlay.get("kdd"), None);
+/// ```
+#[derive(Clone)]
+pub struct Overlay<'a, V, L = usize>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    // Maps in the order of priority.
+    layers: Vec<&'a HashMap<V, L>>,
+}
+
+impl<'a, V, L> Overlay<'a, V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    /// Creates a new [`Overlay`] of `layers` in the order of priority,
+    /// that is, the first map overrides the others.
+    pub fn new<I>(layers: I) -> Self
+    where
+        I: IntoIterator<Item = &'a HashMap<V, L>>,
+    {
+        Self {
+            layers: layers.into_iter().collect(),
+        }
+    }
+
+    /// Adds `map` as the lowest-priority layer, which answers only keys missing in the others.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::{HashMap, Overlay};
+    ///
+    /// let user = HashMap::new(&[("idce", 10)]).unwrap();
+    /// let system = HashMap::new(&[("icdm", 0), ("idce", 1)]).unwrap();
+    /// let mut overlay = Overlay::new([&user]);
+    /// overlay.push(&system);
+    /// assert_eq!(overlay.get("idce"), Some(&10));
+    /// assert_eq!(overlay.get("icdm"), Some(&0));
+    /// ```
+    pub fn push(&mut self, map: &'a HashMap<V, L>) {
+        self.layers.push(map);
+    }
+
+    /// Returns true if any layer contains a value for the specified key.
+    #[inline(always)]
+    pub fn contains_key<K>(&self, key: K) -> bool
+    where
+        K: AsRef<[u8]>,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Returns a reference to the value corresponding to the key in the first layer containing it.
+    #[inline(always)]
+    pub fn get<K>(&self, key: K) -> Option<&'a V>
+    where
+        K: AsRef<[u8]>,
+    {
+        self.get_with_layer(key).map(|(_, val)| val)
+    }
+
+    /// Returns the index of the first layer containing the key and its value there,
+    /// such as to tell entries of a user dictionary from those of a system dictionary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::{HashMap, Overlay};
+    ///
+    /// let user = HashMap::new(&[("idce", 10)]).unwrap();
+    /// let system = HashMap::new(&[("icdm", 0), ("idce", 1)]).unwrap();
+    /// let overlay = Overlay::new([&user, &system]);
+    /// assert_eq!(overlay.get_with_layer("idce"), Some((0, &10)));
+    /// assert_eq!(overlay.get_with_layer("icdm"), Some((1, &0)));
+    /// ```
+    #[inline(always)]
+    pub fn get_with_layer<K>(&self, key: K) -> Option<(usize, &'a V)>
+    where
+        K: AsRef<[u8]>,
+    {
+        let key = key.as_ref();
+        self.layers
+            .iter()
+            .enumerate()
+            .find_map(|(i, map)| map.get(key).map(|val| (i, val)))
+    }
+
+    /// Iterates over the visible keys and values in arbitrary order,
+    /// skipping the keys shadowed by higher-priority layers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::{HashMap, Overlay};
+    ///
+    /// let user = HashMap::new(&[("idce", 10)]).unwrap();
+    /// let system = HashMap::new(&[("icdm", 0), ("idce", 1)]).unwrap();
+    /// let overlay = Overlay::new([&user, &system]);
+    /// let mut entries: Vec<_> = overlay.iter().collect();
+    /// entries.sort();
+    /// assert_eq!(entries, vec![(&b"icdm"[..], &0), (&b"idce"[..], &10)]);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = (&'a [u8], &'a V)> + '_ {
+        self.layers.iter().enumerate().flat_map(move |(i, map)| {
+            map.table
+                .iter()
+                .filter(move |(key, _)| !self.layers[..i].iter().any(|m| m.contains_key(key)))
+                .map(|(key, nd)| (key, &nd.val))
+        })
+    }
+
+    /// Returns the layers in the order of priority.
+    #[inline(always)]
+    pub fn layers(&self) -> &[&'a HashMap<V, L>] {
+        &self.layers
+    }
+
+    /// Returns the number of layers.
+    #[inline(always)]
+    pub fn num_layers(&self) -> usize {
+        self.layers.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::HashMapBuilder;
+
+    #[test]
+    fn test_get() {
+        let system = HashMap::new(&[("icdm", 0), ("idce", 1), ("sigmod", 2), ("", 3)]).unwrap();
+        let user = HashMapBuilder::new()
+            .seed(42)
+            .build(&[("idce", 10), ("sigir", 11)])
+            .unwrap();
+        let extra = HashMap::new(&[("sigir", 20), ("kdd", 21), ("", 22)]).unwrap();
+        let mut overlay = Overlay::new([&user, &system]);
+        overlay.push(&extra);
+        assert_eq!(overlay.num_layers(), 3);
+        assert_eq!(overlay.get_with_layer("idce"), Some((0, &10)));
+        assert_eq!(overlay.get_with_layer("sigir"), Some((0, &11)));
+        assert_eq!(overlay.get_with_layer("icdm"), Some((1, &0)));
+        assert_eq!(overlay.get_with_layer(""), Some((1, &3)));
+        assert_eq!(overlay.get_with_layer("kdd"), Some((2, &21)));
+        assert_eq!(overlay.get("acl"), None);
+        assert!(!overlay.contains_key("acl"));
+
+        let mut entries: Vec<_> = overlay.iter().map(|(k, &v)| (k.to_vec(), v)).collect();
+        entries.sort();
+        let expected: Vec<_> = [
+            ("", 3),
+            ("icdm", 0),
+            ("idce", 10),
+            ("kdd", 21),
+            ("sigir", 11),
+            ("sigmod", 2),
+        ]
+        .iter()
+        .map(|&(k, v)| (k.as_bytes().to_vec(), v))
+        .collect();
+        assert_eq!(entries, expected);
+
+        let empty = Overlay::<u32>::new([]);
+        assert_eq!(empty.get("icdm"), None);
+        assert_eq!(empty.iter().count(), 0);
+    }
+}
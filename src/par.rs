@@ -0,0 +1,116 @@
+//! Parallel construction with rayon.
+
+use rayon::prelude::*;
+
+use crate::{hash_key, Node, Ratio, Table};
+
+use anyhow::{anyhow, Result};
+
+/// Number of items bucketed into shards by a task.
+const CHUNK_LEN: usize = 1 << 16;
+
+impl<N> Table<N>
+where
+    N: Default + Clone + Node,
+{
+    /// Builds a table from the keys of `items` and the corresponding `extras`,
+    /// hashing the keys and detecting duplicate keys in parallel.
+    ///
+    /// Only the placement of the nodes runs serially,
+    /// and it does not compare keys since they are known to be distinct.
+    pub(crate) fn build_par<S, G, I, T, F>(
+        items: &[S],
+        key_of: G,
+        extras: I,
+        load_factor: Ratio,
+        seed: u64,
+        fill: F,
+    ) -> Result<Self>
+    where
+        S: Sync,
+        G: Fn(&S) -> &[u8] + Sync,
+        I: ExactSizeIterator<Item = T>,
+        F: FnMut(&mut N, T) -> Result<()>,
+    {
+        let hashes: Vec<_> = {
+            phase!("hashing", num_keys = items.len());
+            items
+                .par_iter()
+                .map(|item| hash_key(key_of(item), seed))
+                .collect()
+        };
+        {
+            phase!("validation", num_keys = items.len());
+            check_duplicates(items, &key_of, &hashes)?;
+        }
+        let num_bytes = items.par_iter().map(|item| key_of(item).len()).sum();
+        let records = items
+            .iter()
+            .zip(hashes)
+            .zip(extras)
+            .map(|((item, hash), x)| (key_of(item), hash, x));
+        Self::build_from_hashed(records, num_bytes, load_factor, seed, false, fill)
+    }
+}
+
+/// Checks that the keys of `items` are distinct.
+///
+/// The items are bucketed into shards by the upper bits of their hashes,
+/// so that equal keys fall into the same shard, and the shards are checked in parallel
+/// by sorting the items in each shard by their hashes and keys.
+fn check_duplicates<S, G>(items: &[S], key_of: &G, hashes: &[u64]) -> Result<()>
+where
+    S: Sync,
+    G: Fn(&S) -> &[u8] + Sync,
+{
+    let num_shards = (rayon::current_num_threads() * 4).next_power_of_two();
+    let bits = num_shards.trailing_zeros();
+    let shard_of = |hash: u64| hash.rotate_left(bits) as usize & (num_shards - 1);
+
+    let buckets: Vec<Vec<Vec<usize>>> = hashes
+        .par_chunks(CHUNK_LEN)
+        .enumerate()
+        .map(|(c, chunk)| {
+            let mut bucket = vec![vec![]; num_shards];
+            for (i, &hash) in chunk.iter().enumerate() {
+                bucket[shard_of(hash)].push(c * CHUNK_LEN + i);
+            }
+            bucket
+        })
+        .collect();
+
+    let has_duplicate = (0..num_shards).into_par_iter().any(|shard| {
+        let mut indices: Vec<_> = buckets
+            .iter()
+            .flat_map(|bucket| bucket[shard].iter().copied())
+            .collect();
+        indices.sort_unstable_by_key(|&i| (hashes[i], key_of(&items[i])));
+        indices
+            .windows(2)
+            .any(|w| hashes[w[0]] == hashes[w[1]] && key_of(&items[w[0]]) == key_of(&items[w[1]]))
+    });
+    if has_duplicate {
+        return Err(anyhow!("The input must not contain duplicated keys."));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_duplicates() {
+        let keys: Vec<_> = (0..200_000).map(|i| i.to_string()).collect();
+        fn key_of(k: &String) -> &[u8] {
+            k.as_bytes()
+        }
+        let hashes: Vec<_> = keys.iter().map(|k| hash_key(k.as_bytes(), 0)).collect();
+        assert!(check_duplicates(&keys, &key_of, &hashes).is_ok());
+
+        let mut keys = keys;
+        keys.push("123456".to_string());
+        let hashes: Vec<_> = keys.iter().map(|k| hash_key(k.as_bytes(), 0)).collect();
+        assert!(check_duplicates(&keys, &key_of, &hashes).is_err());
+    }
+}
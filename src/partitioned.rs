@@ -0,0 +1,403 @@
+//! Hash map partitioned by hash ranges into shards stored in separate files.
+
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::serial::{self, SectionKind, Sections, Serializable};
+use crate::{hash_key, HashMap, HashMapBuilder, KeyLen};
+
+use anyhow::{anyhow, Result};
+
+/// Name of the manifest file in the directory of a partitioned map.
+const MANIFEST_FILE: &str = "manifest.sah";
+
+/// Hash map partitioned into shards, each serialized into its own file
+/// and loaded independently on demand.
+///
+/// The keys are routed to the shards by contiguous ranges of their hash values,
+/// which [`hash`](crate::hash) computes with [`PartitionedHashMap::seed`],
+/// so a process can serve a subset of the shards of a dictionary larger than its memory budget,
+/// and other processes can route queries to it by the same hash values.
+/// The directory of a map holds a manifest and one file per non-empty shard,
+/// each in the format of [`HashMap::serialize_into`].
+///
+/// # Examples
+///
+/// ```
+/// use simplearrayhash::PartitionedHashMap;
+///
+/// let dir = std::env::temp_dir().join("simplearrayhash-partitioned-doc");
+/// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2), ("sigir", 3)];
+/// PartitionedHashMap::build(&dir, &records, 2).unwrap();
+///
+/// let map = PartitionedHashMap::<i32>::open(&dir).unwrap();
+/// assert_eq!(map.len(), 4);
+/// assert_eq!(map.num_loaded(), 0);
+/// assert_eq!(map.get("idce").unwrap(), Some(&1));
+/// assert_eq!(map.get("kdd").unwrap(), None);
+/// assert!(map.num_loaded() <= 2);
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+pub struct PartitionedHashMap<V, L = usize>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    dir: PathBuf,
+    seed: u64,
+    num_keys: Vec<usize>,
+    shards: Vec<OnceLock<HashMap<V, L>>>,
+}
+
+impl<V> PartitionedHashMap<V>
+where
+    V: Default + Clone + Serializable,
+{
+    /// Builds the shards of `records` with the default options into the directory `dir`,
+    /// creating it if missing, and opens the map without loading the shards.
+    ///
+    /// # Arguments
+    ///
+    /// - `dir`: Directory to store the manifest and shards.
+    /// - `records`: List of key-value pairs.
+    /// - `num_shards`: Number of shards.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `records` is empty,
+    ///  - `records` contains duplicate keys,
+    ///  - `num_shards` is zero, or
+    ///  - writing the files fails.
+    pub fn build<P, K>(dir: P, records: &[(K, V)], num_shards: usize) -> Result<Self>
+    where
+        P: AsRef<Path>,
+        K: AsRef<[u8]>,
+    {
+        Self::build_with(dir, records, num_shards, &HashMapBuilder::new())
+    }
+}
+
+impl<V, L> PartitionedHashMap<V, L>
+where
+    V: Default + Clone + Serializable,
+    L: KeyLen,
+{
+    /// Builds the shards of `records` with the options of `builder` in the same manner as
+    /// [`PartitionedHashMap::build`], routing the keys with the seed of `builder`.
+    ///
+    /// Each shard is built and written before the next one,
+    /// so at most one shard is held in memory besides `records`.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when [`PartitionedHashMap::build`] fails
+    /// or `builder` fails to build a shard.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::{HashMapBuilder, PartitionedHashMap};
+    ///
+    /// let dir = std::env::temp_dir().join("simplearrayhash-partitioned-build-with");
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let builder = HashMapBuilder::new().seed(42).key_len::<u8>();
+    /// let map = PartitionedHashMap::build_with(&dir, &records, 4, &builder).unwrap();
+    /// assert_eq!(map.seed(), 42);
+    /// let shard = map.shard_of("sigmod");
+    /// assert_eq!(map.load_shard(shard).unwrap().get("sigmod"), Some(&2));
+    /// # std::fs::remove_dir_all(&dir).unwrap();
+    /// ```
+    pub fn build_with<P, K>(
+        dir: P,
+        records: &[(K, V)],
+        num_shards: usize,
+        builder: &HashMapBuilder<L>,
+    ) -> Result<Self>
+    where
+        P: AsRef<Path>,
+        K: AsRef<[u8]>,
+    {
+        if records.is_empty() {
+            return Err(anyhow!("The input records must not be empty."));
+        }
+        if num_shards == 0 {
+            return Err(anyhow!("The number of shards must be positive."));
+        }
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let seed = builder.seed;
+        let mut partitions = vec![vec![]; num_shards];
+        for (i, (key, _)) in records.iter().enumerate() {
+            let hash = hash_key(key.as_ref(), seed);
+            partitions[shard_of(hash, num_shards)].push(i);
+        }
+        let mut num_keys = Vec::with_capacity(num_shards);
+        for (shard, indices) in partitions.iter().enumerate() {
+            num_keys.push(indices.len());
+            let path = shard_path(dir, shard);
+            if indices.is_empty() {
+                // A stale shard of a previous build must not be loaded.
+                if path.exists() {
+                    fs::remove_file(path)?;
+                }
+                continue;
+            }
+            let map = builder.build_from_iter(indices.iter().map(|&i| {
+                let (key, val) = &records[i];
+                (key, val.clone())
+            }))?;
+            map.serialize_into(BufWriter::new(File::create(path)?))?;
+        }
+        write_manifest(dir, seed, &num_keys)?;
+        Self::open(dir)
+    }
+
+    /// Opens the map in the directory `dir` written by [`PartitionedHashMap::build`],
+    /// reading only the manifest.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the manifest cannot be read or is broken.
+    pub fn open<P>(dir: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let dir = dir.as_ref().to_path_buf();
+        let (seed, num_keys) = read_manifest(&dir)?;
+        let shards = num_keys.iter().map(|_| OnceLock::new()).collect();
+        Ok(Self {
+            dir,
+            seed,
+            num_keys,
+            shards,
+        })
+    }
+
+    /// Returns a reference to the value corresponding to the key,
+    /// loading the shard of the key if it is not loaded yet.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the shard cannot be loaded.
+    pub fn get<K>(&self, key: K) -> Result<Option<&V>>
+    where
+        K: AsRef<[u8]>,
+    {
+        let key = key.as_ref();
+        let shard = self.shard_of(key);
+        if self.num_keys[shard] == 0 {
+            return Ok(None);
+        }
+        Ok(self.load_shard(shard)?.get(key))
+    }
+
+    /// Returns true if the map contains a value for the specified key,
+    /// loading the shard of the key if it is not loaded yet.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the shard cannot be loaded.
+    pub fn contains_key<K>(&self, key: K) -> Result<bool>
+    where
+        K: AsRef<[u8]>,
+    {
+        self.get(key).map(|val| val.is_some())
+    }
+
+    /// Returns the shard `shard`, loading it from its file if it is not loaded yet.
+    ///
+    /// Concurrent calls may load the same shard at once, in which case one of them is kept.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `shard` is out of range,
+    ///  - the shard is empty and thus has no file, or
+    ///  - reading the file fails or the data is broken.
+    pub fn load_shard(&self, shard: usize) -> Result<&HashMap<V, L>> {
+        let cell = self
+            .shards
+            .get(shard)
+            .ok_or_else(|| anyhow!("The shard {shard} is out of range."))?;
+        if let Some(map) = cell.get() {
+            return Ok(map);
+        }
+        if self.num_keys[shard] == 0 {
+            return Err(anyhow!("The shard {shard} is empty."));
+        }
+        let path = self.shard_path(shard);
+        let map = HashMap::deserialize_from(BufReader::new(File::open(&path)?))?;
+        if map.len() != self.num_keys[shard] {
+            return Err(anyhow!(
+                "The shard {} does not match the manifest.",
+                path.display()
+            ));
+        }
+        let _ = cell.set(map);
+        Ok(cell.get().unwrap())
+    }
+
+    /// Releases the shard `shard` from memory and returns it if it was loaded,
+    /// so that it is loaded again on the next access.
+    pub fn unload_shard(&mut self, shard: usize) -> Option<HashMap<V, L>> {
+        self.shards.get_mut(shard)?.take()
+    }
+
+    /// Returns true if the shard `shard` is loaded.
+    pub fn is_loaded(&self, shard: usize) -> bool {
+        self.shards
+            .get(shard)
+            .is_some_and(|cell| cell.get().is_some())
+    }
+
+    /// Returns the number of loaded shards.
+    pub fn num_loaded(&self) -> usize {
+        self.shards
+            .iter()
+            .filter(|cell| cell.get().is_some())
+            .count()
+    }
+}
+
+impl<V, L> PartitionedHashMap<V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    /// Returns the index of the shard to which `key` is routed.
+    #[inline(always)]
+    pub fn shard_of<K>(&self, key: K) -> usize
+    where
+        K: AsRef<[u8]>,
+    {
+        shard_of(hash_key(key.as_ref(), self.seed), self.shards.len())
+    }
+
+    /// Returns the path of the file of the shard `shard`,
+    /// which exists only if the shard is not empty.
+    pub fn shard_path(&self, shard: usize) -> PathBuf {
+        shard_path(&self.dir, shard)
+    }
+
+    /// Returns the seed of the hash function routing the keys and hashing them in each shard.
+    #[inline(always)]
+    pub const fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the number of shards.
+    #[inline(always)]
+    pub fn num_shards(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Returns the number of elements in the map, including those in unloaded shards.
+    pub fn len(&self) -> usize {
+        self.num_keys.iter().sum()
+    }
+
+    /// Returns true if the map contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Returns the shard of `hash` among `num_shards` shards covering equal ranges of hash values.
+///
+/// The upper bits select the shard, so that they are independent of
+/// the lower bits selecting the slots in the shard.
+#[inline(always)]
+const fn shard_of(hash: u64, num_shards: usize) -> usize {
+    ((hash as u128 * num_shards as u128) >> 64) as usize
+}
+
+fn shard_path(dir: &Path, shard: usize) -> PathBuf {
+    dir.join(format!("shard-{shard}.sah"))
+}
+
+fn write_manifest(dir: &Path, seed: u64, num_keys: &[usize]) -> Result<()> {
+    let mut body = vec![];
+    (num_keys.len() as u64).serialize_to_vec(&mut body);
+    seed.serialize_to_vec(&mut body);
+    for &n in num_keys {
+        (n as u64).serialize_to_vec(&mut body);
+    }
+    let wtr = BufWriter::new(File::create(dir.join(MANIFEST_FILE))?);
+    serial::write_sections(wtr, &[(SectionKind::Partitions as u32, &body)])
+}
+
+fn read_manifest(dir: &Path) -> Result<(u64, Vec<usize>)> {
+    let data = fs::read(dir.join(MANIFEST_FILE))?;
+    let sections = Sections::parse(&data)?;
+    let mut body = sections.require(SectionKind::Partitions)?;
+    let num_shards = serial::read_u64(&mut body)?;
+    let seed = serial::read_u64(&mut body)?;
+    if num_shards == 0 || body.len() as u64 != num_shards.saturating_mul(8) {
+        return Err(anyhow!("The manifest is broken."));
+    }
+    let num_keys = (0..num_shards)
+        .map(|_| serial::to_usize(serial::read_u64(&mut body)?))
+        .collect::<Result<_>>()?;
+    Ok((seed, num_keys))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partitioned() {
+        let dir = std::env::temp_dir().join(format!(
+            "simplearrayhash-partitioned-{}",
+            std::process::id()
+        ));
+        let keys: Vec<_> = (0..1000).map(|i| i.to_string()).collect();
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let builder = HashMapBuilder::new().seed(7);
+        let map = PartitionedHashMap::build_with(&dir, &records, 8, &builder).unwrap();
+        assert_eq!(map.num_shards(), 8);
+        assert_eq!(map.len(), 1000);
+        assert_eq!(map.num_loaded(), 0);
+
+        let mut map = PartitionedHashMap::<usize>::open(&dir).unwrap();
+        let shard = map.shard_of("42");
+        assert_eq!(map.get("42").unwrap(), Some(&42));
+        assert_eq!(map.num_loaded(), 1);
+        assert!(map.is_loaded(shard));
+        for &(k, v) in &records {
+            assert_eq!(map.get(k).unwrap(), Some(&v));
+            let hash = crate::hash(k, map.seed());
+            let shard = map.load_shard(map.shard_of(k)).unwrap();
+            assert_eq!(shard.hash(k), hash);
+        }
+        assert!(!map.contains_key("sigkdd").unwrap());
+        assert_eq!(map.num_loaded(), 8);
+        assert!(map.unload_shard(shard).is_some());
+        assert!(!map.is_loaded(shard));
+        assert!(map.load_shard(8).is_err());
+
+        // Shards cover equal ranges of hash values.
+        assert_eq!(shard_of(0, 8), 0);
+        assert_eq!(shard_of(u64::MAX, 8), 7);
+        assert_eq!(shard_of(1 << 61, 8), 1);
+
+        // Rebuilding with more shards than keys leaves empty shards without files.
+        let map = PartitionedHashMap::build(&dir, &records[..2], 16).unwrap();
+        assert_eq!(map.len(), 2);
+        let empty = (0..16).find(|&i| map.num_keys[i] == 0).unwrap();
+        assert!(!map.shard_path(empty).exists());
+        assert!(map.load_shard(empty).is_err());
+        assert_eq!(map.get("0").unwrap(), Some(&0));
+        assert_eq!(map.get("999").unwrap(), None);
+
+        fs::remove_file(dir.join(MANIFEST_FILE)).unwrap();
+        assert!(PartitionedHashMap::<usize>::open(&dir).is_err());
+        assert!(PartitionedHashMap::build(&dir, &records, 0).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,428 @@
+//! Persistent hash map implementation sharing unchanged storage between versions.
+
+use std::sync::Arc;
+
+use crate::{capacity_for, hash_key, home_slot, next_slot, Counted, HashMap, KeyLen, Ratio};
+
+use anyhow::{anyhow, Result};
+
+/// Number of slots in each chunk.
+const SLOT_CHUNK_LEN: usize = 256;
+/// Number of key bytes in each chunk, unless a longer key occupies a chunk alone.
+const BYTE_CHUNK_LEN: usize = 4096;
+
+/// Chunk of slots, in which empty slots are `None`.
+type SlotChunk<V, L> = Arc<Vec<Option<PersistentSlot<V, L>>>>;
+
+#[derive(Clone)]
+struct PersistentSlot<V, L> {
+    // Index of the chunk of key bytes and the offset of the key in it.
+    chunk: usize,
+    ptr: usize,
+    len: L,
+    val: V,
+}
+
+/// Persistent hash map implementation in which updates return new versions
+/// sharing unchanged storage with the original.
+///
+/// The slots and key bytes are split into fixed-size chunks behind [`Arc`]s,
+/// and [`PersistentHashMap::with`] copies only the chunks it modifies,
+/// so a snapshot such as a configuration can be updated cheaply
+/// while readers holding the old version keep seeing it unchanged.
+/// Cloning a map only clones the lists of chunks.
+///
+/// It is created by [`PersistentHashMap::new`] or [`PersistentHashMap::from_map`].
+///
+/// # Examples
+///
+/// ```
+/// use simplearrayhash::PersistentHashMap;
+///
+/// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+/// let old = PersistentHashMap::new(&records).unwrap();
+/// let new = old.with("idce", 3).unwrap().with("sigir", 4).unwrap();
+/// assert_eq!(old.get("idce"), Some(&1));
+/// assert_eq!(old.get("sigir"), None);
+/// assert_eq!(new.get("idce"), Some(&3));
+/// assert_eq!(new.get("sigir"), Some(&4));
+/// ```
+#[derive(Clone)]
+pub struct PersistentHashMap<V, L = usize>
+where
+    L: KeyLen,
+{
+    slots: Vec<SlotChunk<V, L>>,
+    bytes: Vec<Arc<Vec<u8>>>,
+    capacity: usize,
+    num_keys: usize,
+    seed: u64,
+    load_factor: Ratio,
+}
+
+impl<V> PersistentHashMap<V>
+where
+    V: Default + Clone,
+{
+    /// Creates a new [`PersistentHashMap`] from input records.
+    ///
+    /// # Arguments
+    ///
+    /// - `records`: List of key-value pairs.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `records` is empty, or
+    ///  - `records` contains duplicate keys.
+    pub fn new<K>(records: &[(K, V)]) -> Result<Self>
+    where
+        K: AsRef<[u8]>,
+    {
+        Self::from_map(&HashMap::new(records)?)
+    }
+}
+
+impl<V, L> PersistentHashMap<V, L>
+where
+    V: Clone,
+    L: KeyLen,
+{
+    /// Creates a new [`PersistentHashMap`] with the keys and values of `map`,
+    /// keeping its seed and load factor.
+    ///
+    /// # Errors
+    ///
+    /// It never fails for a map built with a valid load factor.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::{HashMapBuilder, PersistentHashMap};
+    ///
+    /// let map = HashMapBuilder::new()
+    ///     .key_len::<u8>()
+    ///     .seed(42)
+    ///     .build(&[("icdm", 0), ("idce", 1)])
+    ///     .unwrap();
+    /// let map = PersistentHashMap::from_map(&map).unwrap();
+    /// assert_eq!(map.get("icdm"), Some(&0));
+    /// ```
+    pub fn from_map(map: &HashMap<V, L>) -> Result<Self>
+    where
+        V: Default,
+    {
+        let table = &map.table;
+        let load_factor = table.resize.load_factor;
+        let capacity = capacity_for(table.num_keys(), load_factor)?;
+        let entries = table.iter().map(|(key, node)| (key, &node.val));
+        Self::build(entries, capacity, table.num_keys(), table.seed, load_factor)
+    }
+
+    /// Builds a map of `capacity` slots from `num_keys` distinct keys and their values,
+    /// whose lengths are in the range of `L`.
+    fn build<'a, I>(
+        entries: I,
+        capacity: usize,
+        num_keys: usize,
+        seed: u64,
+        load_factor: Ratio,
+    ) -> Result<Self>
+    where
+        I: Iterator<Item = (&'a [u8], &'a V)>,
+        V: 'a,
+    {
+        let mut slots = vec![None; capacity];
+        let mut bytes = vec![];
+        for (key, val) in entries {
+            // The keys come from a map with the same length type.
+            let len = L::from_usize(key.len()).unwrap();
+            let (chunk, ptr) = push_key(&mut bytes, key);
+            let mut pos = home_slot(hash_key(key, seed), capacity);
+            while slots[pos].is_some() {
+                pos = next_slot(pos, capacity);
+            }
+            slots[pos] = Some(PersistentSlot {
+                chunk,
+                ptr,
+                len,
+                val: val.clone(),
+            });
+        }
+        Ok(Self {
+            slots: slots
+                .chunks(SLOT_CHUNK_LEN)
+                .map(|chunk| Arc::new(chunk.to_vec()))
+                .collect(),
+            bytes,
+            capacity,
+            num_keys,
+            seed,
+            load_factor,
+        })
+    }
+
+    /// Returns a new version of the map in which `key` is associated with `val`,
+    /// leaving `self` unchanged.
+    ///
+    /// The new version shares all the chunks of `self` but the modified ones,
+    /// which are the chunk of the slot and, for a new key, the last chunk of key bytes.
+    /// When a new key would exceed the load factor, the new version is rebuilt with
+    /// a larger capacity and shares nothing.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the length of `key` is out of the range of `L`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::PersistentHashMap;
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let old = PersistentHashMap::new(&records).unwrap();
+    /// let new = old.with("sigir", 3).unwrap();
+    /// assert_eq!(old.len(), 3);
+    /// assert_eq!(new.len(), 4);
+    /// ```
+    pub fn with<K>(&self, key: K, val: V) -> Result<Self>
+    where
+        K: AsRef<[u8]>,
+    {
+        let key = key.as_ref();
+        let mut map = self.clone();
+        match map.find(key) {
+            Ok(pos) => map.slot_mut(pos).as_mut().unwrap().val = val,
+            Err(_) => {
+                let len = L::from_usize(key.len()).ok_or_else(|| {
+                    anyhow!("The key length must not exceed the range of the length type.")
+                })?;
+                let num_keys = map.num_keys + 1;
+                if num_keys >= map.capacity || map.load_factor.is_exceeded(num_keys, map.capacity) {
+                    map = map.grown(num_keys)?;
+                }
+                // The key is absent, so the vacant slot ending its probe sequence is free.
+                let pos = map.find(key).unwrap_err();
+                let (chunk, ptr) = push_key(&mut map.bytes, key);
+                *map.slot_mut(pos) = Some(PersistentSlot {
+                    chunk,
+                    ptr,
+                    len,
+                    val,
+                });
+                map.num_keys = num_keys;
+            }
+        }
+        Ok(map)
+    }
+
+    /// Returns a copy of the map rebuilt with enough slots for `num_keys` keys.
+    fn grown(&self, num_keys: usize) -> Result<Self> {
+        let capacity = capacity_for(num_keys, self.load_factor)?;
+        Self::build(
+            self.iter(),
+            capacity,
+            self.num_keys,
+            self.seed,
+            self.load_factor,
+        )
+    }
+
+    /// Returns true if the map contains a value for the specified key.
+    #[inline(always)]
+    pub fn contains_key<K>(&self, key: K) -> bool
+    where
+        K: AsRef<[u8]>,
+    {
+        self.find(key.as_ref()).is_ok()
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    #[inline(always)]
+    pub fn get<K>(&self, key: K) -> Option<&V>
+    where
+        K: AsRef<[u8]>,
+    {
+        let pos = self.find(key.as_ref()).ok()?;
+        self.slot(pos).as_ref().map(|slot| &slot.val)
+    }
+
+    /// Iterates over the keys and values in arbitrary order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&[u8], &V)> + ExactSizeIterator {
+        let slots = self
+            .slots
+            .iter()
+            .flat_map(|chunk| chunk.iter().flatten())
+            .map(|slot| (self.key_of(slot), &slot.val));
+        Counted::new(slots, self.num_keys)
+    }
+
+    /// Returns the number of chunks of slots and key bytes shared with `other`,
+    /// which is another version of the map.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::PersistentHashMap;
+    ///
+    /// let records: Vec<_> = (0..1000).map(|i| (i.to_string(), i)).collect();
+    /// let old = PersistentHashMap::new(&records).unwrap();
+    /// let new = old.with("42", 0).unwrap();
+    /// assert_eq!(old.num_shared_chunks(&new), old.num_chunks() - 1);
+    /// ```
+    pub fn num_shared_chunks(&self, other: &Self) -> usize {
+        let slots = self.slots.iter().zip(&other.slots);
+        let bytes = self.bytes.iter().zip(&other.bytes);
+        slots.filter(|(a, b)| Arc::ptr_eq(a, b)).count()
+            + bytes.filter(|(a, b)| Arc::ptr_eq(a, b)).count()
+    }
+
+    /// Returns the number of chunks of slots and key bytes.
+    pub fn num_chunks(&self) -> usize {
+        self.slots.len() + self.bytes.len()
+    }
+
+    /// Returns the number of elements in the map.
+    #[inline(always)]
+    pub const fn len(&self) -> usize {
+        self.num_keys
+    }
+
+    /// Returns true if the map contains no elements.
+    #[inline(always)]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of slots allocated in the map.
+    #[inline(always)]
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the slot position of `key`, or the vacant slot ending its probe sequence
+    /// as an error if it is not stored.
+    #[inline(always)]
+    fn find(&self, key: &[u8]) -> std::result::Result<usize, usize> {
+        let mut pos = home_slot(hash_key(key, self.seed), self.capacity);
+        while let Some(slot) = self.slot(pos) {
+            if self.key_of(slot) == key {
+                return Ok(pos);
+            }
+            pos = next_slot(pos, self.capacity);
+        }
+        Err(pos)
+    }
+
+    #[inline(always)]
+    fn slot(&self, pos: usize) -> &Option<PersistentSlot<V, L>> {
+        &self.slots[pos / SLOT_CHUNK_LEN][pos % SLOT_CHUNK_LEN]
+    }
+
+    /// Returns the `pos`-th slot, copying its chunk if it is shared.
+    fn slot_mut(&mut self, pos: usize) -> &mut Option<PersistentSlot<V, L>> {
+        &mut Arc::make_mut(&mut self.slots[pos / SLOT_CHUNK_LEN])[pos % SLOT_CHUNK_LEN]
+    }
+
+    #[inline(always)]
+    fn key_of(&self, slot: &PersistentSlot<V, L>) -> &[u8] {
+        &self.bytes[slot.chunk][slot.ptr..slot.ptr + slot.len.to_usize()]
+    }
+}
+
+/// Appends `key` to the chunks of key bytes, returning the index of its chunk and its offset.
+///
+/// The last chunk is copied if it is shared, and a new chunk is started
+/// if the key does not fit in the last one.
+fn push_key(bytes: &mut Vec<Arc<Vec<u8>>>, key: &[u8]) -> (usize, usize) {
+    let fits = bytes
+        .last()
+        .is_some_and(|chunk| chunk.len() + key.len() <= BYTE_CHUNK_LEN);
+    if !fits {
+        bytes.push(Arc::new(Vec::with_capacity(BYTE_CHUNK_LEN.max(key.len()))));
+    }
+    let chunk = bytes.len() - 1;
+    let last = Arc::make_mut(&mut bytes[chunk]);
+    let ptr = last.len();
+    last.extend_from_slice(key);
+    (chunk, ptr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::HashMapBuilder;
+
+    #[test]
+    fn test_with() {
+        let records: Vec<_> = (0..1000).map(|i| (i.to_string(), i)).collect();
+        let base = PersistentHashMap::new(&records).unwrap();
+        let mut map = base.clone();
+        let mut versions = vec![];
+        for i in 500..3000 {
+            map = map.with(i.to_string(), i * 2).unwrap();
+            versions.push(map.clone());
+        }
+        assert_eq!(map.len(), 3000);
+        for i in 0..3000 {
+            let expected = if i < 500 { i } else { i * 2 };
+            assert_eq!(map.get(i.to_string()), Some(&expected));
+        }
+        for (i, version) in (500..3000).zip(&versions) {
+            assert_eq!(version.len(), 1000.max(i + 1));
+            assert_eq!(version.get(i.to_string()), Some(&(i * 2)));
+            assert_eq!(
+                version.get((i + 1).to_string()).copied(),
+                (i < 999).then_some(i + 1)
+            );
+        }
+        assert_eq!(base.len(), 1000);
+        for (k, v) in &records {
+            assert_eq!(base.get(k), Some(v));
+        }
+        assert!(!base.contains_key("1000"));
+
+        let mut entries: Vec<_> = map.iter().map(|(k, &v)| (k.to_vec(), v)).collect();
+        entries.sort();
+        let mut expected: Vec<_> = (0..3000)
+            .map(|i| (i.to_string().into_bytes(), if i < 500 { i } else { i * 2 }))
+            .collect();
+        expected.sort();
+        assert_eq!(entries, expected);
+    }
+
+    #[test]
+    fn test_sharing() {
+        let records: Vec<_> = (0..1000).map(|i| (i.to_string(), i)).collect();
+        let old = PersistentHashMap::new(&records).unwrap();
+        let updated = old.with("42", 0).unwrap();
+        assert_eq!(old.num_shared_chunks(&updated), old.num_chunks() - 1);
+        let inserted = old.with("1000", 0).unwrap();
+        assert_eq!(old.num_shared_chunks(&inserted), old.num_chunks() - 2);
+
+        let mut grown = old.clone();
+        let mut i = 1000;
+        while grown.capacity() == old.capacity() {
+            grown = grown.with(i.to_string(), i).unwrap();
+            i += 1;
+        }
+        assert_eq!(old.num_shared_chunks(&grown), 0);
+    }
+
+    #[test]
+    fn test_from_map() {
+        let map = HashMapBuilder::new()
+            .key_len::<u8>()
+            .seed(7)
+            .build(&[("icdm", 0), ("idce", 1)])
+            .unwrap();
+        let map = PersistentHashMap::from_map(&map).unwrap();
+        assert_eq!(map.get("idce"), Some(&1));
+        assert!(map.with("a".repeat(256), 2).is_err());
+        let map = map.with("a".repeat(255), 2).unwrap();
+        assert_eq!(map.get("a".repeat(255)), Some(&2));
+        assert!(PersistentHashMap::<u32>::new(&[("icdm", 0), ("icdm", 1)]).is_err());
+    }
+}
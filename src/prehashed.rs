@@ -0,0 +1,300 @@
+//! Keys hashed at compile time.
+//!
+//! A [`PrehashedKey`] holds the bytes of a key together with its hash value for the
+//! default seed, computed by a `const` port of the hash function used by the maps.
+//! The [`key!`](crate::key!) macro builds one from a literal in a constant context,
+//! so lookups of fixed keys skip hashing at run time.
+
+use crate::DEFAULT_SEED;
+
+/// Key whose hash value for the default seed is computed in advance,
+/// typically at compile time by the [`key!`](crate::key!) macro.
+///
+/// Maps built with another seed (see [`HashMapBuilder::seed`](crate::HashMapBuilder::seed))
+/// still accept it, hashing the bytes at run time.
+///
+/// # Examples
+///
+/// ```
+/// use simplearrayhash::{HashMap, PrehashedKey};
+///
+/// const IDCE: PrehashedKey = PrehashedKey::new("idce");
+///
+/// let map = HashMap::new(&[("icdm", 0), ("idce", 1)]).unwrap();
+/// assert_eq!(map.get_prehashed(&IDCE), Some(&1));
+/// assert_eq!(IDCE.hash(), map.hash("idce"));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PrehashedKey {
+    bytes: &'static [u8],
+    hash: u64,
+}
+
+impl PrehashedKey {
+    /// Creates a key from a string, hashing it with the default seed.
+    pub const fn new(key: &'static str) -> Self {
+        Self::from_bytes(key.as_bytes())
+    }
+
+    /// Creates a key from bytes, hashing them with the default seed.
+    pub const fn from_bytes(key: &'static [u8]) -> Self {
+        Self {
+            bytes: key,
+            hash: city_hash64_with_seed(key, DEFAULT_SEED),
+        }
+    }
+
+    /// Returns the bytes of the key.
+    #[inline(always)]
+    pub const fn as_bytes(&self) -> &'static [u8] {
+        self.bytes
+    }
+
+    /// Returns the hash value of the key for the default seed,
+    /// which equals [`hash`](crate::hash())`(key, 0)`.
+    #[inline(always)]
+    pub const fn hash(&self) -> u64 {
+        self.hash
+    }
+}
+
+impl AsRef<[u8]> for PrehashedKey {
+    #[inline(always)]
+    fn as_ref(&self) -> &[u8] {
+        self.bytes
+    }
+}
+
+/// Creates a [`PrehashedKey`] from a string literal in a constant context,
+/// so that its hash value is computed at compile time.
+///
+/// # Examples
+///
+/// ```
+/// use simplearrayhash::{key, HashMap};
+///
+/// let map = HashMap::new(&[("content-type", 0), ("content-length", 1)]).unwrap();
+/// assert_eq!(map.get_prehashed(&key!("content-type")), Some(&0));
+/// assert_eq!(map.get_prehashed(&key!("accept")), None);
+/// ```
+#[macro_export]
+macro_rules! key {
+    ($key:expr) => {{
+        const KEY: $crate::PrehashedKey = $crate::PrehashedKey::new($key);
+        KEY
+    }};
+}
+
+const K0: u64 = 0xc3a5c85c97cb3127;
+const K1: u64 = 0xb492b66fbe98f273;
+const K2: u64 = 0x9ae16a3b2f90404f;
+const K3: u64 = 0xc949d7c7509e6557;
+
+/// Computes `CityHash64WithSeed` of `s` in the same manner as
+/// `fasthash::city::hash64_with_seed`, which is not available in constant contexts.
+pub(crate) const fn city_hash64_with_seed(s: &[u8], seed: u64) -> u64 {
+    hash_len16(city_hash64(s).wrapping_sub(K2), seed)
+}
+
+const fn city_hash64(s: &[u8]) -> u64 {
+    let len = s.len();
+    if len <= 16 {
+        return hash_len0to16(s);
+    }
+    if len <= 32 {
+        return hash_len17to32(s);
+    }
+    if len <= 64 {
+        return hash_len33to64(s);
+    }
+
+    // For keys over 64 bytes, the end is hashed first,
+    // and then 64-byte chunks are hashed from the beginning.
+    let mut x = fetch64(s, len - 40);
+    let mut y = fetch64(s, len - 16).wrapping_add(fetch64(s, len - 56));
+    let mut z = hash_len16(
+        fetch64(s, len - 48).wrapping_add(len as u64),
+        fetch64(s, len - 24),
+    );
+    let mut v = weak_hash_len32_with_seeds(s, len - 64, len as u64, z);
+    let mut w = weak_hash_len32_with_seeds(s, len - 32, y.wrapping_add(K1), x);
+    x = x.wrapping_mul(K1).wrapping_add(fetch64(s, 0));
+
+    let end = (len - 1) & !63;
+    let mut p = 0;
+    while p < end {
+        x = x
+            .wrapping_add(y)
+            .wrapping_add(v.0)
+            .wrapping_add(fetch64(s, p + 8))
+            .rotate_right(37)
+            .wrapping_mul(K1);
+        y = y
+            .wrapping_add(v.1)
+            .wrapping_add(fetch64(s, p + 48))
+            .rotate_right(42)
+            .wrapping_mul(K1);
+        x ^= w.1;
+        y = y.wrapping_add(v.0).wrapping_add(fetch64(s, p + 40));
+        z = z.wrapping_add(w.0).rotate_right(33).wrapping_mul(K1);
+        v = weak_hash_len32_with_seeds(s, p, v.1.wrapping_mul(K1), x.wrapping_add(w.0));
+        w = weak_hash_len32_with_seeds(
+            s,
+            p + 32,
+            z.wrapping_add(w.1),
+            y.wrapping_add(fetch64(s, p + 16)),
+        );
+        let t = z;
+        z = x;
+        x = t;
+        p += 64;
+    }
+    hash_len16(
+        hash_len16(v.0, w.0)
+            .wrapping_add(shift_mix(y).wrapping_mul(K1))
+            .wrapping_add(z),
+        hash_len16(v.1, w.1).wrapping_add(x),
+    )
+}
+
+const fn hash_len0to16(s: &[u8]) -> u64 {
+    let len = s.len();
+    if len > 8 {
+        let a = fetch64(s, 0);
+        let b = fetch64(s, len - 8);
+        return hash_len16(a, b.wrapping_add(len as u64).rotate_right(len as u32)) ^ b;
+    }
+    if len >= 4 {
+        let a = fetch32(s, 0);
+        return hash_len16((len as u64).wrapping_add(a << 3), fetch32(s, len - 4));
+    }
+    if len > 0 {
+        let a = s[0] as u32;
+        let b = s[len >> 1] as u32;
+        let c = s[len - 1] as u32;
+        let y = a.wrapping_add(b << 8);
+        let z = (len as u32).wrapping_add(c << 2);
+        return shift_mix((y as u64).wrapping_mul(K2) ^ (z as u64).wrapping_mul(K3))
+            .wrapping_mul(K2);
+    }
+    K2
+}
+
+const fn hash_len17to32(s: &[u8]) -> u64 {
+    let len = s.len();
+    let a = fetch64(s, 0).wrapping_mul(K1);
+    let b = fetch64(s, 8);
+    let c = fetch64(s, len - 8).wrapping_mul(K2);
+    let d = fetch64(s, len - 16).wrapping_mul(K0);
+    hash_len16(
+        a.wrapping_sub(b)
+            .rotate_right(43)
+            .wrapping_add(c.rotate_right(30))
+            .wrapping_add(d),
+        a.wrapping_add((b ^ K3).rotate_right(20))
+            .wrapping_sub(c)
+            .wrapping_add(len as u64),
+    )
+}
+
+const fn hash_len33to64(s: &[u8]) -> u64 {
+    let len = s.len();
+    let mut z = fetch64(s, 24);
+    let mut a = fetch64(s, 0).wrapping_add(
+        (len as u64)
+            .wrapping_add(fetch64(s, len - 16))
+            .wrapping_mul(K0),
+    );
+    let mut b = a.wrapping_add(z).rotate_right(52);
+    let mut c = a.rotate_right(37);
+    a = a.wrapping_add(fetch64(s, 8));
+    c = c.wrapping_add(a.rotate_right(7));
+    a = a.wrapping_add(fetch64(s, 16));
+    let vf = a.wrapping_add(z);
+    let vs = b.wrapping_add(a.rotate_right(31)).wrapping_add(c);
+    a = fetch64(s, 16).wrapping_add(fetch64(s, len - 32));
+    z = fetch64(s, len - 8);
+    b = a.wrapping_add(z).rotate_right(52);
+    c = a.rotate_right(37);
+    a = a.wrapping_add(fetch64(s, len - 24));
+    c = c.wrapping_add(a.rotate_right(7));
+    a = a.wrapping_add(fetch64(s, len - 16));
+    let wf = a.wrapping_add(z);
+    let ws = b.wrapping_add(a.rotate_right(31)).wrapping_add(c);
+    let r = shift_mix(
+        vf.wrapping_add(ws)
+            .wrapping_mul(K2)
+            .wrapping_add(wf.wrapping_add(vs).wrapping_mul(K0)),
+    );
+    shift_mix(r.wrapping_mul(K0).wrapping_add(vs)).wrapping_mul(K2)
+}
+
+/// Hashes the 32 bytes of `s` from `pos` with the seeds `a` and `b` into 128 bits.
+const fn weak_hash_len32_with_seeds(s: &[u8], pos: usize, a: u64, b: u64) -> (u64, u64) {
+    let w = fetch64(s, pos);
+    let x = fetch64(s, pos + 8);
+    let y = fetch64(s, pos + 16);
+    let z = fetch64(s, pos + 24);
+    let a = a.wrapping_add(w);
+    let b = b.wrapping_add(a).wrapping_add(z).rotate_right(21);
+    let c = a;
+    let a = a.wrapping_add(x).wrapping_add(y);
+    let b = b.wrapping_add(a.rotate_right(44));
+    (a.wrapping_add(z), b.wrapping_add(c))
+}
+
+const fn hash_len16(u: u64, v: u64) -> u64 {
+    const MUL: u64 = 0x9ddfea08eb382d69;
+    let mut a = (u ^ v).wrapping_mul(MUL);
+    a ^= a >> 47;
+    let mut b = (v ^ a).wrapping_mul(MUL);
+    b ^= b >> 47;
+    b.wrapping_mul(MUL)
+}
+
+const fn shift_mix(val: u64) -> u64 {
+    val ^ (val >> 47)
+}
+
+const fn fetch64(s: &[u8], pos: usize) -> u64 {
+    u64::from_le_bytes([
+        s[pos],
+        s[pos + 1],
+        s[pos + 2],
+        s[pos + 3],
+        s[pos + 4],
+        s[pos + 5],
+        s[pos + 6],
+        s[pos + 7],
+    ])
+}
+
+const fn fetch32(s: &[u8], pos: usize) -> u64 {
+    u32::from_le_bytes([s[pos], s[pos + 1], s[pos + 2], s[pos + 3]]) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::hash_key;
+
+    #[test]
+    fn test_city_hash64_with_seed() {
+        let bytes: Vec<u8> = (0..300u32).map(|i| (i * 167 + 13) as u8).collect();
+        for len in 0..bytes.len() {
+            for seed in [0, 42, u64::MAX] {
+                let key = &bytes[..len];
+                assert_eq!(city_hash64_with_seed(key, seed), hash_key(key, seed));
+            }
+        }
+    }
+
+    #[test]
+    fn test_key() {
+        const KEY: PrehashedKey = key!("content-type");
+        assert_eq!(KEY.as_bytes(), b"content-type");
+        assert_eq!(KEY.hash(), hash_key(b"content-type", DEFAULT_SEED));
+        assert_eq!(PrehashedKey::from_bytes(b"content-type"), KEY);
+    }
+}
@@ -0,0 +1,105 @@
+//! Progress reports of long-running constructions.
+
+use std::fmt;
+use std::sync::Arc;
+
+/// Number of inserted keys between two reports of the insertion phase.
+pub(crate) const PROGRESS_INTERVAL: usize = 1 << 16;
+
+/// Phase of a construction reported to [`HashMapBuilder::progress`](crate::HashMapBuilder::progress).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BuildPhase {
+    /// Keys are hashed and placed into slots.
+    Insertion,
+    /// Key bytes are laid out and auxiliary indexes are built.
+    Layout,
+    /// The construction has completed.
+    Finished,
+}
+
+/// Progress of a construction passed to the callback of
+/// [`HashMapBuilder::progress`](crate::HashMapBuilder::progress).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Progress {
+    /// Current phase.
+    pub phase: BuildPhase,
+    /// Number of keys processed in the current phase.
+    pub processed: usize,
+    /// Total number of keys.
+    pub total: usize,
+}
+
+/// Shared callback receiving [`Progress`] reports.
+#[derive(Clone)]
+pub(crate) struct ProgressFn(Arc<dyn Fn(Progress) + Send + Sync>);
+
+impl ProgressFn {
+    pub(crate) fn new<F>(f: F) -> Self
+    where
+        F: Fn(Progress) + Send + Sync + 'static,
+    {
+        Self(Arc::new(f))
+    }
+
+    pub(crate) fn report(&self, phase: BuildPhase, processed: usize, total: usize) {
+        (self.0)(Progress {
+            phase,
+            processed,
+            total,
+        });
+    }
+}
+
+impl fmt::Debug for ProgressFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressFn")
+    }
+}
+
+/// Iterator reporting the insertion phase as its records are consumed.
+pub(crate) struct Tracked<I> {
+    records: I,
+    progress: Option<ProgressFn>,
+    processed: usize,
+    total: usize,
+}
+
+impl<I> Tracked<I>
+where
+    I: ExactSizeIterator,
+{
+    /// Wraps `records` to report their consumption to `progress`, if any.
+    pub(crate) fn new(records: I, progress: Option<&ProgressFn>) -> Self {
+        let total = records.len();
+        Self {
+            records,
+            progress: progress.cloned(),
+            processed: 0,
+            total,
+        }
+    }
+}
+
+impl<I> Iterator for Tracked<I>
+where
+    I: ExactSizeIterator,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = self.records.next()?;
+        self.processed += 1;
+        if let Some(progress) = &self.progress {
+            if self.processed.is_multiple_of(PROGRESS_INTERVAL) || self.processed == self.total {
+                progress.report(BuildPhase::Insertion, self.processed, self.total);
+            }
+        }
+        Some(record)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.records.size_hint()
+    }
+}
+
+impl<I> ExactSizeIterator for Tracked<I> where I: ExactSizeIterator {}
@@ -0,0 +1,303 @@
+//! Low-level hash table of byte-string keys for building custom containers.
+
+use crate::map::MapNode;
+use crate::{KeyLen, Table, DEFAULT_LOAD_FACTOR, DEFAULT_SEED};
+
+use anyhow::Result;
+
+/// Low-level hash table storing byte-string keys in a packed arena
+/// with a payload of type `T` in the slot of each key.
+///
+/// It exposes the probing and key storage underlying [`HashMap`](crate::HashMap)
+/// for custom containers such as multimaps and caches:
+/// hash values are computed by [`RawTable::hash`] and passed in by the caller,
+/// so that a key is hashed once for several operations,
+/// and keys are referred to by [`RawSlot`] handles,
+/// through which the caller manages the payloads.
+///
+/// The type parameter `L` specifies the integer type to store key lengths
+/// (see [`HashMapBuilder::key_len`](crate::HashMapBuilder::key_len)).
+///
+/// # Examples
+///
+/// A multimap collecting values of each key:
+///
+/// ```
+/// use simplearrayhash::RawTable;
+///
+/// let mut table = RawTable::<Vec<u32>>::new();
+/// for (key, val) in [("icdm", 0), ("idce", 1), ("icdm", 2)] {
+///     let hash = table.hash(key);
+///     let (slot, _) = table.insert(hash, key).unwrap();
+///     table.payload_mut(slot).unwrap().push(val);
+/// }
+/// let slot = table.find(table.hash("icdm"), "icdm").unwrap();
+/// assert_eq!(table.payload(slot), Some(&vec![0, 2]));
+/// assert_eq!(table.len(), 2);
+/// ```
+#[derive(Clone)]
+pub struct RawTable<T = (), L = usize>
+where
+    T: Default + Clone,
+    L: KeyLen,
+{
+    table: Table<MapNode<T, L>>,
+}
+
+/// Handle to the slot of a key in a [`RawTable`].
+///
+/// A handle is invalidated when its slot may come to store another key,
+/// that is, when a key is removed or the table is rebuilt or cleared.
+/// Inserting keys without growing the table keeps handles valid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RawSlot {
+    pos: usize,
+    generation: u64,
+}
+
+impl RawSlot {
+    /// Returns the position of the slot, which is less than [`RawTable::capacity`].
+    ///
+    /// Payloads can also be kept out of the table in an array indexed by the positions,
+    /// which must be rebuilt whenever [`RawTable::generation`] changes.
+    #[inline(always)]
+    pub const fn index(self) -> usize {
+        self.pos
+    }
+}
+
+impl<T, L> RawTable<T, L>
+where
+    T: Default + Clone,
+    L: KeyLen,
+{
+    /// Creates an empty table.
+    pub fn new() -> Self {
+        Self::with_seed(DEFAULT_SEED)
+    }
+
+    /// Creates an empty table with the given seed of the hash function.
+    pub fn with_seed(seed: u64) -> Self {
+        // Building from no records never fails.
+        let table = Table::build_from_hashed(
+            std::iter::empty::<(&[u8], u64, ())>(),
+            0,
+            DEFAULT_LOAD_FACTOR,
+            seed,
+            false,
+            |_, ()| Ok(()),
+        )
+        .unwrap();
+        Self { table }
+    }
+
+    /// Computes the hash value of `key`, which the other methods take with the key.
+    ///
+    /// A hash value computed otherwise makes the key missed by lookups.
+    #[inline(always)]
+    pub fn hash<K>(&self, key: K) -> u64
+    where
+        K: AsRef<[u8]>,
+    {
+        self.table.hash(key.as_ref())
+    }
+
+    /// Returns the slot of `key` with the hash value `hash`, or `None` if it is not stored.
+    #[inline(always)]
+    pub fn find<K>(&self, hash: u64, key: K) -> Option<RawSlot>
+    where
+        K: AsRef<[u8]>,
+    {
+        let pos = self.table.get_pos_hashed(key.as_ref(), hash)?;
+        Some(self.slot(pos))
+    }
+
+    /// Inserts `key` with the hash value `hash` if it is not stored,
+    /// returning its slot and whether it is newly inserted with the default payload.
+    ///
+    /// The table grows when the insertion would exceed the load factor,
+    /// which invalidates all the handles.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the key length exceeds the range of `L`.
+    pub fn insert<K>(&mut self, hash: u64, key: K) -> Result<(RawSlot, bool)>
+    where
+        K: AsRef<[u8]>,
+    {
+        let (pos, inserted) = self.table.insert_hashed(key.as_ref(), hash)?;
+        Ok((self.slot(pos), inserted))
+    }
+
+    /// Removes the key of `slot` and returns its payload,
+    /// or `None` if the handle is invalidated.
+    ///
+    /// The handle must be obtained from this table;
+    /// a handle from another table may remove an arbitrary key or panic.
+    pub fn remove(&mut self, slot: RawSlot) -> Option<T> {
+        self.is_valid(slot)?;
+        Some(self.table.remove_at(slot.pos).val)
+    }
+
+    /// Returns the key of `slot`, or `None` if the handle is invalidated.
+    #[inline(always)]
+    pub fn key(&self, slot: RawSlot) -> Option<&[u8]> {
+        self.is_valid(slot)?;
+        Some(self.table.get_bytes(&self.table.nodes[slot.pos]))
+    }
+
+    /// Returns a reference to the payload of `slot`, or `None` if the handle is invalidated.
+    #[inline(always)]
+    pub fn payload(&self, slot: RawSlot) -> Option<&T> {
+        self.is_valid(slot)?;
+        Some(&self.table.nodes[slot.pos].val)
+    }
+
+    /// Returns a mutable reference to the payload of `slot`,
+    /// or `None` if the handle is invalidated.
+    #[inline(always)]
+    pub fn payload_mut(&mut self, slot: RawSlot) -> Option<&mut T> {
+        self.is_valid(slot)?;
+        Some(&mut self.table.nodes[slot.pos].val)
+    }
+
+    /// Grows the table at once so that `additional` more keys can be inserted without growth.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the number of keys would reach the maximum number of slots.
+    pub fn reserve(&mut self, additional: usize) -> Result<()> {
+        self.table.reserve(additional)
+    }
+
+    /// Removes all the keys, keeping the allocated slots and key bytes for reuse.
+    pub fn clear(&mut self) {
+        self.table.clear();
+    }
+
+    /// Iterates over the slots, keys, and payloads in slot order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::RawTable;
+    ///
+    /// let mut table = RawTable::<u32>::new();
+    /// for key in ["icdm", "idce", "sigmod"] {
+    ///     let (slot, _) = table.insert(table.hash(key), key).unwrap();
+    ///     *table.payload_mut(slot).unwrap() = key.len() as u32;
+    /// }
+    /// let total: u32 = table.iter().map(|(_, _, &len)| len).sum();
+    /// assert_eq!(total, 14);
+    /// ```
+    pub fn iter(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (RawSlot, &[u8], &T)> + ExactSizeIterator {
+        self.table
+            .occupied_slots()
+            .map(|(pos, node)| (self.slot(pos), self.table.get_bytes(node), &node.val))
+    }
+
+    /// Returns the current generation of the slots,
+    /// which changes whenever the handles are invalidated.
+    #[inline(always)]
+    pub const fn generation(&self) -> u64 {
+        self.table.generation
+    }
+
+    /// Returns the number of keys stored.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.table.num_keys()
+    }
+
+    /// Returns true if the table contains no keys.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of slots allocated in the table.
+    #[inline(always)]
+    pub fn capacity(&self) -> usize {
+        self.table.capacity()
+    }
+
+    #[inline(always)]
+    const fn slot(&self, pos: usize) -> RawSlot {
+        RawSlot {
+            pos,
+            generation: self.table.generation,
+        }
+    }
+
+    #[inline(always)]
+    fn is_valid(&self, slot: RawSlot) -> Option<()> {
+        (slot.generation == self.table.generation).then_some(())
+    }
+}
+
+impl<T, L> Default for RawTable<T, L>
+where
+    T: Default + Clone,
+    L: KeyLen,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let mut table = RawTable::<usize>::new();
+        assert!(table.is_empty());
+        for (i, key) in keys.iter().enumerate() {
+            let (slot, inserted) = table.insert(table.hash(key), key).unwrap();
+            assert!(inserted);
+            *table.payload_mut(slot).unwrap() = i;
+        }
+        assert_eq!(table.len(), keys.len());
+        for (i, key) in keys.iter().enumerate() {
+            let slot = table.find(table.hash(key), key).unwrap();
+            assert_eq!(table.key(slot), Some(key.as_bytes()));
+            assert_eq!(table.payload(slot), Some(&i));
+            let (other, inserted) = table.insert(table.hash(key), key).unwrap();
+            assert_eq!((other, inserted), (slot, false));
+        }
+        assert_eq!(table.find(table.hash("ijcai"), "ijcai"), None);
+
+        let mut found: Vec<_> = table.iter().map(|(_, key, &i)| (key, i)).collect();
+        found.sort_unstable_by_key(|&(_, i)| i);
+        let expected: Vec<_> = keys.iter().map(|key| key.as_bytes()).collect();
+        assert_eq!(
+            found.into_iter().map(|(key, _)| key).collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut table = RawTable::<u32, u8>::with_seed(42);
+        let hash = table.hash("icdm");
+        let (slot, _) = table.insert(hash, "icdm").unwrap();
+        *table.payload_mut(slot).unwrap() = 7;
+        table.insert(table.hash("idce"), "idce").unwrap();
+        let slot = table.find(hash, "icdm").unwrap();
+        let generation = table.generation();
+        assert_eq!(table.remove(slot), Some(7));
+        assert_ne!(table.generation(), generation);
+        assert_eq!(table.remove(slot), None);
+        assert_eq!(table.payload(slot), None);
+        assert_eq!(table.find(hash, "icdm"), None);
+        assert_eq!(table.len(), 1);
+
+        table.clear();
+        assert!(table.is_empty());
+        assert!(table.insert(0, "a".repeat(256)).is_err());
+    }
+}
@@ -0,0 +1,323 @@
+//! Implementations of the [`serde`] traits, enabled by the `serde` feature.
+//!
+//! In human-readable formats such as JSON, a map is represented as an object from keys to values
+//! and a set as an array of keys, both in the ascending order of keys,
+//! so that dictionaries round-trip through configuration files and can be edited by hand.
+//! The keys must then be valid UTF-8, and the options of the builder are not kept.
+//!
+//! In compact formats, a dictionary is represented by its [sectioned format](crate::serial),
+//! except that the values of a map follow as a sequence encoded by their own [`Serialize`].
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, Serialize, SerializeMap, SerializeSeq, SerializeTuple, Serializer};
+
+use crate::map::MapNode;
+use crate::serial::{self, SectionKind, Sections};
+use crate::{HashMap, HashMapBuilder, HashSet, KeyLen, Node, Table};
+
+/// Returns the keys as strings and the nodes of `table` in the ascending order of keys.
+fn utf8_entries<N, E>(table: &Table<N>) -> Result<Vec<(&str, &N)>, E>
+where
+    N: Default + Clone + Node,
+    E: ser::Error,
+{
+    let mut entries = table
+        .iter()
+        .map(|(key, node)| {
+            let key = std::str::from_utf8(key).map_err(|_| {
+                E::custom("The keys must be valid UTF-8 in a human-readable format.")
+            })?;
+            Ok((key, node))
+        })
+        .collect::<Result<Vec<_>, E>>()?;
+    entries.sort_unstable_by_key(|&(key, _)| key);
+    Ok(entries)
+}
+
+/// Byte string serialized by [`Serializer::serialize_bytes`].
+struct Bytes<'a>(&'a [u8]);
+
+impl Serialize for Bytes<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+/// Byte string deserialized from either bytes or a sequence of bytes.
+struct ByteBuf(Vec<u8>);
+
+impl<'de> Deserialize<'de> for ByteBuf {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ByteBufVisitor;
+
+        impl<'de> Visitor<'de> for ByteBufVisitor {
+            type Value = ByteBuf;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a byte string")
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<ByteBuf, E> {
+                Ok(ByteBuf(v.to_vec()))
+            }
+
+            fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<ByteBuf, E> {
+                Ok(ByteBuf(v))
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<ByteBuf, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut v = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(b) = seq.next_element()? {
+                    v.push(b);
+                }
+                Ok(ByteBuf(v))
+            }
+        }
+
+        deserializer.deserialize_byte_buf(ByteBufVisitor)
+    }
+}
+
+impl<V, L> Serialize for HashMap<V, L>
+where
+    V: Default + Clone + Serialize,
+    L: KeyLen,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            let entries = utf8_entries(&self.table)?;
+            let mut map = serializer.serialize_map(Some(entries.len()))?;
+            for (key, node) in entries {
+                map.serialize_entry(key, &node.val)?;
+            }
+            return map.end();
+        }
+        let (header, nodes) = self.table.serialize_sections();
+        let mut sections = vec![];
+        // Writing into a vector never fails.
+        serial::write_sections(
+            &mut sections,
+            &[
+                (SectionKind::Header as u32, &header),
+                (SectionKind::Nodes as u32, &nodes),
+                (SectionKind::Keys as u32, &self.table.bytes),
+            ],
+        )
+        .unwrap();
+        let values: Vec<_> = self.table.occupied().map(|nd| &nd.val).collect();
+        let mut tuple = serializer.serialize_tuple(2)?;
+        tuple.serialize_element(&Bytes(&sections))?;
+        tuple.serialize_element(&values)?;
+        tuple.end()
+    }
+}
+
+impl<'de, V, L> Deserialize<'de> for HashMap<V, L>
+where
+    V: Default + Clone + Deserialize<'de>,
+    L: KeyLen,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct EntriesVisitor<V>(PhantomData<V>);
+
+        impl<'de, V> Visitor<'de> for EntriesVisitor<V>
+        where
+            V: Deserialize<'de>,
+        {
+            type Value = Vec<(String, V)>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map from strings")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut records = Vec::with_capacity(map.size_hint().unwrap_or(0));
+                while let Some(record) = map.next_entry()? {
+                    records.push(record);
+                }
+                Ok(records)
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            let records = deserializer.deserialize_map(EntriesVisitor(PhantomData))?;
+            if records.is_empty() {
+                // The builder rejects no records, so an empty map is made by clearing one.
+                let mut map = HashMapBuilder::new()
+                    .key_len::<L>()
+                    .build(&[("", V::default())])
+                    .map_err(de::Error::custom)?;
+                map.clear();
+                return Ok(map);
+            }
+            // Duplicate keys, which a hand-edited file may contain, are rejected by the builder.
+            return HashMapBuilder::new()
+                .key_len::<L>()
+                .build(&records)
+                .map_err(de::Error::custom);
+        }
+        let (sections, values): (ByteBuf, Vec<V>) = Deserialize::deserialize(deserializer)?;
+        let sections = Sections::parse(&sections.0).map_err(de::Error::custom)?;
+        let mut table =
+            Table::<MapNode<V, L>>::deserialize_sections(&sections).map_err(de::Error::custom)?;
+        if values.len() != table.num_keys() {
+            return Err(de::Error::invalid_length(
+                values.len(),
+                &"as many values as keys",
+            ));
+        }
+        for (node, val) in table.occupied_mut().zip(values) {
+            node.val = val;
+        }
+        Ok(Self { table })
+    }
+}
+
+impl Serialize for HashSet {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() {
+            let entries = utf8_entries(&self.table)?;
+            let mut seq = serializer.serialize_seq(Some(entries.len()))?;
+            for (key, _) in entries {
+                seq.serialize_element(key)?;
+            }
+            return seq.end();
+        }
+        serializer.serialize_bytes(&self.serialize_to_vec())
+    }
+}
+
+impl<'de> Deserialize<'de> for HashSet {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if deserializer.is_human_readable() {
+            let keys = Vec::<String>::deserialize(deserializer)?;
+            if keys.is_empty() {
+                // The constructor rejects no keys, so an empty set is made by clearing one.
+                let mut set = Self::new(&[""]).map_err(de::Error::custom)?;
+                set.clear();
+                return Ok(set);
+            }
+            return Self::new(&keys).map_err(de::Error::custom);
+        }
+        let data = ByteBuf::deserialize(deserializer)?;
+        Self::deserialize_from_slice(&data.0).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let map = HashMap::new(&records).unwrap();
+        let json = serde_json::to_string(&map).unwrap();
+        assert_eq!(
+            json,
+            r#"{"":2,"acl":5,"icdm":0,"idce":1,"sigir":4,"sigmod":3}"#
+        );
+        let other: HashMap<usize> = serde_json::from_str(&json).unwrap();
+        assert_eq!(other.len(), records.len());
+        for &(k, v) in &records {
+            assert_eq!(other.get(k), Some(&v));
+        }
+
+        let other: HashMap<usize> = serde_json::from_str("{}").unwrap();
+        assert!(other.is_empty());
+        assert!(serde_json::from_str::<HashMap<usize>>(r#"{"icdm":0,"icdm":1}"#).is_err());
+        assert!(serde_json::from_str::<HashMap<usize, u8>>(&format!(
+            r#"{{"{}":0}}"#,
+            "a".repeat(256)
+        ))
+        .is_err());
+        let map = HashMap::new(&[(&[0xff][..], 0)]).unwrap();
+        assert!(serde_json::to_string(&map).is_err());
+
+        let set = HashSet::new(&keys).unwrap();
+        let json = serde_json::to_string(&set).unwrap();
+        assert_eq!(json, r#"["","acl","icdm","idce","sigir","sigmod"]"#);
+        let other: HashSet = serde_json::from_str(&json).unwrap();
+        assert!(keys.iter().all(|k| other.contains(k)));
+    }
+
+    #[test]
+    fn test_compact() {
+        use serde_test::{assert_de_tokens, assert_ser_tokens, Configure, Token};
+
+        // Maps are compared by their serialized bytes, which include the slot layout.
+        struct Same(HashMap<u32>);
+
+        impl PartialEq for Same {
+            fn eq(&self, other: &Self) -> bool {
+                self.0.serialize_to_vec() == other.0.serialize_to_vec()
+            }
+        }
+
+        impl fmt::Debug for Same {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_map()
+                    .entries(self.0.slots().map(|(_, k, v)| (k, v)))
+                    .finish()
+            }
+        }
+
+        impl<'de> Deserialize<'de> for Same {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                HashMap::deserialize(deserializer).map(Same)
+            }
+        }
+
+        let records = [("icdm", 0u32), ("idce", 1), ("sigmod", 2)];
+        let map = HashMap::new(&records).unwrap();
+        let sections = map.serialize_to_vec();
+        let sections = Sections::parse(&sections).unwrap();
+        let mut expected = vec![];
+        serial::write_sections(
+            &mut expected,
+            &[SectionKind::Header, SectionKind::Nodes, SectionKind::Keys]
+                .map(|kind| (kind as u32, sections.get(kind).unwrap())),
+        )
+        .unwrap();
+        let mut tokens = vec![
+            Token::Tuple { len: 2 },
+            Token::Bytes(expected.leak()),
+            Token::Seq { len: Some(3) },
+        ];
+        tokens.extend(map.slots().map(|(_, _, &v)| Token::U32(v)));
+        tokens.extend([Token::SeqEnd, Token::TupleEnd]);
+        assert_ser_tokens(&(&map).compact(), &tokens);
+        assert_de_tokens(&Same(map).compact(), &tokens);
+    }
+}
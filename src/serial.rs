@@ -0,0 +1,1035 @@
+//! Serialization in a sectioned binary format.
+//!
+//! A serialized dictionary consists of a fixed preamble, a section table,
+//! and independently addressable sections:
+//!
+//! ```text
+//! magic (8 bytes) | version (u32) | number of sections (u32)
+//! section table: [kind (u32) | reserved (u32) | offset (u64) | length (u64)] * number of sections
+//! section bodies, each aligned to 8 bytes
+//! ```
+//!
+//! All integers are stored in little endian.
+//! The sections of a map are [`SectionKind::Header`], [`SectionKind::Nodes`],
+//! [`SectionKind::Keys`], and [`SectionKind::Values`] (a set and a `HashMap<()>` have no values).
+//! A [`DenseHashMap`](crate::DenseHashMap) of plain-old-data values
+//! has [`SectionKind::PodValues`] instead of [`SectionKind::Values`].
+//! The key structure of a map, that is, the header, nodes, and keys sections,
+//! can also be stored apart from its values, which are then stored with
+//! a [`SectionKind::KeysDigest`] section identifying the key structure
+//! (see [`HashMap::serialize_keys_into`](crate::HashMap::serialize_keys_into)).
+//! Since every section is located through the section table,
+//! tools can inspect or replace one section via [`Sections`] and [`replace_section`]
+//! without decoding the others.
+
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+use crate::set::SetNode;
+use crate::storage::{KeyBytes, KeyStorage, Nodes};
+use crate::{KeyLen, Node, ResizePolicy, Table, DEFAULT_LOAD_FACTOR, KEY_ALIGN};
+
+use anyhow::{anyhow, Result};
+
+/// Magic bytes at the beginning of a serialized dictionary.
+pub const MAGIC: &[u8; 8] = b"SAHASH\0\0";
+
+/// Version of the serialization format.
+pub const VERSION: u32 = 1;
+
+const PREAMBLE_LEN: usize = 16;
+const ENTRY_LEN: usize = 24;
+const ALIGNMENT: usize = 8;
+const EMPTY_SLOT: u64 = u64::MAX;
+const TOMBSTONE_SLOT: u64 = u64::MAX - 1;
+/// Seed of [`keys_digest`], which must never change.
+const KEYS_DIGEST_SEED: u128 = 0x7361_6861_7368_5f6b_6579_735f_6469_6765;
+
+/// Kind of a section.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum SectionKind {
+    /// Table parameters such as the number of keys, capacity, and seed.
+    Header = 1,
+    /// Slots of the table, each holding the position and length of its key
+    /// or a sentinel for an empty slot.
+    Nodes = 2,
+    /// Packed key bytes.
+    Keys = 3,
+    /// Values in slot order, indexed by offsets so that each value can be decoded alone.
+    Values = 4,
+    /// Plain-old-data values of a [`DenseHashMap`](crate::DenseHashMap) stored as a raw array,
+    /// preceded by the index of the value of each key in slot order.
+    PodValues = 5,
+    /// Digest of the header, nodes, and keys sections of a map,
+    /// stored with values serialized apart from the key structure
+    /// to reject loading them with another key structure.
+    KeysDigest = 6,
+    /// Number of shards, seed of the hash function, and number of keys in each shard
+    /// of a [`PartitionedHashMap`](crate::PartitionedHashMap), stored in its manifest.
+    Partitions = 7,
+}
+
+impl SectionKind {
+    /// Converts a raw kind into [`SectionKind`], returning `None` for unknown kinds.
+    pub const fn from_u32(kind: u32) -> Option<Self> {
+        match kind {
+            1 => Some(Self::Header),
+            2 => Some(Self::Nodes),
+            3 => Some(Self::Keys),
+            4 => Some(Self::Values),
+            5 => Some(Self::PodValues),
+            6 => Some(Self::KeysDigest),
+            7 => Some(Self::Partitions),
+            _ => None,
+        }
+    }
+}
+
+/// Parsed section table of a serialized dictionary, borrowing the serialized bytes.
+///
+/// # Examples
+///
+/// ```
+/// use simplearrayhash::HashMap;
+/// use simplearrayhash::serial::{SectionKind, Sections};
+///
+/// let records = vec![("icdm", 0u32), ("idce", 1)];
+/// let map = HashMap::new(&records).unwrap();
+/// let data = map.serialize_to_vec();
+///
+/// let sections = Sections::parse(&data).unwrap();
+/// assert_eq!(sections.get(SectionKind::Keys).unwrap().len(), 8);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Sections<'a> {
+    entries: Vec<(u32, &'a [u8])>,
+}
+
+impl<'a> Sections<'a> {
+    /// Parses the preamble and section table of `data`.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when `data` does not start with [`MAGIC`],
+    /// its version is not supported, or a section is out of bounds.
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        let entries = read_section_table(data)?
+            .into_iter()
+            .map(|(kind, offset, len)| {
+                let offset = to_usize(offset)?;
+                let len = to_usize(len)?;
+                let body = offset
+                    .checked_add(len)
+                    .and_then(|end| data.get(offset..end))
+                    .ok_or_else(|| anyhow!("A section is out of bounds."))?;
+                Ok((kind, body))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { entries })
+    }
+
+    /// Creates a section table from pairs of raw kinds and bodies loaded elsewhere.
+    pub(crate) const fn from_entries(entries: Vec<(u32, &'a [u8])>) -> Self {
+        Self { entries }
+    }
+
+    /// Returns the body of the first section of `kind`.
+    pub fn get(&self, kind: SectionKind) -> Option<&'a [u8]> {
+        self.get_raw(kind as u32)
+    }
+
+    /// Returns the body of the first section of the raw `kind`,
+    /// which may be unknown to this version.
+    pub fn get_raw(&self, kind: u32) -> Option<&'a [u8]> {
+        self.entries
+            .iter()
+            .find(|(k, _)| *k == kind)
+            .map(|&(_, body)| body)
+    }
+
+    /// Iterates over the raw kinds and bodies of the sections in the table order.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &'a [u8])> + '_ {
+        self.entries.iter().copied()
+    }
+
+    pub(crate) fn require(&self, kind: SectionKind) -> Result<&'a [u8]> {
+        self.get(kind)
+            .ok_or_else(|| anyhow!("The section {kind:?} is missing."))
+    }
+}
+
+/// Reads the preamble and section table from `rdr`,
+/// returning the raw kinds, offsets, and lengths of the sections.
+pub(crate) fn read_section_table<R>(mut rdr: R) -> Result<Vec<(u32, u64, u64)>>
+where
+    R: Read,
+{
+    let mut preamble = [0; PREAMBLE_LEN];
+    if rdr.read_exact(&mut preamble).is_err() || &preamble[..8] != MAGIC {
+        return Err(anyhow!("The input data is not a serialized dictionary."));
+    }
+    let mut source = &preamble[8..];
+    let version = read_u32(&mut source)?;
+    if version != VERSION {
+        return Err(anyhow!("The format version {version} is not supported."));
+    }
+    let num_sections = read_u32(&mut source)?;
+    let mut entries = vec![];
+    for _ in 0..num_sections {
+        let mut entry = [0; ENTRY_LEN];
+        rdr.read_exact(&mut entry)
+            .map_err(|_| anyhow!("The section table is truncated."))?;
+        let mut source = &entry[..];
+        let kind = read_u32(&mut source)?;
+        read_u32(&mut source)?; // reserved
+        let offset = read_u64(&mut source)?;
+        let len = read_u64(&mut source)?;
+        entries.push((kind, offset, len));
+    }
+    Ok(entries)
+}
+
+/// Reads a serialized dictionary at the front of `buf`, advancing `buf` past its last section,
+/// and decodes its sections with `f`, which is also given the storage the sections are parsed from.
+///
+/// The section bodies are taken with [`bytes::Buf::copy_to_bytes`],
+/// which shares the underlying buffer without copying if `buf` is [`bytes::Bytes`],
+/// so that the key bytes can be borrowed from the buffer.
+#[cfg(feature = "bytes")]
+pub(crate) fn read_buf<B, T, F>(buf: &mut B, f: F) -> Result<T>
+where
+    B: bytes::Buf,
+    F: FnOnce(&Sections<'_>, &Arc<dyn KeyStorage>) -> Result<T>,
+{
+    let entries = read_section_table(bytes::Buf::reader(&mut *buf))?;
+    let head_len = PREAMBLE_LEN + ENTRY_LEN * entries.len();
+    let mut end = head_len;
+    let mut ranges = Vec::with_capacity(entries.len());
+    for (kind, offset, len) in entries {
+        let start = to_usize(offset)?
+            .checked_sub(head_len)
+            .ok_or_else(|| anyhow!("A section is out of bounds."))?;
+        let len = to_usize(len)?;
+        let section_end = start
+            .checked_add(len)
+            .and_then(|e| e.checked_add(head_len))
+            .ok_or_else(|| anyhow!("A section is out of bounds."))?;
+        end = end.max(section_end);
+        ranges.push((kind, start, len));
+    }
+    if buf.remaining() < end - head_len {
+        return Err(anyhow!("The input data is truncated."));
+    }
+    let storage: Arc<dyn KeyStorage> = Arc::new(buf.copy_to_bytes(end - head_len));
+    let body = storage.as_key_bytes();
+    let entries = ranges
+        .into_iter()
+        .map(|(kind, start, len)| (kind, &body[start..start + len]))
+        .collect();
+    f(&Sections::from_entries(entries), &storage)
+}
+
+/// Writes sections in the sectioned format.
+///
+/// # Arguments
+///
+/// - `wtr`: Writer.
+/// - `sections`: Pairs of raw kinds and bodies.
+///
+/// # Errors
+///
+/// An error will be returned when writing fails.
+pub fn write_sections<W>(mut wtr: W, sections: &[(u32, &[u8])]) -> Result<()>
+where
+    W: Write,
+{
+    let num_sections = u32::try_from(sections.len())?;
+    let mut head = Vec::with_capacity(PREAMBLE_LEN + ENTRY_LEN * sections.len());
+    head.extend_from_slice(MAGIC);
+    head.extend_from_slice(&VERSION.to_le_bytes());
+    head.extend_from_slice(&num_sections.to_le_bytes());
+    let mut offset = align(PREAMBLE_LEN + ENTRY_LEN * sections.len());
+    let mut offsets = Vec::with_capacity(sections.len());
+    for &(kind, body) in sections {
+        head.extend_from_slice(&kind.to_le_bytes());
+        head.extend_from_slice(&0u32.to_le_bytes());
+        head.extend_from_slice(&(offset as u64).to_le_bytes());
+        head.extend_from_slice(&(body.len() as u64).to_le_bytes());
+        offsets.push(offset);
+        offset = align(offset + body.len());
+    }
+    wtr.write_all(&head)?;
+    let mut written = head.len();
+    for (&(_, body), offset) in sections.iter().zip(offsets) {
+        wtr.write_all(&[0; ALIGNMENT][..offset - written])?;
+        wtr.write_all(body)?;
+        written = offset + body.len();
+    }
+    Ok(())
+}
+
+/// Returns a copy of the serialized `data` whose first section of `kind` is replaced with `body`.
+/// The other sections are copied without being decoded.
+///
+/// # Errors
+///
+/// An error will be returned when `data` is not in the sectioned format
+/// or does not contain a section of `kind`.
+///
+/// # Examples
+///
+/// ```
+/// use simplearrayhash::HashMap;
+/// use simplearrayhash::serial::{replace_section, SectionKind, Sections};
+///
+/// let map = HashMap::new(&[("icdm", 0u32), ("idce", 1)]).unwrap();
+/// let other = HashMap::new(&[("icdm", 2u32), ("idce", 3)]).unwrap();
+/// let data = map.serialize_to_vec();
+/// let other_data = other.serialize_to_vec();
+///
+/// // Values are stored in slot order, so the values of a map with the same keys can be swapped.
+/// let values = Sections::parse(&other_data).unwrap().get(SectionKind::Values).unwrap();
+/// let data = replace_section(&data, SectionKind::Values, values).unwrap();
+/// let map = HashMap::<u32>::deserialize_from_slice(&data).unwrap();
+/// assert_eq!(map.get("idce"), Some(&3));
+/// ```
+pub fn replace_section(data: &[u8], kind: SectionKind, body: &[u8]) -> Result<Vec<u8>> {
+    let sections = Sections::parse(data)?;
+    sections.require(kind)?;
+    let mut replaced = false;
+    let entries: Vec<_> = sections
+        .iter()
+        .map(|(k, b)| {
+            if k == kind as u32 && !replaced {
+                replaced = true;
+                (k, body)
+            } else {
+                (k, b)
+            }
+        })
+        .collect();
+    let mut dest = vec![];
+    write_sections(&mut dest, &entries)?;
+    Ok(dest)
+}
+
+/// Computes the body of a [`SectionKind::KeysDigest`] section
+/// from the header, nodes, and keys sections in `sections`.
+pub(crate) fn keys_digest(sections: &Sections) -> Result<[u8; 16]> {
+    let mut digests = Vec::with_capacity(48);
+    for kind in [SectionKind::Header, SectionKind::Nodes, SectionKind::Keys] {
+        let body = sections.require(kind)?;
+        let digest = fasthash::city::hash128_with_seed(body, KEYS_DIGEST_SEED);
+        digests.extend_from_slice(&digest.to_le_bytes());
+    }
+    Ok(fasthash::city::hash128_with_seed(&digests, KEYS_DIGEST_SEED).to_le_bytes())
+}
+
+/// Value type that can be serialized.
+pub trait Serializable: Sized {
+    /// True if the type has the only value serialized into no bytes, such as `()`.
+    ///
+    /// A [`HashMap`](crate::HashMap) of such values is serialized without
+    /// the [`SectionKind::Values`] section, that is, in the same format as a set.
+    const VALUELESS: bool = false;
+
+    /// Appends the serialized value to `dest`.
+    fn serialize_to_vec(&self, dest: &mut Vec<u8>);
+
+    /// Deserializes a value from the beginning of `source`,
+    /// returning the value and the remaining bytes.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when `source` is malformed.
+    fn deserialize_from_slice(source: &[u8]) -> Result<(Self, &[u8])>;
+}
+
+macro_rules! impl_serializable_num {
+    ($($t:ty),*) => {
+        $(
+            impl Serializable for $t {
+                fn serialize_to_vec(&self, dest: &mut Vec<u8>) {
+                    dest.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn deserialize_from_slice(source: &[u8]) -> Result<(Self, &[u8])> {
+                    const LEN: usize = std::mem::size_of::<$t>();
+                    if source.len() < LEN {
+                        return Err(anyhow!("The input data is truncated."));
+                    }
+                    let (head, rest) = source.split_at(LEN);
+                    Ok((Self::from_le_bytes(head.try_into().unwrap()), rest))
+                }
+            }
+        )*
+    };
+}
+
+impl_serializable_num!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+impl Serializable for usize {
+    fn serialize_to_vec(&self, dest: &mut Vec<u8>) {
+        (*self as u64).serialize_to_vec(dest);
+    }
+
+    fn deserialize_from_slice(source: &[u8]) -> Result<(Self, &[u8])> {
+        let (x, rest) = u64::deserialize_from_slice(source)?;
+        Ok((to_usize(x)?, rest))
+    }
+}
+
+impl Serializable for bool {
+    fn serialize_to_vec(&self, dest: &mut Vec<u8>) {
+        dest.push(u8::from(*self));
+    }
+
+    fn deserialize_from_slice(source: &[u8]) -> Result<(Self, &[u8])> {
+        let (x, rest) = u8::deserialize_from_slice(source)?;
+        match x {
+            0 => Ok((false, rest)),
+            1 => Ok((true, rest)),
+            _ => Err(anyhow!("The input data is not a boolean.")),
+        }
+    }
+}
+
+impl Serializable for () {
+    const VALUELESS: bool = true;
+
+    fn serialize_to_vec(&self, _dest: &mut Vec<u8>) {}
+
+    fn deserialize_from_slice(source: &[u8]) -> Result<(Self, &[u8])> {
+        Ok(((), source))
+    }
+}
+
+impl Serializable for String {
+    fn serialize_to_vec(&self, dest: &mut Vec<u8>) {
+        self.as_bytes().to_vec().serialize_to_vec(dest);
+    }
+
+    fn deserialize_from_slice(source: &[u8]) -> Result<(Self, &[u8])> {
+        let (bytes, rest) = Vec::<u8>::deserialize_from_slice(source)?;
+        Ok((Self::from_utf8(bytes)?, rest))
+    }
+}
+
+impl<T> Serializable for Vec<T>
+where
+    T: Serializable,
+{
+    fn serialize_to_vec(&self, dest: &mut Vec<u8>) {
+        self.len().serialize_to_vec(dest);
+        for x in self {
+            x.serialize_to_vec(dest);
+        }
+    }
+
+    fn deserialize_from_slice(source: &[u8]) -> Result<(Self, &[u8])> {
+        let (len, mut source) = usize::deserialize_from_slice(source)?;
+        let mut xs = Self::with_capacity(len.min(source.len()));
+        for _ in 0..len {
+            let (x, rest) = T::deserialize_from_slice(source)?;
+            xs.push(x);
+            source = rest;
+        }
+        Ok((xs, source))
+    }
+}
+
+impl<T> Serializable for Option<T>
+where
+    T: Serializable,
+{
+    fn serialize_to_vec(&self, dest: &mut Vec<u8>) {
+        self.is_some().serialize_to_vec(dest);
+        if let Some(x) = self {
+            x.serialize_to_vec(dest);
+        }
+    }
+
+    fn deserialize_from_slice(source: &[u8]) -> Result<(Self, &[u8])> {
+        let (is_some, source) = bool::deserialize_from_slice(source)?;
+        if is_some {
+            let (x, rest) = T::deserialize_from_slice(source)?;
+            Ok((Some(x), rest))
+        } else {
+            Ok((None, source))
+        }
+    }
+}
+
+impl<A, B> Serializable for (A, B)
+where
+    A: Serializable,
+    B: Serializable,
+{
+    fn serialize_to_vec(&self, dest: &mut Vec<u8>) {
+        self.0.serialize_to_vec(dest);
+        self.1.serialize_to_vec(dest);
+    }
+
+    fn deserialize_from_slice(source: &[u8]) -> Result<(Self, &[u8])> {
+        let (a, source) = A::deserialize_from_slice(source)?;
+        let (b, source) = B::deserialize_from_slice(source)?;
+        Ok(((a, b), source))
+    }
+}
+
+/// Serializes values into the body of a [`SectionKind::Values`] section:
+/// the number of values, the offsets of `count + 1` values, and the payload.
+pub(crate) fn serialize_values<'a, V, I>(values: I) -> Vec<u8>
+where
+    V: Serializable + 'a,
+    I: IntoIterator<Item = &'a V>,
+{
+    let mut offsets = vec![0];
+    let mut payload = vec![];
+    for v in values {
+        v.serialize_to_vec(&mut payload);
+        offsets.push(payload.len() as u64);
+    }
+    let count = offsets.len() - 1;
+    let mut dest = Vec::with_capacity(8 * (count + 2) + payload.len());
+    dest.extend_from_slice(&(count as u64).to_le_bytes());
+    for offset in offsets {
+        dest.extend_from_slice(&offset.to_le_bytes());
+    }
+    dest.extend_from_slice(&payload);
+    dest
+}
+
+/// Index over the body of a [`SectionKind::Values`] section.
+pub(crate) struct ValuesIndex<'a> {
+    offsets: &'a [u8],
+    payload: &'a [u8],
+    count: usize,
+}
+
+impl<'a> ValuesIndex<'a> {
+    pub(crate) fn parse(mut body: &'a [u8]) -> Result<Self> {
+        let count = to_usize(read_u64(&mut body)?)?;
+        let offsets_len = count
+            .checked_add(1)
+            .and_then(|n| n.checked_mul(8))
+            .filter(|&n| n <= body.len())
+            .ok_or_else(|| anyhow!("The values section is truncated."))?;
+        let (offsets, payload) = body.split_at(offsets_len);
+        Ok(Self {
+            offsets,
+            payload,
+            count,
+        })
+    }
+
+    pub(crate) const fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the byte range of the `i`-th value in the payload.
+    pub(crate) fn range(&self, i: usize) -> Result<(u64, u64)> {
+        let mut source = &self.offsets[8 * i..];
+        let start = read_u64(&mut source)?;
+        let end = read_u64(&mut source)?;
+        Ok((start, end))
+    }
+
+    /// Deserializes the `i`-th value.
+    pub(crate) fn get<V>(&self, i: usize) -> Result<V>
+    where
+        V: Serializable,
+    {
+        let (start, end) = self.range(i)?;
+        let bytes = usize::try_from(start)
+            .ok()
+            .zip(usize::try_from(end).ok())
+            .and_then(|(start, end)| self.payload.get(start..end))
+            .ok_or_else(|| anyhow!("A value is out of bounds."))?;
+        decode_exact(bytes)
+    }
+}
+
+/// Deserializes a value that must span the whole `bytes`.
+pub(crate) fn decode_exact<V>(bytes: &[u8]) -> Result<V>
+where
+    V: Serializable,
+{
+    let (v, rest) = V::deserialize_from_slice(bytes)?;
+    if !rest.is_empty() {
+        return Err(anyhow!("A value has trailing bytes."));
+    }
+    Ok(v)
+}
+
+impl<N> Table<N>
+where
+    N: Default + Clone + Node,
+{
+    /// Serializes the table into the bodies of the header and nodes sections.
+    pub(crate) fn serialize_sections(&self) -> (Vec<u8>, Vec<u8>) {
+        let mut header = Vec::with_capacity(64);
+        for x in [
+            self.num_keys as u64,
+            self.nodes.len() as u64,
+            self.max_key_len as u64,
+            self.seed,
+            self.key_align as u64,
+            u64::from(self.share_suffixes),
+            u64::from(self.order.is_some()),
+            u64::from(self.deletions.is_some()),
+            u64::from(self.constant_time),
+            self.stash_len as u64,
+            // The probe bounds are meaningless without the stash.
+            if self.stash_len == 0 {
+                0
+            } else {
+                self.max_probe as u64
+            },
+            if self.stash_len == 0 {
+                0
+            } else {
+                self.probe_limit as u64
+            },
+            u64::from(self.suffixes.is_some()),
+        ] {
+            header.extend_from_slice(&x.to_le_bytes());
+        }
+        let mut nodes = Vec::with_capacity(16 * self.nodes.len());
+        for node in self.nodes.iter() {
+            let (ptr, len) = if node.is_vacant() {
+                (EMPTY_SLOT, 0)
+            } else if node.is_tombstone() {
+                (TOMBSTONE_SLOT, 0)
+            } else {
+                (node.ptr() as u64, node.len() as u64)
+            };
+            nodes.extend_from_slice(&ptr.to_le_bytes());
+            nodes.extend_from_slice(&len.to_le_bytes());
+        }
+        (header, nodes)
+    }
+
+    /// Deserializes the table from the sections, validating its consistency.
+    pub(crate) fn deserialize_sections(sections: &Sections) -> Result<Self> {
+        Self::deserialize_sections_with(sections, None)
+    }
+
+    /// Deserializes the table in the same manner as [`Table::deserialize_sections`],
+    /// borrowing the key bytes from `storage` instead of copying them if given,
+    /// whose bytes `sections` must be parsed from.
+    pub(crate) fn deserialize_sections_with(
+        sections: &Sections,
+        storage: Option<&Arc<dyn KeyStorage>>,
+    ) -> Result<Self> {
+        phase!("validation");
+        let header = Header::parse(sections)?;
+        let mut nodes = Vec::with_capacity(header.capacity);
+        let mut num_tombstones = 0;
+        decode_slots(&header, sections, |slot| {
+            let node = match slot {
+                Slot::Vacant => N::vacant(),
+                Slot::Tombstone => {
+                    num_tombstones += 1;
+                    N::tombstone()
+                }
+                Slot::Key { ptr, len } => {
+                    let len = N::Len::from_usize(len).ok_or_else(|| {
+                        anyhow!("The key length must not exceed the range of the length type.")
+                    })?;
+                    N::new(ptr, len)
+                }
+            };
+            nodes.push(node);
+            Ok(())
+        })?;
+        Self::from_header(header, nodes.into(), num_tombstones, sections, storage)
+    }
+
+    /// Assembles the table from the parsed header and the slots validated against it,
+    /// deserializing the rest of the sections.
+    fn from_header(
+        header: Header,
+        nodes: Nodes<N>,
+        num_tombstones: usize,
+        sections: &Sections,
+        storage: Option<&Arc<dyn KeyStorage>>,
+    ) -> Result<Self> {
+        let Header {
+            num_keys,
+            max_key_len,
+            seed,
+            key_align,
+            share_suffixes,
+            sorted,
+            fuzzy,
+            constant_time,
+            stash_len,
+            max_probe,
+            probe_limit,
+            suffix_index,
+            ..
+        } = header;
+        let keys = sections.require(SectionKind::Keys)?;
+        let bytes = match storage {
+            Some(storage) => {
+                let start = keys.as_ptr() as usize - storage.as_key_bytes().as_ptr() as usize;
+                KeyBytes::shared(Arc::clone(storage), start, start + keys.len())
+            }
+            None => keys.to_vec().into(),
+        };
+        let mut table = Self {
+            nodes,
+            bytes,
+            num_keys,
+            num_tombstones,
+            generation: 0,
+            max_key_len,
+            seed,
+            key_align,
+            share_suffixes,
+            constant_time,
+            stash_len,
+            max_probe,
+            probe_limit,
+            order: None,
+            deletions: None,
+            suffixes: None,
+            resize: ResizePolicy::new(DEFAULT_LOAD_FACTOR),
+            key_len_limit: usize::MAX,
+            lookup_cache: None,
+            #[cfg(feature = "hugepages")]
+            huge_pages: false,
+            #[cfg(feature = "instrument")]
+            probe_counters: Default::default(),
+        };
+        if sorted {
+            let order = table.sorted_positions();
+            if order
+                .windows(2)
+                .any(|w| table.entry_at(w[0]).0 >= table.entry_at(w[1]).0)
+            {
+                return Err(anyhow!("The keys section is not sorted."));
+            }
+            table.order = Some(order);
+        }
+        if fuzzy {
+            table.deletions = Some(table.deletion_index());
+        }
+        if suffix_index {
+            table.suffixes = Some(table.suffix_index());
+        }
+        Ok(table)
+    }
+}
+
+impl Table<SetNode> {
+    /// Deserializes the table in the same manner as [`Table::deserialize_sections_with`],
+    /// also borrowing the slots from `storage` if their records are laid out as in memory
+    /// (see [`Nodes::shared`]), so that neither the slots nor the key bytes are copied.
+    pub(crate) fn deserialize_sections_shared(
+        sections: &Sections,
+        storage: &Arc<dyn KeyStorage>,
+    ) -> Result<Self> {
+        let header = Header::parse(sections)?;
+        let records = sections.require(SectionKind::Nodes)?;
+        let start = records.as_ptr() as usize - storage.as_key_bytes().as_ptr() as usize;
+        let Some(nodes) = Nodes::shared(Arc::clone(storage), start, header.capacity) else {
+            return Self::deserialize_sections_with(sections, Some(storage));
+        };
+        let mut num_tombstones = 0;
+        decode_slots(&header, sections, |slot| {
+            num_tombstones += usize::from(slot == Slot::Tombstone);
+            Ok(())
+        })?;
+        Self::from_header(header, nodes, num_tombstones, sections, Some(storage))
+    }
+}
+
+/// Parameters of a table stored in the header section.
+pub(crate) struct Header {
+    pub(crate) num_keys: usize,
+    // Number of slots including the stash.
+    pub(crate) capacity: usize,
+    pub(crate) max_key_len: usize,
+    pub(crate) seed: u64,
+    pub(crate) key_align: usize,
+    pub(crate) share_suffixes: bool,
+    pub(crate) sorted: bool,
+    pub(crate) fuzzy: bool,
+    pub(crate) constant_time: bool,
+    pub(crate) stash_len: usize,
+    // `usize::MAX` if the table has no stash.
+    pub(crate) max_probe: usize,
+    // `usize::MAX` if the table has no stash.
+    pub(crate) probe_limit: usize,
+    pub(crate) suffix_index: bool,
+}
+
+impl Header {
+    /// Parses and validates the header section.
+    pub(crate) fn parse(sections: &Sections) -> Result<Self> {
+        let mut header = sections.require(SectionKind::Header)?;
+        let num_keys = to_usize(read_u64(&mut header)?)?;
+        let capacity = to_usize(read_u64(&mut header)?)?;
+        let max_key_len = to_usize(read_u64(&mut header)?)?;
+        let seed = read_u64(&mut header)?;
+        let key_align = to_usize(read_u64(&mut header)?)?;
+        let share_suffixes = read_u64(&mut header)?;
+        let sorted = read_u64(&mut header)?;
+        let fuzzy = read_u64(&mut header)?;
+        let constant_time = read_u64(&mut header)?;
+        let stash_len = to_usize(read_u64(&mut header)?)?;
+        let max_probe = to_usize(read_u64(&mut header)?)?;
+        let probe_limit = to_usize(read_u64(&mut header)?)?;
+        let suffix_index = read_u64(&mut header)?;
+        if !header.is_empty()
+            || num_keys >= capacity
+            || stash_len >= capacity
+            || (stash_len != 0 && (max_probe == 0 || probe_limit < max_probe))
+            || ![1, KEY_ALIGN].contains(&key_align)
+            || share_suffixes > 1
+            || sorted > 1
+            || fuzzy > 1
+            || constant_time > 1
+            || suffix_index > 1
+        {
+            return Err(anyhow!("The header section is broken."));
+        }
+        // The capacity sizes allocations of the loaders, so it is checked against
+        // the nodes section before it is trusted.
+        let nodes = sections.require(SectionKind::Nodes)?;
+        if Some(nodes.len()) != capacity.checked_mul(16) {
+            return Err(anyhow!("The nodes section is broken."));
+        }
+        // The probe bounds are meaningless without the stash.
+        let (max_probe, probe_limit) = if stash_len == 0 {
+            (usize::MAX, usize::MAX)
+        } else {
+            (max_probe, probe_limit)
+        };
+        Ok(Self {
+            num_keys,
+            capacity,
+            max_key_len,
+            seed,
+            key_align,
+            share_suffixes: share_suffixes == 1,
+            sorted: sorted == 1,
+            fuzzy: fuzzy == 1,
+            constant_time: constant_time == 1,
+            stash_len,
+            max_probe,
+            probe_limit,
+            suffix_index: suffix_index == 1,
+        })
+    }
+}
+
+/// Content of a slot in the nodes section.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Slot {
+    Vacant,
+    Tombstone,
+    Key { ptr: usize, len: usize },
+}
+
+impl Slot {
+    /// Decodes the `pos`-th slot of a validated nodes section.
+    #[inline(always)]
+    pub(crate) fn decode(nodes: &[u8], pos: usize) -> Self {
+        let entry = &nodes[16 * pos..16 * pos + 16];
+        let ptr = u64::from_le_bytes(entry[..8].try_into().unwrap());
+        let len = u64::from_le_bytes(entry[8..].try_into().unwrap());
+        match ptr {
+            EMPTY_SLOT => Self::Vacant,
+            TOMBSTONE_SLOT => Self::Tombstone,
+            // Validated slots fit in usize.
+            _ => Self::Key {
+                ptr: ptr as usize,
+                len: len as usize,
+            },
+        }
+    }
+}
+
+/// Validates the nodes and keys sections against `header`,
+/// passing each slot in order to `f`.
+pub(crate) fn decode_slots<F>(header: &Header, sections: &Sections, mut f: F) -> Result<()>
+where
+    F: FnMut(Slot) -> Result<()>,
+{
+    let keys = sections.require(SectionKind::Keys)?;
+    if keys.len() % header.key_align != 0 {
+        return Err(anyhow!("The keys section is broken."));
+    }
+    // The length of the nodes section is validated by `Header::parse`.
+    let mut source = sections.require(SectionKind::Nodes)?;
+    let primary_len = header.capacity - header.stash_len;
+    let mut num_occupied = 0;
+    let mut has_vacant = false;
+    for pos in 0..header.capacity {
+        let ptr = read_u64(&mut source)?;
+        let len = read_u64(&mut source)?;
+        let slot = match ptr {
+            EMPTY_SLOT => {
+                // At least one primary slot must be vacant to terminate probing.
+                has_vacant |= pos < primary_len;
+                Slot::Vacant
+            }
+            // Stash slots are never tombstones.
+            TOMBSTONE_SLOT if pos < primary_len => Slot::Tombstone,
+            TOMBSTONE_SLOT => return Err(anyhow!("The nodes section is broken.")),
+            _ => {
+                let (ptr, len) = (to_usize(ptr)?, to_usize(len)?);
+                if len > header.max_key_len
+                    || ptr % header.key_align != 0
+                    || ptr
+                        .checked_add(len)
+                        .filter(|&end| end <= keys.len())
+                        .is_none()
+                {
+                    return Err(anyhow!("The nodes section is broken."));
+                }
+                num_occupied += 1;
+                Slot::Key { ptr, len }
+            }
+        };
+        f(slot)?;
+    }
+    if num_occupied != header.num_keys || !has_vacant {
+        return Err(anyhow!("The nodes section is broken."));
+    }
+    Ok(())
+}
+
+#[inline(always)]
+pub(crate) fn read_u32(source: &mut &[u8]) -> Result<u32> {
+    let (x, rest) = u32::deserialize_from_slice(source)?;
+    *source = rest;
+    Ok(x)
+}
+
+#[inline(always)]
+pub(crate) fn read_u64(source: &mut &[u8]) -> Result<u64> {
+    let (x, rest) = u64::deserialize_from_slice(source)?;
+    *source = rest;
+    Ok(x)
+}
+
+#[inline(always)]
+pub(crate) fn to_usize(x: u64) -> Result<usize> {
+    usize::try_from(x).map_err(|_| anyhow!("The value {x} does not fit in usize."))
+}
+
+const fn align(offset: usize) -> usize {
+    offset.div_ceil(ALIGNMENT) * ALIGNMENT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<V>(v: V)
+    where
+        V: Serializable + PartialEq + std::fmt::Debug,
+    {
+        let mut dest = vec![];
+        v.serialize_to_vec(&mut dest);
+        dest.push(42);
+        let (other, rest) = V::deserialize_from_slice(&dest).unwrap();
+        assert_eq!(v, other);
+        assert_eq!(rest, &[42]);
+    }
+
+    #[test]
+    fn test_serializable() {
+        round_trip(42u8);
+        round_trip(-42i64);
+        round_trip(u128::MAX);
+        round_trip(1.5f64);
+        round_trip(true);
+        round_trip(());
+        round_trip("東京".to_string());
+        round_trip(vec![vec![1u32, 2], vec![]]);
+        round_trip((Some(3u16), None::<u32>));
+    }
+
+    #[test]
+    fn test_sections() {
+        let mut data = vec![];
+        write_sections(&mut data, &[(1, b"abc"), (7, b""), (3, b"defghijkl")]).unwrap();
+        let sections = Sections::parse(&data).unwrap();
+        assert_eq!(sections.get(SectionKind::Header), Some(&b"abc"[..]));
+        assert_eq!(sections.get_raw(7), Some(&b""[..]));
+        assert_eq!(sections.get(SectionKind::Keys), Some(&b"defghijkl"[..]));
+        assert_eq!(sections.get(SectionKind::Values), None);
+        for (_, body) in sections.iter() {
+            let offset = body.as_ptr() as usize - data.as_ptr() as usize;
+            assert_eq!(offset % ALIGNMENT, 0);
+        }
+
+        let data = replace_section(&data, SectionKind::Header, b"xy").unwrap();
+        let sections = Sections::parse(&data).unwrap();
+        assert_eq!(sections.get(SectionKind::Header), Some(&b"xy"[..]));
+        assert_eq!(sections.get(SectionKind::Keys), Some(&b"defghijkl"[..]));
+    }
+
+    #[test]
+    fn test_broken() {
+        assert!(Sections::parse(b"").is_err());
+        assert!(Sections::parse(b"SAHASH\0\0\x02\0\0\0\0\0\0\0").is_err());
+        let mut data = vec![];
+        write_sections(&mut data, &[(1, b"abc")]).unwrap();
+        assert!(Sections::parse(&data[..data.len() - 8]).is_err());
+    }
+
+    #[test]
+    fn test_broken_capacity() {
+        let data = crate::HashMap::new(&[("icdm", 0u32), ("idce", 1)])
+            .unwrap()
+            .serialize_to_vec();
+        let sections = Sections::parse(&data).unwrap();
+        let mut header = sections.get(SectionKind::Header).unwrap().to_vec();
+        header[8..16].copy_from_slice(&(1u64 << 60).to_le_bytes());
+        let data = replace_section(&data, SectionKind::Header, &header).unwrap();
+        assert!(Header::parse(&Sections::parse(&data).unwrap()).is_err());
+        assert!(crate::HashMap::<u32>::deserialize_from_slice(&data).is_err());
+        assert!(crate::HashMap::<u32>::deserialize_from(&data[..]).is_err());
+        assert!(crate::embedded::EmbeddedHashMap::<u32>::new(&data).is_err());
+
+        let data = crate::HashSet::new(&["icdm", "idce"])
+            .unwrap()
+            .serialize_to_vec();
+        let data = replace_section(&data, SectionKind::Header, &header).unwrap();
+        assert!(crate::HashSet::deserialize_from_slice(&data).is_err());
+    }
+
+    #[test]
+    fn test_broken_header_len() {
+        let data = crate::HashSet::new(&["icdm", "idce"])
+            .unwrap()
+            .serialize_to_vec();
+        let sections = Sections::parse(&data).unwrap();
+        let header = sections.get(SectionKind::Header).unwrap();
+        for len in [header.len() - 8, header.len() + 8] {
+            let mut body = header.to_vec();
+            body.resize(len, 0);
+            let data = replace_section(&data, SectionKind::Header, &body).unwrap();
+            assert!(Header::parse(&Sections::parse(&data).unwrap()).is_err());
+            assert!(crate::HashSet::deserialize_from_slice(&data).is_err());
+        }
+    }
+
+    #[test]
+    fn test_values() {
+        let values = ["a".to_string(), String::new(), "bcd".to_string()];
+        let body = serialize_values(values.iter());
+        let index = ValuesIndex::parse(&body).unwrap();
+        assert_eq!(index.len(), 3);
+        for (i, v) in values.iter().enumerate() {
+            assert_eq!(&index.get::<String>(i).unwrap(), v);
+        }
+    }
+}
@@ -1,35 +1,24 @@
 //! Simple fast hash set implementation for string kyes.
 
-use crate::{Node, Table};
+use std::io::{Read, Write};
+use std::sync::Arc;
 
-use anyhow::{anyhow, Result};
-
-#[derive(Default, Clone)]
-struct SetNode {
-    ptr: usize,
-    len: usize,
-}
-
-impl Node for SetNode {
-    fn new(ptr: usize, len: usize) -> Self {
-        Self { ptr, len }
-    }
+use crate::map::MapNode;
+use crate::serial::{self, SectionKind, Sections};
+use crate::storage::KeyStorage;
+use crate::utf16::with_utf8;
+use crate::{HashMap, HashMapBuilder, PrehashedKey, Progress, Ratio, Table};
 
-    #[inline(always)]
-    fn ptr(&self) -> usize {
-        self.ptr
-    }
+use anyhow::{anyhow, Result};
 
-    #[inline(always)]
-    fn len(&self) -> usize {
-        self.len
-    }
-}
+/// Slot of a set, which is the slot of a map without values,
+/// so that a [`HashSet`] and a `HashMap<()>` share the same layout.
+pub(crate) type SetNode = MapNode<(), usize>;
 
 /// Simple fast hash set implementation for string kyes.
 #[derive(Clone)]
 pub struct HashSet {
-    table: Table<SetNode>,
+    pub(crate) table: Table<SetNode>,
 }
 
 impl HashSet {
@@ -60,19 +49,105 @@ impl HashSet {
     where
         K: AsRef<[u8]>,
     {
-        if keys.is_empty() {
-            return Err(anyhow!("The input keys must not be empty."));
-        }
-        let table = Table::<SetNode>::build(keys);
-        let mut flags = vec![false; table.nodes.len()]; // to check duplication
-        for k in keys {
-            let pos = table.get_pos(k).unwrap();
-            if flags[pos] {
-                return Err(anyhow!("The input keys must not be duplicated."));
-            }
-            flags[pos] = true;
-        }
-        Ok(Self { table })
+        HashSetBuilder::new().build(keys)
+    }
+
+    /// Creates a new [`HashSet`] from an iterator of keys with a known length,
+    /// consuming the keys without buffering them.
+    ///
+    /// # Arguments
+    ///
+    /// - `keys`: Iterator of keys.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `keys` is empty,
+    ///  - `keys` contains duplicate keys, or
+    ///  - `keys` yields more keys than its length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let text = "icdm idce sigmod";
+    /// let keys: Vec<_> = text.split(' ').collect();
+    /// let set = HashSet::new_from_iter(keys.into_iter().map(str::to_uppercase)).unwrap();
+    /// assert!(set.contains("IDCE"));
+    /// ```
+    pub fn new_from_iter<I, K>(keys: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = K>,
+        I::IntoIter: ExactSizeIterator,
+        K: AsRef<[u8]>,
+    {
+        HashSetBuilder::new().build_from_iter(keys)
+    }
+
+    /// Creates a new [`HashSet`] from input keys,
+    /// hashing the keys and detecting duplicate keys in parallel.
+    ///
+    /// This is equivalent to [`HashSet::new`], but it speeds up
+    /// the construction from large inputs on multi-threaded machines.
+    ///
+    /// # Arguments
+    ///
+    /// - `keys`: List of keys.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `keys` is empty, or
+    ///  - `keys` contains duplicate keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let keys = vec!["icdm", "idce", "sigmod"];
+    /// let set = HashSet::new_par(&keys).unwrap();
+    /// assert!(set.contains("idce"));
+    /// assert!(!set.contains("sigir"));
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn new_par<K>(keys: &[K]) -> Result<Self>
+    where
+        K: AsRef<[u8]> + Sync,
+    {
+        HashSetBuilder::new().build_par(keys)
+    }
+
+    /// Creates a new [`HashSet`] from the keys of an [`fst::Set`].
+    ///
+    /// # Arguments
+    ///
+    /// - `fst`: Set whose keys are copied.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when `fst` is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let fst = fst::Set::from_iter(["icdm", "idce", "sigmod"]).unwrap();
+    /// let set = HashSet::from_fst_set(&fst).unwrap();
+    /// assert!(set.contains("idce"));
+    /// assert!(!set.contains("sigir"));
+    /// ```
+    #[cfg(feature = "fst")]
+    pub fn from_fst_set<D>(fst: &fst::Set<D>) -> Result<Self>
+    where
+        D: AsRef<[u8]>,
+    {
+        let keys = fst.stream().into_bytes();
+        HashSetBuilder::new().build(&keys)
     }
 
     /// Returns true if the set contains a key.
@@ -95,62 +170,1478 @@ impl HashSet {
         self.table.get(key).is_some()
     }
 
-    /// Returns the number of elements in the set.
+    /// Computes the hash value of `key` with the seed of the set,
+    /// in the same manner as [`HashMap::hash`](crate::HashMap::hash).
     ///
     /// # Examples
     ///
     /// ```
     /// use simplearrayhash::HashSet;
     ///
-    /// let keys = vec!["icdm", "idce", "sigmod"];
-    /// let set = HashSet::new(&keys).unwrap();
-    /// assert_eq!(set.len(), 3);
+    /// let set = HashSet::new(&["icdm", "idce", "sigmod"]).unwrap();
+    /// assert_eq!(set.hash("idce"), simplearrayhash::hash("idce", set.seed()));
     /// ```
     #[inline(always)]
-    pub fn len(&self) -> usize {
-        self.table.num_keys()
+    pub fn hash<K>(&self, key: K) -> u64
+    where
+        K: AsRef<[u8]>,
+    {
+        self.table.hash(key.as_ref())
     }
 
-    /// Returns true if the set contains no elements.
+    /// Returns the seed of the hash function,
+    /// with which [`hash`](crate::hash) computes the same hash values as the set.
     #[inline(always)]
-    pub fn is_empty(&self) -> bool {
-        self.len() == 0
+    pub const fn seed(&self) -> u64 {
+        self.table.seed
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Returns true if the set contains a key given in UTF-16,
+    /// transcoding it into UTF-8 in the same manner as [`HashMap::get_utf16`](crate::HashMap::get_utf16).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let set = HashSet::new(&["icdm", "情報", "sigmod"]).unwrap();
+    /// let key: Vec<u16> = "情報".encode_utf16().collect();
+    /// assert!(set.contains_utf16(&key));
+    /// assert!(!set.contains_utf16(&[0xd800]));
+    /// ```
+    #[inline(always)]
+    pub fn contains_utf16(&self, key: &[u16]) -> bool {
+        with_utf8(key, |key| self.contains(key)).unwrap_or(false)
+    }
 
-    #[test]
-    fn test_basic() {
-        let keys = vec!["icdm", "idce", "", "sigmod", "sigir", "acl"];
-        let set = HashSet::new(&keys).unwrap();
-        assert_eq!(set.len(), 6);
+    /// Returns true if the set contains a key hashed in advance,
+    /// in the same manner as [`HashMap::get_prehashed`](crate::HashMap::get_prehashed).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::{key, HashSet};
+    ///
+    /// let set = HashSet::new(&["icdm", "idce", "sigmod"]).unwrap();
+    /// assert!(set.contains_prehashed(&key!("idce")));
+    /// assert!(!set.contains_prehashed(&key!("sigir")));
+    /// ```
+    #[inline(always)]
+    pub fn contains_prehashed(&self, key: &PrehashedKey) -> bool {
+        self.table.get_pos_prehashed(key).is_some()
     }
 
-    #[test]
-    fn test_get() {
-        let keys = vec!["icdm", "idce", "", "sigmod", "sigir", "acl"];
-        let set = HashSet::new(&keys).unwrap();
-        for &k in &keys {
-            assert!(set.contains(k));
-        }
-        assert!(!set.contains("sigkdd"));
-        assert!(!set.contains("idml"));
+    /// Inserts a key into the set, returning true if the key was not present.
+    ///
+    /// The set grows when the insertion would exceed the load factor,
+    /// as configured by [`HashSetBuilder::growth`] and [`HashSetBuilder::max_capacity`].
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when `key` exceeds the limit of key lengths
+    /// (see [`HashSetBuilder::key_len_limit`]), or the set reaches its maximum capacity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let keys = vec!["icdm", "idce", "sigmod"];
+    /// let mut set = HashSet::new(&keys).unwrap();
+    /// assert_eq!(set.insert("sigir").unwrap(), true);
+    /// assert_eq!(set.insert("idce").unwrap(), false);
+    /// assert!(set.contains("sigir"));
+    /// assert_eq!(set.len(), 4);
+    /// ```
+    pub fn insert<K>(&mut self, key: K) -> Result<bool>
+    where
+        K: AsRef<[u8]>,
+    {
+        Ok(self.table.insert(key.as_ref())?.1)
     }
 
-    #[test]
-    #[should_panic]
-    fn test_empty() {
-        let keys = vec!["icdm"];
-        HashSet::new(&keys[0..0]).unwrap();
+    /// Removes a key from the set, returning true if the key was present.
+    ///
+    /// The slot of the key is left as a tombstone,
+    /// and the set is rebuilt when tombstones exceed [`HashSetBuilder::max_tombstones`].
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned in the same cases as [`HashMap::remove`],
+    /// none of which currently arises for a set, since a set converted or deserialized
+    /// from a map drops the layouts prohibiting removals.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let keys = vec!["icdm", "idce", "sigmod"];
+    /// let mut set = HashSet::new(&keys).unwrap();
+    /// assert_eq!(set.remove("idce").unwrap(), true);
+    /// assert_eq!(set.remove("idce").unwrap(), false);
+    /// assert!(!set.contains("idce"));
+    /// assert_eq!(set.len(), 2);
+    /// ```
+    pub fn remove<K>(&mut self, key: K) -> Result<bool>
+    where
+        K: AsRef<[u8]>,
+    {
+        Ok(self.table.remove(key.as_ref())?.is_some())
     }
 
-    #[test]
-    #[should_panic]
-    fn test_duplicate() {
-        let keys = vec!["icdm", "icdm"];
-        HashSet::new(&keys).unwrap();
+    /// Grows the set at once so that `additional` more keys can be inserted
+    /// without rebuilding it on the way.
+    ///
+    /// The number of slots does not exceed [`HashSetBuilder::max_capacity`].
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the number of keys would reach the maximum capacity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let keys = vec!["icdm", "idce", "sigmod"];
+    /// let mut set = HashSet::new(&keys).unwrap();
+    /// set.reserve(1000).unwrap();
+    /// let capacity = set.capacity();
+    /// for i in 0..1000 {
+    ///     set.insert(i.to_string()).unwrap();
+    /// }
+    /// assert_eq!(set.capacity(), capacity);
+    /// ```
+    pub fn reserve(&mut self, additional: usize) -> Result<()> {
+        self.table.reserve(additional)
+    }
+
+    /// Removes all the keys from the set, keeping the allocated slots and key bytes for reuse.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let keys = vec!["icdm", "idce", "sigmod"];
+    /// let mut set = HashSet::new(&keys).unwrap();
+    /// set.clear();
+    /// assert!(set.is_empty());
+    /// assert!(!set.contains("idce"));
+    /// assert!(set.insert("idce").unwrap());
+    /// ```
+    pub fn clear(&mut self) {
+        self.table.clear();
+    }
+
+    /// Returns true if the set contains all of `keys`.
+    ///
+    /// It stops at the first key not in the set, and returns true for no keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let keys = vec!["icdm", "idce", "sigmod"];
+    /// let set = HashSet::new(&keys).unwrap();
+    /// assert!(set.contains_all(["icdm", "sigmod"]));
+    /// assert!(!set.contains_all(["icdm", "sigir"]));
+    /// ```
+    pub fn contains_all<I, K>(&self, keys: I) -> bool
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<[u8]>,
+    {
+        keys.into_iter().all(|key| self.contains(key))
+    }
+
+    /// Returns true if the set contains any of `keys`.
+    ///
+    /// It stops at the first key in the set, and returns false for no keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let blocklist = HashSet::new(&["spam", "scam"]).unwrap();
+    /// assert!(blocklist.contains_any("buy this spam now".split(' ')));
+    /// assert!(!blocklist.contains_any("see you at sigmod".split(' ')));
+    /// ```
+    pub fn contains_any<I, K>(&self, keys: I) -> bool
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<[u8]>,
+    {
+        keys.into_iter().any(|key| self.contains(key))
+    }
+
+    /// Returns whether the set contains each of `keys`, in the order of `keys`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let keys = vec!["icdm", "idce", "sigmod"];
+    /// let set = HashSet::new(&keys).unwrap();
+    /// assert_eq!(
+    ///     set.contains_batch(["sigmod", "sigir", "icdm"]),
+    ///     vec![true, false, true]
+    /// );
+    /// ```
+    pub fn contains_batch<I, K>(&self, keys: I) -> Vec<bool>
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<[u8]>,
+    {
+        keys.into_iter().map(|key| self.contains(key)).collect()
+    }
+
+    /// Returns the number of keys contained in both sets.
+    ///
+    /// The keys of the smaller set are looked up in the larger one,
+    /// without materializing the intersection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let a = HashSet::new(&["icdm", "idce", "sigmod"]).unwrap();
+    /// let b = HashSet::new(&["sigmod", "sigir", "icdm", "kdd"]).unwrap();
+    /// assert_eq!(a.intersection_count(&b), 2);
+    /// ```
+    pub fn intersection_count(&self, other: &Self) -> usize {
+        let (small, large) = if self.len() <= other.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        small
+            .table
+            .iter()
+            .filter(|(key, _)| large.contains(key))
+            .count()
+    }
+
+    /// Returns the Jaccard similarity of the sets, that is, the number of keys in both sets
+    /// divided by the number of keys in either set, or 1 if both sets are empty.
+    ///
+    /// It is computed from [`HashSet::intersection_count`],
+    /// for example to detect near-duplicate documents by their sets of tokens.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let a = HashSet::new(&["icdm", "idce", "sigmod"]).unwrap();
+    /// let b = HashSet::new(&["sigmod", "sigir", "icdm", "kdd"]).unwrap();
+    /// assert_eq!(a.jaccard(&b), 2.0 / 5.0);
+    /// assert_eq!(a.jaccard(&a), 1.0);
+    /// ```
+    pub fn jaccard(&self, other: &Self) -> f64 {
+        let intersection = self.intersection_count(other);
+        let union = self.len() + other.len() - intersection;
+        if union == 0 {
+            return 1.0;
+        }
+        intersection as f64 / union as f64
+    }
+
+    /// Returns the stored bytes of the key equal to `key`.
+    ///
+    /// The returned bytes are owned by the set, so callers can replace their own copies
+    /// with references into the set for interning.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let keys = vec!["icdm", "idce", "sigmod"];
+    /// let set = HashSet::new(&keys).unwrap();
+    /// let query = String::from("idce");
+    /// assert_eq!(set.get(&query), Some(&b"idce"[..]));
+    /// assert_eq!(set.get("sigir"), None);
+    /// ```
+    #[inline(always)]
+    pub fn get<K>(&self, key: K) -> Option<&[u8]>
+    where
+        K: AsRef<[u8]>,
+    {
+        self.table.get(key).map(|nd| self.table.get_bytes(nd))
+    }
+
+    /// Creates a new set consisting of the keys satisfying `pred`.
+    ///
+    /// The packed key bytes of the kept keys are copied directly,
+    /// without collecting and rebuilding from the keys.
+    /// The new set is built with the default load factor.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let keys = vec!["icdm", "idce", "sigmod"];
+    /// let set = HashSet::new(&keys).unwrap();
+    /// let filtered = set.filter(|key| key.len() == 4);
+    /// assert_eq!(filtered.len(), 2);
+    /// assert!(filtered.contains("idce"));
+    /// assert!(!filtered.contains("sigmod"));
+    /// ```
+    pub fn filter<F>(&self, mut pred: F) -> Self
+    where
+        F: FnMut(&[u8]) -> bool,
+    {
+        let table = self.table.filter(|key, _| pred(key));
+        Self { table }
+    }
+
+    /// Returns the number of elements in the set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let keys = vec!["icdm", "idce", "sigmod"];
+    /// let set = HashSet::new(&keys).unwrap();
+    /// assert_eq!(set.len(), 3);
+    /// ```
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.table.num_keys()
+    }
+
+    /// Returns true if the set contains no elements.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of slots allocated in the set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let keys = vec!["icdm", "idce", "sigmod"];
+    /// let set = HashSet::new(&keys).unwrap();
+    /// assert_eq!(set.capacity(), 4);
+    /// ```
+    #[inline(always)]
+    pub fn capacity(&self) -> usize {
+        self.table.capacity()
+    }
+
+    /// Returns the ratio of the number of elements to the number of slots.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let keys = vec!["icdm", "idce", "sigmod"];
+    /// let set = HashSet::new(&keys).unwrap();
+    /// assert_eq!(set.load_factor(), 0.75);
+    /// ```
+    #[inline(always)]
+    pub fn load_factor(&self) -> f64 {
+        self.table.load_factor()
+    }
+
+    /// Returns the statistics of the lookups recorded since the set was built
+    /// or the statistics were last reset.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let keys = vec!["icdm", "idce", "sigmod"];
+    /// let set = HashSet::new(&keys).unwrap();
+    /// assert!(set.contains("idce") && !set.contains("sigir"));
+    /// let stats = set.probe_stats();
+    /// assert_eq!((stats.hits, stats.misses), (1, 1));
+    /// assert!(stats.probes >= 2);
+    /// ```
+    #[cfg(feature = "instrument")]
+    pub fn probe_stats(&self) -> crate::ProbeStats {
+        self.table.probe_counters.snapshot()
+    }
+
+    /// Resets the statistics of the recorded lookups.
+    #[cfg(feature = "instrument")]
+    pub fn reset_probe_stats(&self) {
+        self.table.probe_counters.reset();
+    }
+
+    /// Rebuilds the set so that its load factor does not exceed the fraction `num / den`,
+    /// repacking the key bytes in the new slot order.
+    ///
+    /// See [`HashMap::optimize_to`].
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when `num / den` is not in `(0, 1]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let keys = vec!["icdm", "idce", "sigmod"];
+    /// let mut set = HashSet::new(&keys).unwrap();
+    /// set.optimize_to(1, 2).unwrap();
+    /// assert_eq!(set.capacity(), 8);
+    /// assert!(set.contains("idce"));
+    /// ```
+    pub fn optimize_to(&mut self, num: u32, den: u32) -> Result<()> {
+        self.table.optimize(Ratio::new(num as u64, den as u64))
+    }
+
+    /// Rebuilds the set like [`HashSet::optimize_to`] with the maximum load factor
+    /// given as a floating-point value, which is rounded to six decimal places.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when `load_factor` is not in `(0, 1]`.
+    pub fn optimize(&mut self, load_factor: f64) -> Result<()> {
+        self.table.optimize(Ratio::from_f64(load_factor))
+    }
+
+    /// Rebuilds the set with a new seed of the hash function,
+    /// keeping the contents and the capacity.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let keys = vec!["icdm", "idce", "sigmod"];
+    /// let mut set = HashSet::new(&keys).unwrap();
+    /// set.rehash(42);
+    /// assert!(set.contains("idce"));
+    /// ```
+    pub fn rehash(&mut self, seed: u64) {
+        self.table.rehash(seed);
+    }
+
+    /// Creates an [`fst::Set`] with the same keys,
+    /// so that ordered and prefix queries can be served by the fst
+    /// while membership queries are served by this set.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when building the fst fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let set = HashSet::new(&["icdm", "idce", "sigmod"]).unwrap();
+    /// let fst = set.to_fst_set().unwrap();
+    /// assert!(fst.contains("idce"));
+    /// ```
+    #[cfg(feature = "fst")]
+    pub fn to_fst_set(&self) -> Result<fst::Set<Vec<u8>>> {
+        let mut builder = fst::SetBuilder::memory();
+        for (key, _) in self.table.sorted_entries() {
+            builder.insert(key)?;
+        }
+        Ok(builder.into_set())
+    }
+
+    /// Serializes the set into a byte vector in the [sectioned format](crate::serial).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let keys = vec!["icdm", "idce", "sigmod"];
+    /// let set = HashSet::new(&keys).unwrap();
+    /// let data = set.serialize_to_vec();
+    /// let other = HashSet::deserialize_from_slice(&data).unwrap();
+    /// assert!(other.contains("idce"));
+    /// ```
+    pub fn serialize_to_vec(&self) -> Vec<u8> {
+        let mut dest = vec![];
+        // Writing into a vector never fails.
+        self.serialize_into(&mut dest).unwrap();
+        dest
+    }
+
+    /// Serializes the set into `wtr` in the [sectioned format](crate::serial).
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when writing fails.
+    pub fn serialize_into<W>(&self, wtr: W) -> Result<()>
+    where
+        W: Write,
+    {
+        let (header, nodes) = self.table.serialize_sections();
+        serial::write_sections(
+            wtr,
+            &[
+                (SectionKind::Header as u32, &header),
+                (SectionKind::Nodes as u32, &nodes),
+                (SectionKind::Keys as u32, &self.table.bytes),
+            ],
+        )
+    }
+
+    /// Deserializes a set from `source` in the [sectioned format](crate::serial).
+    ///
+    /// The values section, if any, is ignored,
+    /// so the keys of a serialized map can be loaded as a set.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when `source` is broken.
+    pub fn deserialize_from_slice(source: &[u8]) -> Result<Self> {
+        Self::from_sections(&Sections::parse(source)?)
+    }
+
+    /// Deserializes a set from the bytes of `storage` in the [sectioned format](crate::serial),
+    /// borrowing the slots and the key bytes from `storage` instead of copying them.
+    ///
+    /// The slots are borrowed if their serialized records are laid out as in memory,
+    /// that is, on 64-bit little-endian targets if the nodes section is 8-byte aligned
+    /// in memory, and are decoded into memory owned by the set otherwise
+    /// (see the [`storage`](crate::storage) module).
+    /// Loading a set from a memory-mapped file thus allocates nothing proportional to its size,
+    /// although the slots are still validated.
+    /// A modification of the set copies the slots and key bytes into its own memory first.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when the data is broken.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let data = HashSet::new(&["icdm", "idce", "sigmod"]).unwrap().serialize_to_vec();
+    /// let data: std::sync::Arc<[u8]> = data.into();
+    ///
+    /// let mut set = HashSet::deserialize_from_storage(data).unwrap();
+    /// assert!(set.shares_storage());
+    /// assert!(set.contains("idce"));
+    ///
+    /// set.insert("sigir").unwrap();
+    /// assert!(!set.shares_storage());
+    /// assert!(set.contains("sigmod"));
+    /// ```
+    pub fn deserialize_from_storage<S>(storage: S) -> Result<Self>
+    where
+        S: KeyStorage,
+    {
+        let storage: Arc<dyn KeyStorage> = Arc::new(storage);
+        let sections = Sections::parse(storage.as_key_bytes())?;
+        let table = Table::<SetNode>::deserialize_sections_shared(&sections, &storage)?;
+        Ok(Self::from_table(table))
+    }
+
+    /// Returns true if the slots or the key bytes are borrowed from a shared backend
+    /// (see [`HashSet::deserialize_from_storage`]).
+    #[inline(always)]
+    pub const fn shares_storage(&self) -> bool {
+        self.table.nodes.is_shared() || self.table.bytes.is_shared()
+    }
+
+    /// Serializes the set into `buf` in the [sectioned format](crate::serial),
+    /// such as a [`bytes::BytesMut`] holding a frame of a network protocol.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when `buf` has no room for the serialized set.
+    #[cfg(feature = "bytes")]
+    pub fn serialize_into_buf<B>(&self, buf: &mut B) -> Result<()>
+    where
+        B: bytes::BufMut,
+    {
+        self.serialize_into(bytes::BufMut::writer(buf))
+    }
+
+    /// Deserializes a set at the front of `buf` in the [sectioned format](crate::serial),
+    /// advancing `buf` past it so that the following data can be read.
+    ///
+    /// If `buf` is [`bytes::Bytes`], the sections are read without copying the underlying
+    /// buffer, and the slots and key bytes are borrowed from it
+    /// as in [`HashSet::deserialize_from_storage`].
+    /// A modification of the set copies them into its own memory first.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when `buf` is broken or truncated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bytes::BytesMut;
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let set = HashSet::new(&["icdm", "idce", "sigmod"]).unwrap();
+    /// let mut buf = BytesMut::new();
+    /// set.serialize_into_buf(&mut buf).unwrap();
+    /// let other = HashSet::deserialize_from_buf(&mut buf.freeze()).unwrap();
+    /// assert!(other.contains("idce"));
+    /// ```
+    #[cfg(feature = "bytes")]
+    pub fn deserialize_from_buf<B>(buf: &mut B) -> Result<Self>
+    where
+        B: bytes::Buf,
+    {
+        serial::read_buf(buf, |sections, storage| {
+            let table = Table::<SetNode>::deserialize_sections_shared(sections, storage)?;
+            Ok(Self::from_table(table))
+        })
+    }
+
+    /// Decodes a set from parsed sections.
+    fn from_sections(sections: &Sections) -> Result<Self> {
+        let table = Table::<SetNode>::deserialize_sections(sections)?;
+        Ok(Self::from_table(table))
+    }
+
+    /// Creates a set from `table`, which may come from a map with the layouts a set does not
+    /// offer (see [`HashSetBuilder`]), dropping them so that keys can be inserted and removed.
+    fn from_table(mut table: Table<SetNode>) -> Self {
+        table.drop_indexes();
+        Self { table }
+    }
+
+    /// Deserializes a set from `rdr` in the [sectioned format](crate::serial).
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when reading fails or the data is broken.
+    pub fn deserialize_from<R>(mut rdr: R) -> Result<Self>
+    where
+        R: Read,
+    {
+        let mut source = vec![];
+        rdr.read_to_end(&mut source)?;
+        Self::deserialize_from_slice(&source)
+    }
+
+    /// Serializes the set into the asynchronous writer `wtr`
+    /// in the [sectioned format](crate::serial).
+    ///
+    /// The set is serialized in memory when this is called,
+    /// and the returned future writes it without blocking the runtime thread on slow writes.
+    /// Like [`Self::serialize_into`], `wtr` is not flushed.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when writing fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// use simplearrayhash::HashSet;
+    ///
+    /// let set = HashSet::new(&["icdm", "idce", "sigmod"]).unwrap();
+    /// let mut data = vec![];
+    /// set.serialize_into_async(&mut data).await.unwrap();
+    /// let other = HashSet::deserialize_from_async(data.as_slice()).await.unwrap();
+    /// assert!(other.contains("idce"));
+    /// # }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub fn serialize_into_async<W>(
+        &self,
+        mut wtr: W,
+    ) -> impl std::future::Future<Output = Result<()>>
+    where
+        W: tokio::io::AsyncWrite + Unpin,
+    {
+        use tokio::io::AsyncWriteExt;
+        // The future does not borrow `self`, so it is `Send` whenever `W` is.
+        let data = self.serialize_to_vec();
+        async move {
+            wtr.write_all(&data).await?;
+            Ok(())
+        }
+    }
+
+    /// Deserializes a set from the asynchronous reader `rdr`
+    /// in the [sectioned format](crate::serial).
+    ///
+    /// The data is read without blocking the runtime thread and then decoded in memory.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when reading fails or the data is broken.
+    #[cfg(feature = "tokio")]
+    pub async fn deserialize_from_async<R>(mut rdr: R) -> Result<Self>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+    {
+        use tokio::io::AsyncReadExt;
+        let mut source = vec![];
+        rdr.read_to_end(&mut source).await?;
+        Self::deserialize_from_slice(&source)
+    }
+}
+
+/// Builder of [`HashSet`] with configurable options.
+///
+/// The options are those of [`HashMapBuilder`] that keep [`HashSet::insert`] and
+/// [`HashSet::remove`] available, and they behave in the same manner as for maps.
+/// Sorting keys, sharing suffixes, and the fuzzy and suffix indexes are not offered.
+///
+/// # Examples
+///
+/// ```
+/// use simplearrayhash::HashSetBuilder;
+///
+/// let keys = vec!["icdm", "idce", "sigmod"];
+/// let set = HashSetBuilder::new()
+///     .load_factor(0.5)
+///     .seed(42)
+///     .build(&keys)
+///     .unwrap();
+/// assert!(set.contains("idce"));
+/// assert_eq!(set.capacity(), 8);
+/// ```
+#[derive(Clone, Debug)]
+pub struct HashSetBuilder {
+    map: HashMapBuilder,
+}
+
+impl HashSetBuilder {
+    /// Creates a new builder with the default options.
+    pub const fn new() -> Self {
+        Self {
+            map: HashMapBuilder::new(),
+        }
+    }
+
+    /// Specifies the maximum ratio of the number of keys to the number of slots
+    /// as the fraction `num / den`, which must be in `(0, 1]` (default: `4 / 5`).
+    ///
+    /// See [`HashMapBuilder::max_load`].
+    pub fn max_load(mut self, num: u32, den: u32) -> Self {
+        self.map = self.map.max_load(num, den);
+        self
+    }
+
+    /// Specifies the maximum load like [`Self::max_load`] as a floating-point value
+    /// in `(0, 1]`, which is rounded to six decimal places (default: `0.8`).
+    pub fn load_factor(mut self, load_factor: f64) -> Self {
+        self.map = self.map.load_factor(load_factor);
+        self
+    }
+
+    /// Specifies the seed of the hash function (default: `0`).
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.map = self.map.seed(seed);
+        self
+    }
+
+    /// Specifies whether to align each stored key to 16 bytes
+    /// and zero-pad the key bytes (default: `false`).
+    ///
+    /// See [`HashMapBuilder::align_keys`].
+    pub fn align_keys(mut self, yes: bool) -> Self {
+        self.map = self.map.align_keys(yes);
+        self
+    }
+
+    /// Specifies whether to make the slots and key bytes depend only on the set of keys,
+    /// not on their input order (default: `false`).
+    ///
+    /// See [`HashMapBuilder::canonical`].
+    pub fn canonical(mut self, yes: bool) -> Self {
+        self.map = self.map.canonical(yes);
+        self
+    }
+
+    /// Specifies whether to advise the kernel to back the slots and key bytes
+    /// with transparent huge pages on Linux (default: `false`).
+    ///
+    /// See [`HashMapBuilder::huge_pages`].
+    #[cfg(feature = "hugepages")]
+    pub fn huge_pages(mut self, yes: bool) -> Self {
+        self.map = self.map.huge_pages(yes);
+        self
+    }
+
+    /// Specifies whether to compare keys in constant time while probing (default: `false`).
+    ///
+    /// See [`HashMapBuilder::constant_time`].
+    pub fn constant_time(mut self, yes: bool) -> Self {
+        self.map = self.map.constant_time(yes);
+        self
+    }
+
+    /// Specifies the multiplier of the number of slots when the set grows on insertion
+    /// as the fraction `num / den`, which must be greater than 1 (default: `2 / 1`).
+    ///
+    /// See [`HashMapBuilder::growth`].
+    pub fn growth(mut self, num: u32, den: u32) -> Self {
+        self.map = self.map.growth(num, den);
+        self
+    }
+
+    /// Specifies the growth like [`Self::growth`] as a floating-point value
+    /// greater than 1, which is rounded to six decimal places (default: `2.0`).
+    pub fn growth_factor(mut self, growth_factor: f64) -> Self {
+        self.map = self.map.growth_factor(growth_factor);
+        self
+    }
+
+    /// Specifies the maximum number of slots the set grows to on insertion
+    /// (default: unlimited).
+    ///
+    /// See [`HashMapBuilder::max_capacity`].
+    pub fn max_capacity(mut self, max_capacity: usize) -> Self {
+        self.map = self.map.max_capacity(max_capacity);
+        self
+    }
+
+    /// Specifies the maximum ratio of tombstones to slots as the fraction `num / den`,
+    /// which must be in `(0, 1]` (default: `1 / 5`).
+    ///
+    /// See [`HashMapBuilder::max_tombstones`].
+    pub fn max_tombstones(mut self, num: u32, den: u32) -> Self {
+        self.map = self.map.max_tombstones(num, den);
+        self
+    }
+
+    /// Specifies the maximum ratio of tombstones like [`Self::max_tombstones`]
+    /// as a floating-point value in `(0, 1]`,
+    /// which is rounded to six decimal places (default: `0.2`).
+    pub fn tombstone_ratio(mut self, tombstone_ratio: f64) -> Self {
+        self.map = self.map.tombstone_ratio(tombstone_ratio);
+        self
+    }
+
+    /// Specifies the number of stash slots and the maximum probe length (default: no stash).
+    ///
+    /// See [`HashMapBuilder::stash`].
+    pub fn stash(mut self, stash_len: usize, max_probe: usize) -> Self {
+        self.map = self.map.stash(stash_len, max_probe);
+        self
+    }
+
+    /// Specifies the maximum length of keys in bytes (default: unlimited).
+    ///
+    /// See [`HashMapBuilder::key_len_limit`].
+    pub fn key_len_limit(mut self, limit: usize) -> Self {
+        self.map = self.map.key_len_limit(limit);
+        self
+    }
+
+    /// Specifies the number of recent lookups cached in front of the probe path
+    /// (default: `0`, no cache).
+    ///
+    /// See [`HashMapBuilder::lookup_cache`].
+    pub fn lookup_cache(mut self, capacity: usize) -> Self {
+        self.map = self.map.lookup_cache(capacity);
+        self
+    }
+
+    /// Specifies a callback receiving the progress of building.
+    ///
+    /// See [`HashMapBuilder::progress`].
+    pub fn progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(Progress) + Send + Sync + 'static,
+    {
+        self.map = self.map.progress(callback);
+        self
+    }
+
+    /// Builds a new [`HashSet`] from input keys.
+    ///
+    /// # Arguments
+    ///
+    /// - `keys`: List of keys.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `keys` is empty,
+    ///  - `keys` contains duplicate keys, or
+    ///  - the options are invalid.
+    pub fn build<K>(&self, keys: &[K]) -> Result<HashSet>
+    where
+        K: AsRef<[u8]>,
+    {
+        let num_bytes = keys.iter().map(|k| k.as_ref().len()).sum();
+        self.build_keys(keys.iter(), num_bytes)
+    }
+
+    /// Builds a new [`HashSet`] from an iterator of keys with a known length,
+    /// consuming the keys without buffering them.
+    ///
+    /// # Arguments
+    ///
+    /// - `keys`: Iterator of keys.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `keys` is empty,
+    ///  - `keys` contains duplicate keys,
+    ///  - `keys` yields more keys than its length, or
+    ///  - the options are invalid.
+    pub fn build_from_iter<I, K>(&self, keys: I) -> Result<HashSet>
+    where
+        I: IntoIterator<Item = K>,
+        I::IntoIter: ExactSizeIterator,
+        K: AsRef<[u8]>,
+    {
+        self.build_keys(keys.into_iter(), 0)
+    }
+
+    /// Builds a new [`HashSet`] from input keys,
+    /// hashing the keys and detecting duplicate keys in parallel.
+    ///
+    /// # Arguments
+    ///
+    /// - `keys`: List of keys.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `keys` is empty,
+    ///  - `keys` contains duplicate keys, or
+    ///  - the options are invalid.
+    #[cfg(feature = "rayon")]
+    pub fn build_par<K>(&self, keys: &[K]) -> Result<HashSet>
+    where
+        K: AsRef<[u8]> + Sync,
+    {
+        if keys.is_empty() {
+            return Err(anyhow!("The input keys must not be empty."));
+        }
+        let table = self.map.build_table_par(
+            keys,
+            |k| k.as_ref(),
+            std::iter::repeat_n((), keys.len()),
+            |_: &mut SetNode, ()| Ok(()),
+        )?;
+        Ok(HashSet { table })
+    }
+
+    /// Builds a new [`HashSet`] from an iterator of keys
+    /// that have `num_bytes` bytes in total if known, or 0.
+    fn build_keys<I, K>(&self, keys: I, num_bytes: usize) -> Result<HashSet>
+    where
+        I: ExactSizeIterator<Item = K>,
+        K: AsRef<[u8]>,
+    {
+        if keys.len() == 0 {
+            return Err(anyhow!("The input keys must not be empty."));
+        }
+        let records = keys.map(|k| (k, ()));
+        let table = self
+            .map
+            .build_table(records, num_bytes, |_: &mut SetNode, ()| Ok(()))?;
+        Ok(HashSet { table })
+    }
+}
+
+impl Default for HashSetBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Converts a valueless map into a set without copying,
+/// since they share the same layout.
+///
+/// If the map sorts keys, shares suffixes, or has the fuzzy or suffix index,
+/// the set is rebuilt without them, as a set does not offer them (see [`HashSetBuilder`]).
+///
+/// # Examples
+///
+/// ```
+/// use simplearrayhash::{HashMap, HashSet};
+///
+/// let map = HashMap::new(&[("icdm", ()), ("idce", ())]).unwrap();
+/// let set = HashSet::from(map);
+/// assert!(set.contains("idce"));
+/// ```
+impl From<HashMap<()>> for HashSet {
+    fn from(map: HashMap<()>) -> Self {
+        Self::from_table(map.table)
+    }
+}
+
+/// Converts a set into a valueless map without copying,
+/// since they share the same layout.
+///
+/// # Examples
+///
+/// ```
+/// use simplearrayhash::{HashMap, HashSet};
+///
+/// let set = HashSet::new(&["icdm", "idce"]).unwrap();
+/// let map = HashMap::from(set);
+/// assert_eq!(map.get("idce"), Some(&()));
+/// ```
+impl From<HashSet> for HashMap<()> {
+    fn from(set: HashSet) -> Self {
+        Self { table: set.table }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_basic() {
+        let keys = vec!["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let set = HashSet::new(&keys).unwrap();
+        assert_eq!(set.len(), 6);
+    }
+
+    #[test]
+    fn test_get() {
+        let keys = vec!["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let set = HashSet::new(&keys).unwrap();
+        for &k in &keys {
+            assert!(set.contains(k));
+        }
+        assert!(!set.contains("sigkdd"));
+        assert!(!set.contains("idml"));
+    }
+
+    #[test]
+    fn test_new_from_iter() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let set = HashSet::new_from_iter(keys.iter().map(|k| k.as_bytes().to_vec())).unwrap();
+        assert_eq!(set.len(), 6);
+        for &k in &keys {
+            assert!(set.contains(k));
+        }
+        assert!(!set.contains("sigkdd"));
+        assert!(HashSet::new_from_iter(["icdm", "icdm"]).is_err());
+        assert!(HashSet::new_from_iter(Vec::<&str>::new()).is_err());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_new_par() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let set = HashSet::new_par(&keys).unwrap();
+        assert_eq!(set.len(), 6);
+        for &k in &keys {
+            assert!(set.contains(k));
+        }
+        assert!(!set.contains("sigkdd"));
+        assert!(HashSet::new_par(&["icdm", "icdm"]).is_err());
+        assert!(HashSet::new_par::<&str>(&[]).is_err());
+    }
+
+    #[cfg(feature = "fst")]
+    #[test]
+    fn test_fst_set() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let set = HashSet::new(&keys).unwrap();
+        let fst = set.to_fst_set().unwrap();
+        assert_eq!(fst.len(), keys.len());
+        for k in keys {
+            assert!(fst.contains(k));
+        }
+        let other = HashSet::from_fst_set(&fst).unwrap();
+        assert_eq!(other.len(), keys.len());
+        for k in keys {
+            assert!(other.contains(k));
+        }
+        assert!(!other.contains("sigkdd"));
+        assert!(HashSet::from_fst_set(&fst::Set::default()).is_err());
+    }
+
+    #[test]
+    fn test_contains_all_any() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let set = HashSet::new(&keys).unwrap();
+        assert!(set.contains_all(keys));
+        assert!(set.contains_all(["", "acl"]));
+        assert!(!set.contains_all(["acl", "sigkdd"]));
+        assert!(set.contains_all(Vec::<&str>::new()));
+        assert!(set.contains_any(["sigkdd", "sigir"]));
+        assert!(!set.contains_any(["sigkdd", "www"]));
+        assert!(!set.contains_any(Vec::<&str>::new()));
+
+        // Short-circuits at the first decisive key.
+        let mut visited = 0;
+        assert!(set.contains_any(keys.iter().inspect(|_| visited += 1)));
+        assert_eq!(visited, 1);
+        let mut visited = 0;
+        assert!(!set.contains_all(["sigkdd", "icdm"].iter().inspect(|_| visited += 1)));
+        assert_eq!(visited, 1);
+    }
+
+    #[test]
+    fn test_contains_batch() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let set = HashSet::new(&keys).unwrap();
+        assert_eq!(set.contains_batch(keys), vec![true; 6]);
+        assert_eq!(
+            set.contains_batch(["acl", "sigkdd", "", "www"]),
+            vec![true, false, true, false]
+        );
+        assert!(set.contains_batch(Vec::<&str>::new()).is_empty());
+    }
+
+    #[test]
+    fn test_similarity() {
+        let a = HashSet::new(&["icdm", "idce", "", "sigmod"]).unwrap();
+        let b = HashSet::new(&["sigmod", "", "sigir", "acl", "kdd", "icdm"]).unwrap();
+        assert_eq!(a.intersection_count(&b), 3);
+        assert_eq!(b.intersection_count(&a), 3);
+        assert_eq!(a.jaccard(&b), 3.0 / 7.0);
+        assert_eq!(b.jaccard(&a), 3.0 / 7.0);
+        assert_eq!(a.jaccard(&a), 1.0);
+
+        let mut c = HashSet::new(&["acl"]).unwrap();
+        assert_eq!(a.intersection_count(&c), 0);
+        assert_eq!(a.jaccard(&c), 0.0);
+        c.remove("acl").unwrap();
+        assert_eq!(a.jaccard(&c), 0.0);
+        assert_eq!(c.jaccard(&c), 1.0);
+
+        let d = HashSetBuilder::new()
+            .seed(42)
+            .build(&["kdd", "acl"])
+            .unwrap();
+        assert_eq!(b.intersection_count(&d), 2);
+    }
+
+    #[test]
+    fn test_insert_remove() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let mut set = HashSet::new(&keys[..1]).unwrap();
+        for &k in &keys[1..] {
+            assert!(set.insert(k).unwrap());
+        }
+        for &k in &keys {
+            assert!(!set.insert(k).unwrap());
+            assert!(set.contains(k));
+        }
+        assert!(!set.contains("sigkdd"));
+        assert_eq!(set.len(), 6);
+        assert!(set.load_factor() <= 0.8);
+
+        for &k in &keys {
+            assert!(set.remove(k).unwrap());
+            assert!(!set.remove(k).unwrap());
+            assert!(!set.contains(k));
+        }
+        assert!(set.is_empty());
+        assert!(set.insert("icdm").unwrap());
+        assert!(set.contains("icdm"));
+    }
+
+    #[test]
+    fn test_bytes_capacity() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let set = HashSet::new(&keys).unwrap();
+        assert_eq!(set.table.bytes.len(), 22);
+        assert_eq!(set.table.bytes.capacity(), 22);
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn test_deserialize_from_buf() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let set = HashSet::new(&keys).unwrap();
+        let mut buf = bytes::BytesMut::new();
+        set.serialize_into_buf(&mut buf).unwrap();
+        let mut buf = buf.freeze();
+        let frame = buf.clone();
+        let mut other = HashSet::deserialize_from_buf(&mut buf).unwrap();
+        assert!(other.table.bytes.is_shared());
+        let keys_ptr = other.table.bytes.as_ptr();
+        assert!(frame.as_ptr_range().contains(&keys_ptr));
+        if cfg!(not(feature = "safe")) {
+            let nodes_ptr = other.table.nodes.as_ptr().cast::<u8>();
+            assert!(frame.as_ptr_range().contains(&nodes_ptr));
+        }
+        assert!(keys.iter().all(|k| other.contains(k)));
+
+        assert!(other.insert("kdd").unwrap());
+        assert!(!other.shares_storage());
+        assert!(keys.iter().all(|k| other.contains(k)));
+    }
+
+    /// Storage whose bytes start one byte into `self.0`, misaligning the sections.
+    struct Misaligned(Vec<u8>);
+
+    impl AsRef<[u8]> for Misaligned {
+        fn as_ref(&self) -> &[u8] {
+            &self.0[1..]
+        }
+    }
+
+    #[test]
+    fn test_deserialize_from_storage() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let mut set = HashSet::new(&keys).unwrap();
+        assert!(set.remove("sigir").unwrap());
+        let data: Arc<[u8]> = set.serialize_to_vec().into();
+
+        let mut other = HashSet::deserialize_from_storage(Arc::clone(&data)).unwrap();
+        assert!(other.shares_storage());
+        assert!(other.table.bytes.is_shared());
+        assert_eq!(other.table.nodes.is_shared(), cfg!(not(feature = "safe")));
+        if other.table.nodes.is_shared() {
+            let nodes_ptr = other.table.nodes.as_ptr().cast::<u8>();
+            assert!(data.as_ptr_range().contains(&nodes_ptr));
+        }
+        assert_eq!(other.len(), 5);
+        assert_eq!(other.table.num_tombstones, set.table.num_tombstones);
+        assert!(!other.contains("sigir"));
+        for &k in &keys[..4] {
+            assert!(other.contains(k));
+        }
+        assert_eq!(other.serialize_to_vec(), &data[..]);
+
+        assert!(other.remove("icdm").unwrap());
+        assert!(!other.table.nodes.is_shared());
+        assert!(!other.contains("icdm"));
+        assert!(other.contains("idce"));
+        assert!(HashSet::deserialize_from_storage(Arc::clone(&data))
+            .unwrap()
+            .contains("icdm"));
+
+        // Misaligned slots are decoded instead of borrowed.
+        let mut bytes = vec![0];
+        bytes.extend_from_slice(&data);
+        let other = HashSet::deserialize_from_storage(Misaligned(bytes)).unwrap();
+        assert!(!other.table.nodes.is_shared());
+        assert!(other.table.bytes.is_shared());
+        assert!(other.contains("icdm"));
+
+        assert!(HashSet::deserialize_from_storage(data[..data.len() - 1].to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_from_indexed_map() {
+        let records = [("icdm", ()), ("idce", ()), ("cdm", ()), ("sigmod", ())];
+        let maps = [
+            HashMapBuilder::new().sort_keys(true).build(&records),
+            HashMapBuilder::new().share_suffixes(true).build(&records),
+            HashMapBuilder::new().fuzzy_index(true).build(&records),
+            HashMapBuilder::new().suffix_index(true).build(&records),
+        ];
+        for map in maps {
+            let map = map.unwrap();
+            let data = map.serialize_to_vec();
+            let sets = [
+                HashSet::from(map),
+                HashSet::deserialize_from_slice(&data).unwrap(),
+            ];
+            for mut set in sets {
+                assert!(set.insert("sigir").unwrap());
+                assert!(set.remove("icdm").unwrap());
+                assert!(set.contains("cdm") && set.contains("sigir"));
+                assert!(!set.contains("icdm"));
+                assert_eq!(set.len(), 4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_valueless_map() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let set = HashSet::new(&keys).unwrap();
+        let map = HashMap::from(set.clone());
+        for k in keys {
+            assert_eq!(map.get(k), Some(&()));
+        }
+
+        let data = map.serialize_to_vec();
+        assert_eq!(data, set.serialize_to_vec());
+        let other = HashMap::<()>::deserialize_from_slice(&data).unwrap();
+        assert_eq!(other.len(), keys.len());
+        let other = HashSet::from(other);
+        assert!(keys.iter().all(|k| other.contains(k)));
+    }
+
+    #[test]
+    fn test_slot_size() {
+        assert_eq!(
+            std::mem::size_of::<SetNode>(),
+            2 * std::mem::size_of::<usize>()
+        );
+    }
+
+    #[test]
+    fn test_get_canonical() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let set = HashSet::new(&keys).unwrap();
+        let range = set.table.bytes.as_ptr_range();
+        for &k in &keys {
+            let stored = set.get(k).unwrap();
+            assert_eq!(stored, k.as_bytes());
+            assert!(range.start <= stored.as_ptr() && stored.as_ptr() <= range.end);
+        }
+        assert_eq!(set.get("sigkdd"), None);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let set = HashSet::new(&keys).unwrap();
+        assert!(set.capacity().is_power_of_two());
+        assert!(set.capacity() >= set.len());
+        assert_eq!(set.load_factor(), set.len() as f64 / set.capacity() as f64);
+        assert!(!crate::DEFAULT_LOAD_FACTOR.is_exceeded(set.len(), set.capacity()));
+    }
+
+    #[test]
+    fn test_optimize() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let mut set = HashSet::new(&keys).unwrap();
+        set.optimize(0.1).unwrap();
+        assert_eq!(set.capacity(), 64);
+        for &k in &keys {
+            assert!(set.contains(k));
+        }
+        assert!(!set.contains("sigkdd"));
+        set.optimize_to(1, 1).unwrap();
+        assert_eq!(set.capacity(), 8);
+        assert!(set.contains("sigmod"));
+        assert!(set.optimize_to(0, 1).is_err());
+    }
+
+    #[test]
+    fn test_rehash() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let mut set = HashSet::new(&keys).unwrap();
+        for seed in 1..10 {
+            set.rehash(seed);
+            for &k in &keys {
+                assert!(set.contains(k));
+            }
+            assert!(!set.contains("sigkdd"));
+        }
+    }
+
+    #[test]
+    fn test_filter() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let set = HashSet::new(&keys).unwrap();
+        let filtered = set.filter(|key| key.starts_with(b"i") || key.is_empty());
+        assert_eq!(filtered.len(), 3);
+        for k in ["icdm", "idce", ""] {
+            assert!(filtered.contains(k));
+        }
+        for k in ["sigmod", "sigir", "acl"] {
+            assert!(!filtered.contains(k));
+        }
+
+        let filtered = set.filter(|_| false);
+        assert!(filtered.is_empty());
+        assert!(!filtered.contains(""));
+    }
+
+    #[test]
+    fn test_serialize() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let set = HashSet::new(&keys).unwrap();
+        let data = set.serialize_to_vec();
+        let other = HashSet::deserialize_from(data.as_slice()).unwrap();
+        assert_eq!(other.len(), set.len());
+        assert_eq!(other.capacity(), set.capacity());
+        for &k in &keys {
+            assert!(other.contains(k));
+        }
+        assert!(!other.contains("sigkdd"));
+        assert!(HashSet::deserialize_from_slice(&data[..data.len() - 1]).is_err());
+
+        let records: Vec<_> = keys.iter().map(|&k| (k, 0u8)).collect();
+        let data = crate::HashMap::new(&records).unwrap().serialize_to_vec();
+        let other = HashSet::deserialize_from_slice(&data).unwrap();
+        for &k in &keys {
+            assert!(other.contains(k));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty() {
+        let keys = ["icdm"];
+        HashSet::new(&keys[0..0]).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_duplicate() {
+        let keys = vec!["icdm", "icdm"];
+        HashSet::new(&keys).unwrap();
+    }
+
+    #[test]
+    fn test_builder() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let builder = HashSetBuilder::new()
+            .max_load(1, 2)
+            .seed(7)
+            .canonical(true)
+            .growth(3, 2)
+            .max_tombstones(1, 4);
+        let mut set = builder.build(&keys).unwrap();
+        assert_eq!(set.capacity(), 16);
+        for &k in &keys {
+            assert!(set.contains(k));
+        }
+        let other = builder.build_from_iter(keys.iter().rev()).unwrap();
+        assert_eq!(other.serialize_to_vec(), set.serialize_to_vec());
+
+        let map = crate::HashMapBuilder::new()
+            .max_load(1, 2)
+            .seed(7)
+            .build(&keys.map(|k| (k, ())))
+            .unwrap();
+        assert_eq!(map.hash("sigmod"), set.table.hash(b"sigmod"));
+
+        for i in 0..4 {
+            assert!(set.insert(i.to_string()).unwrap());
+        }
+        assert_eq!(set.capacity(), 24);
+
+        let set = HashSetBuilder::new().stash(2, 1).build(&keys).unwrap();
+        for &k in &keys {
+            assert!(set.contains(k));
+        }
+        assert!(HashSetBuilder::new().load_factor(1.5).build(&keys).is_err());
+        assert!(HashSetBuilder::new().build::<&str>(&[]).is_err());
+
+        let mut set = HashSetBuilder::new()
+            .max_capacity(8)
+            .key_len_limit(6)
+            .build(&keys)
+            .unwrap();
+        assert!(set.insert("sigkdd").unwrap());
+        let err = set.insert("sigmetrics").unwrap_err();
+        assert!(err.is::<crate::KeyTooLong>());
+        assert!(!set.contains("sigmetrics"));
+        assert!(set.reserve(8).is_err());
+        assert_eq!(set.capacity(), 8);
+        assert!(HashSetBuilder::new().key_len_limit(4).build(&keys).is_err());
     }
 }
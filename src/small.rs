@@ -0,0 +1,230 @@
+//! Simple fast hash map implementation with fixed-size inline storage.
+
+use crate::{hash_key, DEFAULT_SEED};
+
+use anyhow::{anyhow, Result};
+
+#[derive(Clone)]
+struct SmallSlot<V> {
+    ptr: usize,
+    len: usize,
+    val: V,
+}
+
+/// Simple fast hash map implementation whose slots and key bytes
+/// live in fixed-size inline arrays.
+///
+/// `N` is the number of slots, which must be a power of two greater than the number of keys,
+/// and `B` is the capacity of the key bytes.
+/// Since it never allocates on the heap, it suits small static dictionaries
+/// in environments where allocation is unavailable or undesirable.
+///
+/// # Examples
+///
+/// ```
+/// use simplearrayhash::SmallMap;
+///
+/// let records = [("icdm", 0), ("idce", 1), ("sigmod", 2)];
+/// let map = SmallMap::<_, 4, 16>::new(&records).unwrap();
+/// assert_eq!(map.get("idce"), Some(&1));
+/// assert_eq!(map.get("sigir"), None);
+/// ```
+#[derive(Clone)]
+pub struct SmallMap<V, const N: usize, const B: usize> {
+    slots: [Option<SmallSlot<V>>; N],
+    bytes: [u8; B],
+    num_keys: usize,
+}
+
+impl<V, const N: usize, const B: usize> SmallMap<V, N, B>
+where
+    V: Clone,
+{
+    /// Creates a new [`SmallMap`] from input records.
+    ///
+    /// # Arguments
+    ///
+    /// - `records`: List of key-value pairs.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `records` is empty,
+    ///  - `records` contains duplicate keys,
+    ///  - `N` is not a power of two or not greater than the number of records, or
+    ///  - the total length of the keys exceeds `B`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::SmallMap;
+    ///
+    /// let records = [("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// assert!(SmallMap::<_, 4, 14>::new(&records).is_ok());
+    /// assert!(SmallMap::<_, 4, 13>::new(&records).is_err());
+    /// assert!(SmallMap::<_, 2, 14>::new(&records).is_err());
+    /// ```
+    pub fn new<K>(records: &[(K, V)]) -> Result<Self>
+    where
+        K: AsRef<[u8]>,
+    {
+        if records.is_empty() {
+            return Err(anyhow!("The input records must not be empty."));
+        }
+        if !N.is_power_of_two() || records.len() >= N {
+            return Err(anyhow!(
+                "The number of slots must be a power of two greater than the number of records."
+            ));
+        }
+        let mut map = Self {
+            slots: std::array::from_fn(|_| None),
+            bytes: [0; B],
+            num_keys: 0,
+        };
+        let mut num_bytes = 0;
+        for (key, val) in records {
+            let key = key.as_ref();
+            let Err(pos) = map.get_pos(key) else {
+                return Err(anyhow!(
+                    "The input records must not contain duplicated keys."
+                ));
+            };
+            let end = num_bytes + key.len();
+            if end > B {
+                return Err(anyhow!(
+                    "The total length of the keys must not exceed the capacity of the key bytes."
+                ));
+            }
+            map.bytes[num_bytes..end].copy_from_slice(key);
+            map.slots[pos] = Some(SmallSlot {
+                ptr: num_bytes,
+                len: key.len(),
+                val: val.clone(),
+            });
+            num_bytes = end;
+            map.num_keys += 1;
+        }
+        Ok(map)
+    }
+}
+
+impl<V, const N: usize, const B: usize> SmallMap<V, N, B> {
+    /// Returns true if the map contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::SmallMap;
+    ///
+    /// let records = [("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = SmallMap::<_, 4, 16>::new(&records).unwrap();
+    /// assert_eq!(map.contains_key("idce"), true);
+    /// assert_eq!(map.contains_key("sigir"), false);
+    /// ```
+    #[inline(always)]
+    pub fn contains_key<K>(&self, key: K) -> bool
+    where
+        K: AsRef<[u8]>,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::SmallMap;
+    ///
+    /// let records = [("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = SmallMap::<_, 4, 16>::new(&records).unwrap();
+    /// assert_eq!(map.get("idce"), Some(&1));
+    /// assert_eq!(map.get("sigir"), None);
+    /// ```
+    #[inline(always)]
+    pub fn get<K>(&self, key: K) -> Option<&V>
+    where
+        K: AsRef<[u8]>,
+    {
+        let pos = self.get_pos(key.as_ref()).ok()?;
+        self.slots[pos].as_ref().map(|slot| &slot.val)
+    }
+
+    /// Returns the number of elements in the map.
+    #[inline(always)]
+    pub const fn len(&self) -> usize {
+        self.num_keys
+    }
+
+    /// Returns true if the map contains no elements.
+    #[inline(always)]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of slots in the map, which is `N`.
+    #[inline(always)]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Returns the position of the slot with `key` in `Ok`,
+    /// or the position of the empty slot to insert it in `Err`.
+    #[inline(always)]
+    fn get_pos(&self, key: &[u8]) -> Result<usize, usize> {
+        let mask = N - 1;
+        let mut pos = hash_key(key, DEFAULT_SEED) as usize & mask;
+        while let Some(slot) = &self.slots[pos] {
+            if key == &self.bytes[slot.ptr..slot.ptr + slot.len] {
+                return Ok(pos);
+            }
+            pos = (pos + 1) & mask;
+        }
+        Err(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let map = SmallMap::<_, 8, 32>::new(&records).unwrap();
+        assert_eq!(map.len(), 6);
+        assert_eq!(map.capacity(), 8);
+        for &(k, v) in &records {
+            assert_eq!(map.get(k), Some(&v));
+            assert!(map.contains_key(k));
+        }
+        assert_eq!(map.get("sigkdd"), None);
+        assert!(!map.contains_key("idml"));
+    }
+
+    #[test]
+    fn test_capacity() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        assert!(SmallMap::<_, 8, 22>::new(&records).is_ok());
+        assert!(SmallMap::<_, 8, 21>::new(&records).is_err());
+        assert!(SmallMap::<_, 6, 32>::new(&records).is_err());
+        assert!(SmallMap::<_, 4, 32>::new(&records).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_empty() {
+        let records: [(&str, u32); 0] = [];
+        SmallMap::<_, 4, 16>::new(&records).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_duplicate() {
+        let records = [("icdm", 0), ("icdm", 1)];
+        SmallMap::<_, 4, 16>::new(&records).unwrap();
+    }
+}
@@ -0,0 +1,279 @@
+//! Backends of the key bytes and slots of hash maps.
+//!
+//! The key bytes of a [`HashMap`](crate::HashMap) are either owned by the map or borrowed
+//! from a [`KeyStorage`], so the same type serves owned, shared, and memory-mapped data.
+//! The slots of a [`HashSet`](crate::HashSet), which embed no values, are borrowed from
+//! the storage as well, where their serialized records are laid out as in memory,
+//! that is, on 64-bit little-endian targets if the nodes section is 8-byte aligned in memory.
+//! Otherwise, and for maps, whose slots embed the values, the slots are owned by the table.
+
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+use crate::set::SetNode;
+use crate::wipe;
+
+/// Backend holding the key bytes of a [`HashMap`](crate::HashMap) outside the map,
+/// such as a memory-mapped file, a static slice, or a buffer shared with other maps.
+///
+/// It is implemented for every type exposing bytes by [`AsRef<[u8]>`],
+/// including `Vec<u8>`, `Arc<[u8]>`, `&'static [u8]`, and `memmap2::Mmap`.
+/// The bytes must stay at the same address while the backend is alive,
+/// since the slots of a set may refer to them directly.
+pub trait KeyStorage: Send + Sync + 'static {
+    /// Returns the bytes held by the backend.
+    fn as_key_bytes(&self) -> &[u8];
+}
+
+impl<T> KeyStorage for T
+where
+    T: AsRef<[u8]> + Send + Sync + 'static,
+{
+    #[inline(always)]
+    fn as_key_bytes(&self) -> &[u8] {
+        self.as_ref()
+    }
+}
+
+/// Key bytes of a table, either owned or borrowed from a shared backend.
+///
+/// Shared bytes are copied into owned ones on the first modification.
+#[derive(Clone)]
+pub(crate) enum KeyBytes {
+    Owned(Vec<u8>),
+    Shared {
+        storage: Arc<dyn KeyStorage>,
+        start: usize,
+        end: usize,
+    },
+}
+
+impl KeyBytes {
+    /// Borrows `storage.as_key_bytes()[start..end]`, which must be in bounds.
+    pub(crate) fn shared(storage: Arc<dyn KeyStorage>, start: usize, end: usize) -> Self {
+        assert!(start <= end && end <= storage.as_key_bytes().len());
+        Self::Shared {
+            storage,
+            start,
+            end,
+        }
+    }
+
+    /// Returns true if the bytes are borrowed from a shared backend.
+    #[inline(always)]
+    pub(crate) const fn is_shared(&self) -> bool {
+        matches!(self, Self::Shared { .. })
+    }
+
+    /// Returns the owned bytes, copying shared ones first.
+    pub(crate) fn to_mut(&mut self) -> &mut Vec<u8> {
+        if let Self::Shared { .. } = self {
+            *self = Self::Owned(self.to_vec());
+        }
+        match self {
+            Self::Owned(bytes) => bytes,
+            Self::Shared { .. } => unreachable!(),
+        }
+    }
+
+    /// Returns the number of bytes that can be held without reallocation.
+    #[cfg(test)]
+    pub(crate) fn capacity(&self) -> usize {
+        match self {
+            Self::Owned(bytes) => bytes.capacity(),
+            Self::Shared { .. } => self.len(),
+        }
+    }
+
+    /// Clears the bytes, wiping owned ones (see [`wipe`]) and releasing shared ones.
+    pub(crate) fn wipe(&mut self) {
+        match self {
+            Self::Owned(bytes) => wipe(bytes),
+            Self::Shared { .. } => *self = Self::default(),
+        }
+    }
+}
+
+impl Default for KeyBytes {
+    fn default() -> Self {
+        Self::Owned(vec![])
+    }
+}
+
+impl From<Vec<u8>> for KeyBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::Owned(bytes)
+    }
+}
+
+impl Deref for KeyBytes {
+    type Target = [u8];
+
+    #[inline(always)]
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Owned(bytes) => bytes,
+            Self::Shared {
+                storage,
+                start,
+                end,
+            } => &storage.as_key_bytes()[*start..*end],
+        }
+    }
+}
+
+/// Slots of a table, either owned or borrowed from a shared backend.
+///
+/// Shared slots are copied into owned ones on the first modification,
+/// including any mutable dereference.
+pub(crate) enum Nodes<N> {
+    Owned(Vec<N>),
+    #[cfg(not(feature = "safe"))]
+    Shared {
+        // Keeps the slots pointed to by `ptr` alive.
+        _storage: Arc<dyn KeyStorage>,
+        ptr: *const N,
+        len: usize,
+    },
+}
+
+// SAFETY: Shared slots are immutable and kept alive by a `Send + Sync` backend.
+#[cfg(not(feature = "safe"))]
+unsafe impl<N: Send> Send for Nodes<N> {}
+// SAFETY: Shared slots are immutable and kept alive by a `Send + Sync` backend.
+#[cfg(not(feature = "safe"))]
+unsafe impl<N: Sync> Sync for Nodes<N> {}
+
+impl<N> Nodes<N> {
+    /// Returns true if the slots are borrowed from a shared backend.
+    #[inline(always)]
+    pub(crate) const fn is_shared(&self) -> bool {
+        #[cfg(not(feature = "safe"))]
+        if let Self::Shared { .. } = self {
+            return true;
+        }
+        false
+    }
+
+    /// Clears the slots, wiping owned ones (see [`wipe`]) and releasing shared ones.
+    pub(crate) fn wipe(&mut self) {
+        match self {
+            Self::Owned(nodes) => wipe(nodes),
+            #[cfg(not(feature = "safe"))]
+            Self::Shared { .. } => *self = Self::default(),
+        }
+    }
+}
+
+impl<N> Nodes<N>
+where
+    N: Clone,
+{
+    /// Returns the owned slots, copying shared ones first.
+    pub(crate) fn to_mut(&mut self) -> &mut Vec<N> {
+        #[cfg(not(feature = "safe"))]
+        if let Self::Shared { .. } = self {
+            *self = Self::Owned(self.to_vec());
+        }
+        match self {
+            Self::Owned(nodes) => nodes,
+            #[cfg(not(feature = "safe"))]
+            Self::Shared { .. } => unreachable!(),
+        }
+    }
+
+    /// Takes the slots out as an owned vector, leaving no slots.
+    pub(crate) fn take(&mut self) -> Vec<N> {
+        match std::mem::take(self) {
+            Self::Owned(nodes) => nodes,
+            #[cfg(not(feature = "safe"))]
+            shared @ Self::Shared { .. } => shared.to_vec(),
+        }
+    }
+}
+
+impl Nodes<SetNode> {
+    /// Borrows `len` slots serialized from offset `start` of `storage.as_key_bytes()`,
+    /// which must be in bounds and validated, if they are laid out as [`SetNode`] in memory.
+    ///
+    /// Returns `None` if the target or the alignment of the records differs from the layout,
+    /// or if the `safe` feature is enabled.
+    pub(crate) fn shared(storage: Arc<dyn KeyStorage>, start: usize, len: usize) -> Option<Self> {
+        #[cfg(all(
+            target_endian = "little",
+            target_pointer_width = "64",
+            not(feature = "safe")
+        ))]
+        {
+            let records = &storage.as_key_bytes()[start..start + 16 * len];
+            if records
+                .as_ptr()
+                .align_offset(std::mem::align_of::<SetNode>())
+                == 0
+            {
+                let ptr = records.as_ptr().cast::<SetNode>();
+                return Some(Self::Shared {
+                    _storage: storage,
+                    ptr,
+                    len,
+                });
+            }
+        }
+        let _ = (storage, start, len);
+        None
+    }
+}
+
+impl<N> Clone for Nodes<N>
+where
+    N: Clone,
+{
+    fn clone(&self) -> Self {
+        match self {
+            Self::Owned(nodes) => Self::Owned(nodes.clone()),
+            #[cfg(not(feature = "safe"))]
+            Self::Shared { _storage, ptr, len } => Self::Shared {
+                _storage: Arc::clone(_storage),
+                ptr: *ptr,
+                len: *len,
+            },
+        }
+    }
+}
+
+impl<N> Default for Nodes<N> {
+    fn default() -> Self {
+        Self::Owned(vec![])
+    }
+}
+
+impl<N> From<Vec<N>> for Nodes<N> {
+    fn from(nodes: Vec<N>) -> Self {
+        Self::Owned(nodes)
+    }
+}
+
+impl<N> Deref for Nodes<N> {
+    type Target = [N];
+
+    #[inline(always)]
+    fn deref(&self) -> &[N] {
+        match self {
+            Self::Owned(nodes) => nodes,
+            // SAFETY: `ptr` points to `len` aligned slots in the bytes of the backend,
+            // which are valid as `N` by `Nodes::shared` and live as long as `self`.
+            #[cfg(not(feature = "safe"))]
+            Self::Shared { ptr, len, .. } => unsafe { std::slice::from_raw_parts(*ptr, *len) },
+        }
+    }
+}
+
+impl<N> DerefMut for Nodes<N>
+where
+    N: Clone,
+{
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut [N] {
+        self.to_mut()
+    }
+}
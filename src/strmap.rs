@@ -0,0 +1,348 @@
+//! Hash map guaranteeing that all the keys are valid UTF-8.
+
+use std::io::Write;
+
+use crate::serial::Serializable;
+use crate::{HashMap, KeyLen};
+
+use anyhow::{anyhow, Result};
+
+/// Wrapper of [`HashMap`] whose keys are all valid UTF-8,
+/// taking and yielding keys as `&str` instead of byte slices.
+///
+/// The keys are validated once when the map is created from a [`HashMap`] or deserialized,
+/// so iterations yield the keys without validating them again.
+///
+/// # Examples
+///
+/// ```
+/// use simplearrayhash::StrHashMap;
+///
+/// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+/// let map = StrHashMap::new(&records).unwrap();
+/// assert_eq!(map.get("idce"), Some(&1));
+///
+/// let mut keys: Vec<&str> = map.keys().collect();
+/// keys.sort_unstable();
+/// assert_eq!(keys, vec!["icdm", "idce", "sigmod"]);
+/// ```
+#[derive(Clone)]
+pub struct StrHashMap<V, L = usize>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    map: HashMap<V, L>,
+}
+
+impl<V> StrHashMap<V>
+where
+    V: Default + Clone,
+{
+    /// Creates a new [`StrHashMap`] from input records with string keys.
+    ///
+    /// # Arguments
+    ///
+    /// - `records`: List of key-value pairs.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when
+    ///
+    ///  - `records` is empty, or
+    ///  - `records` contains duplicate keys.
+    pub fn new<K>(records: &[(K, V)]) -> Result<Self>
+    where
+        K: AsRef<str>,
+    {
+        let records: Vec<_> = records
+            .iter()
+            .map(|(k, v)| (k.as_ref(), v.clone()))
+            .collect();
+        let map = HashMap::new(&records)?;
+        Ok(Self { map })
+    }
+}
+
+impl<V, L> StrHashMap<V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    /// Wraps `map` after validating that all its keys are valid UTF-8,
+    /// such as a map built by [`HashMapBuilder`](crate::HashMapBuilder) with custom options.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when a key is not valid UTF-8.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::{HashMap, HashMapBuilder, StrHashMap};
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMapBuilder::new().key_len::<u8>().build(&records).unwrap();
+    /// let map = StrHashMap::from_map(map).unwrap();
+    /// assert_eq!(map.get("sigmod"), Some(&2));
+    ///
+    /// let map = HashMap::new(&[(b"\xff", 0)]).unwrap();
+    /// assert!(StrHashMap::from_map(map).is_err());
+    /// ```
+    pub fn from_map(map: HashMap<V, L>) -> Result<Self> {
+        if map
+            .table
+            .iter()
+            .any(|(key, _)| std::str::from_utf8(key).is_err())
+        {
+            return Err(anyhow!("The keys must be valid UTF-8."));
+        }
+        Ok(Self { map })
+    }
+
+    /// Returns true if the map contains a value for the specified key.
+    #[inline(always)]
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    #[inline(always)]
+    pub fn get(&self, key: &str) -> Option<&V> {
+        self.map.get(key)
+    }
+
+    /// Returns a reference to the value corresponding to the key given in UTF-16,
+    /// in the same manner as [`HashMap::get_utf16`].
+    #[inline(always)]
+    pub fn get_utf16(&self, key: &[u16]) -> Option<&V> {
+        self.map.get_utf16(key)
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key.
+    #[inline(always)]
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut V> {
+        self.map.get_mut(key)
+    }
+
+    /// Inserts a key-value pair in the same manner as [`HashMap::insert`],
+    /// returning the previous value of the key if any.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when [`HashMap::insert`] fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::StrHashMap;
+    ///
+    /// let mut map = StrHashMap::new(&[("icdm", 0)]).unwrap();
+    /// assert_eq!(map.insert("sigir", 1).unwrap(), None);
+    /// assert_eq!(map.insert("icdm", 2).unwrap(), Some(0));
+    /// assert_eq!(map.get("sigir"), Some(&1));
+    /// ```
+    pub fn insert(&mut self, key: &str, val: V) -> Result<Option<V>> {
+        self.map.insert(key, val)
+    }
+
+    /// Removes a key in the same manner as [`HashMap::remove`],
+    /// returning its value if the key was stored.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when [`HashMap::remove`] fails.
+    pub fn remove(&mut self, key: &str) -> Result<Option<V>> {
+        self.map.remove(key)
+    }
+
+    /// Iterates over the keys and values in arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::StrHashMap;
+    ///
+    /// let map = StrHashMap::new(&[("icdm", 0), ("idce", 1)]).unwrap();
+    /// let mut entries: Vec<_> = map.iter().collect();
+    /// entries.sort_unstable();
+    /// assert_eq!(entries, vec![("icdm", &0), ("idce", &1)]);
+    /// ```
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&str, &V)> + ExactSizeIterator + '_ {
+        self.map
+            .table
+            .iter()
+            .map(|(key, nd)| (validated_str(key), &nd.val))
+    }
+
+    /// Iterates over the keys in arbitrary order.
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &str> + ExactSizeIterator + '_ {
+        self.iter().map(|(key, _)| key)
+    }
+
+    /// Iterates over the values in arbitrary order.
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> + ExactSizeIterator + '_ {
+        self.iter().map(|(_, val)| val)
+    }
+
+    /// Returns the number of elements in the map.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns true if the map contains no elements.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the underlying [`HashMap`].
+    #[inline(always)]
+    pub const fn as_map(&self) -> &HashMap<V, L> {
+        &self.map
+    }
+
+    /// Unwraps the underlying [`HashMap`].
+    pub fn into_map(self) -> HashMap<V, L> {
+        self.map
+    }
+}
+
+impl<V, L> StrHashMap<V, L>
+where
+    V: Default + Clone + Serializable,
+    L: KeyLen,
+{
+    /// Serializes the map into the format of [`HashMap::serialize_into`].
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when writing fails.
+    pub fn serialize_into<W>(&self, wtr: W) -> Result<()>
+    where
+        W: Write,
+    {
+        self.map.serialize_into(wtr)
+    }
+
+    /// Deserializes a map from `source` in the format of [`HashMap::serialize_into`],
+    /// validating that all the keys are valid UTF-8.
+    ///
+    /// # Errors
+    ///
+    /// An error will be returned when `source` is broken or a key is not valid UTF-8.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::StrHashMap;
+    ///
+    /// let map = StrHashMap::new(&[("icdm", 0), ("idce", 1)]).unwrap();
+    /// let mut bytes = vec![];
+    /// map.serialize_into(&mut bytes).unwrap();
+    /// let other = StrHashMap::<i32>::deserialize_from_slice(&bytes).unwrap();
+    /// assert_eq!(other.get("idce"), Some(&1));
+    /// ```
+    pub fn deserialize_from_slice(source: &[u8]) -> Result<Self> {
+        Self::from_map(HashMap::deserialize_from_slice(source)?)
+    }
+}
+
+/// Converts a key already validated as UTF-8 into a string.
+#[inline(always)]
+fn validated_str(key: &[u8]) -> &str {
+    #[cfg(not(feature = "safe"))]
+    // SAFETY: The keys are validated when the map is created,
+    // and only keys given as strings are inserted later.
+    unsafe {
+        std::str::from_utf8_unchecked(key)
+    }
+    #[cfg(feature = "safe")]
+    std::str::from_utf8(key).expect("The keys must be valid UTF-8.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::HashMapBuilder;
+
+    #[test]
+    fn test_str_map() {
+        let records = [
+            ("icdm", 0),
+            ("idce", 1),
+            ("", 2),
+            ("情報", 3),
+            ("sigmod", 4),
+        ];
+        let mut map = StrHashMap::new(&records).unwrap();
+        assert_eq!(map.len(), 5);
+        for &(k, v) in &records {
+            assert!(map.contains_key(k));
+            assert_eq!(map.get(k), Some(&v));
+        }
+        assert_eq!(map.get("情"), None);
+
+        map.insert("検索", 5).unwrap();
+        *map.get_mut("icdm").unwrap() = 10;
+        assert_eq!(map.remove("idce").unwrap(), Some(1));
+        let mut entries: Vec<_> = map.iter().map(|(k, &v)| (k, v)).collect();
+        entries.sort_unstable();
+        assert_eq!(
+            entries,
+            vec![
+                ("", 2),
+                ("icdm", 10),
+                ("sigmod", 4),
+                ("情報", 3),
+                ("検索", 5)
+            ]
+        );
+        assert_eq!(map.values().sum::<i32>(), 24);
+
+        let mut bytes = vec![];
+        map.serialize_into(&mut bytes).unwrap();
+        let other = StrHashMap::<i32>::deserialize_from_slice(&bytes).unwrap();
+        assert_eq!(other.get("検索"), Some(&5));
+        assert_eq!(other.into_map().get("sigmod"), Some(&4));
+    }
+
+    #[test]
+    fn test_iter_rev() {
+        let map = StrHashMap::new(&[("icdm", 0), ("情報", 1), ("sigmod", 2)]).unwrap();
+        assert_eq!(map.iter().len(), 3);
+        let forward: Vec<_> = map.iter().collect();
+        let mut backward: Vec<_> = map.iter().rev().collect();
+        backward.reverse();
+        assert_eq!(backward, forward);
+
+        let mut keys = map.keys();
+        keys.next_back().unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(map.values().rev().len(), 3);
+    }
+
+    #[test]
+    fn test_from_map() {
+        let map = HashMapBuilder::new()
+            .key_len::<u8>()
+            .share_suffixes(true)
+            .build(&[("情報", 0), ("報", 1), ("icdm", 2)])
+            .unwrap();
+        let map = StrHashMap::from_map(map).unwrap();
+        let mut keys: Vec<_> = map.keys().collect();
+        keys.sort_unstable();
+        assert_eq!(keys, vec!["icdm", "報", "情報"]);
+        assert_eq!(map.as_map().len(), 3);
+
+        // A key cut in the middle of a character is not valid UTF-8.
+        let map = HashMap::new(&[(&"情報".as_bytes()[..4], 0)]).unwrap();
+        assert!(StrHashMap::from_map(map).is_err());
+        let bytes = HashMap::new(&[(b"\xc0\x80", 0)])
+            .unwrap()
+            .serialize_to_vec();
+        assert!(StrHashMap::<i32>::deserialize_from_slice(&bytes).is_err());
+    }
+}
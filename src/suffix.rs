@@ -0,0 +1,42 @@
+//! Reverse-key index for enumerating the keys ending with a suffix.
+//!
+//! The slot positions of the keys are sorted in the lexicographic order of the reversed keys,
+//! in which the keys ending with any suffix are contiguous.
+
+use std::cmp::Ordering;
+
+use crate::{Node, Table};
+
+use anyhow::{anyhow, Result};
+
+impl<N> Table<N>
+where
+    N: Default + Clone + Node,
+{
+    /// Returns the slot positions in the ascending order of the reversed keys.
+    pub(crate) fn suffix_index(&self) -> Vec<usize> {
+        let mut suffixes: Vec<_> = (0..self.nodes.len())
+            .filter(|&pos| self.nodes[pos].is_occupied())
+            .collect();
+        // Keys are distinct, so the unstable sort is deterministic.
+        suffixes.sort_unstable_by(|&a, &b| cmp_reversed(self.entry_at(a).0, self.entry_at(b).0));
+        suffixes
+    }
+
+    /// Returns the slot positions of the keys ending with `suffix`
+    /// in the ascending order of the reversed keys.
+    pub(crate) fn suffix_positions(&self, suffix: &[u8]) -> Result<&[usize]> {
+        let suffixes = self.suffixes.as_deref().ok_or_else(|| {
+            anyhow!("The suffix index must be built to support suffix enumeration.")
+        })?;
+        let lo =
+            suffixes.partition_point(|&pos| cmp_reversed(self.entry_at(pos).0, suffix).is_lt());
+        let hi = lo + suffixes[lo..].partition_point(|&pos| self.entry_at(pos).0.ends_with(suffix));
+        Ok(&suffixes[lo..hi])
+    }
+}
+
+/// Compares `a` and `b` in the lexicographic order of their reversed bytes.
+fn cmp_reversed(a: &[u8], b: &[u8]) -> Ordering {
+    a.iter().rev().cmp(b.iter().rev())
+}
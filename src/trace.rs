@@ -0,0 +1,100 @@
+//! Tracing of construction phases, enabled by the `tracing` feature.
+
+use std::time::Instant;
+
+use tracing::span::EnteredSpan;
+use tracing::Span;
+
+/// Guard of a construction phase, which enters the span of the phase
+/// and emits an event with the elapsed time when dropped.
+pub struct Phase {
+    _span: EnteredSpan,
+    start: Instant,
+}
+
+impl Phase {
+    pub fn enter(span: Span) -> Self {
+        Self {
+            _span: span.entered(),
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Drop for Phase {
+    fn drop(&mut self) {
+        let elapsed_us = self.start.elapsed().as_micros() as u64;
+        tracing::debug!(elapsed_us, "finished");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    use crate::{HashMapBuilder, HashSet};
+
+    /// Subscriber recording the names of entered spans.
+    #[derive(Default)]
+    struct Recorder {
+        names: Arc<Mutex<Vec<&'static str>>>,
+        spans: Mutex<Vec<&'static str>>,
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut spans = self.spans.lock().unwrap();
+            spans.push(span.metadata().name());
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, _: &Event<'_>) {}
+
+        fn enter(&self, span: &Id) {
+            let name = self.spans.lock().unwrap()[span.into_u64() as usize - 1];
+            self.names.lock().unwrap().push(name);
+        }
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn test_phases() {
+        let recorder = Recorder::default();
+        let names = recorder.names.clone();
+        tracing::subscriber::with_default(recorder, || {
+            let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+            let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+            let map = HashMapBuilder::new()
+                .sort_keys(true)
+                .build(&records)
+                .unwrap();
+            HashSet::new(&keys).unwrap();
+            crate::HashMap::<usize>::deserialize_from_slice(&map.serialize_to_vec()).unwrap();
+        });
+        assert_eq!(
+            *names.lock().unwrap(),
+            [
+                "slot_assignment",
+                "layout",
+                "slot_assignment",
+                "byte_packing",
+                "indexes",
+                "slot_assignment",
+                "layout",
+                "validation",
+            ]
+        );
+    }
+}
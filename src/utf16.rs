@@ -0,0 +1,82 @@
+//! Transcoding of UTF-16 queries into UTF-8 keys.
+
+/// Maximum number of bytes of a key transcoded on the stack.
+const STACK_BUF_LEN: usize = 256;
+
+/// Transcodes `units` into UTF-8 and passes the bytes to `f`,
+/// or returns `None` if `units` contains an unpaired surrogate.
+///
+/// The bytes are written into a stack buffer if the query is short enough,
+/// so short queries involve no allocation.
+#[inline(always)]
+pub(crate) fn with_utf8<F, R>(units: &[u16], f: F) -> Option<R>
+where
+    F: FnOnce(&[u8]) -> R,
+{
+    // Each unit is encoded into at most three bytes,
+    // and a surrogate pair of two units into four bytes.
+    let max_len = units.len() * 3;
+    if max_len <= STACK_BUF_LEN {
+        let mut buf = [0; STACK_BUF_LEN];
+        let len = transcode(units, &mut buf)?;
+        Some(f(&buf[..len]))
+    } else {
+        let mut buf = vec![0; max_len];
+        let len = transcode(units, &mut buf)?;
+        Some(f(&buf[..len]))
+    }
+}
+
+/// Writes `units` into `buf` as UTF-8 and returns the number of written bytes,
+/// or `None` if `units` contains an unpaired surrogate.
+///
+/// `buf` must have at least three bytes per unit.
+fn transcode(units: &[u16], buf: &mut [u8]) -> Option<usize> {
+    let mut len = 0;
+    for c in char::decode_utf16(units.iter().copied()) {
+        len += c.ok()?.encode_utf8(&mut buf[len..]).len();
+    }
+    Some(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(s: &str) -> Vec<u16> {
+        s.encode_utf16().collect()
+    }
+
+    #[test]
+    fn test_with_utf8() {
+        for s in ["", "icdm", "情報検索", "𝄞 clef", "é\u{10ffff}"] {
+            assert_eq!(
+                with_utf8(&utf16(s), <[u8]>::to_vec),
+                Some(s.as_bytes().to_vec())
+            );
+        }
+        let long = "情".repeat(STACK_BUF_LEN);
+        assert_eq!(
+            with_utf8(&utf16(&long), <[u8]>::to_vec),
+            Some(long.into_bytes())
+        );
+        assert_eq!(with_utf8(&[0x61, 0xd800], <[u8]>::to_vec), None);
+        assert_eq!(with_utf8(&[0xdc00, 0x61], <[u8]>::to_vec), None);
+    }
+
+    #[test]
+    fn test_get_utf16() {
+        let long = "検索".repeat(STACK_BUF_LEN);
+        let keys = ["icdm", "", "情報", "𝄞", long.as_str()];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let map = crate::HashMap::new(&records).unwrap();
+        let set = crate::HashSet::new(&keys).unwrap();
+        for (i, k) in keys.iter().enumerate() {
+            assert_eq!(map.get_utf16(&utf16(k)), Some(&i));
+            assert!(set.contains_utf16(&utf16(k)));
+        }
+        assert_eq!(map.get_utf16(&utf16("情")), None);
+        assert_eq!(map.get_utf16(&[0xd834]), None);
+        assert!(!set.contains_utf16(&utf16(&long[3..])));
+    }
+}
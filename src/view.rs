@@ -0,0 +1,291 @@
+//! Lightweight views restricting or rewriting queries to a hash map.
+
+use crate::{HashMap, KeyLen};
+
+const WORD_LEN: usize = 64;
+const STACK_BUF_LEN: usize = 128;
+
+/// View of a [`HashMap`] that only answers for an allowed subset of keys.
+///
+/// It shares the storage of the underlying map and
+/// only keeps one bit per slot to mark the allowed keys.
+#[derive(Clone)]
+pub struct View<'a, V, L = usize>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    map: &'a HashMap<V, L>,
+    allowed: Vec<u64>,
+    num_keys: usize,
+}
+
+impl<'a, V, L> View<'a, V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    /// Creates a new [`View`] of `map` restricted to `keys`.
+    /// Keys not contained in `map` are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// - `map`: Map to be viewed.
+    /// - `keys`: List of allowed keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::{HashMap, View};
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let view = View::new(&map, &["idce", "sigmod", "sigir"]);
+    /// assert_eq!(view.len(), 2);
+    /// assert_eq!(view.get("idce"), Some(&1));
+    /// assert_eq!(view.get("icdm"), None);
+    /// ```
+    pub fn new<K>(map: &'a HashMap<V, L>, keys: &[K]) -> Self
+    where
+        K: AsRef<[u8]>,
+    {
+        let mut allowed = vec![0; map.capacity().div_ceil(WORD_LEN)];
+        let mut num_keys = 0;
+        for key in keys {
+            if let Some(pos) = map.table.get_pos(key) {
+                let (q, r) = (pos / WORD_LEN, pos % WORD_LEN);
+                if allowed[q] & (1 << r) == 0 {
+                    allowed[q] |= 1 << r;
+                    num_keys += 1;
+                }
+            }
+        }
+        Self {
+            map,
+            allowed,
+            num_keys,
+        }
+    }
+
+    /// Returns true if the view contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::{HashMap, View};
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let view = View::new(&map, &["idce"]);
+    /// assert_eq!(view.contains_key("idce"), true);
+    /// assert_eq!(view.contains_key("icdm"), false);
+    /// ```
+    #[inline(always)]
+    pub fn contains_key<K>(&self, key: K) -> bool
+    where
+        K: AsRef<[u8]>,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Returns a reference to the value corresponding to the key
+    /// if the key is allowed in the view.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::{HashMap, View};
+    ///
+    /// let records = vec![("icdm", 0), ("idce", 1), ("sigmod", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let view = View::new(&map, &["idce"]);
+    /// assert_eq!(view.get("idce"), Some(&1));
+    /// assert_eq!(view.get("icdm"), None);
+    /// ```
+    #[inline(always)]
+    pub fn get<K>(&self, key: K) -> Option<&'a V>
+    where
+        K: AsRef<[u8]>,
+    {
+        let map = self.map;
+        let pos = map.table.get_pos(key)?;
+        if self.allowed[pos / WORD_LEN] & (1 << (pos % WORD_LEN)) == 0 {
+            return None;
+        }
+        map.table.node(pos).map(|nd| &nd.val)
+    }
+
+    /// Returns the number of allowed elements in the view.
+    #[inline(always)]
+    pub const fn len(&self) -> usize {
+        self.num_keys
+    }
+
+    /// Returns true if the view contains no elements.
+    #[inline(always)]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// View of a [`HashMap`] that prepends a fixed prefix to every query.
+///
+/// It lets several logical namespaces such as `"tenant42/"` share one physical map.
+/// Queries whose prefixed key is short are concatenated in a stack buffer,
+/// so no allocation is needed in most cases.
+#[derive(Clone)]
+pub struct PrefixedView<'a, V, L = usize>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    map: &'a HashMap<V, L>,
+    prefix: Vec<u8>,
+}
+
+impl<'a, V, L> PrefixedView<'a, V, L>
+where
+    V: Default + Clone,
+    L: KeyLen,
+{
+    /// Creates a new [`PrefixedView`] of `map` with `prefix`.
+    ///
+    /// # Arguments
+    ///
+    /// - `map`: Map to be viewed.
+    /// - `prefix`: Prefix prepended to every query.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::{HashMap, PrefixedView};
+    ///
+    /// let records = vec![("a/icdm", 0), ("a/idce", 1), ("b/icdm", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let view = PrefixedView::new(&map, "b/");
+    /// assert_eq!(view.get("icdm"), Some(&2));
+    /// assert_eq!(view.get("idce"), None);
+    /// ```
+    pub fn new<P>(map: &'a HashMap<V, L>, prefix: P) -> Self
+    where
+        P: AsRef<[u8]>,
+    {
+        Self {
+            map,
+            prefix: prefix.as_ref().to_vec(),
+        }
+    }
+
+    /// Returns the prefix prepended to every query.
+    #[inline(always)]
+    pub fn prefix(&self) -> &[u8] {
+        &self.prefix
+    }
+
+    /// Returns true if the map contains a value for the prefixed key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::{HashMap, PrefixedView};
+    ///
+    /// let records = vec![("a/icdm", 0), ("a/idce", 1), ("b/icdm", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let view = PrefixedView::new(&map, "a/");
+    /// assert_eq!(view.contains_key("idce"), true);
+    /// assert_eq!(view.contains_key("a/idce"), false);
+    /// ```
+    #[inline(always)]
+    pub fn contains_key<K>(&self, key: K) -> bool
+    where
+        K: AsRef<[u8]>,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Returns a reference to the value corresponding to the prefixed key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use simplearrayhash::{HashMap, PrefixedView};
+    ///
+    /// let records = vec![("a/icdm", 0), ("a/idce", 1), ("b/icdm", 2)];
+    /// let map = HashMap::new(&records).unwrap();
+    /// let view = PrefixedView::new(&map, "a/");
+    /// assert_eq!(view.get("idce"), Some(&1));
+    /// assert_eq!(view.get("sigir"), None);
+    /// ```
+    #[inline(always)]
+    pub fn get<K>(&self, key: K) -> Option<&'a V>
+    where
+        K: AsRef<[u8]>,
+    {
+        let map = self.map;
+        let key = key.as_ref();
+        let (plen, len) = (self.prefix.len(), self.prefix.len() + key.len());
+        if len <= STACK_BUF_LEN {
+            let mut buf = [0; STACK_BUF_LEN];
+            buf[..plen].copy_from_slice(&self.prefix);
+            buf[plen..len].copy_from_slice(key);
+            map.get(&buf[..len])
+        } else {
+            let mut buf = Vec::with_capacity(len);
+            buf.extend_from_slice(&self.prefix);
+            buf.extend_from_slice(key);
+            map.get(&buf)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get() {
+        let keys = ["icdm", "idce", "", "sigmod", "sigir", "acl"];
+        let records: Vec<_> = keys.iter().enumerate().map(|(i, k)| (k, i)).collect();
+        let map = HashMap::new(&records).unwrap();
+        let view = View::new(&map, &["idce", "", "sigkdd", "idce"]);
+        assert_eq!(view.len(), 2);
+        assert_eq!(view.get("idce"), Some(&1));
+        assert_eq!(view.get(""), Some(&2));
+        for k in ["icdm", "sigmod", "sigir", "acl", "sigkdd"] {
+            assert_eq!(view.get(k), None);
+            assert!(!view.contains_key(k));
+        }
+    }
+
+    #[test]
+    fn test_prefixed() {
+        let long_key = format!("t1/{}", "a".repeat(STACK_BUF_LEN));
+        let records = [
+            ("t1/icdm", 0),
+            ("t1/", 1),
+            ("t2/icdm", 2),
+            ("icdm", 3),
+            (long_key.as_str(), 4),
+        ];
+        let map = HashMap::new(&records).unwrap();
+        let view = PrefixedView::new(&map, "t1/");
+        assert_eq!(view.prefix(), b"t1/");
+        assert_eq!(view.get("icdm"), Some(&0));
+        assert_eq!(view.get(""), Some(&1));
+        assert_eq!(view.get("a".repeat(STACK_BUF_LEN)), Some(&4));
+        assert_eq!(view.get("t1/icdm"), None);
+        assert_eq!(view.get("idce"), None);
+
+        let view = PrefixedView::new(&map, "");
+        assert_eq!(view.get("icdm"), Some(&3));
+    }
+
+    #[test]
+    fn test_empty() {
+        let records = [("icdm", 0)];
+        let map = HashMap::new(&records).unwrap();
+        let view = View::new(&map, &["sigir"]);
+        assert!(view.is_empty());
+        assert_eq!(view.get("icdm"), None);
+    }
+}